@@ -1,9 +1,15 @@
 extern crate rabe;
+extern crate rabe_bn;
+extern crate rand;
 #[macro_use]
 extern crate criterion;
 
 use criterion::{criterion_group, criterion_main, Criterion, Throughput, BenchmarkId};
 use rabe::schemes;
+use rabe::utils::policy::pest::PolicyLanguage;
+use rabe::utils::pairing::multi_pairing;
+use rabe_bn::{pairing, Fr, Gt, G1, G2};
+use rand::Rng;
 
 
 fn criterion_compare_schemes_setup(c: &mut Criterion) {
@@ -40,14 +46,140 @@ fn criterion_compare_schemes_setup(c: &mut Criterion) {
     });
     group.bench_with_input(BenchmarkId::new("YCT14", 1), &1_usize, |b, &_usize| {
         b.iter(|| {
-            schemes::yct14::setup((0..10).into_iter().map(|v: usize| v.to_string()).collect())
+            let universe: Vec<String> = (0..10).map(|v: usize| v.to_string()).collect();
+            schemes::yct14::setup(universe.iter().map(|v| v.as_str()).collect())
         } );
     });
     group.finish();
 }
 
+// an OR of an AND of `width` attributes and a single attribute, the key holds all attributes. Decryption only needs
+// the single attribute, while the first satisfied branch needs `width` leaves.
+fn wide_or(width: usize) -> (String, Vec<String>) {
+    let mut attributes: Vec<String> = (0..width).map(|i| format!("A{}", i)).collect();
+    let and: Vec<String> = attributes.iter().map(|a| format!("\"{}\"", a)).collect();
+    attributes.push(String::from("B"));
+    (format!("({}) or \"B\"", and.join(" and ")), attributes)
+}
+
+fn criterion_compare_schemes_decrypt_wide_or(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decrypt wide or");
+    let plaintext = String::from("dance like no one's watching, encrypt like everyone is!").into_bytes();
+    for width in [2usize, 8, 32] {
+        let (policy, attributes) = wide_or(width);
+        let attributes: Vec<&str> = attributes.iter().map(|a| a.as_str()).collect();
+        group.throughput(Throughput::Elements(width as u64));
+        let (pk, msk) = schemes::ac17::setup();
+        let sk = schemes::ac17::cp_keygen(&msk, &attributes).unwrap();
+        let ct = schemes::ac17::cp_encrypt(&pk, (&policy, PolicyLanguage::HumanPolicy), &plaintext).unwrap();
+        group.bench_with_input(BenchmarkId::new("AC17", width), &width, |b, &_width| {
+            b.iter(|| {
+                schemes::ac17::cp_decrypt(&sk, &ct).unwrap()
+            } );
+        });
+        let (pk, msk) = schemes::bsw::setup();
+        let sk = schemes::bsw::keygen(&pk, &msk, &attributes).unwrap();
+        let ct = schemes::bsw::encrypt(&pk, (&policy, PolicyLanguage::HumanPolicy), &plaintext).unwrap();
+        group.bench_with_input(BenchmarkId::new("BSW", width), &width, |b, &_width| {
+            b.iter(|| {
+                schemes::bsw::decrypt(&sk, &ct).unwrap()
+            } );
+        });
+        let (pk, msk) = schemes::ghw11::setup();
+        let attributes: Vec<String> = attributes.iter().map(|a| a.to_string()).collect();
+        let sk = schemes::ghw11::keygen(&pk, &msk, &attributes).unwrap();
+        let (tk, _rk) = schemes::ghw11::tkgen(sk).unwrap();
+        let ct = schemes::ghw11::encrypt(&pk, (&policy, PolicyLanguage::HumanPolicy), &plaintext).unwrap();
+        group.bench_with_input(BenchmarkId::new("GHW11", width), &width, |b, &_width| {
+            b.iter(|| {
+                schemes::ghw11::transform(ct.header.clone(), tk.clone()).unwrap()
+            } );
+        });
+    }
+    group.finish();
+}
+
+// the product of `n` pairings with coefficients, as computed by the decryption of a policy with `n` leaves, once with a
+// pairing per leaf and once with the pairs merged by bilinearity. Half of the pairings share their G2 element, like the
+// pairings with e2 in LSW.
+fn criterion_compare_multi_pairing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pairing product");
+    let mut rng = rand::thread_rng();
+    for n in [2usize, 8, 32] {
+        let shared: G2 = rng.gen();
+        let terms: Vec<(G1, G2, Fr)> = (0..n)
+            .map(|i| (rng.gen(), if i % 2 == 0 { shared } else { rng.gen() }, rng.gen()))
+            .collect();
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::new("pairing", n), &n, |b, &_n| {
+            b.iter(|| {
+                terms.iter().fold(Gt::one(), |product, (p, q, x)| product * pairing(*p * *x, *q))
+            } );
+        });
+        group.bench_with_input(BenchmarkId::new("multi_pairing", n), &n, |b, &_n| {
+            b.iter(|| {
+                let pairs: Vec<(G1, G2)> = terms.iter().map(|(p, q, x)| (*p * *x, *q)).collect();
+                multi_pairing(&pairs)
+            } );
+        });
+    }
+    group.finish();
+}
+
+// encryption under a policy with `n` attributes, with a public key and with a prepared public key that is created once
+// and reused for all encryptions
+fn criterion_compare_schemes_encrypt_prepared(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encrypt prepared");
+    let plaintext = String::from("dance like no one's watching, encrypt like everyone is!").into_bytes();
+    for n in [2usize, 8, 32] {
+        let attributes: Vec<String> = (0..n).map(|i| format!("\"A{}\"", i)).collect();
+        let policy = attributes.join(" and ");
+        group.throughput(Throughput::Elements(1));
+        let (pk, _msk) = schemes::ac17::setup();
+        let prepared = schemes::ac17::Ac17PreparedPublicKey::new(&pk);
+        group.bench_with_input(BenchmarkId::new("AC17", n), &n, |b, &_n| {
+            b.iter(|| {
+                schemes::ac17::cp_encrypt(&pk, (&policy, PolicyLanguage::HumanPolicy), &plaintext).unwrap()
+            } );
+        });
+        group.bench_with_input(BenchmarkId::new("AC17 prepared", n), &n, |b, &_n| {
+            b.iter(|| {
+                schemes::ac17::cp_encrypt(&prepared, (&policy, PolicyLanguage::HumanPolicy), &plaintext).unwrap()
+            } );
+        });
+        let (pk, _msk) = schemes::bsw::setup();
+        let prepared = schemes::bsw::CpAbePreparedPublicKey::new(&pk);
+        group.bench_with_input(BenchmarkId::new("BSW", n), &n, |b, &_n| {
+            b.iter(|| {
+                schemes::bsw::encrypt(&pk, (&policy, PolicyLanguage::HumanPolicy), &plaintext).unwrap()
+            } );
+        });
+        group.bench_with_input(BenchmarkId::new("BSW prepared", n), &n, |b, &_n| {
+            b.iter(|| {
+                schemes::bsw::encrypt(&prepared, (&policy, PolicyLanguage::HumanPolicy), &plaintext).unwrap()
+            } );
+        });
+        let (pk, _msk) = schemes::ghw11::setup();
+        let prepared = schemes::ghw11::Ghw11PreparedPublicKey::new(&pk);
+        group.bench_with_input(BenchmarkId::new("GHW11", n), &n, |b, &_n| {
+            b.iter(|| {
+                schemes::ghw11::encrypt(&pk, (&policy, PolicyLanguage::HumanPolicy), &plaintext).unwrap()
+            } );
+        });
+        group.bench_with_input(BenchmarkId::new("GHW11 prepared", n), &n, |b, &_n| {
+            b.iter(|| {
+                schemes::ghw11::encrypt(&prepared, (&policy, PolicyLanguage::HumanPolicy), &plaintext).unwrap()
+            } );
+        });
+    }
+    group.finish();
+}
+
 criterion_group!(benches,
     criterion_compare_schemes_setup,
+    criterion_compare_schemes_decrypt_wide_or,
+    criterion_compare_multi_pairing,
+    criterion_compare_schemes_encrypt_prepared,
 );
 
 criterion_main!(benches);
\ No newline at end of file
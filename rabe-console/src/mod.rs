@@ -2,22 +2,10 @@
 //!
 extern crate rand;
 extern crate rabe;
-extern crate deflate;
-extern crate inflate;
-#[cfg(feature = "borsh")]
-extern crate borsh;
-#[cfg(feature = "serde")]
-extern crate serde;
-#[cfg(feature = "serde")]
-extern crate serde_cbor;
-
-extern crate rustc_hex as hex;
-
 #[macro_use]
 extern crate clap;
 extern crate core;
 
-use hex::{FromHex, ToHex};
 use clap::{App, Arg, ArgMatches, SubCommand};
 use crate::rabe::{
     error::RabeError,
@@ -28,31 +16,22 @@ use crate::rabe::{
         bsw,
         lsw,
         mke08,
-        yct14
+        yct14,
+        CpAbe,
+        KpAbe,
+        MultiAuthorityAbe
     },
     utils::{
         policy::pest::PolicyLanguage,
-        file::{write_file, read_file, read_raw, write_from_vec, read_to_vec}
+        container::Container,
+        file::{write_file, read_file, write_from_vec, read_to_vec}
     }
 };
-#[cfg(feature = "serde")]
-use serde::{
-    de::DeserializeOwned,
-    Serialize
-};
-#[cfg(feature = "borsh")]
-use borsh::{BorshSerialize, BorshDeserialize};
 
 use std::{
     process,
     path::Path
 };
-#[cfg(feature = "serde")]
-use serde_cbor::{
-    ser::to_vec_packed,
-    from_slice
-};
-use rabe::schemes::mke08::Mke08PublicAttributeKey;
 
 // File extensions
 const CT_EXTENSION: &'static str = "ct";
@@ -78,26 +57,6 @@ const PKA_FILE: &'static str = "pka";
 const AU_PK_FILE: &'static str = "pkau";
 const AU_SK_FILE: &'static str = "skau";
 
-// Key file header and footer
-const GP_BEGIN: &'static str = "-----BEGIN GP-----\n";
-const GP_END: &'static str = "\n-----END GP-----";
-const SK_BEGIN: &'static str = "-----BEGIN SK-----\n";
-const SK_END: &'static str = "\n-----END SK-----";
-const MSK_BEGIN: &'static str = "-----BEGIN MSK-----\n";
-const MSK_END: &'static str = "\n-----END MSK-----";
-const PK_BEGIN: &'static str = "-----BEGIN PK-----\n";
-const PK_END: &'static str = "\n-----END PK-----";
-const CT_BEGIN: &'static str = "-----BEGIN CT-----\n";
-const CT_END: &'static str = "\n-----END CT-----";
-const SKA_BEGIN: &'static str = "-----BEGIN SAK-----\n";
-const SKA_END: &'static str = "\n-----END SAK-----";
-const PKA_BEGIN: &'static str = "-----BEGIN PAK-----\n";
-const PKA_END: &'static str = "\n-----END PAK-----";
-const AU_PK_BEGIN: &'static str = "-----BEGIN PAUK-----\n";
-const AU_PK_END: &'static str = "\n-----END PAUK-----";
-const AU_SK_BEGIN: &'static str = "-----BEGIN SAUK-----\n";
-const AU_SK_END: &'static str = "\n-----END SAUK-----";
-
 // Application commands
 const CMD_SETUP: &'static str = "setup";
 const CMD_AUTHGEN: &'static str = "authgen";
@@ -469,7 +428,7 @@ fn main() {
         .get_matches();
 
     if let Err(e) = run(_abe_app) {
-        println!("Application Error: {}", e);
+        eprintln!("Application Error: {}", e);
         process::exit(1);
     }
 
@@ -507,8 +466,7 @@ fn main() {
                 _ => Ok(()),
             }
         } else {
-            println!("Application error: Scheme missing");
-            process::exit(1);
+            Err(RabeError::new("sorry, no scheme given."))
         }
     }
 
@@ -541,67 +499,41 @@ fn main() {
             Some(_file) => _gp_file = _file.to_string(),
         }
         match _scheme {
-            Scheme::AC17CP | Scheme::AC17KP => {
-                let (_pk, _msk) = ac17::setup();
-                write_file(
-                    Path::new(&_msk_file),
-                    ser_enc(_msk, MSK_BEGIN, MSK_END)
-                );
-                write_file(
-                    Path::new(&_pk_file),
-                    ser_enc(_pk, PK_BEGIN, PK_END)
-                );
-            }
+            Scheme::AC17CP => cp_setup::<ac17::Ac17Cp>(&_pk_file, &_msk_file),
+            Scheme::AC17KP => kp_setup::<ac17::Ac17Kp>(&_pk_file, &_msk_file, &[]),
             Scheme::AW11 => {
                 let _gp = aw11::setup();
                 write_file(
                     Path::new(&_msk_file),
-                    ser_enc(_gp, GP_BEGIN, GP_END)
-                );
+                    _gp.to_pem()?
+                )?;
+                Ok(())
             }
             Scheme::BDABE => {
                 let (_pk, _msk) = bdabe::setup();
                 write_file(
                     Path::new(&_msk_file),
-                    ser_enc(_msk, MSK_BEGIN, MSK_END)
-                );
-                write_file(
-                    Path::new(&_pk_file),
-                    ser_enc(_pk, PK_BEGIN, PK_END)
-                );
-            }
-            Scheme::BSW => {
-                let (_pk, _msk) = bsw::setup();
-                write_file(
-                    Path::new(&_msk_file),
-                    ser_enc(_msk, MSK_BEGIN, MSK_END)
-                );
+                    _msk.to_pem()?
+                )?;
                 write_file(
                     Path::new(&_pk_file),
-                    ser_enc(_pk, PK_BEGIN, PK_END)
-                );
-            }
-            Scheme::LSW => {
-                let (_pk, _msk) = lsw::setup();
-                write_file(
-                    Path::new(&_msk_file),
-                    ser_enc(_msk, MSK_BEGIN, MSK_END)
-                );
-                write_file(
-                    Path::new(&_pk_file),
-                    ser_enc(_pk, PK_BEGIN, PK_END)
-                );
+                    _pk.to_pem()?
+                )?;
+                Ok(())
             }
+            Scheme::BSW => cp_setup::<bsw::Bsw>(&_pk_file, &_msk_file),
+            Scheme::LSW => kp_setup::<lsw::Lsw>(&_pk_file, &_msk_file, &[]),
             Scheme::MKE08 => {
                 let (_pk, _msk) = mke08::setup();
                 write_file(
                     Path::new(&_msk_file),
-                    ser_enc(_msk, MSK_BEGIN, MSK_END)
-                );
+                    _msk.to_pem()?
+                )?;
                 write_file(
                     Path::new(&_pk_file),
-                    ser_enc(_pk, PK_BEGIN, PK_END)
-                );
+                    _pk.to_pem()?
+                )?;
+                Ok(())
             },
             Scheme::YCT14 => {
                 let mut attributes: Vec<&str> = Vec::new();
@@ -612,22 +544,13 @@ fn main() {
                     }
                 }
                 if attributes.len() > 0 {
-                    let (_pk, _msk) = yct14::setup(attributes);
-                    write_file(
-                        Path::new(&_msk_file),
-                        ser_enc(_msk, MSK_BEGIN, MSK_END)
-                    );
-                    write_file(
-                        Path::new(&_pk_file),
-                        ser_enc(_pk, PK_BEGIN, PK_END)
-                    );
+                    kp_setup::<yct14::Yct14>(&_pk_file, &_msk_file, &attributes)
                 }
                 else {
-                    return Err(RabeError::new("sorry, yct14 needs attributes at setup()"));
+                    Err(RabeError::new("sorry, yct14 needs attributes at setup()"))
                 }
             }
         }
-        Ok(())
     }
 
     fn run_authgen(
@@ -700,12 +623,12 @@ fn main() {
                     Some((_pk, _msk)) => {
                         write_file(
                             Path::new(&_msk_file),
-                            ser_enc(_msk, AU_SK_BEGIN, AU_SK_END)
-                        );
+                            _msk.to_pem()?
+                        )?;
                         write_file(
                             Path::new(&_pk_file),
-                            ser_enc(_pk, AU_PK_BEGIN, AU_PK_END)
-                        );
+                            _pk.to_pem()?
+                        )?;
                     }
                 }
             }
@@ -722,8 +645,8 @@ fn main() {
                     bdabe::authgen(&_pk, &_msk, &_name);
                 write_file(
                     Path::new(&_au_file),
-                    ser_enc(_sk, AU_SK_BEGIN, AU_SK_END)
-                );
+                    _sk.to_pem()?
+                )?;
             }
             Scheme::MKE08 => {
                 let _pk: mke08::Mke08PublicKey = match ser_dec(&_pk_file) {
@@ -737,8 +660,8 @@ fn main() {
                 let _sk: mke08::Mke08SecretAuthorityKey = mke08::authgen(&_name);
                 write_file(
                     Path::new(&_au_file),
-                    ser_enc(_sk, AU_SK_BEGIN, AU_SK_END)
-                );
+                    _sk.to_pem()?
+                )?;
             },
             _ => {
                 return Err(RabeError::new("sorry, this is not a multi-authoriy scheme"));
@@ -823,68 +746,10 @@ fn main() {
             }
         }
         match _scheme {
-            Scheme::AC17CP => {
-                print!("loading {} ...", _msk_file);
-                let _msk: ac17::Ac17MasterKey = match ser_dec(&_msk_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => panic!("{}", e.to_string())
-                };
-                println!("... done");
-                print!("creating AC17CP sk for {:?} ...", _attributes);
-                match ac17::cp_keygen(&_msk, &_attributes) {
-                    Ok(sk) => {
-                        println!("... done");
-                        println!("saving as {}", _sk_file);
-                        write_file(
-                            Path::new(&_sk_file),
-                            ser_enc(sk, SK_BEGIN, SK_END)
-                        )
-                    },
-                    Err(e) => panic!("{}", e.to_string())
-                };
-            }
-            Scheme::AC17KP => {
-                let _msk: ac17::Ac17MasterKey = match ser_dec(&_msk_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                let _sk: ac17::Ac17KpSecretKey = ac17::kp_keygen(&_msk, &_policy, _lang).unwrap();
-                write_file(
-                    Path::new(&_sk_file),
-                    ser_enc(_sk, SK_BEGIN, SK_END)
-                );
-            }
-            Scheme::BSW => {
-                let _pk: bsw::CpAbePublicKey = match ser_dec(&_pk_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                let _msk: bsw::CpAbeMasterKey = match ser_dec(&_msk_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                let _sk: bsw::CpAbeSecretKey = bsw::keygen(&_pk, &_msk, &_attributes)
-                    .unwrap();
-                write_file(
-                    Path::new(&_sk_file),
-                    ser_enc(_sk, SK_BEGIN, SK_END)
-                );
-            }
-            Scheme::LSW => {
-                let _pk: lsw::KpAbePublicKey = match ser_dec(&_pk_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                let _msk: lsw::KpAbeMasterKey = match ser_dec(&_msk_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                let _sk: lsw::KpAbeSecretKey = lsw::keygen(&_pk, &_msk, &_policy, _lang).unwrap();
-                write_file(
-                    Path::new(&_sk_file),
-                    ser_enc(_sk, SK_BEGIN, SK_END)
-                );
-            }
+            Scheme::AC17CP => cp_keygen::<ac17::Ac17Cp>(&_pk_file, &_msk_file, &_sk_file, &_attributes)?,
+            Scheme::AC17KP => kp_keygen::<ac17::Ac17Kp>(&_pk_file, &_msk_file, &_sk_file, &_policy, _lang)?,
+            Scheme::BSW => cp_keygen::<bsw::Bsw>(&_pk_file, &_msk_file, &_sk_file, &_attributes)?,
+            Scheme::LSW => kp_keygen::<lsw::Lsw>(&_pk_file, &_msk_file, &_sk_file, &_policy, _lang)?,
             Scheme::AW11 => {
                 let _pk: aw11::Aw11GlobalKey = match ser_dec(&_gp_file) {
                     Ok(parsed) => parsed,
@@ -895,11 +760,11 @@ fn main() {
                     Err(e) => return Err(e)
                 };
                 let _sk: aw11::Aw11SecretKey =
-                    aw11::keygen(&_pk, &_msk, &_name, &_attributes).unwrap();
+                    aw11::keygen(&_pk, &_msk, &_name, &_attributes)?;
                 write_file(
                     Path::new(&_name_file),
-                    ser_enc(_sk, SK_BEGIN, SK_END)
-                );
+                    _sk.to_pem()?
+                )?;
             }
             Scheme::BDABE => {
                 let _pk: bdabe::BdabePublicKey = match ser_dec(&_pk_file) {
@@ -913,8 +778,8 @@ fn main() {
                 let _sk: bdabe::BdabeUserKey = bdabe::keygen(&_pk, &_msk, &_name);
                 write_file(
                     Path::new(&_name_file),
-                    ser_enc(_sk, SK_BEGIN, SK_END)
-                );
+                    _sk.to_pem()?
+                )?;
             }
             Scheme::MKE08 => {
                 let _pk: mke08::Mke08PublicKey = match ser_dec(&_pk_file) {
@@ -929,29 +794,15 @@ fn main() {
                     let _sk: mke08::Mke08UserKey = mke08::keygen(&_pk, &_msk, &_name);
                     write_file(
                         Path::new(&_name_file),
-                        ser_enc(_sk, SK_BEGIN, SK_END)
-                    );
+                        _sk.to_pem()?
+                    )?;
                 } else {
                     return Err(RabeError::new(
                         "sorry, the name/id for the user key is not set.",
                     ));
                 }
             }
-            Scheme::YCT14 => {
-                let _pk: yct14::Yct14AbePublicKey = match ser_dec(&_pk_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                let _msk: yct14::Yct14AbeMasterKey = match ser_dec(&_msk_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                let _sk: yct14::Yct14AbeSecretKey = yct14::keygen(&_msk, &_policy, _lang).unwrap();
-                write_file(
-                    Path::new(&_sk_file),
-                    ser_enc(_sk, SK_BEGIN, SK_END)
-                );
-            }
+            Scheme::YCT14 => kp_keygen::<yct14::Yct14>(&_pk_file, &_msk_file, &_sk_file, &_policy, _lang)?,
         }
         Ok(())
     }
@@ -1007,21 +858,11 @@ fn main() {
                     Ok(parsed) => parsed,
                     Err(e) => return Err(e)
                 };
-                let _sk: Option<bsw::CpAbeSecretKey> =
-                    bsw::delegate(&_pk, &_msk, &_attributes);
-                match _sk {
-                    None => {
-                        return Err(RabeError::new(
-                            "sorry, could not delegate attributes. The given attributes are not a subset.",
-                        ));
-                    }
-                    Some(_del) => {
-                        write_file(
-                            Path::new(&_dg_file),
-                            ser_enc(_del, SK_BEGIN, SK_END)
-                        );
-                    }
-                }
+                let _del: bsw::CpAbeSecretKey = bsw::delegate(&_pk, &_msk, &_attributes)?;
+                write_file(
+                    Path::new(&_dg_file),
+                    _del.to_pem()?
+                )?;
             }
             _ => {
                 return Err(RabeError::new(
@@ -1052,7 +893,10 @@ fn main() {
                 _pk_files.push(_pk_file.clone());
             }
             Some(_file) => {
-                let files: Vec<_> = arguments.values_of(PK_FILE).unwrap().collect();
+                let files: Vec<_> = arguments
+                    .values_of(PK_FILE)
+                    .ok_or_else(|| RabeError::new("sorry, could not read the public key files."))?
+                    .collect();
                 for file in files {
                     _pk_files.push(file.to_string())
                 }
@@ -1085,172 +929,17 @@ fn main() {
                 _ct_file.push_str(&CT_EXTENSION);
             }
         }
-        let buffer: Vec<u8> = read_to_vec(Path::new(&_pt_file));
+        let buffer: Vec<u8> = read_to_vec(Path::new(&_pt_file))?;
         match _scheme {
-            Scheme::AC17CP => {
-                // only one pk is allowed
-                if _pk_files.len() == 1 {
-                    _pk_file = _pk_files[0].clone();
-                    let _pk: ac17::Ac17PublicKey = match ser_dec(&_pk_file) {
-                        Ok(parsed) => parsed,
-                        Err(e) => return Err(e)
-                    };
-                    match ac17::cp_encrypt(&_pk, &_policy, &buffer, _lang){
-                        Ok(_ct) => {
-                            write_file(
-                                Path::new(&_ct_file),
-                                ser_enc(_ct, CT_BEGIN, CT_END)
-                            );
-                        },
-                        Err(e) => return Err(e)
-                    }
-                } else {
-                    return Err(RabeError::new(
-                        "sorry, encryption using the AC17CP Scheme with zero or multiple PKs is not possible. ",
-                    ));
-                }
-            }
-            Scheme::AC17KP => {
-                // only one pk is allowed
-                if _pk_files.len() == 1 {
-                    let _pk: ac17::Ac17PublicKey = match ser_dec(&_pk_file) {
-                        Ok(parsed) => parsed,
-                        Err(e) => return Err(e)
-                    };
-                    match ac17::kp_encrypt(&_pk, &_attributes, &buffer) {
-                        Ok(_ct) => {
-                            write_file(
-                                Path::new(&_ct_file),
-                                ser_enc(_ct, CT_BEGIN, CT_END)
-                            );
-                        },
-                        Err(e) => return Err(e)
-                    }
-                } else {
-                    return Err(RabeError::new(
-                        "sorry, encryption using the AC17KP Scheme with zero or multiple PKs is not possible. ",
-                    ));
-                }
-            }
-            Scheme::BSW => {
-                // only one pk is allowed
-                if _pk_files.len() == 1 {
-                    let _pk: bsw::CpAbePublicKey = match ser_dec(&_pk_files[0].clone()) {
-                        Ok(parsed) => parsed,
-                        Err(e) => return Err(e)
-                    };
-                    let _ct = bsw::encrypt(&_pk, &_policy, _lang, &buffer);
-                    write_file(
-                        Path::new(&_ct_file),
-                        ser_enc(_ct, CT_BEGIN, CT_END)
-                    );
-                } else {
-                    return Err(RabeError::new(
-                        "sorry, encryption using the BSW Scheme with zero or multiple PKs is not possible. ",
-                    ));
-                }
-            }
-            Scheme::LSW => {
-                // only one pk is allowed
-                if _pk_files.len() == 1 {
-                    let _pk: lsw::KpAbePublicKey = match ser_dec(&_pk_files[0].clone()) {
-                        Ok(parsed) => parsed,
-                        Err(e) => return Err(e)
-                    };
-                    let _ct = lsw::encrypt(&_pk, &_attributes, &buffer);
-                    write_file(
-                        Path::new(&_ct_file),
-                        ser_enc(_ct, CT_BEGIN, CT_END)
-                    );
-                } else {
-                    return Err(RabeError::new(
-                        "sorry, encryption using the LSW Scheme with zero or multiple PKs is not possible. ",
-                    ));
-                }
-            }
-            Scheme::AW11 => {
-                let _gp: aw11::Aw11GlobalKey = match ser_dec(&_gp_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                let mut _pks: Vec<aw11::Aw11PublicKey> = Vec::new();
-                for filename in _pk_files {
-                    let _pka: aw11::Aw11PublicKey = match ser_dec(&filename) {
-                        Ok(parsed) => parsed,
-                        Err(e) => return Err(e)
-                    };
-                    _pks.push(_pka);
-                }
-                let pks: Vec<&aw11::Aw11PublicKey> = _pks.iter().map(|a| a).collect();
-                let _ct = aw11::encrypt(&_gp, pks.as_slice(), &_policy, _lang, &buffer);
-                write_file(
-                    Path::new(&_ct_file),
-                    ser_enc(_ct, CT_BEGIN, CT_END)
-                );
-            }
-            Scheme::BDABE => {
-                let _pk: bdabe::BdabePublicKey = match ser_dec(&_pk_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                let mut _attr_vec: Vec<bdabe::BdabePublicAttributeKey> = Vec::new();
-                for filename in _pk_files {
-                    let _pka: bdabe::BdabePublicAttributeKey = match ser_dec(&filename) {
-                        Ok(parsed) => parsed,
-                        Err(e) => return Err(e)
-                    };
-                    _attr_vec.push(_pka);
-                }
-                let attr_pks: Vec<&bdabe::BdabePublicAttributeKey> = _attr_vec.iter().map(|a| a).collect();
-                let _ct = bdabe::encrypt(&_pk, attr_pks.as_slice(), &_policy, _lang, &buffer);
-                write_file(
-                    Path::new(&_ct_file),
-                    ser_enc(_ct, CT_BEGIN, CT_END)
-                );
-            }
-            Scheme::MKE08 => {
-                let _pk: mke08::Mke08PublicKey = match ser_dec(&_pk_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                let mut _attr_vec: Vec<mke08::Mke08PublicAttributeKey> = Vec::new();
-                for filename in _pk_files {
-                    let _pka: mke08::Mke08PublicAttributeKey = match ser_dec(&filename) {
-                        Ok(parsed) => parsed,
-                        Err(e) => return Err(e)
-                    };
-                    _attr_vec.push(_pka);
-                }
-                let attr_v: Vec<&Mke08PublicAttributeKey> = _attr_vec.iter().map(|a| a ).collect();
-                let _ct = mke08::encrypt(&_pk, &attr_v, &_policy, _lang, &buffer);
-                write_file(
-                    Path::new(&_ct_file),
-                    ser_enc(_ct, CT_BEGIN, CT_END)
-                );
-            }
-            Scheme::YCT14 => {
-                if _pk_files.len() == 1 {
-                    let _pk: yct14::Yct14AbePublicKey = match ser_dec(&_pk_files[0].clone()) {
-                        Ok(parsed) => parsed,
-                        Err(e) => return Err(e)
-                    };
-                    match yct14::encrypt(&_pk, &_attributes, &buffer) {
-                        Ok(_ct) => {
-                            write_file(
-                                Path::new(&_ct_file),
-                                ser_enc(_ct, CT_BEGIN, CT_END)
-                            );
-                        },
-                        Err(e) => return Err(e)
-                    }
-                } else {
-                    return Err(RabeError::new(
-                        "sorry, encryption using the LSW Scheme with zero or multiple PKs is not possible. ",
-                    ));
-                }
-            }
+            Scheme::AC17CP => cp_encrypt::<ac17::Ac17Cp>(&_pk_files, &_policy, _lang, &buffer, &_ct_file),
+            Scheme::AC17KP => kp_encrypt::<ac17::Ac17Kp>(&_pk_files, &_attributes, &buffer, &_ct_file),
+            Scheme::BSW => cp_encrypt::<bsw::Bsw>(&_pk_files, &_policy, _lang, &buffer, &_ct_file),
+            Scheme::LSW => kp_encrypt::<lsw::Lsw>(&_pk_files, &_attributes, &buffer, &_ct_file),
+            Scheme::AW11 => ma_encrypt::<aw11::Aw11>(&_gp_file, &_pk_files, &_policy, _lang, &buffer, &_ct_file),
+            Scheme::BDABE => ma_encrypt::<bdabe::Bdabe>(&_pk_file, &_pk_files, &_policy, _lang, &buffer, &_ct_file),
+            Scheme::MKE08 => ma_encrypt::<mke08::Mke08>(&_pk_file, &_pk_files, &_policy, _lang, &buffer, &_ct_file),
+            Scheme::YCT14 => kp_encrypt::<yct14::Yct14>(&_pk_files, &_attributes, &buffer, &_ct_file),
         }
-        Ok(())
     }
 
     fn run_decrypt(
@@ -1297,108 +986,16 @@ fn main() {
         //     None => {}
         //     Some(x) => _policy = x.to_string(),
         // }
-        match _scheme {
-            Scheme::AC17CP => {
-                let _sk: ac17::Ac17CpSecretKey = match ser_dec(&_sk_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                let _ct: ac17::Ac17CpCiphertext = match ser_dec(&_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                _pt_option = ac17::cp_decrypt(&_sk, &_ct);
-            }
-            Scheme::AC17KP => {
-                let _sk: ac17::Ac17KpSecretKey = match ser_dec(&_sk_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                let _ct: ac17::Ac17KpCiphertext = match ser_dec(&_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                _pt_option = ac17::kp_decrypt(&_sk, &_ct);
-            }
-            Scheme::BSW => {
-                let _sk: bsw::CpAbeSecretKey = match ser_dec(&_sk_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                let _ct: bsw::CpAbeCiphertext = match ser_dec(&_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                _pt_option = bsw::decrypt(&_sk, &_ct);
-            }
-            Scheme::LSW => {
-                let _sk: lsw::KpAbeSecretKey = match ser_dec(&_sk_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                let _ct: lsw::KpAbeCiphertext = match ser_dec(&_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                _pt_option = lsw::decrypt(&_sk, &_ct);
-            }
-            Scheme::AW11 => {
-                let _gp: aw11::Aw11GlobalKey = match ser_dec(&_gp_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                let _sk: aw11::Aw11SecretKey = match ser_dec(&_sk_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                let _ct: aw11::Aw11Ciphertext = match ser_dec(&_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                _pt_option = aw11::decrypt(&_gp, &_sk, &_ct);
-            }
-            Scheme::BDABE => {
-                let _pk: bdabe::BdabePublicKey = match ser_dec(&_pk_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                let _sk: bdabe::BdabeUserKey = match ser_dec(&_sk_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                let _ct: bdabe::BdabeCiphertext = match ser_dec(&_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                _pt_option = bdabe::decrypt(&_sk, &_ct);
-            }
-            Scheme::MKE08 => {
-                let _pk: mke08::Mke08PublicKey = match ser_dec(&_gp_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                let _sk: mke08::Mke08UserKey = match ser_dec(&_sk_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                let _ct: mke08::Mke08Ciphertext = match ser_dec(&_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                _pt_option = mke08::decrypt(&_sk, &_ct);
-            }
-            Scheme::YCT14 => {
-                let _sk: yct14::Yct14AbeSecretKey = match ser_dec(&_sk_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                let _ct: yct14::Yct14AbeCiphertext = match ser_dec(&_file) {
-                    Ok(parsed) => parsed,
-                    Err(e) => return Err(e)
-                };
-                _pt_option = yct14::decrypt(&_sk, &_ct);
-            }
-        }
+        _pt_option = match _scheme {
+            Scheme::AC17CP => cp_decrypt::<ac17::Ac17Cp>(&_sk_file, &_file),
+            Scheme::AC17KP => kp_decrypt::<ac17::Ac17Kp>(&_sk_file, &_file),
+            Scheme::BSW => cp_decrypt::<bsw::Bsw>(&_sk_file, &_file),
+            Scheme::LSW => kp_decrypt::<lsw::Lsw>(&_sk_file, &_file),
+            Scheme::AW11 => ma_decrypt::<aw11::Aw11>(&_gp_file, &_sk_file, &_file),
+            Scheme::BDABE => ma_decrypt::<bdabe::Bdabe>(&_pk_file, &_sk_file, &_file),
+            Scheme::MKE08 => ma_decrypt::<mke08::Mke08>(&_gp_file, &_sk_file, &_file),
+            Scheme::YCT14 => kp_decrypt::<yct14::Yct14>(&_sk_file, &_file),
+        };
         match _pt_option {
             Err(e) => {
                 return Err(e);
@@ -1409,7 +1006,7 @@ fn main() {
                 out_file.pop();
                 out_file.pop();
 
-                write_from_vec(Path::new(&out_file), &_pt_u);
+                write_from_vec(Path::new(&out_file), &_pt_u)?;
             }
         }
         Ok(())
@@ -1474,8 +1071,8 @@ fn main() {
                         Ok(_a_pk) => {
                             write_file(
                                 Path::new(&_pka_file),
-                                ser_enc(_a_pk, PKA_BEGIN, PKA_END)
-                            );
+                                _a_pk.to_pem()?
+                            )?;
                         },
                         Err(e) => return Err(e)
                     }
@@ -1493,8 +1090,8 @@ fn main() {
                         Ok(_a_pk) => {
                             write_file(
                                 Path::new(&_pka_file),
-                                ser_enc(_a_pk, PKA_BEGIN, PKA_END)
-                            );
+                                _a_pk.to_pem()?
+                            )?;
                         },
                         Err(e) => return Err(e)
                     }
@@ -1576,8 +1173,8 @@ fn main() {
                         Ok(_a_sk) => {
                             write_file(
                                 Path::new(&_ask_file),
-                                ser_enc(_a_sk, SKA_BEGIN, SKA_END),
-                            );
+                                _a_sk.to_pem()?,
+                            )?;
                         },
                         Err(e) => return Err(e)
                     }
@@ -1599,10 +1196,10 @@ fn main() {
                         Ok(_a_sk) => {
                             write_file(
                                 Path::new(&_ask_file),
-                                ser_enc(_a_sk, SKA_BEGIN, SKA_END)
-                            );
+                                _a_sk.to_pem()?
+                            )?;
                         },
-                        Err(e) => println!("Error: {}", e.to_string())
+                        Err(e) => return Err(e)
                     }
                 }
                 _ => {
@@ -1620,65 +1217,158 @@ fn main() {
     }
 }
 
-#[cfg(feature = "borsh")]
-fn ser_enc<T: BorshSerialize>(input: T, head: &str, tail: &str) -> String {
-    use deflate::deflate_bytes;
-    let mut buffer: Vec<u8> = Vec::new();
-    input.serialize(&mut buffer).unwrap();
-    [
-        head.to_string(),
-        deflate_bytes(
-                &buffer
-        ).to_hex(),
-        tail.to_string()
-    ].concat()
+fn ser_dec<T: Container>(file_name: &String) -> Result<T, RabeError> {
+    T::from_pem(&read_file(Path::new(file_name))?)
 }
 
-#[cfg(feature = "serde")]
-fn ser_enc<T: Serialize>(input: T, head: &str, tail: &str) -> String {
-    use deflate::deflate_bytes;
-    [
-        head.to_string(),
-        deflate_bytes(
-            &to_vec_packed(&input).unwrap()
-        )
-            .to_hex(),
-        tail.to_string()
-    ].concat()
-}
 
-#[cfg(feature = "borsh")]
-fn ser_dec<T: BorshDeserialize>(file_name: &String) -> Result<T, RabeError> {
-    match ser_dec_bin(file_name) {
-        Ok(parsed_bin) => match T::try_from_slice(&parsed_bin) {
-            Ok(parsed) => Ok(parsed),
-            Err(e) => return Err(RabeError::new(&format!("try_from_slice: {}", e.to_string().as_str())))
-        },
-        Err(e) => return Err(RabeError::new(&format!("ser_dec_bin: {}", e.to_string().as_str())))
+fn single_pk_file(pk_files: &[String]) -> Result<&String, RabeError> {
+    match pk_files {
+        [pk_file] => Ok(pk_file),
+        _ => Err(RabeError::new(
+            "sorry, encryption using this scheme with zero or multiple PKs is not possible. ",
+        ))
     }
 }
 
-#[cfg(feature = "serde")]
-fn ser_dec<T: DeserializeOwned>(file_name: &String) -> Result<T, RabeError> {
-    match ser_dec_bin(file_name) {
-        Ok(parsed_bin) => match from_slice(&parsed_bin) {
-            Ok(parsed_res) => Ok(parsed_res),
-            Err(e) => return Err(RabeError::new(&format!("from_slice: {}", e.to_string().as_str())))
-        },
-        Err(e) => return Err(RabeError::new(&format!("ser_dec_bin: {}", e.to_string().as_str())))
-    }
+fn write_setup<PK: Container, MSK: Container>(
+    pk: PK,
+    msk: MSK,
+    pk_file: &String,
+    msk_file: &String
+) -> Result<(), RabeError> {
+    write_file(
+        Path::new(msk_file),
+        msk.to_pem()?
+    )?;
+    write_file(
+        Path::new(pk_file),
+        pk.to_pem()?
+    )?;
+    Ok(())
 }
 
-fn ser_dec_bin(file_name: &String) -> Result<Vec<u8>, RabeError> {
-    use inflate::inflate_bytes;
-    let string = read_raw(&read_file(Path::new(file_name)));
-    match string.from_hex::<Vec<u8>>() {
-        Ok(byte_slice) => {
-            match inflate_bytes(&byte_slice) {
-                Ok(bytes) => Ok(bytes),
-                Err(e) => return Err(RabeError::new(&format!("inflate_bytes: {}", e.to_string().as_str())))
-            }
-        },
-        Err(e) => return Err(RabeError::new(&format!("read_raw: {}", e.to_string().as_str())))
+fn cp_setup<S: CpAbe>(pk_file: &String, msk_file: &String) -> Result<(), RabeError>
+    where S::PublicKey: Container, S::MasterKey: Container {
+    let (_pk, _msk) = S::setup()?;
+    write_setup(_pk, _msk, pk_file, msk_file)
+}
+
+fn kp_setup<S: KpAbe>(pk_file: &String, msk_file: &String, attributes: &[&str]) -> Result<(), RabeError>
+    where S::PublicKey: Container, S::MasterKey: Container {
+    let (_pk, _msk) = S::setup(attributes)?;
+    write_setup(_pk, _msk, pk_file, msk_file)
+}
+
+fn cp_keygen<S: CpAbe>(
+    pk_file: &String,
+    msk_file: &String,
+    sk_file: &String,
+    attributes: &[&str]
+) -> Result<(), RabeError>
+    where S::PublicKey: Container, S::MasterKey: Container, S::SecretKey: Container {
+    let _pk: S::PublicKey = ser_dec(pk_file)?;
+    let _msk: S::MasterKey = ser_dec(msk_file)?;
+    let _sk = S::keygen(&_pk, &_msk, attributes)?;
+    write_file(
+        Path::new(sk_file),
+        _sk.to_pem()?
+    )?;
+    Ok(())
+}
+
+fn kp_keygen<S: KpAbe>(
+    pk_file: &String,
+    msk_file: &String,
+    sk_file: &String,
+    policy: &str,
+    lang: PolicyLanguage
+) -> Result<(), RabeError>
+    where S::PublicKey: Container, S::MasterKey: Container, S::SecretKey: Container {
+    let _pk: S::PublicKey = ser_dec(pk_file)?;
+    let _msk: S::MasterKey = ser_dec(msk_file)?;
+    let _sk = S::keygen(&_pk, &_msk, (policy, lang))?;
+    write_file(
+        Path::new(sk_file),
+        _sk.to_pem()?
+    )?;
+    Ok(())
+}
+
+fn cp_encrypt<S: CpAbe>(
+    pk_files: &[String],
+    policy: &str,
+    lang: PolicyLanguage,
+    plaintext: &[u8],
+    ct_file: &String
+) -> Result<(), RabeError>
+    where S::PublicKey: Container, S::Ciphertext: Container {
+    let _pk: S::PublicKey = ser_dec(single_pk_file(pk_files)?)?;
+    let _ct = S::encrypt(&_pk, (policy, lang), plaintext)?;
+    write_file(
+        Path::new(ct_file),
+        _ct.to_pem()?
+    )?;
+    Ok(())
+}
+
+fn kp_encrypt<S: KpAbe>(
+    pk_files: &[String],
+    attributes: &[&str],
+    plaintext: &[u8],
+    ct_file: &String
+) -> Result<(), RabeError>
+    where S::PublicKey: Container, S::Ciphertext: Container {
+    let _pk: S::PublicKey = ser_dec(single_pk_file(pk_files)?)?;
+    let _ct = S::encrypt(&_pk, attributes, plaintext)?;
+    write_file(
+        Path::new(ct_file),
+        _ct.to_pem()?
+    )?;
+    Ok(())
+}
+
+fn ma_encrypt<S: MultiAuthorityAbe>(
+    gk_file: &String,
+    attr_pk_files: &[String],
+    policy: &str,
+    lang: PolicyLanguage,
+    plaintext: &[u8],
+    ct_file: &String
+) -> Result<(), RabeError>
+    where S::GlobalKey: Container, S::AttributePublicKey: Container, S::Ciphertext: Container {
+    let _gk: S::GlobalKey = ser_dec(gk_file)?;
+    let mut _attr_pks: Vec<S::AttributePublicKey> = Vec::new();
+    for filename in attr_pk_files {
+        _attr_pks.push(ser_dec(filename)?);
     }
-}
\ No newline at end of file
+    let attr_pks: Vec<&S::AttributePublicKey> = _attr_pks.iter().collect();
+    let _ct = S::encrypt(&_gk, &attr_pks, (policy, lang), plaintext)?;
+    write_file(
+        Path::new(ct_file),
+        _ct.to_pem()?
+    )?;
+    Ok(())
+}
+
+fn cp_decrypt<S: CpAbe>(sk_file: &String, ct_file: &String) -> Result<Vec<u8>, RabeError>
+    where S::SecretKey: Container, S::Ciphertext: Container {
+    let _sk: S::SecretKey = ser_dec(sk_file)?;
+    let _ct: S::Ciphertext = ser_dec(ct_file)?;
+    S::decrypt(&_sk, &_ct)
+}
+
+fn kp_decrypt<S: KpAbe>(sk_file: &String, ct_file: &String) -> Result<Vec<u8>, RabeError>
+    where S::SecretKey: Container, S::Ciphertext: Container {
+    let _sk: S::SecretKey = ser_dec(sk_file)?;
+    let _ct: S::Ciphertext = ser_dec(ct_file)?;
+    S::decrypt(&_sk, &_ct)
+}
+
+fn ma_decrypt<S: MultiAuthorityAbe>(gk_file: &String, sk_file: &String, ct_file: &String) -> Result<Vec<u8>, RabeError>
+    where S::GlobalKey: Container, S::SecretKey: Container, S::Ciphertext: Container {
+    let _gk: S::GlobalKey = ser_dec(gk_file)?;
+    let _sk: S::SecretKey = ser_dec(sk_file)?;
+    let _ct: S::Ciphertext = ser_dec(ct_file)?;
+    S::decrypt(&_gk, &_sk, &_ct)
+}
@@ -2,80 +2,240 @@ use std::{fmt::{
     Display,
     Result,
     Formatter
-}, cmp};
+}, error::Error};
 use pest::error::{Error as PestError, LineColLocation};
 use utils::policy::pest::json::Rule as jsonRule;
 use utils::policy::pest::human::Rule as humanRule;
+use utils::policy::pest::PolicyLanguage;
 use std::array::TryFromSliceError;
+use std::io::Error as IoError;
+use std::sync::Arc;
 use rabe_bn::FieldError;
-#[cfg(feature = "serde")]
-use serde::{Serialize, Deserialize};
-#[cfg(feature = "borsh")]
-use borsh::{BorshSerialize, BorshDeserialize};
-
-#[derive(Clone, PartialEq, Debug)]
-#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub struct RabeError {
-    details: String,
+
+/// The error type of all rabe operations
+///
+/// Errors can be cloned and compared. Sources are shared with an [`Arc`] and compared by their message, as trait
+/// objects and I/O errors cannot be compared. Unlike the former string-only error, `RabeError` cannot be serialized
+/// with serde or borsh anymore, because its sources cannot be serialized; serialize its `Display` output instead.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum RabeError {
+    /// The policy could not be parsed
+    PolicyParse {
+        language: PolicyLanguage,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// The policy is well-formed, but cannot be used (e.g. it is empty or expands to too many terms)
+    InvalidPolicy(String),
+    /// The attributes do not satisfy the policy
+    PolicyNotSatisfied(String),
+    /// An attribute is unknown to a key, an authority or the attribute universe
+    UnknownAttribute(String),
+    /// A key is malformed or cannot be used for the requested operation
+    InvalidKey(String),
+    /// An argument is empty or otherwise invalid
+    InvalidInput(String),
+    /// An object could not be serialized or deserialized
+    Serialization(Box<SerializationError>),
+    /// The symmetric encryption of the data failed
+    SymmetricEncryption,
+    /// The symmetric ciphertext could not be decrypted: the key is wrong, or the ciphertext or its associated data were modified
+    SymmetricDecryption,
+    /// An I/O error
+    Io(Arc<IoError>),
+    /// Any other error
+    Other(String),
+}
+
+/// The details of a [`RabeError::Serialization`]
+#[derive(Clone, Debug)]
+pub struct SerializationError {
+    pub message: String,
+    pub source: Option<Arc<dyn Error + Send + Sync>>,
+}
+
+impl PartialEq for SerializationError {
+    fn eq(&self, other: &Self) -> bool {
+        self.message == other.message
+            && self.source.as_ref().map(ToString::to_string) == other.source.as_ref().map(ToString::to_string)
+    }
+}
+
+impl PartialEq for RabeError {
+    fn eq(&self, other: &Self) -> bool {
+        use self::RabeError::*;
+        match (self, other) {
+            (
+                PolicyParse { language, line, column, message },
+                PolicyParse { language: other_language, line: other_line, column: other_column, message: other_message },
+            ) => (language, line, column, message) == (other_language, other_line, other_column, other_message),
+            (InvalidPolicy(a), InvalidPolicy(b))
+            | (PolicyNotSatisfied(a), PolicyNotSatisfied(b))
+            | (UnknownAttribute(a), UnknownAttribute(b))
+            | (InvalidKey(a), InvalidKey(b))
+            | (InvalidInput(a), InvalidInput(b))
+            | (Other(a), Other(b)) => a == b,
+            (Serialization(a), Serialization(b)) => a == b,
+            (SymmetricEncryption, SymmetricEncryption) | (SymmetricDecryption, SymmetricDecryption) => true,
+            (Io(a), Io(b)) => a.kind() == b.kind() && a.to_string() == b.to_string(),
+            _ => false,
+        }
+    }
 }
 
 impl RabeError {
-    /// Creates a new Error
+    /// Creates a new generic Error
     pub fn new(msg: &str) -> RabeError {
-        RabeError { details: msg.to_string() }
+        RabeError::Other(msg.to_string())
+    }
+
+    /// Creates a new Serialization Error without a source
+    pub fn serialization(msg: &str) -> RabeError {
+        RabeError::Serialization(Box::new(SerializationError { message: msg.to_string(), source: None }))
+    }
+
+    /// Creates a new Serialization Error, caused by `source`
+    pub fn serialization_from<E: Error + Send + Sync + 'static>(msg: &str, source: E) -> RabeError {
+        RabeError::Serialization(Box::new(SerializationError {
+            message: format!("{}: {}", msg, source),
+            source: Some(Arc::new(source)),
+        }))
     }
 }
 
 impl Display for RabeError {
     fn fmt(&self, f: &mut Formatter) -> Result {
-        write!(f, "Error: {}", self.details)
+        match self {
+            RabeError::PolicyParse { language, line, column, message } => {
+                let language = match language {
+                    PolicyLanguage::JsonPolicy => "JSON",
+                    PolicyLanguage::HumanPolicy => "Human",
+                };
+                write!(f, "{} policy error in line {}, column {}: {}", language, line, column, message)
+            }
+            RabeError::InvalidPolicy(msg) => write!(f, "invalid policy: {}", msg),
+            RabeError::PolicyNotSatisfied(msg) => write!(f, "policy not satisfied: {}", msg),
+            RabeError::UnknownAttribute(attribute) => write!(f, "unknown attribute: {}", attribute),
+            RabeError::InvalidKey(msg) => write!(f, "invalid key: {}", msg),
+            RabeError::InvalidInput(msg) => write!(f, "invalid input: {}", msg),
+            RabeError::Serialization(error) => write!(f, "serialization error: {}", error.message),
+            RabeError::SymmetricEncryption => write!(f, "symmetric encryption failed"),
+            RabeError::SymmetricDecryption => write!(f, "symmetric decryption failed: wrong key or modified ciphertext"),
+            RabeError::Io(error) => write!(f, "I/O error: {}", error),
+            RabeError::Other(msg) => write!(f, "{}", msg),
+        }
     }
 }
 
+impl Error for RabeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RabeError::Serialization(error) => error.source.as_ref().map(|source| source.as_ref() as &(dyn Error + 'static)),
+            RabeError::Io(error) => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+fn policy_parse_error<R: pest::RuleType>(error: PestError<R>, language: PolicyLanguage) -> RabeError {
+    let (line, column) = match error.line_col {
+        LineColLocation::Pos(pos) => pos,
+        LineColLocation::Span(start, _) => start,
+    };
+    RabeError::PolicyParse { language, line, column, message: error.variant.message().to_string() }
+}
 
 impl From<PestError<jsonRule>> for RabeError {
     fn from(error: PestError<jsonRule>) -> Self {
-        let line = match error.line_col.to_owned() {
-            LineColLocation::Pos((line, _)) => line,
-            LineColLocation::Span((start_line, _), (end_line, _)) => cmp::max(start_line, end_line),
-        };
-        RabeError::new(
-            format!("Json Policy Error in line {}\n", line).as_ref()
-        )
+        policy_parse_error(error, PolicyLanguage::JsonPolicy)
     }
 }
 
 impl From<PestError<humanRule>> for RabeError {
     fn from(error: PestError<humanRule>) -> Self {
-        let line = match error.line_col.to_owned() {
-            LineColLocation::Pos((line, _)) => line,
-            LineColLocation::Span((start_line, _), (end_line, _)) => cmp::max(start_line, end_line),
-        };
-        RabeError::new(
-            format!("Json Policy Error in line {}\n", line).as_ref()
-        )
+        policy_parse_error(error, PolicyLanguage::HumanPolicy)
     }
 }
+
 impl From<FieldError> for RabeError {
     fn from(error: FieldError) -> Self {
-        // Aead's error is intentionally opaque, there is no more information in here
-        match error {
-            FieldError::InvalidSliceLength => RabeError::new("FieldError::InvalidSliceLength"),
-            FieldError::InvalidU512Encoding => RabeError::new("FieldError::InvalidU512Encoding"),
-            FieldError::NotMember => RabeError::new("FieldError::NotMember"),
-        }
+        // FieldError does not implement std::error::Error, so there is no source
+        RabeError::serialization(&format!("{:?}", error))
     }
 }
 
 impl From<TryFromSliceError> for RabeError {
-    fn from(_error: TryFromSliceError) -> Self {
-        RabeError::new(&_error.to_string())
+    fn from(error: TryFromSliceError) -> Self {
+        RabeError::serialization_from("invalid length", error)
     }
 }
 
 impl From<String> for RabeError {
-    fn from(_error: String) -> Self {
-        RabeError::new(_error.as_str())
+    fn from(error: String) -> Self {
+        RabeError::Other(error)
+    }
+}
+
+impl From<IoError> for RabeError {
+    fn from(error: IoError) -> Self {
+        // unwrap errors of the stream adapters, that had to be wrapped in an IoError
+        if error.get_ref().is_some_and(|inner| inner.is::<RabeError>()) {
+            if let Some(inner) = error.into_inner() {
+                if let Ok(inner) = inner.downcast::<RabeError>() {
+                    return *inner;
+                }
+            }
+            return RabeError::new("I/O error");
+        }
+        RabeError::Io(Arc::new(error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::policy::pest::parse;
+
+    #[test]
+    fn policy_parse() {
+        match parse(r#""A" and and "B""#, PolicyLanguage::HumanPolicy) {
+            Err(RabeError::PolicyParse { language, line, column, .. }) => {
+                assert_eq!(language, PolicyLanguage::HumanPolicy);
+                assert_eq!(line, 1);
+                assert_eq!(column, 9);
+            }
+            other => panic!("expected a PolicyParse error, got {:?}", other.err()),
+        }
+        match parse(r#"{"name": "and", "children": [}"#, PolicyLanguage::JsonPolicy) {
+            Err(error @ RabeError::PolicyParse { .. }) => {
+                assert!(error.to_string().starts_with("JSON policy error in line 1"));
+            }
+            other => panic!("expected a PolicyParse error, got {:?}", other.err()),
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn source() {
+        let error = RabeError::from(IoError::other("disk on fire"));
+        assert!(error.source().is_some());
+        assert_eq!(error.to_string(), "I/O error: disk on fire");
+        let error = RabeError::serialization_from("could not decode", IoError::other("eof"));
+        assert_eq!(error.source().map(|e| e.to_string()), Some(String::from("eof")));
+        assert!(RabeError::PolicyNotSatisfied(String::from("bsw/decrypt")).source().is_none());
+    }
+
+    #[test]
+    fn clone_and_compare() {
+        let error = RabeError::serialization_from("could not decode", IoError::other("eof"));
+        assert_eq!(error.clone(), error);
+        assert_ne!(error, RabeError::serialization("could not decode: eof"));
+        let error = RabeError::from(IoError::other("disk on fire"));
+        assert_eq!(error.clone(), error);
+        assert_eq!(error.clone().source().map(|e| e.to_string()), Some(String::from("disk on fire")));
+        assert_ne!(error, RabeError::from(IoError::other("disk is fine")));
+        assert_eq!(RabeError::InvalidKey(String::from("k")), RabeError::InvalidKey(String::from("k")));
+        assert_ne!(RabeError::InvalidKey(String::from("k")), RabeError::InvalidInput(String::from("k")));
+    }
+}
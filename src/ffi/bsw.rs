@@ -100,7 +100,7 @@ pub extern "C" fn rabe_bsw_encrypt(
     let _slice = unsafe { slice::from_raw_parts(pt, pt_len as usize) };
     let mut _data_vec = Vec::new();
     _data_vec.extend_from_slice(_slice);
-    let _res = encrypt(&(_ctx._pk), &pol_tmp, &_data_vec, PolicyLanguage::JsonPolicy);
+    let _res = encrypt(&(_ctx._pk), (&pol_tmp, PolicyLanguage::JsonPolicy), &_data_vec);
     if let None = _res {
         return -1;
     }
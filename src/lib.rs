@@ -10,20 +10,33 @@
 extern crate borsh;
 #[cfg(feature = "serde")]
 extern crate serde;
+#[cfg(feature = "serde")]
+extern crate serde_cbor;
+#[cfg(feature = "parallel")]
+extern crate rayon;
 
 extern crate rabe_bn;
 extern crate rand;
 extern crate pest;
 extern crate aes_gcm;
+extern crate aes_gcm_siv;
+extern crate chacha20poly1305;
+extern crate base64;
+extern crate hkdf;
 extern crate sha3;
+extern crate subtle;
+extern crate zeroize;
 #[macro_use]
 extern crate pest_derive;
 extern crate core;
+#[cfg(test)]
+extern crate rand_chacha;
 
-/// rabe schemes
-pub mod schemes;
 /// rabe library utilities
+#[macro_use]
 pub mod utils;
+/// rabe schemes
+pub mod schemes;
 /// rabe error, that is used in the library
 pub mod error;
 
@@ -19,7 +19,7 @@
 //! let plaintext = String::from("our plaintext!").into_bytes();
 //! let policy = String::from(r#""A" and "B""#);
 //! let ct: Ac17KpCiphertext =  kp_encrypt(&pk, &vec!["A","B"], &plaintext).unwrap();
-//! let sk: Ac17KpSecretKey = kp_keygen(&msk, &policy, PolicyLanguage::HumanPolicy).unwrap();
+//! let sk: Ac17KpSecretKey = kp_keygen(&msk, (&policy, PolicyLanguage::HumanPolicy)).unwrap();
 //! assert_eq!(kp_decrypt(&sk, &ct).unwrap(), plaintext);
 //! ```
 //!
@@ -31,30 +31,53 @@
 //! let (pk, msk) = setup();
 //! let plaintext = String::from("our plaintext!").into_bytes();
 //! let policy = String::from(r#""A" and "B""#);
-//! let ct: Ac17CpCiphertext =  cp_encrypt(&pk, &policy, &plaintext, PolicyLanguage::HumanPolicy).unwrap();
+//! let ct: Ac17CpCiphertext =  cp_encrypt(&pk, (&policy, PolicyLanguage::HumanPolicy), &plaintext).unwrap();
 //! let sk: Ac17CpSecretKey = cp_keygen(&msk, &vec!["A","B"]).unwrap();
 //! assert_eq!(cp_decrypt(&sk, &ct).unwrap(), plaintext);
 //! ```
+//!
+//! An AC17 CP-ABE Key Encapsulation Example, where the SharedKey can be used with any DEM:
+//!
+//! ```
+//! use rabe::schemes::ac17::*;
+//! use rabe::utils::policy::pest::PolicyLanguage;
+//! let (pk, msk) = setup();
+//! let policy = String::from(r#""A" and "B""#);
+//! let (key, header) = cp_encapsulate(&pk, (&policy, PolicyLanguage::HumanPolicy)).unwrap();
+//! let sk: Ac17CpSecretKey = cp_keygen(&msk, &vec!["A","B"]).unwrap();
+//! assert_eq!(cp_decapsulate(&sk, &header).unwrap(), key);
+//! ```
 use std::{
     string::String,
-    ops::Neg
+    ops::Neg,
+    borrow::Cow
 };
 use rabe_bn::{Group, Gt, G1, G2, Fr, pairing};
-use rand::Rng;
+use rand::{CryptoRng, Rng, RngCore};
 use utils::{
     policy::msp::AbePolicy,
-    tools::*,
-    secretsharing::*,
+    secretsharing::{calc_pruned_minimal, LeafWeights},
+    pairing::multi_pairing,
     aes::*,
-    hash::sha3_hash
+    hash::{hash_to_g1, HashInput},
+    fixed_base::FixedBase,
+    parallel,
+    container::{Container, SchemeId, ObjectType},
 };
-use utils::policy::pest::{PolicyLanguage, parse, PolicyType};
+use utils::policy::pest::{PolicyLanguage, PolicyValue, parse, check_monotone};
+use utils::policy::explain::not_satisfied;
+use utils::policy::ast::PolicySource;
 use crate::error::RabeError;
+use schemes::traits::{CpAbe, KpAbe};
+use schemes::options::EncryptOptions;
 #[cfg(feature = "serde")]
 use serde::{Serialize, Deserialize};
 #[cfg(feature = "borsh")]
 use borsh::{BorshSerialize, BorshDeserialize};
 
+/// The decryption cost of the leaves of a policy: one multiplication in G1 per row of the msp
+const LEAF_WEIGHTS: LeafWeights = LeafWeights { attribute: 1, negated: 1 };
+
 /// An AC17 Public Key (PK)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -65,6 +88,60 @@ pub struct Ac17PublicKey {
     pub e_gh_ka: Vec<Gt>,
 }
 
+validate_fields!(Ac17PublicKey { g, h_a, e_gh_ka });
+
+impl Container for Ac17PublicKey {
+    const SCHEME: SchemeId = SchemeId::Ac17;
+    const OBJECT: ObjectType = ObjectType::PublicKey;
+}
+
+/// An AC17 Public Key (PK) with precomputed tables for all bases that are exponentiated during encryption, which
+/// speeds up encryption if the key is reused for many encryptions
+#[derive(Clone, PartialEq, Debug)]
+pub struct Ac17PreparedPublicKey {
+    pub pk: Ac17PublicKey,
+    h_a: Vec<FixedBase<G2>>,
+    e_gh_ka: Vec<FixedBase<Gt>>,
+}
+
+impl Ac17PreparedPublicKey {
+    /// Precomputes the tables of the given Ac17PublicKey
+    pub fn new(pk: &Ac17PublicKey) -> Ac17PreparedPublicKey {
+        Ac17PreparedPublicKey {
+            pk: pk.clone(),
+            h_a: pk.h_a.iter().map(|h| FixedBase::prepare(*h)).collect(),
+            e_gh_ka: pk.e_gh_ka.iter().map(|e| FixedBase::prepare(*e)).collect(),
+        }
+    }
+
+    // the bases of the given key without tables
+    fn unprepared(pk: &Ac17PublicKey) -> Ac17PreparedPublicKey {
+        Ac17PreparedPublicKey {
+            pk: pk.clone(),
+            h_a: pk.h_a.iter().map(|h| FixedBase::new(*h)).collect(),
+            e_gh_ka: pk.e_gh_ka.iter().map(|e| FixedBase::new(*e)).collect(),
+        }
+    }
+}
+
+/// A key that encrypts: an Ac17PublicKey or an Ac17PreparedPublicKey
+pub trait Ac17EncryptionKey {
+    /// Returns the bases of the key, with tables if the key is prepared
+    fn prepared(&self) -> Cow<'_, Ac17PreparedPublicKey>;
+}
+
+impl Ac17EncryptionKey for Ac17PublicKey {
+    fn prepared(&self) -> Cow<'_, Ac17PreparedPublicKey> {
+        Cow::Owned(Ac17PreparedPublicKey::unprepared(self))
+    }
+}
+
+impl Ac17EncryptionKey for Ac17PreparedPublicKey {
+    fn prepared(&self) -> Cow<'_, Ac17PreparedPublicKey> {
+        Cow::Borrowed(self)
+    }
+}
+
 /// An AC17 Public Key (MK)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -77,33 +154,99 @@ pub struct Ac17MasterKey {
     pub b: Vec<Fr>,
 }
 
-/// An AC17 Ciphertext (CT)
+validate_fields!(Ac17MasterKey { g, h, g_k, a, b });
+
+impl Container for Ac17MasterKey {
+    const SCHEME: SchemeId = SchemeId::Ac17;
+    const OBJECT: ObjectType = ObjectType::MasterKey;
+}
+
+/// An AC17 CP-ABE Header, i.e. the encapsulated key under a policy
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub struct Ac17Ciphertext {
+pub struct Ac17CpHeader {
+    pub policy: (String, PolicyLanguage),
     pub c_0: Vec<G2>,
     pub c: Vec<(String, Vec<G1>)>,
     pub c_p: Gt,
-    pub ct: Vec<u8>,
+    pub cipher: SymmetricCipher,
+}
+
+validate_fields!(Ac17CpHeader { policy, c_0, c, c_p, cipher });
+
+impl Container for Ac17CpHeader {
+    const SCHEME: SchemeId = SchemeId::Ac17Cp;
+    const OBJECT: ObjectType = ObjectType::Header;
+}
+
+impl Ac17CpHeader {
+    /// Returns the associated data that binds this header to the symmetrically encrypted data:
+    /// the scheme id, the format version, the policy and the caller supplied `aad`.
+    pub fn associated_data(&self, aad: &[u8]) -> Vec<u8> {
+        let language = [self.policy.1 as u8];
+        associated_data("AC17CP", &[self.policy.0.as_bytes(), &language], aad)
+    }
+}
+
+/// An AC17 KP-ABE Header, i.e. the encapsulated key under a set of attributes
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Ac17KpHeader {
+    pub attr: Vec<String>,
+    pub c_0: Vec<G2>,
+    pub c: Vec<(String, Vec<G1>)>,
+    pub c_p: Gt,
+    pub cipher: SymmetricCipher,
+}
+
+validate_fields!(Ac17KpHeader { attr, c_0, c, c_p, cipher });
+
+impl Container for Ac17KpHeader {
+    const SCHEME: SchemeId = SchemeId::Ac17Kp;
+    const OBJECT: ObjectType = ObjectType::Header;
+}
+
+impl Ac17KpHeader {
+    /// Returns the associated data that binds this header to the symmetrically encrypted data:
+    /// the scheme id, the format version, the attributes and the caller supplied `aad`.
+    pub fn associated_data(&self, aad: &[u8]) -> Vec<u8> {
+        let attributes: Vec<&[u8]> = self.attr.iter().map(|a| a.as_bytes()).collect();
+        associated_data("AC17KP", &attributes, aad)
+    }
 }
 
-/// An AC17 CP-ABE Ciphertext (CT), composed of a policy and an Ac17Ciphertext.
+/// An AC17 CP-ABE Ciphertext (CT), composed of an Ac17CpHeader and the symmetrically encrypted data.
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Ac17CpCiphertext {
-    pub policy: (String, PolicyLanguage),
-    pub ct: Ac17Ciphertext,
+    pub header: Ac17CpHeader,
+    pub ct: Vec<u8>,
+}
+
+validate_fields!(Ac17CpCiphertext { header, ct });
+
+impl Container for Ac17CpCiphertext {
+    const SCHEME: SchemeId = SchemeId::Ac17Cp;
+    const OBJECT: ObjectType = ObjectType::Ciphertext;
 }
 
-/// An AC17 KP-ABE Ciphertext (CT), composed of a set of attributes and an Ac17Ciphertext.
+/// An AC17 KP-ABE Ciphertext (CT), composed of an Ac17KpHeader and the symmetrically encrypted data.
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Ac17KpCiphertext {
-    pub attr: Vec<String>,
-    pub ct: Ac17Ciphertext,
+    pub header: Ac17KpHeader,
+    pub ct: Vec<u8>,
+}
+
+validate_fields!(Ac17KpCiphertext { header, ct });
+
+impl Container for Ac17KpCiphertext {
+    const SCHEME: SchemeId = SchemeId::Ac17Kp;
+    const OBJECT: ObjectType = ObjectType::Ciphertext;
 }
 
 /// An AC17 Secret Key (SK)
@@ -116,6 +259,8 @@ pub struct Ac17SecretKey {
     pub k_p: Vec<G1>,
 }
 
+validate_fields!(Ac17SecretKey { k_0, k, k_p });
+
 /// An AC17 KP-ABE Secret Key (SK), composed of a policy and an Ac17Ciphertext.
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -125,6 +270,13 @@ pub struct Ac17KpSecretKey {
     pub sk: Ac17SecretKey,
 }
 
+validate_fields!(Ac17KpSecretKey { policy, sk });
+
+impl Container for Ac17KpSecretKey {
+    const SCHEME: SchemeId = SchemeId::Ac17Kp;
+    const OBJECT: ObjectType = ObjectType::SecretKey;
+}
+
 /// An AC17 CP-ABE Secret Key (SK), composed of a set of attributes and an Ac17Ciphertext.
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -134,13 +286,72 @@ pub struct Ac17CpSecretKey {
     pub sk: Ac17SecretKey,
 }
 
+validate_fields!(Ac17CpSecretKey { attr, sk });
+
+impl Container for Ac17CpSecretKey {
+    const SCHEME: SchemeId = SchemeId::Ac17Cp;
+    const OBJECT: ObjectType = ObjectType::SecretKey;
+}
+
 /// The assumption size of the pairing in the AC17 scheme.
 const ASSUMPTION_SIZE: usize = 2;
 
+// Keys and headers may be deserialized from untrusted input, so the length of
+// every vector is checked before it is indexed.
+fn check_public_key(pk: &Ac17PublicKey) -> Result<(), RabeError> {
+    if pk.h_a.len() != ASSUMPTION_SIZE + 1 || pk.e_gh_ka.len() != ASSUMPTION_SIZE {
+        return Err(RabeError::InvalidKey(String::from("ac17: malformed public key")));
+    }
+    Ok(())
+}
+
+fn check_master_key(msk: &Ac17MasterKey) -> Result<(), RabeError> {
+    if msk.a.len() != ASSUMPTION_SIZE || msk.b.len() != ASSUMPTION_SIZE || msk.g_k.len() != ASSUMPTION_SIZE + 1 {
+        return Err(RabeError::InvalidKey(String::from("ac17: malformed master key")));
+    }
+    Ok(())
+}
+
+fn check_secret_key(sk: &Ac17SecretKey, with_k_p: bool) -> Result<(), RabeError> {
+    if sk.k_0.len() != ASSUMPTION_SIZE + 1
+        || (with_k_p && sk.k_p.len() != ASSUMPTION_SIZE + 1)
+        || sk.k.iter().any(|(_, k)| k.len() != ASSUMPTION_SIZE + 1) {
+        return Err(RabeError::InvalidKey(String::from("ac17: malformed secret key")));
+    }
+    Ok(())
+}
+
+fn check_header(c_0: &[G2], c: &[(String, Vec<G1>)]) -> Result<(), RabeError> {
+    if c_0.len() != ASSUMPTION_SIZE + 1 || c.iter().any(|(_, c)| c.len() != ASSUMPTION_SIZE + 1) {
+        return Err(RabeError::InvalidInput(String::from("ac17: malformed header")));
+    }
+    Ok(())
+}
+
+// the attributes of the cheapest satisfying set, so that only their rows are combined. All attributes, if the
+// policy is not satisfied.
+fn used_attributes(attributes: &Vec<String>, policy: &PolicyValue) -> Result<Vec<String>, RabeError> {
+    let (satisfied, pruned) = calc_pruned_minimal(attributes, policy, LEAF_WEIGHTS)?;
+    if !satisfied {
+        return Ok(attributes.clone());
+    }
+    let mut used: Vec<String> = pruned.into_iter().map(|(attribute, _)| attribute).collect();
+    used.sort();
+    used.dedup();
+    Ok(used)
+}
+
+fn inverse(a: Fr) -> Result<Fr, RabeError> {
+    a.inverse().ok_or_else(|| RabeError::InvalidKey(String::from("ac17: master key contains zero")))
+}
+
 /// The setup algorithm of both AC17CP and AC17KP. Generates an Ac17PublicKey and an Ac17MasterKey.
 pub fn setup() -> (Ac17PublicKey, Ac17MasterKey) {
-    // random number generator
-    let mut rng = rand::thread_rng();
+    setup_with_rng(&mut rand::thread_rng())
+}
+
+/// Like `setup()`, but draws all randomness from the given random number generator `rng`.
+pub fn setup_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> (Ac17PublicKey, Ac17MasterKey) {
     // generator of group G1: g and generator of group G2: h
     let g:G1 = rng.gen();
     let h:G2 = rng.gen();
@@ -191,14 +402,22 @@ pub fn setup() -> (Ac17PublicKey, Ac17MasterKey) {
 pub fn cp_keygen(
     msk: &Ac17MasterKey,
     attributes: &[&str]
+) -> Result<Ac17CpSecretKey, RabeError> {
+    cp_keygen_with_rng(msk, attributes, &mut rand::thread_rng())
+}
+
+/// Like `cp_keygen()`, but draws all randomness from the given random number generator `rng`.
+pub fn cp_keygen_with_rng<R: RngCore + CryptoRng>(
+    msk: &Ac17MasterKey,
+    attributes: &[&str],
+    rng: &mut R
 ) -> Result<Ac17CpSecretKey, RabeError> {
     // if no attibutes or an empty policy
     // maybe add empty msk also here
     if attributes.is_empty() {
-        return Err(RabeError::new("empty attributes!"));
+        return Err(RabeError::InvalidInput(String::from("ac17/keygen: attributes are empty")));
     }
-    // random number generator
-    let mut rng = rand::thread_rng();
+    check_master_key(msk)?;
     // pick randomness
     let mut r: Vec<Fr> = Vec::new();
     let mut sum = Fr::zero();
@@ -226,13 +445,10 @@ pub fn cp_keygen(
         let sigma_attr:Fr = rng.gen();
         for _t in 0usize..ASSUMPTION_SIZE {
             let mut prod = G1::zero();
-            let _a_t = a[_t].inverse().unwrap();
+            let _a_t = inverse(a[_t])?;
             for _l in 0usize..(ASSUMPTION_SIZE + 1) {
-                let mut _hash = String::new();
-                _hash.push_str(&attr);
-                _hash.push_str(&_l.to_string());
-                _hash.push_str(&_t.to_string());
-                prod = prod + (sha3_hash(msk.g, &_hash).expect("could not hash _hash") * (br[_l] * _a_t));
+                let _hash = HashInput::new("attribute").string(attr).index(_l).index(_t);
+                prod = prod + (hash_to_g1(SchemeId::Ac17, &_hash)? * (br[_l] * _a_t));
             }
             prod = prod + (msk.g * (sigma_attr * _a_t));
             key.push(prod);
@@ -246,13 +462,11 @@ pub fn cp_keygen(
     let _sigma:Fr = rng.gen();
     for _t in 0usize..ASSUMPTION_SIZE {
         let mut _prod = _g_k[_t];
-        let _a_t = a[_t].inverse().unwrap();
+        let _a_t = inverse(a[_t])?;
         for _l in 0usize..(ASSUMPTION_SIZE + 1) {
-            let mut _hash = String::new();
-            _hash.push_str(&String::from("01"));
-            _hash.push_str(&_l.to_string());
-            _hash.push_str(&_t.to_string());
-            _prod = _prod + (sha3_hash(msk.g, &_hash).expect("could not hash _hash") * (br[_l] * _a_t));
+            // the first column of the policy matrix
+            let _hash = HashInput::new("column").index(1).index(_l).index(_t);
+            _prod = _prod + (hash_to_g1(SchemeId::Ac17, &_hash)? * (br[_l] * _a_t));
         }
         _prod = _prod + (msk.g * (_sigma * _a_t));
         _k_p.push(_prod);
@@ -267,22 +481,61 @@ pub fn cp_keygen(
 ///
 /// # Arguments
 ///
-///	* `pk` - A Public Key (PK), generated by the function setup()
-///	* `policy` - An access policy given as JSON String
+///	* `pk` - A Public Key (PK), generated by the function setup(), or an Ac17PreparedPublicKey
+///	* `policy` - An access policy, given as text and its PolicyLanguage or as Policy, see [PolicySource]
 ///	* `plaintext` - plaintext data given as a Vector of u8
 ///
-pub fn cp_encrypt(
-    pk: &Ac17PublicKey,
-    policy: &str,
+pub fn cp_encrypt<K: Ac17EncryptionKey + ?Sized, P: PolicySource>(
+    pk: &K,
+    policy: P,
+    plaintext: &[u8]
+) -> Result<Ac17CpCiphertext, RabeError> {
+    cp_encrypt_with(pk, policy, plaintext, EncryptOptions::new())
+}
+
+/// Like `cp_encrypt()`, but with the given [EncryptOptions], i.e. the random number generator, the associated data
+/// that has to be passed to `cp_decrypt_with_aad()`, the symmetric cipher and the maximal reuse of an attribute.
+pub fn cp_encrypt_with<K: Ac17EncryptionKey + ?Sized, P: PolicySource, R: RngCore + CryptoRng>(
+    pk: &K,
+    policy: P,
     plaintext: &[u8],
-    language: PolicyLanguage,
+    mut options: EncryptOptions<R>
 ) -> Result<Ac17CpCiphertext, RabeError> {
-    // random number generator
-    let mut rng = rand::thread_rng();
-    match parse(policy, language) {
-        Ok(_policy) => {
+    let (key, header) = cp_encapsulate_with(pk, policy, options.by_ref())?;
+    //Encrypt plaintext using the encapsulated key, binding the header as associated data
+    let ct = header.cipher.encrypt(&key, plaintext, &header.associated_data(options.aad), &mut options.rng)?;
+    Ok(Ac17CpCiphertext { header, ct })
+}
+
+/// The key encapsulation algorithm of AC17CP. Generates a new SharedKey and an Ac17CpHeader that encapsulates it under an access policy given as String.
+///
+/// # Arguments
+///
+///	* `pk` - A Public Key (PK), generated by the function setup(), or an Ac17PreparedPublicKey
+///	* `policy` - An access policy, given as text and its PolicyLanguage or as Policy, see [PolicySource]
+///
+pub fn cp_encapsulate<K: Ac17EncryptionKey + ?Sized, P: PolicySource>(
+    pk: &K,
+    policy: P
+) -> Result<(SharedKey, Ac17CpHeader), RabeError> {
+    cp_encapsulate_with(pk, policy, EncryptOptions::new())
+}
+
+/// Like `cp_encapsulate()`, but with the given [EncryptOptions], i.e. the random number generator, the symmetric
+/// cipher that is recorded in the header and the maximal reuse of an attribute.
+pub fn cp_encapsulate_with<K: Ac17EncryptionKey + ?Sized, P: PolicySource, R: RngCore + CryptoRng>(
+    pk: &K,
+    policy: P,
+    mut options: EncryptOptions<R>
+) -> Result<(SharedKey, Ac17CpHeader), RabeError> {
+    let rng = &mut options.rng;
+    let pk = pk.prepared();
+    check_public_key(&pk.pk)?;
+    match policy.value() {
+        Ok((_policy, policy, language)) => {
+            check_monotone(&_policy, "ac17/cp_encapsulate")?;
             // an msp policy from the given String
-            let msp: AbePolicy = AbePolicy::from_policy(&_policy).unwrap();
+            let msp: AbePolicy = AbePolicy::from_policy_with_max_reuse(&_policy, options.max_reuse)?;
             let num_cols = msp.m[0].len();
             let num_rows = msp.m.len();
             // pick randomness
@@ -295,81 +548,52 @@ pub fn cp_encrypt(
             }
             // compute the [As]_2 term
             let mut c_0: Vec<G2> = Vec::new();
-            let h_a = pk.h_a.clone();
             for _i in 0usize..ASSUMPTION_SIZE {
-                c_0.push(h_a[_i.clone()] * s[_i]);
+                c_0.push(pk.h_a[_i].exp(s[_i]));
             }
-            c_0.push(h_a[ASSUMPTION_SIZE] * sum);
+            c_0.push(pk.h_a[ASSUMPTION_SIZE].exp(sum));
             // compute the [(V^T As||U^T_2 As||...) M^T_i + W^T_i As]_1 terms
             // pre-compute hashes
-            let mut _hash_table: Vec<Vec<Vec<G1>>> = Vec::new();
-            for _j in 0usize..num_cols {
+            let columns: Vec<usize> = (0..num_cols).collect();
+            let _hash_table: Vec<Vec<Vec<G1>>> = parallel::try_map(&columns, |&_j| {
                 let mut _x: Vec<Vec<G1>> = Vec::new();
-                let mut _hash1 = String::new();
-                _hash1.push_str(&String::from("0"));
-                _hash1.push_str(&(_j + 1).to_string());
                 for _l in 0usize..(ASSUMPTION_SIZE + 1) {
                     let mut _y: Vec<G1> = Vec::new();
-                    let mut _hash2 = String::new();
-                    _hash2.push_str(&_hash1);
-                    _hash2.push_str(&_l.to_string());
                     for _t in 0usize..ASSUMPTION_SIZE {
-                        let mut _hash3 = String::new();
-                        _hash3.push_str(&_hash2);
-                        _hash3.push_str(&_t.to_string());
-                        match sha3_hash(pk.g, &_hash3) {
-                            Ok(hashed) => _y.push(hashed),
-                            Err(e) => return Err(e)
-                        };
+                        let _hash = HashInput::new("column").index(_j + 1).index(_l).index(_t);
+                        _y.push(hash_to_g1(SchemeId::Ac17, &_hash)?);
                     }
                     _x.push(_y)
                 }
-                _hash_table.push(_x);
-            }
-            let mut c: Vec<(String, Vec<G1>)> = Vec::new();
-            for _i in 0usize..num_rows {
+                Ok(_x)
+            })?;
+            let rows: Vec<usize> = (0..num_rows).collect();
+            let c: Vec<(String, Vec<G1>)> = parallel::try_map(&rows, |&_i| {
                 let mut _ct: Vec<G1> = Vec::new();
                 for _l in 0usize..(ASSUMPTION_SIZE + 1) {
                     let mut _prod = G1::zero();
                     for _t in 0usize..ASSUMPTION_SIZE {
-                        let mut _hash = String::new();
-                        _hash.push_str(&msp.pi[_i]);
-                        _hash.push_str(&_l.to_string());
-                        _hash.push_str(&_t.to_string());
-                        match sha3_hash(pk.g, &_hash) {
-                            Ok(mut hash) => {
-                                for _j in 0usize..num_cols {
-                                    if msp.m[_i][_j] == 1 {
-                                        hash = hash + _hash_table[_j][_l][_t];
-                                    } else if msp.m[_i][_j] == -1 {
-                                        hash = hash - _hash_table[_j][_l][_t];
-                                    }
-                                }
-                                _prod = _prod + (hash * s[_t]);
-                            },
-                            Err(e) => return Err(e)
+                        let _hash = HashInput::new("attribute").string(&msp.pi[_i]).index(_l).index(_t);
+                        let mut hash = hash_to_g1(SchemeId::Ac17, &_hash)?;
+                        for _j in 0usize..num_cols {
+                            if !msp.m[_i][_j].is_zero() {
+                                hash = hash + _hash_table[_j][_l][_t] * msp.m[_i][_j];
+                            }
                         }
+                        _prod = _prod + (hash * s[_t]);
                     }
                     _ct.push(_prod);
                 }
-                c.push((msp.pi[_i].to_string(), _ct));
-            }
+                Ok((msp.pi[_i].to_string(), _ct))
+            })?;
             let mut c_p = Gt::one();
             for _i in 0usize..ASSUMPTION_SIZE {
-                c_p = c_p * (pk.e_gh_ka[_i].pow(s[_i]));
+                c_p = c_p * pk.e_gh_ka[_i].exp(s[_i]);
             }
             // random msg
             let msg: Gt = rng.gen();
-            //Encrypt plaintext using derived key from secret
-            match encrypt_symmetric(msg, &plaintext.to_vec()) {
-                Ok(ct) => {
-                    Ok(Ac17CpCiphertext {
-                        policy: (policy.to_string(), language),
-                        ct: Ac17Ciphertext { c_0, c, c_p: c_p * msg, ct },
-                    })
-                },
-                Err(e) => Err(e)
-            }
+            let header = Ac17CpHeader { policy: (policy.to_string(), language), c_0, c, c_p: c_p * msg, cipher: options.cipher };
+            Ok((SharedKey::derive_for(msg, &header)?, header))
         },
         Err(e) => Err(e)
     }
@@ -386,42 +610,60 @@ pub fn cp_decrypt(
     sk: &Ac17CpSecretKey,
     ct: &Ac17CpCiphertext
 ) -> Result<Vec<u8>, RabeError> {
-    match parse(ct.policy.0.as_ref(), ct.policy.1) {
+    cp_decrypt_with_aad(sk, ct, &[])
+}
+
+/// Like `cp_decrypt()`, but for ciphertexts that were generated by `cp_encrypt_with()` with the associated data `aad`.
+pub fn cp_decrypt_with_aad(
+    sk: &Ac17CpSecretKey,
+    ct: &Ac17CpCiphertext,
+    aad: &[u8]
+) -> Result<Vec<u8>, RabeError> {
+    let key = cp_decapsulate(sk, &ct.header)?;
+    // Decrypt plaintext using the key recovered from the cp-abe scheme
+    ct.header.cipher.decrypt(&key, &ct.ct, &ct.header.associated_data(aad))
+}
+
+/// The key decapsulation algorithm of AC17CP. Recovers the SharedKey of an Ac17CpHeader with a matching Ac17CpSecretKey.
+///
+/// # Arguments
+///
+///	* `sk` - A Secret Key (SK), generated by the function cp_keygen()
+///	* `header` - An AC17CP Header
+///
+pub fn cp_decapsulate(
+    sk: &Ac17CpSecretKey,
+    header: &Ac17CpHeader
+) -> Result<SharedKey, RabeError> {
+    check_secret_key(&sk.sk, true)?;
+    check_header(&header.c_0, &header.c)?;
+    match parse(header.policy.0.as_ref(), header.policy.1) {
         Ok(pol) => {
-            return if traverse_policy(&sk.attr, &pol, PolicyType::Leaf) == false {
-                Err(RabeError::new("Error in cp_decrypt: attributes in SK do not match policy in CT."))
-            } else {
-                match calc_pruned(&sk.attr, &pol, None) {
-                    Err(e) => Err(e),
-                    Ok((_match, _list)) => {
-                        if _match {
-                            let mut _prod1_gt = Gt::one();
-                            let mut _prod2_gt = Gt::one();
-                            for _i in 0usize..(ASSUMPTION_SIZE + 1) {
-                                let mut _prod_h = G1::zero();
-                                let mut _prod_g = G1::zero();
-                                for _current in _list.iter() {
-                                    for _attr in ct.ct.c.iter() {
-                                        if _attr.0 == _current.0.to_string() {
-                                            _prod_g = _prod_g + _attr.1[_i];
-                                        }
-                                    }
-                                    for _attr in sk.sk.k.iter() {
-                                        if _attr.0 == _current.0.to_string() {
-                                            _prod_h = _prod_h + _attr.1[_i];
-                                        }
-                                    }
-                                }
-                                _prod1_gt = _prod1_gt * pairing(sk.sk.k_p[_i] + _prod_h, ct.ct.c_0[_i]);
-                                _prod2_gt = _prod2_gt * pairing(_prod_g, sk.sk.k_0[_i]);
-                            }
-                            let _msg = ct.ct.c_p * (_prod2_gt * _prod1_gt.inverse());
-                            // Decrypt plaintext using derived secret from cp-abe scheme
-                            decrypt_symmetric(_msg, &ct.ct.ct)
-                        } else {
-                            Err(RabeError::new("Error: attributes in sk do not match policy in ct."))
+            let msp: AbePolicy = AbePolicy::from_policy(&pol)?;
+            if header.c.len() != msp.pi.len() || header.c.iter().zip(msp.pi.iter()).any(|(c, pi)| c.0 != *pi) {
+                return Err(RabeError::InvalidInput(String::from("ac17/cp_decapsulate: header does not match its policy")));
+            }
+            // attributes may occur in several rows, so the rows are combined with the coefficients of the msp
+            return match msp.reconstruction(&used_attributes(&sk.attr, &pol)?) {
+                None => Err(not_satisfied("ac17/cp_decapsulate: attributes in sk do not match policy in ct", &sk.attr, &pol)),
+                Some(_coefficients) => {
+                    let mut _pairs: Vec<(G1, G2)> = Vec::new();
+                    for _i in 0usize..(ASSUMPTION_SIZE + 1) {
+                        let mut _prod_h = G1::zero();
+                        let mut _prod_g = G1::zero();
+                        for (_row, _coeff) in _coefficients.iter().enumerate().filter(|(_, c)| !c.is_zero()) {
+                            let _k = sk.sk.k
+                                .iter()
+                                .find(|k| k.0 == msp.pi[_row])
+                                .ok_or_else(|| RabeError::InvalidKey(format!("ac17/cp_decapsulate: no key for attribute {}", msp.pi[_row])))?;
+                            _prod_g = _prod_g + header.c[_row].1[_i] * *_coeff;
+                            _prod_h = _prod_h + _k.1[_i] * *_coeff;
                         }
+                        _pairs.push((_prod_g, sk.sk.k_0[_i]));
+                        _pairs.push((-(sk.sk.k_p[_i] + _prod_h), header.c_0[_i]));
                     }
+                    let _msg = header.c_p * multi_pairing(&_pairs);
+                    SharedKey::derive_for(_msg, header)
                 }
             };
         },
@@ -434,26 +676,44 @@ pub fn cp_decrypt(
 /// # Arguments
 ///
 ///	* `msk` - A Master Key (MSK), generated by the function setup()
-///	* `policy` - An access policy given as JSON String
+///	* `policy` - An access policy, given as text and its PolicyLanguage or as Policy, see [PolicySource]
 ///
-pub fn kp_keygen(
+pub fn kp_keygen<P: PolicySource>(
     msk: &Ac17MasterKey,
-    policy: &str,
-    lang: PolicyLanguage
+    policy: P
 ) -> Result<Ac17KpSecretKey, RabeError> {
-    // random number generator
-    let mut _rng = rand::thread_rng();
-    match parse(policy, lang) {
-        Ok(pol) => {
+    kp_keygen_with_rng(msk, policy, &mut rand::thread_rng())
+}
+
+/// Like `kp_keygen()`, but draws all randomness from the given random number generator `rng`.
+pub fn kp_keygen_with_rng<P: PolicySource, R: RngCore + CryptoRng>(
+    msk: &Ac17MasterKey,
+    policy: P,
+    rng: &mut R
+) -> Result<Ac17KpSecretKey, RabeError> {
+    kp_keygen_with_max_reuse(msk, policy, usize::MAX, rng)
+}
+
+/// Like `kp_keygen_with_rng()`, but rejects policies in which an attribute is used more than `max_reuse` times.
+pub fn kp_keygen_with_max_reuse<P: PolicySource, R: RngCore + CryptoRng>(
+    msk: &Ac17MasterKey,
+    policy: P,
+    max_reuse: usize,
+    rng: &mut R
+) -> Result<Ac17KpSecretKey, RabeError> {
+    check_master_key(msk)?;
+    match policy.value() {
+        Ok((pol, policy, lang)) => {
+            check_monotone(&pol, "ac17/kp_keygen")?;
             // an msp policy from the given String
-            let msp: AbePolicy = AbePolicy::from_policy(&pol).unwrap();
+            let msp: AbePolicy = AbePolicy::from_policy_with_max_reuse(&pol, max_reuse)?;
             let _num_cols = msp.m[0].len();
             let _num_rows = msp.m.len();
             // pick randomness
             let mut _r: Vec<Fr> = Vec::new();
             let mut _sum = Fr::zero();
             for _i in 0usize..ASSUMPTION_SIZE {
-                let _rand:Fr = _rng.gen();
+                let _rand:Fr = rng.gen();
                 _r.push(_rand);
                 _sum = _sum + _rand;
             }
@@ -470,7 +730,7 @@ pub fn kp_keygen(
             }
             let mut _sigma_prime: Vec<Fr> = Vec::new();
             for _i in 0usize..(_num_cols - 1) {
-                _sigma_prime.push(_rng.gen())
+                _sigma_prime.push(rng.gen())
             }
             // compute [W_1 Br]_1, ...
             let mut _k: Vec<(String, Vec<G1>)> = Vec::new();
@@ -478,59 +738,42 @@ pub fn kp_keygen(
             let _g = msk.g.clone();
             for _i in 0usize.._num_rows {
                 let mut _key: Vec<G1> = Vec::new();
-                let _sigma_attr:Fr = _rng.gen();
+                let _sigma_attr:Fr = rng.gen();
                 // calculate _sk_i1 and _sk_i2 terms
                 for _t in 0usize..ASSUMPTION_SIZE {
                     let mut _prod = G1::zero();
-                    let _a_t = _a[_t].inverse().unwrap();
+                    let _a_t = inverse(_a[_t])?;
                     for _l in 0usize..(ASSUMPTION_SIZE + 1) {
-                        let mut _hash = String::new();
-                        _hash.push_str(&msp.pi[_i]);
-                        _hash.push_str(&_l.to_string());
-                        _hash.push_str(&_t.to_string());
-                        _prod = _prod + (sha3_hash(msk.g, &_hash).expect("could not hash _hash") * (_br[_l] * _a_t));
+                        let _hash = HashInput::new("attribute").string(&msp.pi[_i]).index(_l).index(_t);
+                        _prod = _prod + (hash_to_g1(SchemeId::Ac17, &_hash)? * (_br[_l] * _a_t));
                     }
                     _prod = _prod + (msk.g * (_sigma_attr * _a_t));
-                    if msp.m[_i][0] == 1 {
-                        _prod = _prod + (msk.g_k[_t]);
-                    } else if msp.m[_i][0] == -1 {
-                        _prod = _prod - (msk.g_k[_t]);
+                    if !msp.m[_i][0].is_zero() {
+                        _prod = _prod + msk.g_k[_t] * msp.m[_i][0];
                     }
                     let mut _temp = G1::zero();
                     for _j in 1usize.._num_cols {
                         // sum term of _sk_it
-                        let mut _hash0 = String::new();
-                        _hash0.push_str(&String::from("0"));
-                        _hash0.push_str(&_j.to_string());
                         for _l in 0usize..(ASSUMPTION_SIZE + 1) {
-                            let mut _hash1 = String::new();
-                            _hash1.push_str(&_hash0);
-                            _hash1.push_str(&_l.to_string());
-                            _hash1.push_str(&_t.to_string());
-                            _temp = _temp + (sha3_hash(msk.g, &_hash1).expect("could not hash _hash") * (_br[_l] * _a_t));
+                            let _hash = HashInput::new("column").index(_j).index(_l).index(_t);
+                            _temp = _temp + (hash_to_g1(SchemeId::Ac17, &_hash)? * (_br[_l] * _a_t));
                         }
                         _temp = _temp + (msk.g * _sigma_prime[_j - 1].neg());
-                        if msp.m[_i][_j] == 1 {
-                            _prod = _prod + _temp;
-                        } else if msp.m[_i][_j] == -1 {
-                            _prod = _prod - _temp;
+                        if !msp.m[_i][_j].is_zero() {
+                            _prod = _prod + _temp * msp.m[_i][_j];
                         }
                     }
                     _key.push(_prod);
                 }
                 // calculate _sk_i3 term
                 let mut _sk_i3 = msk.g * _sigma_attr.neg();
-                if msp.m[_i][0] == 1 {
-                    _sk_i3 = _sk_i3 + (msk.g_k[ASSUMPTION_SIZE]);
-                } else if msp.m[_i][0] == -1 {
-                    _sk_i3 = _sk_i3 - (msk.g_k[ASSUMPTION_SIZE]);
+                if !msp.m[_i][0].is_zero() {
+                    _sk_i3 = _sk_i3 + msk.g_k[ASSUMPTION_SIZE] * msp.m[_i][0];
                 }
                 // sum term of _sk_i3
                 for _j in 1usize.._num_cols {
-                    if msp.m[_i][_j] == 1 {
-                        _sk_i3 = _sk_i3 + (msk.g * _sigma_prime[_j - 1].neg());
-                    } else if msp.m[_i][_j] == -1 {
-                        _sk_i3 = _sk_i3 - (msk.g * _sigma_prime[_j - 1].neg());
+                    if !msp.m[_i][_j].is_zero() {
+                        _sk_i3 = _sk_i3 + (msk.g * _sigma_prime[_j - 1].neg()) * msp.m[_i][_j];
                     }
                 }
                 _key.push(_sk_i3);
@@ -549,17 +792,56 @@ pub fn kp_keygen(
 ///
 /// # Arguments
 ///
-///	* `pk` - A Public Key (MSK), generated by the function setup()
+///	* `pk` - A Public Key (PK), generated by the function setup(), or an Ac17PreparedPublicKey
 ///	* `attributes` - A set of attributes given as Vec<String>
 ///	* `plaintext` - plaintext data given as a Vector of u8
 ///
-pub fn kp_encrypt(
-    pk: &Ac17PublicKey,
+pub fn kp_encrypt<K: Ac17EncryptionKey + ?Sized>(
+    pk: &K,
+    attributes: &[&str],
+    data: &[u8]
+) -> Result<Ac17KpCiphertext, RabeError> {
+    kp_encrypt_with(pk, attributes, data, EncryptOptions::new())
+}
+
+/// Like `kp_encrypt()`, but with the given [EncryptOptions], i.e. the random number generator, the associated data
+/// that has to be passed to `kp_decrypt_with_aad()` and the symmetric cipher.
+pub fn kp_encrypt_with<K: Ac17EncryptionKey + ?Sized, R: RngCore + CryptoRng>(
+    pk: &K,
     attributes: &[&str],
     data: &[u8],
+    mut options: EncryptOptions<R>
 ) -> Result<Ac17KpCiphertext, RabeError> {
-    // random number generator
-    let mut rng = rand::thread_rng();
+    let (key, header) = kp_encapsulate_with(pk, attributes, options.by_ref())?;
+    //Encrypt plaintext using the encapsulated key, binding the header as associated data
+    let ct = header.cipher.encrypt(&key, data, &header.associated_data(options.aad), &mut options.rng)?;
+    Ok(Ac17KpCiphertext { header, ct })
+}
+
+/// The key encapsulation algorithm of AC17KP. Generates a new SharedKey and an Ac17KpHeader that encapsulates it under a set of attributes.
+///
+/// # Arguments
+///
+///	* `pk` - A Public Key (PK), generated by the function setup(), or an Ac17PreparedPublicKey
+///	* `attributes` - A set of attributes given as Vec<String>
+///
+pub fn kp_encapsulate<K: Ac17EncryptionKey + ?Sized>(
+    pk: &K,
+    attributes: &[&str]
+) -> Result<(SharedKey, Ac17KpHeader), RabeError> {
+    kp_encapsulate_with(pk, attributes, EncryptOptions::new())
+}
+
+/// Like `kp_encapsulate()`, but with the given [EncryptOptions], i.e. the random number generator and the symmetric
+/// cipher that is recorded in the header.
+pub fn kp_encapsulate_with<K: Ac17EncryptionKey + ?Sized, R: RngCore + CryptoRng>(
+    pk: &K,
+    attributes: &[&str],
+    mut options: EncryptOptions<R>
+) -> Result<(SharedKey, Ac17KpHeader), RabeError> {
+    let rng = &mut options.rng;
+    let pk = pk.prepared();
+    check_public_key(&pk.pk)?;
     // pick randomness
     let mut s: Vec<Fr> = Vec::new();
     let mut sum = Fr::zero();
@@ -570,49 +852,31 @@ pub fn kp_encrypt(
     }
     // compute the [As]_2 term
     let mut c_0: Vec<G2> = Vec::new();
-    let h_a = pk.h_a.clone();
     for _i in 0usize..ASSUMPTION_SIZE {
-        c_0.push(h_a[_i.clone()] * s[_i]);
+        c_0.push(pk.h_a[_i].exp(s[_i]));
     }
-    c_0.push(h_a[ASSUMPTION_SIZE] * sum);
+    c_0.push(pk.h_a[ASSUMPTION_SIZE].exp(sum));
     // compute ct_y terms
-    let mut c: Vec<(String, Vec<G1>)> = Vec::new();
-    for _attr in attributes {
+    let c: Vec<(String, Vec<G1>)> = parallel::try_map(attributes, |_attr| {
         let mut _ct: Vec<G1> = Vec::new();
         for _l in 0usize..(ASSUMPTION_SIZE + 1) {
             let mut _prod = G1::zero();
             for _t in 0usize..ASSUMPTION_SIZE {
-                let mut _hash = String::new();
-                _hash.push_str(&_attr);
-                _hash.push_str(&_l.to_string());
-                _hash.push_str(&_t.to_string());
-                match sha3_hash(pk.g, &_hash) {
-                    Ok(hash) => {
-                        _prod = _prod + (hash * s[_t])
-                    },
-                    Err(e) => return Err(e)
-                };
+                let _hash = HashInput::new("attribute").string(_attr).index(_l).index(_t);
+                _prod = _prod + hash_to_g1(SchemeId::Ac17, &_hash)? * s[_t];
             }
             _ct.push(_prod);
         }
-        c.push((_attr.to_string(), _ct));
-    }
+        Ok((_attr.to_string(), _ct))
+    })?;
     let mut c_p = Gt::one();
     for _i in 0usize..ASSUMPTION_SIZE {
-        c_p = c_p * (pk.e_gh_ka[_i].pow(s[_i]));
+        c_p = c_p * pk.e_gh_ka[_i].exp(s[_i]);
     }
     // random msg
     let _msg: Gt = rng.gen();
-    //Encrypt plaintext using derived key from secret
-    match encrypt_symmetric(_msg, &data.to_vec()) {
-        Ok(ct) => {
-            Ok(Ac17KpCiphertext {
-                attr: attributes.iter().map(|a| a.to_string()).collect(),
-                ct: Ac17Ciphertext { c_0, c, c_p: c_p * _msg, ct },
-            })
-        },
-        Err(e) => Err(e)
-    }
+    let header = Ac17KpHeader { attr: attributes.iter().map(|a| a.to_string()).collect(), c_0, c, c_p: c_p * _msg, cipher: options.cipher };
+    Ok((SharedKey::derive_for(_msg, &header)?, header))
 }
 
 /// The decrypt algorithm of AC17KP. Reconstructs the original plaintext data as Vec<u8>, given a Ac17KpCiphertext with a matching Ac17KpSecretKey.
@@ -626,58 +890,187 @@ pub fn kp_decrypt(
     sk: &Ac17KpSecretKey,
     ct: &Ac17KpCiphertext
 ) -> Result<Vec<u8>, RabeError> {
+    kp_decrypt_with_aad(sk, ct, &[])
+}
+
+/// Like `kp_decrypt()`, but for ciphertexts that were generated by `kp_encrypt_with()` with the associated data `aad`.
+pub fn kp_decrypt_with_aad(
+    sk: &Ac17KpSecretKey,
+    ct: &Ac17KpCiphertext,
+    aad: &[u8]
+) -> Result<Vec<u8>, RabeError> {
+    let key = kp_decapsulate(sk, &ct.header)?;
+    // Decrypt plaintext using the key recovered from the kp-abe scheme
+    ct.header.cipher.decrypt(&key, &ct.ct, &ct.header.associated_data(aad))
+}
+
+/// The key decapsulation algorithm of AC17KP. Recovers the SharedKey of an Ac17KpHeader with a matching Ac17KpSecretKey.
+///
+/// # Arguments
+///
+///	* `sk` - A Secret Key (SK), generated by the function kp_keygen()
+///	* `header` - An AC17KP Header
+///
+pub fn kp_decapsulate(
+    sk: &Ac17KpSecretKey,
+    header: &Ac17KpHeader
+) -> Result<SharedKey, RabeError> {
+    check_secret_key(&sk.sk, false)?;
+    check_header(&header.c_0, &header.c)?;
     match parse(sk.policy.0.as_ref(), sk.policy.1) {
         Ok(pol) => {
-            return if traverse_policy(&ct.attr, &pol, PolicyType::Leaf) == false {
-                Err(RabeError::new("Error in kp_decrypt: attributes in ct do not match policy in sk."))
-            } else {
-                match calc_pruned(&ct.attr, &pol, None) {
-                    Err(e) => Err(e),
-                    Ok(_p) => {
-                        let (_match, _list) = _p;
-                        if _match {
-                            let mut _prod1_gt = Gt::one();
-                            let mut _prod2_gt = Gt::one();
-                            for _i in 0usize..(ASSUMPTION_SIZE + 1) {
-                                let mut _prod_h = G1::zero();
-                                let mut _prod_g = G1::zero();
-                                for _current in _list.iter() {
-                                    for _attr in ct.ct.c.iter() {
-                                        if _attr.0 == _current.0.to_string() {
-                                            _prod_g = _prod_g + _attr.1[_i];
-                                        }
-                                    }
-                                    for _attr in sk.sk.k.iter() {
-                                        if _attr.0 == _current.0.to_string() {
-                                            _prod_h = _prod_h + _attr.1[_i];
-                                        }
-                                    }
-                                }
-                                // for _j in 0usize..ct._ct._c.len() {
-                                //     _prod_h = _prod_h + sk._sk._k[_j].1[_i];
-                                //     _prod_g = _prod_g + ct._ct._c[_j].1[_i];
-                                // }
-                                _prod1_gt = _prod1_gt * pairing(_prod_h, ct.ct.c_0[_i]);
-                                _prod2_gt = _prod2_gt * pairing(_prod_g, sk.sk.k_0[_i]);
-                            }
-                            let _msg = ct.ct.c_p * (_prod2_gt * _prod1_gt.inverse());
-                            // Decrypt plaintext using derived secret from cp-abe scheme
-                            decrypt_symmetric(_msg, &ct.ct.ct)
-                        } else {
-                            Err(RabeError::new("Error in kp_decrypt: pruned attributes in sk do not match policy in ct."))
+            let msp: AbePolicy = AbePolicy::from_policy(&pol)?;
+            if sk.sk.k.len() != msp.pi.len() || sk.sk.k.iter().zip(msp.pi.iter()).any(|(k, pi)| k.0 != *pi) {
+                return Err(RabeError::InvalidKey(String::from("ac17/kp_decapsulate: secret key does not match its policy")));
+            }
+            // attributes may occur in several rows, so the rows are combined with the coefficients of the msp
+            return match msp.reconstruction(&used_attributes(&header.attr, &pol)?) {
+                None => Err(not_satisfied("ac17/kp_decapsulate: attributes in ct do not match policy in sk", &header.attr, &pol)),
+                Some(_coefficients) => {
+                    let mut _pairs: Vec<(G1, G2)> = Vec::new();
+                    for _i in 0usize..(ASSUMPTION_SIZE + 1) {
+                        let mut _prod_h = G1::zero();
+                        let mut _prod_g = G1::zero();
+                        for (_row, _coeff) in _coefficients.iter().enumerate().filter(|(_, c)| !c.is_zero()) {
+                            let _c = header.c
+                                .iter()
+                                .find(|c| c.0 == msp.pi[_row])
+                                .ok_or_else(|| RabeError::InvalidInput(format!("ac17/kp_decapsulate: header has no value for attribute {}", msp.pi[_row])))?;
+                            _prod_h = _prod_h + sk.sk.k[_row].1[_i] * *_coeff;
+                            _prod_g = _prod_g + _c.1[_i] * *_coeff;
                         }
+                        _pairs.push((_prod_g, sk.sk.k_0[_i]));
+                        _pairs.push((-_prod_h, header.c_0[_i]));
                     }
+                    let _msg = header.c_p * multi_pairing(&_pairs);
+                    SharedKey::derive_for(_msg, header)
                 }
-            }
+            };
         },
         Err(e) => Err(e)
     }
 }
 
+/// The AC17 CP-ABE scheme, to be used through the [`CpAbe`](../traits/trait.CpAbe.html) trait.
+pub struct Ac17Cp;
+
+impl CpAbe for Ac17Cp {
+    type PublicKey = Ac17PublicKey;
+    type MasterKey = Ac17MasterKey;
+    type SecretKey = Ac17CpSecretKey;
+    type Ciphertext = Ac17CpCiphertext;
+    type Header = Ac17CpHeader;
+
+    fn setup_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> Result<(Ac17PublicKey, Ac17MasterKey), RabeError> {
+        Ok(setup_with_rng(rng))
+    }
+
+    fn keygen_with_rng<R: RngCore + CryptoRng>(
+        _pk: &Ac17PublicKey,
+        msk: &Ac17MasterKey,
+        attributes: &[&str],
+        rng: &mut R
+    ) -> Result<Ac17CpSecretKey, RabeError> {
+        cp_keygen_with_rng(msk, attributes, rng)
+    }
+
+    fn encrypt_with<P: PolicySource, R: RngCore + CryptoRng>(
+        pk: &Ac17PublicKey,
+        policy: P,
+        plaintext: &[u8],
+        options: EncryptOptions<R>
+    ) -> Result<Ac17CpCiphertext, RabeError> {
+        cp_encrypt_with(pk, policy, plaintext, options)
+    }
+
+    fn decrypt_with_aad(
+        sk: &Ac17CpSecretKey,
+        ct: &Ac17CpCiphertext,
+        aad: &[u8]
+    ) -> Result<Vec<u8>, RabeError> {
+        cp_decrypt_with_aad(sk, ct, aad)
+    }
+
+    fn encapsulate_with<P: PolicySource, R: RngCore + CryptoRng>(
+        pk: &Ac17PublicKey,
+        policy: P,
+        options: EncryptOptions<R>
+    ) -> Result<(SharedKey, Ac17CpHeader), RabeError> {
+        cp_encapsulate_with(pk, policy, options)
+    }
+
+    fn decapsulate(
+        sk: &Ac17CpSecretKey,
+        header: &Ac17CpHeader
+    ) -> Result<SharedKey, RabeError> {
+        cp_decapsulate(sk, header)
+    }
+}
+
+/// The AC17 KP-ABE scheme, to be used through the [`KpAbe`](../traits/trait.KpAbe.html) trait.
+pub struct Ac17Kp;
+
+impl KpAbe for Ac17Kp {
+    type PublicKey = Ac17PublicKey;
+    type MasterKey = Ac17MasterKey;
+    type SecretKey = Ac17KpSecretKey;
+    type Ciphertext = Ac17KpCiphertext;
+    type Header = Ac17KpHeader;
+
+    fn setup_with_rng<R: RngCore + CryptoRng>(_attributes: &[&str], rng: &mut R) -> Result<(Ac17PublicKey, Ac17MasterKey), RabeError> {
+        Ok(setup_with_rng(rng))
+    }
+
+    fn keygen_with_rng<P: PolicySource, R: RngCore + CryptoRng>(
+        _pk: &Ac17PublicKey,
+        msk: &Ac17MasterKey,
+        policy: P,
+        rng: &mut R
+    ) -> Result<Ac17KpSecretKey, RabeError> {
+        kp_keygen_with_rng(msk, policy, rng)
+    }
+
+    fn encrypt_with<R: RngCore + CryptoRng>(
+        pk: &Ac17PublicKey,
+        attributes: &[&str],
+        plaintext: &[u8],
+        options: EncryptOptions<R>
+    ) -> Result<Ac17KpCiphertext, RabeError> {
+        kp_encrypt_with(pk, attributes, plaintext, options)
+    }
+
+    fn decrypt_with_aad(
+        sk: &Ac17KpSecretKey,
+        ct: &Ac17KpCiphertext,
+        aad: &[u8]
+    ) -> Result<Vec<u8>, RabeError> {
+        kp_decrypt_with_aad(sk, ct, aad)
+    }
+
+    fn encapsulate_with<R: RngCore + CryptoRng>(
+        pk: &Ac17PublicKey,
+        attributes: &[&str],
+        options: EncryptOptions<R>
+    ) -> Result<(SharedKey, Ac17KpHeader), RabeError> {
+        kp_encapsulate_with(pk, attributes, options)
+    }
+
+    fn decapsulate(
+        sk: &Ac17KpSecretKey,
+        header: &Ac17KpHeader
+    ) -> Result<SharedKey, RabeError> {
+        kp_decapsulate(sk, header)
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use schemes::harness;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+    use utils::policy::comparison::expand_attributes;
 
     #[test]
     fn kp_and() {
@@ -692,7 +1085,7 @@ mod tests {
         let ct: Ac17KpCiphertext =
             kp_encrypt(&pk, &vec!["A", "B"], &plaintext).unwrap();
         // a kp-abe SK key
-        let sk: Ac17KpSecretKey = kp_keygen(&msk, &policy, PolicyLanguage::JsonPolicy).unwrap();
+        let sk: Ac17KpSecretKey = kp_keygen(&msk, (&policy, PolicyLanguage::JsonPolicy)).unwrap();
         // and now decrypt again
         assert_eq!(kp_decrypt(&sk, &ct).unwrap(), plaintext);
     }
@@ -710,14 +1103,14 @@ mod tests {
         let ct: Ac17KpCiphertext =
             kp_encrypt(&pk, &vec!["A", "B"], &plaintext).unwrap();
         // a kp-abe SK key
-        let sk: Ac17KpSecretKey = kp_keygen(&msk, &policy, PolicyLanguage::JsonPolicy).unwrap();
+        let sk: Ac17KpSecretKey = kp_keygen(&msk, (&policy, PolicyLanguage::JsonPolicy)).unwrap();
         // and now decrypt again
         assert_eq!(kp_decrypt(&sk, &ct).unwrap(), plaintext);
         // kp-abe ciphertext
         let ct: Ac17KpCiphertext =
             kp_encrypt(&pk, &vec!["C", "D"], &plaintext).unwrap();
         // a kp-abe SK key
-        let sk: Ac17KpSecretKey = kp_keygen(&msk, &policy, PolicyLanguage::JsonPolicy).unwrap();
+        let sk: Ac17KpSecretKey = kp_keygen(&msk, (&policy, PolicyLanguage::JsonPolicy)).unwrap();
         // and now decrypt again
         assert_eq!(kp_decrypt(&sk, &ct).unwrap(), plaintext);
     }
@@ -734,7 +1127,7 @@ mod tests {
         // kp-abe ciphertext
         let ct: Ac17KpCiphertext = kp_encrypt(&pk, &vec!["B"], &plaintext).unwrap();
         // a kp-abe SK key
-        let sk: Ac17KpSecretKey = kp_keygen(&msk, &policy, PolicyLanguage::JsonPolicy).unwrap();
+        let sk: Ac17KpSecretKey = kp_keygen(&msk, (&policy, PolicyLanguage::JsonPolicy)).unwrap();
         // and now decrypt again
         assert_eq!(kp_decrypt(&sk, &ct).unwrap(), plaintext);
     }
@@ -751,7 +1144,7 @@ mod tests {
         // kp-abe ciphertext
         let ct: Ac17KpCiphertext = kp_encrypt(&pk, &vec!["C"], &plaintext).unwrap();
         // a kp-abe SK key
-        let sk: Ac17KpSecretKey = kp_keygen(&msk, &policy, PolicyLanguage::JsonPolicy).unwrap();
+        let sk: Ac17KpSecretKey = kp_keygen(&msk, (&policy, PolicyLanguage::JsonPolicy)).unwrap();
         // and now decrypt again
         assert_eq!(kp_decrypt(&sk, &ct).is_ok(), false);
     }
@@ -766,7 +1159,7 @@ mod tests {
         // our policy
         let policy = String::from(r#"{"name": "and", "children": [{"name": "A"}, {"name": "B"}]}"#);
         // kp-abe ciphertext
-        let ct: Ac17CpCiphertext = cp_encrypt(&pk, &policy, &plaintext, PolicyLanguage::JsonPolicy).unwrap();
+        let ct: Ac17CpCiphertext = cp_encrypt(&pk, (&policy, PolicyLanguage::JsonPolicy), &plaintext).unwrap();
         // a kp-abe SK key
         let sk: Ac17CpSecretKey = cp_keygen(&msk, &vec!["A", "B"]).unwrap();
         // and now decrypt again
@@ -783,7 +1176,7 @@ mod tests {
         // our policy
         let policy = String::from(r#"{"name": "or", "children": [{"name": "A"}, {"name": "B"}, {"name": "C"}]}"#);
         // kp-abe ciphertext
-        let ct: Ac17CpCiphertext = cp_encrypt(&pk, &policy, &plaintext, PolicyLanguage::JsonPolicy).unwrap();
+        let ct: Ac17CpCiphertext = cp_encrypt(&pk, (&policy, PolicyLanguage::JsonPolicy), &plaintext).unwrap();
         // a matching kp-abe SK key
         let sk_m1: Ac17CpSecretKey = cp_keygen(&msk, &vec!["A"]).unwrap();
         let pt = cp_decrypt(&sk_m1, &ct);
@@ -801,10 +1194,81 @@ mod tests {
         // our policy
         let policy = String::from(r#"{"name": "or", "children": [{"name": "and", "children": [{"name": "A"}, {"name": "B"}]}, {"name": "and", "children": [{"name": "C"}, {"name": "D"}]}]}"#);
         // kp-abe ciphertext
-        let ct: Ac17CpCiphertext = cp_encrypt(&pk, &policy, &plaintext, PolicyLanguage::JsonPolicy).unwrap();
+        let ct: Ac17CpCiphertext = cp_encrypt(&pk, (&policy, PolicyLanguage::JsonPolicy), &plaintext).unwrap();
         // a kp-abe SK key
         let sk: Ac17CpSecretKey = cp_keygen(&msk, &vec!["A","B","C","D"], ).unwrap();
         // and now decrypt again
         assert_eq!(cp_decrypt(&sk, &ct).unwrap(), plaintext);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn cp_not_satisfied() {
+        // setup scheme
+        let (pk, msk) = setup();
+        // our plaintext
+        let plaintext = String::from("dance like no one's watching, encrypt like everyone is!")
+            .into_bytes();
+        // our policy
+        let policy = String::from(r#"("A" and "B") or ("C" and "D" and "E")"#);
+        // cp-abe ciphertext
+        let ct: Ac17CpCiphertext = cp_encrypt(&pk, (&policy, PolicyLanguage::HumanPolicy), &plaintext).unwrap();
+        // a cp-abe SK key, that is missing "B"
+        let sk: Ac17CpSecretKey = cp_keygen(&msk, &vec!["A", "C"]).unwrap();
+        // the error explains which attributes are missing
+        match cp_decrypt(&sk, &ct) {
+            Err(RabeError::PolicyNotSatisfied(message)) => assert!(message.ends_with(r#"missing one of ["B"]"#), "{}", message),
+            _ => panic!("ac17: decryption succeeded"),
+        }
+    }
+
+    #[test]
+    fn prepared_public_key() {
+        let (pk, msk) = setup();
+        let prepared = Ac17PreparedPublicKey::new(&pk);
+        let policy = String::from(r#""A" and ("B" or "C")"#);
+        // the same randomness yields the same header with and without tables
+        let cp_plain = cp_encapsulate_with(&pk, (&policy, PolicyLanguage::HumanPolicy), EncryptOptions::new().rng(ChaCha20Rng::seed_from_u64(20))).unwrap();
+        let cp_prepared = cp_encapsulate_with(&prepared, (&policy, PolicyLanguage::HumanPolicy), EncryptOptions::new().rng(ChaCha20Rng::seed_from_u64(20))).unwrap();
+        assert!(cp_plain == cp_prepared);
+        let kp_plain = kp_encapsulate_with(&pk, &["A", "C"], EncryptOptions::new().rng(ChaCha20Rng::seed_from_u64(20))).unwrap();
+        let kp_prepared = kp_encapsulate_with(&prepared, &["A", "C"], EncryptOptions::new().rng(ChaCha20Rng::seed_from_u64(20))).unwrap();
+        assert!(kp_plain == kp_prepared);
+        let plaintext = String::from("dance like no one's watching, encrypt like everyone is!").into_bytes();
+        let ct = cp_encrypt(&prepared, (&policy, PolicyLanguage::HumanPolicy), &plaintext).unwrap();
+        assert_eq!(cp_decrypt(&cp_keygen(&msk, &["A", "C"]).unwrap(), &ct).unwrap(), plaintext);
+        let ct = kp_encrypt(&prepared, &["A", "C"], &plaintext).unwrap();
+        assert_eq!(kp_decrypt(&kp_keygen(&msk, (&policy, PolicyLanguage::HumanPolicy)).unwrap(), &ct).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn max_reuse() {
+        let (pk, msk) = setup();
+        let plaintext = String::from("dance like no one's watching, encrypt like everyone is!").into_bytes();
+        // "A" labels two rows of the msp
+        let policy = String::from(r#"("A" and "B") or ("A" and "C")"#);
+        let mut rng = rand::thread_rng();
+        assert!(matches!(cp_encrypt_with(&pk, (&policy, PolicyLanguage::HumanPolicy), &plaintext, EncryptOptions::new().max_reuse(1).rng(&mut rng)), Err(RabeError::InvalidPolicy(_))));
+        assert!(matches!(kp_keygen_with_max_reuse(&msk, (&policy, PolicyLanguage::HumanPolicy), 1, &mut rng), Err(RabeError::InvalidPolicy(_))));
+        let ct = cp_encrypt_with(&pk, (&policy, PolicyLanguage::HumanPolicy), &plaintext, EncryptOptions::new().max_reuse(2).rng(&mut rng)).unwrap();
+        assert_eq!(cp_decrypt(&cp_keygen(&msk, &["A", "C"]).unwrap(), &ct).unwrap(), plaintext);
+        let sk = kp_keygen_with_max_reuse(&msk, (&policy, PolicyLanguage::HumanPolicy), 2, &mut rng).unwrap();
+        assert_eq!(kp_decrypt(&sk, &kp_encrypt(&pk, &["A", "C"], &plaintext).unwrap()).unwrap(), plaintext);
+        // the bounds of a range use distinct bit attributes
+        let policy = r#""A" and age in [18, 65]"#;
+        let ct = cp_encrypt_with(&pk, (policy, PolicyLanguage::HumanPolicy), &plaintext, EncryptOptions::new().max_reuse(1).rng(&mut rng)).unwrap();
+        let attributes = expand_attributes(&["A", "age = 42"]).unwrap();
+        let attributes: Vec<&str> = attributes.iter().map(|a| a.as_str()).collect();
+        assert_eq!(cp_decrypt(&cp_keygen(&msk, &attributes).unwrap(), &ct).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn cp_abe() {
+        harness::cp_abe::<Ac17Cp>().unwrap();
+    }
+
+    #[test]
+    fn kp_abe() {
+        // negations are rejected, since the MSP of AC17 is monotone
+        harness::kp_abe::<Ac17Kp>(false).unwrap();
+    }
+}
@@ -18,31 +18,41 @@
 //!let plaintext = String::from("our plaintext!").into_bytes();
 //!let policy = String::from(r#""A" or "B""#);
 //!let bob = keygen(&gk, &msk, &String::from("bob"), &vec!["A"]).unwrap();
-//!let ct: Aw11Ciphertext = encrypt(&gk, &[&pk], &policy, PolicyLanguage::HumanPolicy, &plaintext).unwrap();
+//!let ct: Aw11Ciphertext = encrypt(&gk, &[&pk], (&policy, PolicyLanguage::HumanPolicy), &plaintext).unwrap();
 //!let matching = decrypt(&gk, &bob, &ct).unwrap();
 //!assert_eq!(matching, plaintext);
 //! ```
 use std::string::String;
-use rand::Rng;
+use rand::{CryptoRng, Rng, RngCore};
 use rabe_bn::{Fr, G1, G2, Gt, pairing};
 use utils::{
     secretsharing::{
         calc_coefficients,
-        calc_pruned
+        calc_pruned_minimal,
+        LeafWeights
     },
     policy::msp::AbePolicy,
     tools::*,
     aes::*,
-    hash::sha3_hash
+    hash::{hash_to_g1, HashInput},
+    container::{Container, SchemeId, ObjectType},
+    pairing::multi_pairing,
 };
-use utils::policy::pest::{PolicyLanguage, parse, PolicyType};
-use utils::secretsharing::{gen_shares_policy, remove_index};
+use utils::policy::pest::{PolicyLanguage, parse, check_monotone, PolicyType};
+use utils::policy::ast::PolicySource;
+use utils::policy::explain::not_satisfied;
+use utils::secretsharing::{gen_shares_policy_with_rng, remove_index};
 use crate::error::RabeError;
+use schemes::traits::MultiAuthorityAbe;
+use schemes::options::EncryptOptions;
 #[cfg(feature = "serde")]
 use serde::{Serialize, Deserialize};
 #[cfg(feature = "borsh")]
 use borsh::{BorshSerialize, BorshDeserialize};
 
+/// The decryption cost of the leaves of a policy: one pairing and one exponentiation in Gt per leaf
+const LEAF_WEIGHTS: LeafWeights = LeafWeights { attribute: 2, negated: 2 };
+
 /// An AW11 Global Parameters Key (GK)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -52,6 +62,13 @@ pub struct Aw11GlobalKey {
     pub g2: G2,
 }
 
+validate_fields!(Aw11GlobalKey { g1, g2 });
+
+impl Container for Aw11GlobalKey {
+    const SCHEME: SchemeId = SchemeId::Aw11;
+    const OBJECT: ObjectType = ObjectType::GlobalKey;
+}
+
 /// An AW11 Public Key (PK)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -60,6 +77,13 @@ pub struct Aw11PublicKey {
     pub attr: Vec<(String, Gt, G2)>,
 }
 
+validate_fields!(Aw11PublicKey { attr });
+
+impl Container for Aw11PublicKey {
+    const SCHEME: SchemeId = SchemeId::Aw11;
+    const OBJECT: ObjectType = ObjectType::PublicKey;
+}
+
 /// An AW11 Master Key (MK)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -68,17 +92,56 @@ pub struct Aw11MasterKey {
     pub attr: Vec<(String, Fr, Fr)>,
 }
 
-/// An AW11 Ciphertext (CT)
+validate_fields!(Aw11MasterKey { attr });
+
+impl Container for Aw11MasterKey {
+    const SCHEME: SchemeId = SchemeId::Aw11;
+    const OBJECT: ObjectType = ObjectType::MasterKey;
+}
+
+/// An AW11 Header, i.e. the encapsulated key under a policy
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub struct Aw11Ciphertext {
+pub struct Aw11Header {
     pub policy: (String, PolicyLanguage),
     pub c_0: Gt,
     pub c: Vec<(String, Gt, G2, G2)>,
+    pub cipher: SymmetricCipher,
+}
+
+validate_fields!(Aw11Header { policy, c_0, c, cipher });
+
+impl Container for Aw11Header {
+    const SCHEME: SchemeId = SchemeId::Aw11;
+    const OBJECT: ObjectType = ObjectType::Header;
+}
+
+impl Aw11Header {
+    /// Returns the associated data that binds this header to the symmetrically encrypted data:
+    /// the scheme id, the format version, the policy and the caller supplied `aad`.
+    pub fn associated_data(&self, aad: &[u8]) -> Vec<u8> {
+        let language = [self.policy.1 as u8];
+        associated_data("AW11", &[self.policy.0.as_bytes(), &language], aad)
+    }
+}
+
+/// An AW11 Ciphertext (CT), composed of an Aw11Header and the symmetrically encrypted data
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Aw11Ciphertext {
+    pub header: Aw11Header,
     pub ct: Vec<u8>,
 }
 
+validate_fields!(Aw11Ciphertext { header, ct });
+
+impl Container for Aw11Ciphertext {
+    const SCHEME: SchemeId = SchemeId::Aw11;
+    const OBJECT: ObjectType = ObjectType::Ciphertext;
+}
+
 /// An AW11 Secret Key (SK)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -88,6 +151,13 @@ pub struct Aw11SecretKey {
     pub attr: Vec<(String, G1)>,
 }
 
+validate_fields!(Aw11SecretKey { gid, attr });
+
+impl Container for Aw11SecretKey {
+    const SCHEME: SchemeId = SchemeId::Aw11;
+    const OBJECT: ObjectType = ObjectType::SecretKey;
+}
+
 /// A global Context for an AW11 Global Parameters Key (GP)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -96,10 +166,15 @@ pub struct Aw11GlobalContext {
     pub key: Aw11GlobalKey,
 }
 
+validate_fields!(Aw11GlobalContext { key });
+
 /// Sets up a new AW11 Scheme by creating a Global Parameters Key (GK)
 pub fn setup() -> Aw11GlobalKey {
-    // random number generator
-    let mut rng = rand::thread_rng();
+    setup_with_rng(&mut rand::thread_rng())
+}
+
+/// Like `setup()`, but draws all randomness from the given random number generator `rng`.
+pub fn setup_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> Aw11GlobalKey {
     // generator of group G1: g1 and generator of group G2: g2
     Aw11GlobalKey {
         g1: rng.gen(),
@@ -119,16 +194,23 @@ pub fn setup() -> Aw11GlobalKey {
 /// In this scheme, all attributes are converted to upper case bevor calculation, i.e. they are case insensitive
 /// This means that all attributes "tEsT", "TEST" and "test" are the same in this scheme.
 pub fn authgen(
+    gk: &Aw11GlobalKey,
+    attributes: &[&str]
+) -> Option<(Aw11PublicKey, Aw11MasterKey)> {
+    authgen_with_rng(gk, attributes, &mut rand::thread_rng())
+}
+
+/// Like `authgen()`, but draws all randomness from the given random number generator `rng`.
+pub fn authgen_with_rng<R: RngCore + CryptoRng>(
     gk: &Aw11GlobalKey,
     attributes: &[&str],
+    rng: &mut R
 ) -> Option<(Aw11PublicKey, Aw11MasterKey)> {
     // if no attibutes or an empty policy
     // maybe add empty msk also here
     if attributes.is_empty() {
         return None;
     }
-    // random number generator
-    let mut rng = rand::thread_rng();
     // generator of group G1: g and generator of group G2: h
     let mut sk: Vec<(String, Fr, Fr)> = Vec::new(); //dictionary of {s: {alpha_i, y_i}}
     let mut pk: Vec<(String, Gt, G2)> = Vec::new(); // dictionary of {s: {e(g,g)^alpha_i, g1^y_i}}
@@ -170,10 +252,10 @@ pub fn keygen(
 ) -> Result<Aw11SecretKey, RabeError> {
     // if no attibutes or no gid
     if attributes.is_empty() {
-        Err(RabeError::new("empty _attributes"))
+        Err(RabeError::InvalidInput(String::from("aw11/keygen: attributes are empty")))
     }
     else if name.is_empty() {
-        Err(RabeError::new("empty _name"))
+        Err(RabeError::InvalidInput(String::from("aw11/keygen: name is empty")))
     }
     else {
         let mut _sk: Aw11SecretKey = Aw11SecretKey {
@@ -205,25 +287,27 @@ pub fn add_to_attribute(
 ) -> Result<(), RabeError> {
     // if no attibutes or no gid
     if attribute.is_empty() {
-        Err(RabeError::new("empty _attributes"))
+        Err(RabeError::InvalidInput(String::from("aw11/add_to_attribute: attributes are empty")))
     }
     else if sk.gid.is_empty() {
-        Err(RabeError::new("empty _gid"))
+        Err(RabeError::InvalidInput(String::from("aw11/add_to_attribute: gid is empty")))
     }
     else {
-        match sha3_hash(gk.g1, &sk.gid) {
+        match hash_to_g1(SchemeId::Aw11, &HashInput::new("gid").string(&sk.gid)) {
             Ok(hash) => {
-                let auth_attribute = msk
+                match msk
                     .attr
                     .iter()
-                    .filter(|_attr| _attr.0 == attribute.to_string())
-                    .nth(0)
-                    .unwrap();
-                sk.attr.push((
-                    auth_attribute.0.clone().to_uppercase(),
-                    (gk.g1 * auth_attribute.1) + (hash * auth_attribute.2),
-                ));
-                Ok(())
+                    .find(|_attr| _attr.0 == attribute.to_uppercase()) {
+                    Some(auth_attribute) => {
+                        sk.attr.push((
+                            auth_attribute.0.clone().to_uppercase(),
+                            (gk.g1 * auth_attribute.1) + (hash * auth_attribute.2),
+                        ));
+                        Ok(())
+                    },
+                    None => Err(RabeError::UnknownAttribute(attribute.to_string()))
+                }
             },
             Err(e) => Err(e)
         }
@@ -236,35 +320,75 @@ pub fn add_to_attribute(
 ///
 ///	* `gk` - A Global Parameters Key (GK), generated by setup()
 ///	* `pk` - A Public Parameters Key (MK), associated with an authority and generated by authgen()
-///	* `policy` - A JSON String policy describing the access rights
+///	* `policy` - An access policy, given as text and its PolicyLanguage or as Policy, see [PolicySource]
 ///	* `plaintext` - The plaintext data given as a Vector of u8.
-pub fn encrypt(
+pub fn encrypt<P: PolicySource>(
     gk: &Aw11GlobalKey,
     pks: &[&Aw11PublicKey],
-    policy: &str,
-    language: PolicyLanguage,
+    policy: P,
+    data: &[u8]
+) -> Result<Aw11Ciphertext, RabeError> {
+    encrypt_with(gk, pks, policy, data, EncryptOptions::new())
+}
+
+/// Like `encrypt()`, but with the given [EncryptOptions], i.e. the random number generator, the associated data
+/// that has to be passed to `decrypt_with_aad()`, the symmetric cipher and the maximal reuse of an attribute.
+pub fn encrypt_with<P: PolicySource, R: RngCore + CryptoRng>(
+    gk: &Aw11GlobalKey,
+    pks: &[&Aw11PublicKey],
+    policy: P,
     data: &[u8],
+    mut options: EncryptOptions<R>
 ) -> Result<Aw11Ciphertext, RabeError> {
-    // random number generator
-    let mut _rng = rand::thread_rng();
-    match parse(policy, language) {
-        Ok(pol) => {
+    let (key, header) = encapsulate_with(gk, pks, policy, options.by_ref())?;
+    //Encrypt plaintext using the encapsulated key, binding the header as associated data
+    let ct = header.cipher.encrypt(&key, data, &header.associated_data(options.aad), &mut options.rng)?;
+    Ok(Aw11Ciphertext { header, ct })
+}
+
+/// This function encapsulates a new 'SharedKey' using a given JSON String policy and produces an 'Aw11Header' if successfull.
+///
+/// # Arguments
+///
+///	* `gk` - A Global Parameters Key (GK), generated by setup()
+///	* `pk` - A Public Parameters Key (MK), associated with an authority and generated by authgen()
+///	* `policy` - An access policy, given as text and its PolicyLanguage or as Policy, see [PolicySource]
+pub fn encapsulate<P: PolicySource>(
+    gk: &Aw11GlobalKey,
+    pks: &[&Aw11PublicKey],
+    policy: P
+) -> Result<(SharedKey, Aw11Header), RabeError> {
+    encapsulate_with(gk, pks, policy, EncryptOptions::new())
+}
+
+/// Like `encapsulate()`, but with the given [EncryptOptions], i.e. the random number generator, the symmetric
+/// cipher that is recorded in the header and the maximal reuse of an attribute.
+pub fn encapsulate_with<P: PolicySource, R: RngCore + CryptoRng>(
+    gk: &Aw11GlobalKey,
+    pks: &[&Aw11PublicKey],
+    policy: P,
+    mut options: EncryptOptions<R>
+) -> Result<(SharedKey, Aw11Header), RabeError> {
+    let rng = &mut options.rng;
+    match policy.value() {
+        Ok((pol, policy, language)) => {
+            check_monotone(&pol, "aw11/encapsulate")?;
             // an msp policy from the given String
-            let msp: AbePolicy = AbePolicy::from_policy(&pol).unwrap();
+            let msp: AbePolicy = AbePolicy::from_policy_with_max_reuse(&pol, options.max_reuse)?;
             let _num_cols = msp.m[0].len();
             let _num_rows = msp.m.len();
             // pick randomness
-            let _s:Fr = _rng.gen();
+            let _s:Fr = rng.gen();
             // and calculate shares "s" and "zero"
-            let _s_shares = gen_shares_policy(_s, &pol, None).unwrap();
-            let _w_shares = gen_shares_policy(Fr::zero(), &pol, None).unwrap();
+            let _s_shares = gen_shares_policy_with_rng(_s, &pol, None, rng)?;
+            let _w_shares = gen_shares_policy_with_rng(Fr::zero(), &pol, None, rng)?;
             // calculate c0 with a randomly selected "msg"
-            let _msg: Gt = _rng.gen();
+            let _msg: Gt = rng.gen();
             let c_0 = _msg * pairing(gk.g1, gk.g2).pow(_s);
             // now calculate the C1,x C2,x and C3,x parts
             let mut c: Vec<(String, Gt, G2, G2)> = Vec::new();
-            for (_i, (_attr_name, _attr_share)) in _s_shares.into_iter().enumerate() {
-                let _r_x:Fr = _rng.gen();
+            for ((_attr_name, _attr_share), (_, _w_share)) in _s_shares.into_iter().zip(_w_shares) {
+                let _r_x:Fr = rng.gen();
                 let _pk_attr = find_pk_attr(pks, &remove_index(&_attr_name.to_uppercase()));
                 match _pk_attr {
                     None => {},
@@ -273,16 +397,13 @@ pub fn encrypt(
                             _attr_name.clone().to_uppercase(),
                             pairing(gk.g1, gk.g2).pow(_attr_share) * _attr.1.pow(_r_x),
                             gk.g2 * _r_x,
-                            (_attr.2 * _r_x) + (gk.g2 * _w_shares[_i].1),
+                            (_attr.2 * _r_x) + (gk.g2 * _w_share),
                         ));
                     }
                 }
             }
-            //Encrypt plaintext using derived key from secret
-            match encrypt_symmetric(_msg, &data.to_vec()) {
-                Ok(ct) => Ok(Aw11Ciphertext { policy: (policy.to_string(), language), c_0, c, ct }),
-                Err(e) => Err(e)
-            }
+            let header = Aw11Header { policy: (policy.to_string(), language), c_0, c, cipher: options.cipher };
+            Ok((SharedKey::derive_for(_msg, &header)?, header))
         },
         Err(e) => Err(e)
     }
@@ -300,6 +421,33 @@ pub fn decrypt(
     sk: &Aw11SecretKey,
     ct: &Aw11Ciphertext
 ) -> Result<Vec<u8>, RabeError> {
+    decrypt_with_aad(gk, sk, ct, &[])
+}
+
+/// Like `decrypt()`, but for ciphertexts that were generated by `encrypt_with()` with the associated data `aad`.
+pub fn decrypt_with_aad(
+    gk: &Aw11GlobalKey,
+    sk: &Aw11SecretKey,
+    ct: &Aw11Ciphertext,
+    aad: &[u8]
+) -> Result<Vec<u8>, RabeError> {
+    let key = decapsulate(gk, sk, &ct.header)?;
+    // Decrypt plaintext using the key recovered from the cp-abe scheme
+    ct.header.cipher.decrypt(&key, &ct.ct, &ct.header.associated_data(aad))
+}
+
+/// This function decapsulates the 'SharedKey' of an 'Aw11Header' if the attributes in SK match its policy.
+///
+/// # Arguments
+///
+///	* `_gk` - A Global Parameters Key (GK), generated by setup(). Not needed since the gid is hashed independently of the generator
+///	* `sk` - A secret user key (SK), associated with a set of attributes.
+///	* `header` - An Aw11Header
+pub fn decapsulate(
+    _gk: &Aw11GlobalKey,
+    sk: &Aw11SecretKey,
+    header: &Aw11Header
+) -> Result<SharedKey, RabeError> {
     let str_attr = sk
         .attr
         .iter()
@@ -308,54 +456,53 @@ pub fn decrypt(
             _str
         })
         .collect::<Vec<_>>();
-    return match parse(ct.policy.0.as_ref(), ct.policy.1) {
+    // attributes are case insensitive, see authgen()
+    return match parse(&header.policy.0.to_uppercase(), header.policy.1) {
         Ok(pol) => {
             return if traverse_policy(&str_attr, &pol, PolicyType::Leaf) == false {
-                Err(RabeError::new("Error: attributes in sk do not match policy in ct."))
+                Err(not_satisfied("aw11/decapsulate: attributes in sk do not match policy in ct", &str_attr, &pol))
             } else {
-                let _pruned = calc_pruned(&str_attr, &pol, None);
+                let _pruned = calc_pruned_minimal(&str_attr, &pol, LEAF_WEIGHTS);
                 match _pruned {
                     Err(e) => Err(e),
                     Ok(_p) => {
                         let (_match, _list) = _p;
                         let mut coeff_list: Vec<(String, Fr)> = Vec::new();
-                        coeff_list = calc_coefficients(&pol, Some(Fr::one()), coeff_list,None).unwrap();
+                        coeff_list = calc_coefficients(&pol, Some(Fr::one()), coeff_list, None, &_list)?;
                         if _match {
-                            match sha3_hash(gk.g1, &sk.gid) {
+                            match hash_to_g1(SchemeId::Aw11, &HashInput::new("gid").string(&sk.gid)) {
                                 Ok(hash) => {
-                                    let mut _egg_s = Gt::one();
+                                    // e(g, g)^s is the product of c_1^w * e(hash, c_3^w) * e(k, c_2^w)^-1 over all leaves
+                                    // with coefficient w, the pairings with hash are merged into one
+                                    let mut _c1_s = Gt::one();
+                                    let mut _pairs: Vec<(G1, G2)> = Vec::new();
                                     for _current in _list.iter() {
                                         let _sk_attr = sk
                                             .attr
                                             .iter()
-                                            .filter(|_attr| _attr.0 == _current.0.to_string())
-                                            .nth(0)
-                                            .unwrap();
-                                        let _ct_attr = ct
+                                            .find(|_attr| _attr.0 == _current.0.to_string())
+                                            .ok_or_else(|| RabeError::InvalidKey(format!("aw11/decapsulate: no key for attribute {}", _current.0)))?;
+                                        let _ct_attr = header
                                             .c
                                             .iter()
-                                            .filter(|_attr| _attr.0 == _current.1.to_string())
-                                            .nth(0)
-                                            .unwrap();
-                                        let num = _ct_attr.1 * pairing(hash, _ct_attr.3);
-                                        let dem = pairing(_sk_attr.1, _ct_attr.2);
+                                            .find(|_attr| _attr.0 == _current.1.to_string())
+                                            .ok_or_else(|| RabeError::InvalidInput(format!("aw11/decapsulate: header has no value for {}", _current.1)))?;
                                         let _coeff = coeff_list
                                             .iter()
-                                            .filter(|_c| _c.0 == _current.1.to_string())
+                                            .find(|_c| _c.0 == _current.1.to_string())
                                             .map(|_c| _c.1)
-                                            .nth(0)
-                                            .unwrap();
-                                        _egg_s = _egg_s * ((num * dem.inverse()).pow(_coeff));
+                                            .ok_or_else(|| RabeError::InvalidPolicy(format!("aw11/decapsulate: no coefficient for {}", _current.1)))?;
+                                        _c1_s = _c1_s * _ct_attr.1.pow(_coeff);
+                                        _pairs.push((-hash, _ct_attr.3 * _coeff));
+                                        _pairs.push((_sk_attr.1 * _coeff, _ct_attr.2));
                                     }
-                                    let _msg = ct.c_0 * _egg_s.inverse();
-                                    //println!("dec: {:?}", serde_json::to_string(&_msg).unwrap());
-                                    // Decrypt plaintext using derived secret from cp-abe scheme
-                                    decrypt_symmetric(_msg, &ct.ct)
+                                    let _msg = header.c_0 * _c1_s.inverse() * multi_pairing(&_pairs);
+                                    SharedKey::derive_for(_msg, header)
                                 },
                                 Err(e) => Err(e)
                             }
                         } else {
-                            Err(RabeError::new("Error in aw11/decrypt: attributes in sk do not match policy in ct."))
+                            Err(not_satisfied("aw11/decrypt: attributes in sk do not match policy in ct", &str_attr, &pol))
                         }
                     }
                 }
@@ -389,10 +536,108 @@ fn find_pk_attr(
     return None;
 }
 
+/// The AW11 multi-authority scheme, to be used through the [`MultiAuthorityAbe`](../traits/trait.MultiAuthorityAbe.html) trait.
+///
+/// AW11 has no central master key, so `MasterKey` is `()`. An authority is the key pair returned by authgen().
+pub struct Aw11;
+
+impl MultiAuthorityAbe for Aw11 {
+    type GlobalKey = Aw11GlobalKey;
+    type MasterKey = ();
+    type AuthorityKey = (Aw11PublicKey, Aw11MasterKey);
+    type AttributePublicKey = Aw11PublicKey;
+    type SecretKey = Aw11SecretKey;
+    type Ciphertext = Aw11Ciphertext;
+    type Header = Aw11Header;
+
+    fn setup_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> Result<(Aw11GlobalKey, ()), RabeError> {
+        Ok((setup_with_rng(rng), ()))
+    }
+
+    fn authgen_with_rng<R: RngCore + CryptoRng>(
+        gk: &Aw11GlobalKey,
+        _msk: &(),
+        _name: &str,
+        attributes: &[&str],
+        rng: &mut R
+    ) -> Result<(Aw11PublicKey, Aw11MasterKey), RabeError> {
+        authgen_with_rng(gk, attributes, rng).ok_or_else(|| RabeError::InvalidInput(String::from("aw11/authgen: attributes are empty")))
+    }
+
+    fn attribute_public_key(
+        _gk: &Aw11GlobalKey,
+        authority: &(Aw11PublicKey, Aw11MasterKey),
+        attribute: &str
+    ) -> Result<Aw11PublicKey, RabeError> {
+        let name = attribute.to_uppercase();
+        match authority.0.attr.iter().find(|attr| attr.0 == name) {
+            Some(attr) => Ok(Aw11PublicKey { attr: vec![attr.clone()] }),
+            None => Err(RabeError::UnknownAttribute(attribute.to_string()))
+        }
+    }
+
+    fn keygen_with_rng<R: RngCore + CryptoRng>(
+        gk: &Aw11GlobalKey,
+        _msk: &(),
+        authority: &(Aw11PublicKey, Aw11MasterKey),
+        name: &str,
+        attributes: &[&str],
+        _rng: &mut R
+    ) -> Result<Aw11SecretKey, RabeError> {
+        keygen(gk, &authority.1, name, attributes)
+    }
+
+    fn add_attribute(
+        gk: &Aw11GlobalKey,
+        authority: &(Aw11PublicKey, Aw11MasterKey),
+        attribute: &str,
+        sk: &mut Aw11SecretKey
+    ) -> Result<(), RabeError> {
+        add_to_attribute(gk, &authority.1, attribute, sk)
+    }
+
+    fn encrypt_with<P: PolicySource, R: RngCore + CryptoRng>(
+        gk: &Aw11GlobalKey,
+        attr_pks: &[&Aw11PublicKey],
+        policy: P,
+        plaintext: &[u8],
+        options: EncryptOptions<R>
+    ) -> Result<Aw11Ciphertext, RabeError> {
+        encrypt_with(gk, attr_pks, policy, plaintext, options)
+    }
+
+    fn decrypt_with_aad(
+        gk: &Aw11GlobalKey,
+        sk: &Aw11SecretKey,
+        ct: &Aw11Ciphertext,
+        aad: &[u8]
+    ) -> Result<Vec<u8>, RabeError> {
+        decrypt_with_aad(gk, sk, ct, aad)
+    }
+
+    fn encapsulate_with<P: PolicySource, R: RngCore + CryptoRng>(
+        gk: &Aw11GlobalKey,
+        attr_pks: &[&Aw11PublicKey],
+        policy: P,
+        options: EncryptOptions<R>
+    ) -> Result<(SharedKey, Aw11Header), RabeError> {
+        encapsulate_with(gk, attr_pks, policy, options)
+    }
+
+    fn decapsulate(
+        gk: &Aw11GlobalKey,
+        sk: &Aw11SecretKey,
+        header: &Aw11Header
+    ) -> Result<SharedKey, RabeError> {
+        decapsulate(gk, sk, header)
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use schemes::harness;
 
     #[test]
     fn and() {
@@ -427,7 +672,7 @@ mod tests {
         // a vector of public attribute keys
         let pks: Vec<&Aw11PublicKey> = vec![&_auth3_pk, &_auth1_pk];
         // cp-abe ciphertext
-        let ct_cp: Aw11Ciphertext = encrypt(&_gp, pks.as_slice(), &_policy, PolicyLanguage::JsonPolicy, &_plaintext).unwrap();
+        let ct_cp: Aw11Ciphertext = encrypt(&_gp, pks.as_slice(), (&_policy, PolicyLanguage::JsonPolicy), &_plaintext).unwrap();
         // and now decrypt again with mathcing sk
         let _matching = decrypt(&_gp, &_bob, &ct_cp).unwrap();
         assert_eq!(_matching, _plaintext);
@@ -468,7 +713,7 @@ mod tests {
             panic!("Error: {}", e.to_string())
         }
         // cp-abe ciphertext
-        let ct_cp: Aw11Ciphertext = encrypt(&_gp, &pks, &_policy,PolicyLanguage::JsonPolicy,  &_plaintext).unwrap();
+        let ct_cp: Aw11Ciphertext = encrypt(&_gp, &pks, (&_policy, PolicyLanguage::JsonPolicy),  &_plaintext).unwrap();
         // and now decrypt again with mathcing sk
         let _matching = decrypt(&_gp, &_bob, &ct_cp).unwrap();
         assert_eq!(_matching, _plaintext);
@@ -514,7 +759,7 @@ mod tests {
         }
 
         // cp-abe ciphertext
-        let ct_cp: Aw11Ciphertext = encrypt(&_gp, pks.as_slice(), &_policy, PolicyLanguage::JsonPolicy, &_plaintext).unwrap();
+        let ct_cp: Aw11Ciphertext = encrypt(&_gp, pks.as_slice(), (&_policy, PolicyLanguage::JsonPolicy), &_plaintext).unwrap();
         // and now decrypt again with mathcing sk
         let _matching = decrypt(&_gp, &_bob, &ct_cp).unwrap();
         assert_eq!(_matching, _plaintext);
@@ -553,9 +798,29 @@ mod tests {
             panic!("Error: {}", e.to_string())
         }
         // cp-abe ciphertext
-        let ct_cp: Aw11Ciphertext = encrypt(&_gp, pks.as_slice(), &_policy, PolicyLanguage::JsonPolicy, &_plaintext).unwrap();
+        let ct_cp: Aw11Ciphertext = encrypt(&_gp, pks.as_slice(), (&_policy, PolicyLanguage::JsonPolicy), &_plaintext).unwrap();
         // and now decrypt again
         let pt = decrypt(&_gp, &_bob, &ct_cp);
         assert_eq!(pt.is_ok(), false);
     }
+
+    #[test]
+    fn max_reuse() {
+        let _gp = setup();
+        let (_auth1_pk, _auth1_msk) = authgen(&_gp, &["A", "B", "C"]).unwrap();
+        let _bob = keygen(&_gp, &_auth1_msk, &String::from("bob"), &["A", "C"]).unwrap();
+        let _plaintext = String::from("dance like no one's watching, encrypt like everyone is!").into_bytes();
+        // "A" labels two rows of the msp
+        let _policy = String::from(r#"("A" and "B") or ("A" and "C")"#);
+        let pks: Vec<&Aw11PublicKey> = vec![&_auth1_pk];
+        let mut rng = rand::thread_rng();
+        assert!(matches!(encrypt_with(&_gp, &pks, (&_policy, PolicyLanguage::HumanPolicy), &_plaintext, EncryptOptions::new().max_reuse(1).rng(&mut rng)), Err(RabeError::InvalidPolicy(_))));
+        let ct_cp = encrypt_with(&_gp, &pks, (&_policy, PolicyLanguage::HumanPolicy), &_plaintext, EncryptOptions::new().max_reuse(2).rng(&mut rng)).unwrap();
+        assert_eq!(decrypt(&_gp, &_bob, &ct_cp).unwrap(), _plaintext);
+    }
+
+    #[test]
+    fn ma_abe() {
+        harness::ma_abe::<Aw11>().unwrap();
+    }
 }
@@ -17,26 +17,32 @@
 //! let auth1 = authgen(&pk, &msk, &String::from("auth1"));
 //! let mut sk = keygen(&pk, &auth1, &String::from("u1"));
 //! let attr_a_pk = request_attribute_pk(&pk, &auth1, "auth1::A").unwrap();
+//! let attr_b_pk = request_attribute_pk(&pk, &auth1, "auth1::B").unwrap();
 //! sk.sk_a.push(request_attribute_sk(&sk.pk, &auth1, "auth1::A").unwrap());
 //! let plaintext = String::from("our plaintext!").into_bytes();
 //! let policy = String::from(r#""auth1::A" or "auth1::B""#);
-//! let ct: BdabeCiphertext = encrypt(&pk, &vec![&attr_a_pk], &policy, PolicyLanguage::HumanPolicy, &plaintext).unwrap();
+//! let ct: BdabeCiphertext = encrypt(&pk, &vec![&attr_a_pk, &attr_b_pk], (&policy, PolicyLanguage::HumanPolicy), &plaintext).unwrap();
 //! let ct_decrypted = decrypt(&sk, &ct);
 //! assert_eq!(ct_decrypted.is_ok(), true);
 //! assert_eq!(ct_decrypted.unwrap(), plaintext);
 //! ```
 use std::string::String;
-use rand::Rng;
+use rand::{CryptoRng, Rng, RngCore};
 use rabe_bn::{Group, Fr, G1, G2, Gt, pairing};
 use utils::{
     policy::*,
     tools::*,
     aes::*,
-    hash::sha3_hash_fr
+    hash::{hash_to_fr, HashInput},
+    container::{Container, SchemeId, ObjectType},
+    pairing::multi_pairing,
 };
-use utils::policy::pest::{PolicyLanguage, parse, PolicyType};
+use utils::policy::pest::{PolicyLanguage, parse, check_monotone, PolicyType};
+use utils::policy::ast::PolicySource;
+use utils::policy::explain::not_satisfied;
 use crate::error::RabeError;
-use utils::policy::dnf::policy_in_dnf;
+use schemes::traits::MultiAuthorityAbe;
+use schemes::options::EncryptOptions;
 #[cfg(feature = "serde")]
 use serde::{Serialize, Deserialize};
 #[cfg(feature = "borsh")]
@@ -54,6 +60,13 @@ pub struct BdabePublicKey {
     pub e_gg_y: Gt,
 }
 
+validate_fields!(BdabePublicKey { g1, g2, p1, p2, e_gg_y });
+
+impl Container for BdabePublicKey {
+    const SCHEME: SchemeId = SchemeId::Bdabe;
+    const OBJECT: ObjectType = ObjectType::PublicKey;
+}
+
 /// A BDABE Master Key (MK)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -62,6 +75,13 @@ pub struct BdabeMasterKey {
     pub y: Fr,
 }
 
+validate_fields!(BdabeMasterKey { y });
+
+impl Container for BdabeMasterKey {
+    const SCHEME: SchemeId = SchemeId::Bdabe;
+    const OBJECT: ObjectType = ObjectType::MasterKey;
+}
+
 /// A BDABE User Key (PKu, SKu and SKa's)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -72,6 +92,13 @@ pub struct BdabeUserKey {
     pub sk_a: Vec<BdabeSecretAttributeKey>,
 }
 
+validate_fields!(BdabeUserKey { sk, pk, sk_a });
+
+impl Container for BdabeUserKey {
+    const SCHEME: SchemeId = SchemeId::Bdabe;
+    const OBJECT: ObjectType = ObjectType::SecretKey;
+}
+
 /// A BDABE Public User Key (PKu)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -82,6 +109,8 @@ pub struct BdabePublicUserKey {
     pub u2: G2,
 }
 
+validate_fields!(BdabePublicUserKey { u, u1, u2 });
+
 /// A BDABE Secret User Key (SKu)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -91,6 +120,8 @@ pub struct BdabeSecretUserKey {
     pub u2: G2,
 }
 
+validate_fields!(BdabeSecretUserKey { u1, u2 });
+
 /// A BDABE Secret Attribute Key (SKa)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -101,6 +132,13 @@ pub struct BdabeSecretAttributeKey {
     pub au2: G2,
 }
 
+validate_fields!(BdabeSecretAttributeKey { attr, au1, au2 });
+
+impl Container for BdabeSecretAttributeKey {
+    const SCHEME: SchemeId = SchemeId::Bdabe;
+    const OBJECT: ObjectType = ObjectType::AttributeSecretKey;
+}
+
 /// A BDABE Public Attribute Key (PKa)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -112,6 +150,13 @@ pub struct BdabePublicAttributeKey {
     pub a3: Gt,
 }
 
+validate_fields!(BdabePublicAttributeKey { attr, a1, a2, a3 });
+
+impl Container for BdabePublicAttributeKey {
+    const SCHEME: SchemeId = SchemeId::Bdabe;
+    const OBJECT: ObjectType = ObjectType::AttributeKey;
+}
+
 /// A BDABE Secret Authority Key (SKauth)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -123,6 +168,13 @@ pub struct BdabeSecretAuthorityKey {
     pub a3: Fr,
 }
 
+validate_fields!(BdabeSecretAuthorityKey { name, a1, a2, a3 });
+
+impl Container for BdabeSecretAuthorityKey {
+    const SCHEME: SchemeId = SchemeId::Bdabe;
+    const OBJECT: ObjectType = ObjectType::AuthorityKey;
+}
+
 /// A Ciphertext Tuple representing a conjunction in a CT
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -136,20 +188,57 @@ pub struct BdabeCiphertextTuple {
     pub e5: G2,
 }
 
-/// A BDABE Ciphertext (CT)
+validate_fields!(BdabeCiphertextTuple { attr, e1, e2, e3, e4, e5 });
+
+/// A BDABE Header, i.e. the encapsulated key under a policy
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub struct BdabeCiphertext {
+pub struct BdabeHeader {
     pub policy: (String, PolicyLanguage),
     pub j: Vec<BdabeCiphertextTuple>,
+    pub cipher: SymmetricCipher,
+}
+
+validate_fields!(BdabeHeader { policy, j, cipher });
+
+impl Container for BdabeHeader {
+    const SCHEME: SchemeId = SchemeId::Bdabe;
+    const OBJECT: ObjectType = ObjectType::Header;
+}
+
+impl BdabeHeader {
+    /// Returns the associated data that binds this header to the symmetrically encrypted data:
+    /// the scheme id, the format version, the policy and the caller supplied `aad`.
+    pub fn associated_data(&self, aad: &[u8]) -> Vec<u8> {
+        let language = [self.policy.1 as u8];
+        associated_data("BDABE", &[self.policy.0.as_bytes(), &language], aad)
+    }
+}
+
+/// A BDABE Ciphertext (CT), composed of a BdabeHeader and the symmetrically encrypted data
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BdabeCiphertext {
+    pub header: BdabeHeader,
     pub ct: Vec<u8>,
 }
 
+validate_fields!(BdabeCiphertext { header, ct });
+
+impl Container for BdabeCiphertext {
+    const SCHEME: SchemeId = SchemeId::Bdabe;
+    const OBJECT: ObjectType = ObjectType::Ciphertext;
+}
+
 /// The setup algorithm of BDABE. Generates a BdabePublicKey and a BdabeMasterKey.
 pub fn setup() -> (BdabePublicKey, BdabeMasterKey) {
-    // random number generator
-    let mut rng = rand::thread_rng();
+    setup_with_rng(&mut rand::thread_rng())
+}
+
+/// Like `setup()`, but draws all randomness from the given random number generator `rng`.
+pub fn setup_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> (BdabePublicKey, BdabeMasterKey) {
     let g1:G1 = rng.gen();
     let g2:G2 = rng.gen();
     let p1:G1 = rng.gen();
@@ -172,12 +261,20 @@ pub fn setup() -> (BdabePublicKey, BdabeMasterKey) {
 ///	* `name` - The name of the authority the key is associated with. Must be unique.
 ///
 pub fn authgen(
+    pk: &BdabePublicKey,
+    msk: &BdabeMasterKey,
+    name: &str
+) -> BdabeSecretAuthorityKey {
+    authgen_with_rng(pk, msk, name, &mut rand::thread_rng())
+}
+
+/// Like `authgen()`, but draws all randomness from the given random number generator `rng`.
+pub fn authgen_with_rng<R: RngCore + CryptoRng>(
     pk: &BdabePublicKey,
     msk: &BdabeMasterKey,
     name: &str,
+    rng: &mut R
 ) -> BdabeSecretAuthorityKey {
-    // random number generator
-    let mut rng = rand::thread_rng();
     let _alpha: Fr = rng.gen();
     let _beta = msk.y - _alpha;
     let a1 = pk.g1 * _alpha;
@@ -199,12 +296,20 @@ pub fn authgen(
 ///	* `name` - The name of the user the key is associated with. Must be unique.
 ///
 pub fn keygen(
+    pk: &BdabePublicKey,
+    sk_a: &BdabeSecretAuthorityKey,
+    name: &str
+) -> BdabeUserKey {
+    keygen_with_rng(pk, sk_a, name, &mut rand::thread_rng())
+}
+
+/// Like `keygen()`, but draws all randomness from the given random number generator `rng`.
+pub fn keygen_with_rng<R: RngCore + CryptoRng>(
     pk: &BdabePublicKey,
     sk_a: &BdabeSecretAuthorityKey,
     name: &str,
+    rng: &mut R
 ) -> BdabeUserKey {
-    // random number generator
-    let mut rng = rand::thread_rng();
     let r_u: Fr = rng.gen();
     // return pk_u and sk_u
     BdabeUserKey {
@@ -236,9 +341,9 @@ pub fn request_attribute_pk(
 ) -> Result<BdabePublicAttributeKey, RabeError> {
     // if attribute a is from authority sk_a
     return if from_authority(attribute, &sk_a.name) {
-        match sha3_hash_fr(attribute) {
+        match hash_to_fr(SchemeId::Bdabe, &HashInput::new("attribute").string(attribute)) {
             Ok(hash_1) => {
-                match sha3_hash_fr(&sk_a.name) {
+                match hash_to_fr(SchemeId::Bdabe, &HashInput::new("authority").string(&sk_a.name)) {
                     Ok(hash_2) => {
                         let exp = hash_1 * hash_2 * sk_a.a3;
                         // return PK and mke
@@ -255,7 +360,7 @@ pub fn request_attribute_pk(
             Err(e) => Err(e)
         }
     } else {
-        Err(RabeError::new(&format!("attribute {} is not from_authority() or !is_eligible()", attribute.to_string())))
+        Err(RabeError::UnknownAttribute(attribute.to_string()))
     }
 }
 
@@ -274,9 +379,9 @@ pub fn request_attribute_sk(
 ) -> Result<BdabeSecretAttributeKey, RabeError> {
     // if attribute a is from authority sk_a
     return if from_authority(attribute, &sk_a.name) && is_eligible(attribute, &pk_u.u) {
-        match sha3_hash_fr(attribute) {
+        match hash_to_fr(SchemeId::Bdabe, &HashInput::new("attribute").string(attribute)) {
             Ok(hash_1) => {
-                match sha3_hash_fr(&sk_a.name) {
+                match hash_to_fr(SchemeId::Bdabe, &HashInput::new("authority").string(&sk_a.name)) {
                     Ok(hash_2) => {
                         let exp = hash_1 * hash_2 * sk_a.a3;
                         // return PK and mke
@@ -292,7 +397,7 @@ pub fn request_attribute_sk(
             Err(e) => Err(e)
         }
     } else {
-        Err(RabeError::new(&format!("attribute {} is not from_authority() or !is_eligible()", attribute.to_string())))
+        Err(RabeError::UnknownAttribute(attribute.to_string()))
     }
 }
 
@@ -303,47 +408,81 @@ pub fn request_attribute_sk(
 ///
 ///	* `pk` - A Public Key (PK), generated by the function setup()
 ///	* `attr_pks` - A Vector of all BdabePublicAttributeKeys that are involded in the policy
-///	* `policy` - An access policy given as JSON String
+///	* `policy` - An access policy, given as text and its PolicyLanguage or as Policy, see [PolicySource]
 ///	* `plaintext` - plaintext data given as a Vector of u8
 ///
-pub fn encrypt(
+pub fn encrypt<P: PolicySource>(
+    pk: &BdabePublicKey,
+    attr_pks: &[&BdabePublicAttributeKey],
+    policy: P,
+    plaintext: &[u8]
+) -> Result<BdabeCiphertext, RabeError> {
+    encrypt_with(pk, attr_pks, policy, plaintext, EncryptOptions::new())
+}
+
+/// Like `encrypt()`, but with the given [EncryptOptions], i.e. the random number generator, the associated data
+/// that has to be passed to `decrypt_with_aad()` and the symmetric cipher.
+pub fn encrypt_with<P: PolicySource, R: RngCore + CryptoRng>(
     pk: &BdabePublicKey,
     attr_pks: &[&BdabePublicAttributeKey],
-    policy: &str,
-    language: PolicyLanguage,
+    policy: P,
     plaintext: &[u8],
+    mut options: EncryptOptions<R>
 ) -> Result<BdabeCiphertext, RabeError> {
-    match parse(policy, language) {
-        Ok(pol) => {
-            // if policy is in DNF
-            if policy_in_dnf(&pol, false, None) {
-                // random number generator
-                let mut rng = rand::thread_rng();
-                // an DNF policy from the given String
-                let dnf: dnf::DnfPolicy = dnf::DnfPolicy::from_string(policy, attr_pks, language).unwrap();
-                // random Gt msg
-                let _msg = pairing(rng.gen(), rng.gen());
-                let mut j: Vec<BdabeCiphertextTuple> = Vec::new();
-                // now add randomness using _r_j
-                for _term in dnf.terms {
-                    let _r_j: Fr = rng.gen();
-                    j.push(BdabeCiphertextTuple {
-                        attr: _term.0,
-                        e1: _term.1.pow(_r_j) * _msg,
-                        e2: pk.p1 * _r_j,
-                        e3: pk.p2 * _r_j,
-                        e4: _term.3 * _r_j,
-                        e5: _term.4 * _r_j,
-                    });
-                }
-                //Encrypt plaintext using derived key from secret
-                match encrypt_symmetric(_msg, &plaintext.to_vec()) {
-                    Ok(ct) => Ok(BdabeCiphertext { policy: (policy.to_string(), language), j, ct }),
-                    Err(e) => Err(e)
-                }
-            } else {
-                Err(RabeError::new("Error in bdabe/encrypt: Policy not in DNF."))
+    let (key, header) = encapsulate_with(pk, attr_pks, policy, options.by_ref())?;
+    //Encrypt plaintext using the encapsulated key, binding the header as associated data
+    let ct = header.cipher.encrypt(&key, plaintext, &header.associated_data(options.aad), &mut options.rng)?;
+    Ok(BdabeCiphertext { header, ct })
+}
+
+/// The key encapsulation algorithm of BDABE. Generates a new SharedKey and a BdabeHeader using an BdabePublicKey,
+/// a Vector of BdabePublicAttributeKeys and an access policy given as String.
+///
+/// # Arguments
+///
+///	* `pk` - A Public Key (PK), generated by the function setup()
+///	* `attr_pks` - A Vector of all BdabePublicAttributeKeys that are involded in the policy
+///	* `policy` - An access policy, given as text and its PolicyLanguage or as Policy, see [PolicySource]
+///
+pub fn encapsulate<P: PolicySource>(
+    pk: &BdabePublicKey,
+    attr_pks: &[&BdabePublicAttributeKey],
+    policy: P
+) -> Result<(SharedKey, BdabeHeader), RabeError> {
+    encapsulate_with(pk, attr_pks, policy, EncryptOptions::new())
+}
+
+/// Like `encapsulate()`, but with the given [EncryptOptions], i.e. the random number generator and the symmetric
+/// cipher that is recorded in the header.
+pub fn encapsulate_with<P: PolicySource, R: RngCore + CryptoRng>(
+    pk: &BdabePublicKey,
+    attr_pks: &[&BdabePublicAttributeKey],
+    policy: P,
+    mut options: EncryptOptions<R>
+) -> Result<(SharedKey, BdabeHeader), RabeError> {
+    let rng = &mut options.rng;
+    match policy.value() {
+        Ok((pol, policy, language)) => {
+            check_monotone(&pol, "bdabe/encapsulate")?;
+            // the policy converted to DNF
+            let dnf: dnf::DnfPolicy = dnf::DnfPolicy::from_policy(&pol, attr_pks)?;
+            // random Gt msg
+            let _msg = pairing(rng.gen(), rng.gen());
+            let mut j: Vec<BdabeCiphertextTuple> = Vec::new();
+            // now add randomness using _r_j
+            for _term in dnf.terms {
+                let _r_j: Fr = rng.gen();
+                j.push(BdabeCiphertextTuple {
+                    attr: _term.0,
+                    e1: _term.1.pow(_r_j) * _msg,
+                    e2: pk.p1 * _r_j,
+                    e3: pk.p2 * _r_j,
+                    e4: _term.3 * _r_j,
+                    e5: _term.4 * _r_j,
+                });
             }
+            let header = BdabeHeader { policy: (policy.to_string(), language), j, cipher: options.cipher };
+            Ok((SharedKey::derive_for(_msg, &header)?, header))
         },
         Err(e) => Err(e)
     }
@@ -360,29 +499,55 @@ pub fn decrypt(
     sk: &BdabeUserKey,
     ct: &BdabeCiphertext
 ) -> Result<Vec<u8>, RabeError> {
+    decrypt_with_aad(sk, ct, &[])
+}
+
+/// Like `decrypt()`, but for ciphertexts that were generated by `encrypt_with()` with the associated data `aad`.
+pub fn decrypt_with_aad(
+    sk: &BdabeUserKey,
+    ct: &BdabeCiphertext,
+    aad: &[u8]
+) -> Result<Vec<u8>, RabeError> {
+    let key = decapsulate(sk, &ct.header)?;
+    // Decrypt plaintext using the key recovered from the Bdabe scheme
+    ct.header.cipher.decrypt(&key, &ct.ct, &ct.header.associated_data(aad))
+}
+
+/// The key decapsulation algorithm of BDABE. Recovers the SharedKey of a BdabeHeader with a matching BdabeUserKey.
+///
+/// # Arguments
+///
+///	* `sk` - A BdabeUserKey (SK), generated by the function keygen()
+///	* `header` - A BdabeHeader
+///
+pub fn decapsulate(
+    sk: &BdabeUserKey,
+    header: &BdabeHeader
+) -> Result<SharedKey, RabeError> {
     let str_attr = sk
         .sk_a
         .iter()
         .map(|v| v.attr.to_string())
         .collect::<Vec<_>>();
-    match parse(ct.policy.0.as_ref(), ct.policy.1) {
+    match parse(header.policy.0.as_ref(), header.policy.1) {
         Ok(pol) => {
             if traverse_policy(&str_attr, &pol, PolicyType::Leaf) == false {
-                Err(RabeError::new("Error in bdabe/decrypt: attributes in sk do not match policy in ct."))
+                Err(not_satisfied("bdabe/decrypt: attributes in sk do not match policy in ct", &str_attr, &pol))
             } else {
                 let mut msg = Gt::one();
-                for (_i, _ct_j) in ct.j.iter().enumerate() {
+                for (_i, _ct_j) in header.j.iter().enumerate() {
                     if is_satisfiable(&_ct_j.attr, &sk.sk_a) {
                         let _sk_sum = calc_satisfiable(&_ct_j.attr, &sk.sk_a);
-                        msg = _ct_j.e1
-                            * pairing(_ct_j.e2, _sk_sum.1)
-                            * pairing(_sk_sum.0, _ct_j.e3)
-                            * (pairing(_ct_j.e4, sk.sk.u2) * pairing(sk.sk.u1, _ct_j.e5)).inverse();
+                        msg = _ct_j.e1 * multi_pairing(&[
+                            (_ct_j.e2, _sk_sum.1),
+                            (_sk_sum.0, _ct_j.e3),
+                            (-_ct_j.e4, sk.sk.u2),
+                            (-sk.sk.u1, _ct_j.e5),
+                        ]);
                         break;
                     }
                 }
-                // Decrypt plaintext using derived secret from Bdabe scheme
-                decrypt_symmetric(msg, &ct.ct)
+                SharedKey::derive_for(msg, header)
             }
         },
         Err(e) => Err(e)
@@ -464,7 +629,7 @@ fn from_authority(
 ) -> bool {
     let v: Vec<_> = attr.match_indices("::").collect();
     if v.len() == 1 {
-        return attr.get(0..v[0].0).unwrap().to_string() == authority.to_string();
+        return attr.get(0..v[0].0) == Some(authority);
     }
     return false;
 }
@@ -485,9 +650,109 @@ fn is_eligible(
     return true;
 }
 
+/// The BDABE multi-authority scheme, to be used through the [`MultiAuthorityAbe`](../traits/trait.MultiAuthorityAbe.html) trait.
+///
+/// Attribute keys are derived on request, so the attributes given to authgen() are ignored.
+pub struct Bdabe;
+
+impl MultiAuthorityAbe for Bdabe {
+    type GlobalKey = BdabePublicKey;
+    type MasterKey = BdabeMasterKey;
+    type AuthorityKey = BdabeSecretAuthorityKey;
+    type AttributePublicKey = BdabePublicAttributeKey;
+    type SecretKey = BdabeUserKey;
+    type Ciphertext = BdabeCiphertext;
+    type Header = BdabeHeader;
+
+    fn setup_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> Result<(BdabePublicKey, BdabeMasterKey), RabeError> {
+        Ok(setup_with_rng(rng))
+    }
+
+    fn authgen_with_rng<R: RngCore + CryptoRng>(
+        pk: &BdabePublicKey,
+        msk: &BdabeMasterKey,
+        name: &str,
+        _attributes: &[&str],
+        rng: &mut R
+    ) -> Result<BdabeSecretAuthorityKey, RabeError> {
+        Ok(authgen_with_rng(pk, msk, name, rng))
+    }
+
+    fn attribute_public_key(
+        pk: &BdabePublicKey,
+        authority: &BdabeSecretAuthorityKey,
+        attribute: &str
+    ) -> Result<BdabePublicAttributeKey, RabeError> {
+        request_attribute_pk(pk, authority, attribute)
+    }
+
+    fn keygen_with_rng<R: RngCore + CryptoRng>(
+        pk: &BdabePublicKey,
+        _msk: &BdabeMasterKey,
+        authority: &BdabeSecretAuthorityKey,
+        name: &str,
+        attributes: &[&str],
+        rng: &mut R
+    ) -> Result<BdabeUserKey, RabeError> {
+        let mut sk = keygen_with_rng(pk, authority, name, rng);
+        for attribute in attributes {
+            <Bdabe as MultiAuthorityAbe>::add_attribute(pk, authority, attribute, &mut sk)?;
+        }
+        Ok(sk)
+    }
+
+    fn add_attribute(
+        _pk: &BdabePublicKey,
+        authority: &BdabeSecretAuthorityKey,
+        attribute: &str,
+        sk: &mut BdabeUserKey
+    ) -> Result<(), RabeError> {
+        let sk_a = request_attribute_sk(&sk.pk, authority, attribute)?;
+        sk.sk_a.push(sk_a);
+        Ok(())
+    }
+
+    fn encrypt_with<P: PolicySource, R: RngCore + CryptoRng>(
+        pk: &BdabePublicKey,
+        attr_pks: &[&BdabePublicAttributeKey],
+        policy: P,
+        plaintext: &[u8],
+        options: EncryptOptions<R>
+    ) -> Result<BdabeCiphertext, RabeError> {
+        encrypt_with(pk, attr_pks, policy, plaintext, options)
+    }
+
+    fn decrypt_with_aad(
+        _pk: &BdabePublicKey,
+        sk: &BdabeUserKey,
+        ct: &BdabeCiphertext,
+        aad: &[u8]
+    ) -> Result<Vec<u8>, RabeError> {
+        decrypt_with_aad(sk, ct, aad)
+    }
+
+    fn encapsulate_with<P: PolicySource, R: RngCore + CryptoRng>(
+        pk: &BdabePublicKey,
+        attr_pks: &[&BdabePublicAttributeKey],
+        policy: P,
+        options: EncryptOptions<R>
+    ) -> Result<(SharedKey, BdabeHeader), RabeError> {
+        encapsulate_with(pk, attr_pks, policy, options)
+    }
+
+    fn decapsulate(
+        _pk: &BdabePublicKey,
+        sk: &BdabeUserKey,
+        header: &BdabeHeader
+    ) -> Result<SharedKey, RabeError> {
+        decapsulate(sk, header)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use schemes::harness;
 
     #[test]
     fn and() {
@@ -524,8 +789,7 @@ mod tests {
             encrypt(
                 &_pk,
                 &attr_pks.as_slice(),
-                &policy,
-                PolicyLanguage::JsonPolicy,
+                (&policy, PolicyLanguage::JsonPolicy),
                 &_plaintext
             ).unwrap();
         // and now decrypt again with mathcing sk
@@ -562,11 +826,11 @@ mod tests {
         let _plaintext =
             String::from("dance like no one's watching, encrypt like everyone is!").into_bytes();
         // our policy
-        let _policy = String::from(r#"{"name": "or", "children": [{"name": "aa1::A"}, {"name": "aa2::B"}]}"#);
+        let _policy = String::from(r#"{"name": "or", "children": [{"name": "aa1::C"}, {"name": "aa2::B"}]}"#);
         let attr_pks: Vec<&BdabePublicAttributeKey> = vec!(&_att1_pk, &_att2_pk);
         // cp-abe ciphertext
         let _ct: BdabeCiphertext =
-            encrypt(&_pk, &attr_pks.as_slice(), &_policy, PolicyLanguage::JsonPolicy, &_plaintext).unwrap();
+            encrypt(&_pk, &attr_pks.as_slice(), (&_policy, PolicyLanguage::JsonPolicy), &_plaintext).unwrap();
         // and now decrypt again with mathcing sk
         let _match = decrypt(&sk, &_ct);
         assert_eq!(_match.is_ok(), true);
@@ -594,7 +858,7 @@ mod tests {
         // authority2 owns B
         let _att2_pk = request_attribute_pk(&_pk, &_a2_key, &_att2).unwrap();
         // authority3 owns C
-        let _att2_pk = request_attribute_pk(&_pk, &_a3_key, &_att3).unwrap();
+        let _att3_pk = request_attribute_pk(&_pk, &_a3_key, &_att3).unwrap();
         // add attribute sk's to user key
         sk
             .sk_a
@@ -610,12 +874,12 @@ mod tests {
             String::from("dance like no one's watching, encrypt like everyone is!").into_bytes();
         // our policy
         let _policy = String::from(
-            r#"{"name": "or", "children": [{"name": "and", "children": [{"name": "aa3::C"}, {"name": "aa2::B"}]}, {"name": "aa1::X"}]}"#,
+            r#"{"name": "or", "children": [{"name": "and", "children": [{"name": "aa3::C"}, {"name": "aa2::B"}]}, {"name": "aa1::A"}]}"#,
         );
-        let attr_pks: Vec<&BdabePublicAttributeKey> = vec!(&_att1_pk, &_att2_pk);
+        let attr_pks: Vec<&BdabePublicAttributeKey> = vec!(&_att1_pk, &_att2_pk, &_att3_pk);
         // cp-abe ciphertext
         let _ct: BdabeCiphertext =
-            encrypt(&_pk, &attr_pks.as_slice(), &_policy,PolicyLanguage::JsonPolicy, &_plaintext).unwrap();
+            encrypt(&_pk, &attr_pks.as_slice(), (&_policy, PolicyLanguage::JsonPolicy), &_plaintext).unwrap();
         // and now decrypt again with mathcing sk
         let _match = decrypt(&sk, &_ct);
         assert_eq!(_match.is_ok(), true);
@@ -652,12 +916,13 @@ mod tests {
         // our policy
         let _policy = String::from(r#"{"name": "or", "children": [{"name": "aa1::B"}, {"name": "aa2::A"}]}"#);
         let attr_pks: Vec<&BdabePublicAttributeKey> = vec!(&_att1_pk, &_att2_pk);
-        // cp-abe ciphertext
-        let _ct: BdabeCiphertext =
-            encrypt(&_pk, &attr_pks.as_slice(), &_policy, PolicyLanguage::JsonPolicy, &_plaintext).unwrap();
-        // and now decrypt again with mathcing sk
-        let _match = decrypt(&sk, &_ct);
-        assert_eq!(_match.is_ok(), false);
+        // none of the attributes of the policy has a public key, so there is nothing to encrypt to
+        let _ct = encrypt(&_pk, &attr_pks.as_slice(), (&_policy, PolicyLanguage::JsonPolicy), &_plaintext);
+        assert!(matches!(_ct, Err(RabeError::UnknownAttribute(_))));
     }
 
+    #[test]
+    fn ma_abe() {
+        harness::ma_abe::<Bdabe>().unwrap();
+    }
 }
@@ -16,26 +16,38 @@
 //! let (pk, msk) = setup();
 //! let plaintext = String::from("dance like no one's watching, encrypt like everyone is!").into_bytes();
 //! let policy = String::from(r#""A" and "B""#);
-//! let ct_cp: CpAbeCiphertext = encrypt(&pk, &policy, PolicyLanguage::HumanPolicy, &plaintext).unwrap();
+//! let ct_cp: CpAbeCiphertext = encrypt(&pk, (&policy, PolicyLanguage::HumanPolicy), &plaintext).unwrap();
 //! let sk: CpAbeSecretKey = keygen(&pk, &msk, &vec!["A", "B"]).unwrap();
 //! assert_eq!(decrypt(&sk, &ct_cp).unwrap(), plaintext);
 //! ```
+use std::borrow::Cow;
 use rabe_bn::{Fr, G1, G2, Gt, pairing};
-use rand::Rng;
+use rand::{CryptoRng, Rng, RngCore};
 use utils::{
-    secretsharing::{gen_shares_policy, calc_pruned, calc_coefficients},
+    secretsharing::{gen_shares_policy_with_rng, calc_pruned_minimal, calc_coefficients, LeafWeights},
     tools::*,
     aes::*,
-    hash::*
+    hash::*,
+    container::{Container, SchemeId, ObjectType},
+    pairing::multi_pairing,
+    fixed_base::FixedBase,
+    parallel,
 };
-use utils::policy::pest::{PolicyLanguage, parse, PolicyType};
+use utils::policy::pest::{PolicyLanguage, parse, check_monotone, PolicyType};
+use utils::policy::ast::PolicySource;
+use utils::policy::explain::not_satisfied;
 use crate::error::RabeError;
+use schemes::traits::{CpAbe, DelegatableCpAbe};
+use schemes::options::EncryptOptions;
 use utils::secretsharing::remove_index;
 #[cfg(feature = "borsh")]
 use borsh::{BorshSerialize, BorshDeserialize};
 #[cfg(feature = "serde")]
 use serde::{Serialize, Deserialize};
 
+/// The decryption cost of the leaves of a policy: two pairings per leaf
+const LEAF_WEIGHTS: LeafWeights = LeafWeights { attribute: 2, negated: 2 };
+
 /// A BSW Public Key (PK)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -48,6 +60,63 @@ pub struct CpAbePublicKey {
     pub e_gg_alpha: Gt,
 }
 
+validate_fields!(CpAbePublicKey { g1, g2, h, f, e_gg_alpha });
+
+impl Container for CpAbePublicKey {
+    const SCHEME: SchemeId = SchemeId::Bsw;
+    const OBJECT: ObjectType = ObjectType::PublicKey;
+}
+
+/// A BSW Public Key (PK) with precomputed tables for all bases that are exponentiated during encryption, which
+/// speeds up encryption if the key is reused for many encryptions
+#[derive(Clone, PartialEq, Debug)]
+pub struct CpAbePreparedPublicKey {
+    pub pk: CpAbePublicKey,
+    g1: FixedBase<G1>,
+    h: FixedBase<G1>,
+    e_gg_alpha: FixedBase<Gt>,
+}
+
+impl CpAbePreparedPublicKey {
+    /// Precomputes the tables of the given CpAbePublicKey
+    pub fn new(pk: &CpAbePublicKey) -> CpAbePreparedPublicKey {
+        CpAbePreparedPublicKey {
+            pk: pk.clone(),
+            g1: FixedBase::prepare(pk.g1),
+            h: FixedBase::prepare(pk.h),
+            e_gg_alpha: FixedBase::prepare(pk.e_gg_alpha),
+        }
+    }
+
+    // the bases of the given key without tables
+    fn unprepared(pk: &CpAbePublicKey) -> CpAbePreparedPublicKey {
+        CpAbePreparedPublicKey {
+            pk: pk.clone(),
+            g1: FixedBase::new(pk.g1),
+            h: FixedBase::new(pk.h),
+            e_gg_alpha: FixedBase::new(pk.e_gg_alpha),
+        }
+    }
+}
+
+/// A key that encrypts: a CpAbePublicKey or a CpAbePreparedPublicKey
+pub trait CpAbeEncryptionKey {
+    /// Returns the bases of the key, with tables if the key is prepared
+    fn prepared(&self) -> Cow<'_, CpAbePreparedPublicKey>;
+}
+
+impl CpAbeEncryptionKey for CpAbePublicKey {
+    fn prepared(&self) -> Cow<'_, CpAbePreparedPublicKey> {
+        Cow::Owned(CpAbePreparedPublicKey::unprepared(self))
+    }
+}
+
+impl CpAbeEncryptionKey for CpAbePreparedPublicKey {
+    fn prepared(&self) -> Cow<'_, CpAbePreparedPublicKey> {
+        Cow::Borrowed(self)
+    }
+}
+
 /// A BSW Master Key (MSK)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -57,18 +126,57 @@ pub struct CpAbeMasterKey {
     pub g2_alpha: G2,
 }
 
-/// A BSW Ciphertext (CT)
+validate_fields!(CpAbeMasterKey { beta, g2_alpha });
+
+impl Container for CpAbeMasterKey {
+    const SCHEME: SchemeId = SchemeId::Bsw;
+    const OBJECT: ObjectType = ObjectType::MasterKey;
+}
+
+/// A BSW Header, i.e. the encapsulated key under a policy
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub struct CpAbeCiphertext {
+pub struct CpAbeHeader {
     pub policy: (String, PolicyLanguage),
     pub c: G1,
     pub c_p: Gt,
     pub c_y: Vec<CpAbeAttribute>,
+    pub cipher: SymmetricCipher,
+}
+
+validate_fields!(CpAbeHeader { policy, c, c_p, c_y, cipher });
+
+impl Container for CpAbeHeader {
+    const SCHEME: SchemeId = SchemeId::Bsw;
+    const OBJECT: ObjectType = ObjectType::Header;
+}
+
+impl CpAbeHeader {
+    /// Returns the associated data that binds this header to the symmetrically encrypted data:
+    /// the scheme id, the format version, the policy and the caller supplied `aad`.
+    pub fn associated_data(&self, aad: &[u8]) -> Vec<u8> {
+        let language = [self.policy.1 as u8];
+        associated_data("BSW", &[self.policy.0.as_bytes(), &language], aad)
+    }
+}
+
+/// A BSW Ciphertext (CT), composed of a CpAbeHeader and the symmetrically encrypted data
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CpAbeCiphertext {
+    pub header: CpAbeHeader,
     pub data: Vec<u8>,
 }
 
+validate_fields!(CpAbeCiphertext { header, data });
+
+impl Container for CpAbeCiphertext {
+    const SCHEME: SchemeId = SchemeId::Bsw;
+    const OBJECT: ObjectType = ObjectType::Ciphertext;
+}
+
 /// A BSW Secret User Key (SK)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -78,6 +186,13 @@ pub struct CpAbeSecretKey {
     pub d_j: Vec<CpAbeAttribute>,
 }
 
+validate_fields!(CpAbeSecretKey { d, d_j });
+
+impl Container for CpAbeSecretKey {
+    const SCHEME: SchemeId = SchemeId::Bsw;
+    const OBJECT: ObjectType = ObjectType::SecretKey;
+}
+
 /// A BSW Attribute
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -88,20 +203,31 @@ pub struct CpAbeAttribute {
     pub g2: G2,
 }
 
+validate_fields!(CpAbeAttribute { string, g1, g2 });
+
 /// The setup algorithm of BSW CP-ABE. Generates a new CpAbePublicKey and a new CpAbeMasterKey.
 pub fn setup() -> (CpAbePublicKey, CpAbeMasterKey) {
-    // random number generator
-    let mut rng = rand::thread_rng();
+    setup_with_rng(&mut rand::thread_rng())
+}
+
+/// Like `setup()`, but draws all randomness from the given random number generator `rng`.
+pub fn setup_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> (CpAbePublicKey, CpAbeMasterKey) {
     // generator of group G1: g1 and generator of group G2: g2
     let g1:G1 = rng.gen();
     let g2:G2 = rng.gen();
     // random
-    let beta:Fr = rng.gen();
+    let (beta, beta_inverse) = loop {
+        // zero has no inverse, draw again
+        let beta:Fr = rng.gen();
+        if let Some(beta_inverse) = beta.inverse() {
+            break (beta, beta_inverse);
+        }
+    };
     let alpha:Fr = rng.gen();
     // vectors
     // calulate h and f
     let h = g1 * beta;
-    let f = g2 * beta.inverse().unwrap();
+    let f = g2 * beta_inverse;
     // calculate g2^alpha
     let g2_alpha = g2 * alpha;
     // calculate the pairing between g1 and g2^alpha
@@ -123,32 +249,41 @@ pub fn setup() -> (CpAbePublicKey, CpAbeMasterKey) {
 ///	* `attributes` - A Vector of String attributes assigned to this user key
 ///
 pub fn keygen(
+    pk: &CpAbePublicKey,
+    msk: &CpAbeMasterKey,
+    attributes: &[&str]
+) -> Result<CpAbeSecretKey, RabeError> {
+    keygen_with_rng(pk, msk, attributes, &mut rand::thread_rng())
+}
+
+/// Like `keygen()`, but draws all randomness from the given random number generator `rng`.
+pub fn keygen_with_rng<R: RngCore + CryptoRng>(
     pk: &CpAbePublicKey,
     msk: &CpAbeMasterKey,
     attributes: &[&str],
-) -> Option<CpAbeSecretKey> {
+    rng: &mut R
+) -> Result<CpAbeSecretKey, RabeError> {
     // if no attibutes or an empty policy
     // maybe add empty msk also here
-    if attributes.is_empty() || attributes.len() == 0 {
-        return None;
+    if attributes.is_empty() {
+        return Err(RabeError::InvalidInput(String::from("bsw/keygen: attributes are empty")));
     }
-    // random number generator
-    let mut rng = rand::thread_rng();
     // generate random r1 and r2 and sum of both
     // compute Br as well because it will be used later too
     let r:Fr = rng.gen();
     let g2_r = pk.g2 * r;
-    let d = (msk.g2_alpha + g2_r) * msk.beta.inverse().unwrap();
+    let beta_inverse = msk.beta.inverse().ok_or_else(|| RabeError::InvalidKey(String::from("bsw/keygen: master key contains zero")))?;
+    let d = (msk.g2_alpha + g2_r) * beta_inverse;
     let mut d_j: Vec<CpAbeAttribute> = Vec::new();
     for j in attributes {
         let r_j:Fr = rng.gen();
         d_j.push(CpAbeAttribute {
             string: j.to_string(), // attribute name
             g1: pk.g1 * r_j, // D_j Prime
-            g2: g2_r + (sha3_hash(pk.g2, j).expect("could not hash _j") * r_j), // D_j
+            g2: g2_r + (hash_to_g2(SchemeId::Bsw, &HashInput::new("attribute").string(j))? * r_j), // D_j
         });
     }
-    return Some(CpAbeSecretKey { d, d_j });
+    Ok(CpAbeSecretKey { d, d_j })
 }
 
 /// The delegate generation algorithm of BSW CP-ABE. Generates a new CpAbeSecretKey using a CpAbePublicKey, a CpAbeSecretKey and a subset of attributes (of the key _sk) given as Vec<String>.
@@ -160,26 +295,33 @@ pub fn keygen(
 ///	* `subset` - A Vector of String attributes delegated to the new user key
 ///
 pub fn delegate(
+    pk: &CpAbePublicKey,
+    sk: &CpAbeSecretKey,
+    subset: &[&str]
+) -> Result<CpAbeSecretKey, RabeError> {
+    delegate_with_rng(pk, sk, subset, &mut rand::thread_rng())
+}
+
+/// Like `delegate()`, but draws all randomness from the given random number generator `rng`.
+pub fn delegate_with_rng<R: RngCore + CryptoRng>(
     pk: &CpAbePublicKey,
     sk: &CpAbeSecretKey,
     subset: &[&str],
-) -> Option<CpAbeSecretKey> {
+    rng: &mut R
+) -> Result<CpAbeSecretKey, RabeError> {
     let attr_str = sk.d_j
         .iter()
         .map(|val| val.string.as_str())
         .collect::<Vec<_>>();
     return if !is_subset(subset, &attr_str) {
-        println!("Error: the given attribute set is not a subset of the given sk.");
-        None
+        let missing = subset.iter().find(|attr| !attr_str.contains(attr)).unwrap_or(&"");
+        Err(RabeError::UnknownAttribute(format!("bsw/delegate: {} is not an attribute of the key", missing)))
     } else {
         // if no attibutes or an empty policy
         // maybe add empty msk also here
-        if subset.is_empty() || subset.len() == 0 {
-            println!("Error: the given attribute subset is empty.");
-            return None;
+        if subset.is_empty() {
+            return Err(RabeError::InvalidInput(String::from("bsw/delegate: the attribute subset is empty")));
         }
-        // random number generator
-        let mut rng = rand::thread_rng();
         // generate random r
         let r: Fr = rng.gen();
         // calculate derived _k_0
@@ -191,14 +333,14 @@ pub fn delegate(
                 .iter()
                 .find(|x| x.string == attr.to_string())
                 .map(|x| (x.g1, x.g2))
-                .unwrap();
+                .ok_or_else(|| RabeError::UnknownAttribute(attr.to_string()))?;
             d_j.push(CpAbeAttribute {
                 string: attr.to_string(),
                 g1: d_j_val.0 + (pk.g1 * r_j),
-                g2: d_j_val.1 + (sha3_hash(pk.g2, attr.as_ref()).expect("could not hash _attr") * r_j) + (pk.g2 * r),
+                g2: d_j_val.1 + (hash_to_g2(SchemeId::Bsw, &HashInput::new("attribute").string(attr.as_ref()))? * r_j) + (pk.g2 * r),
             });
         }
-        Some(CpAbeSecretKey {
+        Ok(CpAbeSecretKey {
             d: sk.d + (pk.f * r),
             d_j,
         })
@@ -209,42 +351,74 @@ pub fn delegate(
 ///
 /// # Arguments
 ///
-///	* `pk` - A Public Key (PK), generated by the function setup()
-///	* `policy` - An access policy given as JSON String
-///	* `language` - The policy language
+///	* `pk` - A Public Key (PK), generated by the function setup(), or a CpAbePreparedPublicKey
+///	* `policy` - An access policy, given as text and its PolicyLanguage or as Policy, see [PolicySource]
 ///	* `plaintext` - plaintext data given as a Vector of u8
 ///
-pub fn encrypt(
-    pk: &CpAbePublicKey,
-    policy: &str,
-    language: PolicyLanguage,
+pub fn encrypt<K: CpAbeEncryptionKey + ?Sized, P: PolicySource>(
+    pk: &K,
+    policy: P,
+    plaintext: &[u8]
+) -> Result<CpAbeCiphertext, RabeError> {
+    encrypt_with(pk, policy, plaintext, EncryptOptions::new())
+}
+
+/// Like `encrypt()`, but with the given [EncryptOptions], i.e. the random number generator, the associated data
+/// that has to be passed to `decrypt_with_aad()` and the symmetric cipher.
+pub fn encrypt_with<K: CpAbeEncryptionKey + ?Sized, P: PolicySource, R: RngCore + CryptoRng>(
+    pk: &K,
+    policy: P,
     plaintext: &[u8],
+    mut options: EncryptOptions<R>
 ) -> Result<CpAbeCiphertext, RabeError> {
-    if plaintext.is_empty() || policy.is_empty() {
-        RabeError::new("Error in bsw/encrypt: data or policy is empty.");
-    }
-    let mut rng = rand::thread_rng();
+    let (key, header) = encapsulate_with(pk, policy, options.by_ref())?;
+    //Encrypt plaintext using the encapsulated key, binding the header as associated data
+    let data = header.cipher.encrypt(&key, plaintext, &header.associated_data(options.aad), &mut options.rng)?;
+    Ok(CpAbeCiphertext { header, data })
+}
+
+/// The key encapsulation algorithm of BSW CP-ABE. Generates a new SharedKey and a CpAbeHeader that encapsulates it under an access policy given as String.
+///
+/// # Arguments
+///
+///	* `pk` - A Public Key (PK), generated by the function setup(), or a CpAbePreparedPublicKey
+///	* `policy` - An access policy, given as text and its PolicyLanguage or as Policy, see [PolicySource]
+///
+pub fn encapsulate<K: CpAbeEncryptionKey + ?Sized, P: PolicySource>(
+    pk: &K,
+    policy: P
+) -> Result<(SharedKey, CpAbeHeader), RabeError> {
+    encapsulate_with(pk, policy, EncryptOptions::new())
+}
+
+/// Like `encapsulate()`, but with the given [EncryptOptions], i.e. the random number generator and the symmetric
+/// cipher that is recorded in the header.
+pub fn encapsulate_with<K: CpAbeEncryptionKey + ?Sized, P: PolicySource, R: RngCore + CryptoRng>(
+    pk: &K,
+    policy: P,
+    mut options: EncryptOptions<R>
+) -> Result<(SharedKey, CpAbeHeader), RabeError> {
+    let rng = &mut options.rng;
+    let pk = pk.prepared();
     // the shared root secret
     let secret:Fr = rng.gen();
     let msg: Gt = rng.gen();
-    match parse(policy, language) {
-        Ok(policy_value) => {
-            let shares: Vec<(String, Fr)> = gen_shares_policy(secret, &policy_value, None).unwrap();
-            let c = pk.h * secret;
-            let c_p = pk.e_gg_alpha.pow(secret) * msg;
-            let mut c_y: Vec<CpAbeAttribute> = Vec::new();
-            for (node, i_val) in shares.clone() {
-                let j = remove_index(&node);
-                c_y.push(CpAbeAttribute {
-                    string: node,
-                    g1: pk.g1 * i_val,
-                    g2: sha3_hash(pk.g2, &j).expect("could not hash j") * i_val,
-                });
-            }
-            match encrypt_symmetric(msg, &plaintext.to_vec()) {
-                Ok(data) => Ok(CpAbeCiphertext { policy: (policy.to_string(), language), c, c_p, c_y, data }),
-                Err(e) => Err(e)
-            }
+    match policy.value() {
+        Ok((policy_value, policy, language)) => {
+            check_monotone(&policy_value, "bsw/encapsulate")?;
+            let shares: Vec<(String, Fr)> = gen_shares_policy_with_rng(secret, &policy_value, None, rng)?;
+            let c = pk.h.exp(secret);
+            let c_p = pk.e_gg_alpha.exp(secret) * msg;
+            let c_y: Vec<CpAbeAttribute> = parallel::try_map(&shares, |(node, i_val)| {
+                let j = remove_index(node);
+                Ok(CpAbeAttribute {
+                    string: node.clone(),
+                    g1: pk.g1.exp(*i_val),
+                    g2: hash_to_g2(SchemeId::Bsw, &HashInput::new("attribute").string(&j))? * *i_val,
+                })
+            })?;
+            let header = CpAbeHeader { policy: (policy.to_string(), language), c, c_p, c_y, cipher: options.cipher };
+            Ok((SharedKey::derive_for(msg, &header)?, header))
         }
         Err(e) => Err(e)
     }
@@ -261,37 +435,61 @@ pub fn decrypt(
     sk: &CpAbeSecretKey,
     ct: &CpAbeCiphertext
 ) -> Result<Vec<u8>, RabeError> {
+    decrypt_with_aad(sk, ct, &[])
+}
+
+/// Like `decrypt()`, but for ciphertexts that were generated by `encrypt_with()` with the associated data `aad`.
+pub fn decrypt_with_aad(
+    sk: &CpAbeSecretKey,
+    ct: &CpAbeCiphertext,
+    aad: &[u8]
+) -> Result<Vec<u8>, RabeError> {
+    let key = decapsulate(sk, &ct.header)?;
+    // Decrypt plaintext using the key recovered from the cp-abe scheme
+    ct.header.cipher.decrypt(&key, &ct.data, &ct.header.associated_data(aad))
+}
+
+/// The key decapsulation algorithm of BSW CP-ABE. Recovers the SharedKey of a CpAbeHeader with a matching CpAbeSecretKey.
+///
+/// # Arguments
+///
+///	* `sk` - A Secret Key (SK), generated by the function keygen()
+///	* `header` - A BSW CP-ABE Header
+///
+pub fn decapsulate(
+    sk: &CpAbeSecretKey,
+    header: &CpAbeHeader
+) -> Result<SharedKey, RabeError> {
     let attr = sk.d_j
         .iter()
         .map(|v| v.string.clone() )
         .collect::<Vec<_>>();
-    match parse(ct.policy.0.as_ref(), ct.policy.1) {
+    match parse(header.policy.0.as_ref(), header.policy.1) {
         Ok(policy_value) => {
             return if traverse_policy(&attr, &policy_value, PolicyType::Leaf) == false {
-                Err(RabeError::new("Error in bsw/encrypt: attributes do not match policy."))
+                Err(not_satisfied("bsw/decapsulate: attributes do not match policy", &attr, &policy_value))
             } else {
-                match calc_pruned(&attr, &policy_value, None) {
+                match calc_pruned_minimal(&attr, &policy_value, LEAF_WEIGHTS) {
                     Err(e) => Err(e),
                     Ok(pruned) => {
                         if !pruned.0 {
-                            Err(RabeError::new("Error in bsw/encrypt: attributes do not match policy."))
+                            Err(not_satisfied("bsw/decapsulate: attributes do not match policy", &attr, &policy_value))
                         } else {
                             let mut z: Vec<(String, Fr)> = Vec::new();
-                            z = calc_coefficients(&policy_value, Some(Fr::one()), z, None).unwrap();
-                            let mut a = Gt::one();
+                            z = calc_coefficients(&policy_value, Some(Fr::one()), z, None, &pruned.1)?;
+                            // e(c, d)^-1 and the pairings of the leaves, weighted by their coefficients
+                            let mut _pairs: Vec<(G1, G2)> = vec![(-header.c, sk.d)];
                             for _i in pruned.1 {
                                 let _k = _i.0;
                                 let _j = _i.1;
-                                match ct.c_y.iter().find(|x| x.string == _j.to_string()) {
+                                match header.c_y.iter().find(|x| x.string == _j.to_string()) {
                                     Some(c_y) => {
                                         match sk.d_j.iter().find(|x| x.string == _k.to_string()) {
                                             Some(d_j) => {
                                                 for _z_tuple in z.iter() {
                                                     if _z_tuple.0 == _j {
-                                                        a = a *
-                                                            (pairing(c_y.g1, d_j.g2) *
-                                                                pairing(d_j.g1, c_y.g2).inverse())
-                                                                .pow(_z_tuple.1);
+                                                        _pairs.push((c_y.g1 * _z_tuple.1, d_j.g2));
+                                                        _pairs.push((-(d_j.g1 * _z_tuple.1), c_y.g2));
                                                     }
                                                 }
                                             }
@@ -305,9 +503,8 @@ pub fn decrypt(
                                     }
                                 }
                             }
-                            let _msg = ct.c_p * ((pairing(ct.c, sk.d)) * a.inverse()).inverse();
-                            // Decrypt plaintext using derived secret from cp-abe scheme
-                            decrypt_symmetric(_msg, &ct.data)
+                            let _msg = header.c_p * multi_pairing(&_pairs);
+                            SharedKey::derive_for(_msg, header)
                         }
                     }
                 }
@@ -317,10 +514,81 @@ pub fn decrypt(
     }
 }
 
+/// The BSW CP-ABE scheme, to be used through the [`CpAbe`](../traits/trait.CpAbe.html) trait.
+pub struct Bsw;
+
+impl CpAbe for Bsw {
+    type PublicKey = CpAbePublicKey;
+    type MasterKey = CpAbeMasterKey;
+    type SecretKey = CpAbeSecretKey;
+    type Ciphertext = CpAbeCiphertext;
+    type Header = CpAbeHeader;
+
+    fn setup_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> Result<(CpAbePublicKey, CpAbeMasterKey), RabeError> {
+        Ok(setup_with_rng(rng))
+    }
+
+    fn keygen_with_rng<R: RngCore + CryptoRng>(
+        pk: &CpAbePublicKey,
+        msk: &CpAbeMasterKey,
+        attributes: &[&str],
+        rng: &mut R
+    ) -> Result<CpAbeSecretKey, RabeError> {
+        keygen_with_rng(pk, msk, attributes, rng)
+    }
+
+    fn encrypt_with<P: PolicySource, R: RngCore + CryptoRng>(
+        pk: &CpAbePublicKey,
+        policy: P,
+        plaintext: &[u8],
+        options: EncryptOptions<R>
+    ) -> Result<CpAbeCiphertext, RabeError> {
+        encrypt_with(pk, policy, plaintext, options)
+    }
+
+    fn decrypt_with_aad(
+        sk: &CpAbeSecretKey,
+        ct: &CpAbeCiphertext,
+        aad: &[u8]
+    ) -> Result<Vec<u8>, RabeError> {
+        decrypt_with_aad(sk, ct, aad)
+    }
+
+    fn encapsulate_with<P: PolicySource, R: RngCore + CryptoRng>(
+        pk: &CpAbePublicKey,
+        policy: P,
+        options: EncryptOptions<R>
+    ) -> Result<(SharedKey, CpAbeHeader), RabeError> {
+        encapsulate_with(pk, policy, options)
+    }
+
+    fn decapsulate(
+        sk: &CpAbeSecretKey,
+        header: &CpAbeHeader
+    ) -> Result<SharedKey, RabeError> {
+        decapsulate(sk, header)
+    }
+}
+
+impl DelegatableCpAbe for Bsw {
+    fn delegate_with_rng<R: RngCore + CryptoRng>(
+        pk: &CpAbePublicKey,
+        sk: &CpAbeSecretKey,
+        subset: &[&str],
+        rng: &mut R
+    ) -> Result<CpAbeSecretKey, RabeError> {
+        delegate_with_rng(pk, sk, subset, rng)
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use schemes::harness;
+    use utils::aes::decrypt_with_key;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
 
     #[test]
     fn or() {
@@ -344,7 +612,7 @@ mod tests {
         let policy = String::from(r#"{"name": "or", "children": [{"name": "A"}, {"name": "B"}]}"#);
 
         // cp-abe ciphertext
-        let ct_cp: CpAbeCiphertext = encrypt(&pk, &policy, PolicyLanguage::JsonPolicy, &plaintext).unwrap();
+        let ct_cp: CpAbeCiphertext = encrypt(&pk, (&policy, PolicyLanguage::JsonPolicy), &plaintext).unwrap();
 
         // and now decrypt again with mathcing sk
         let _match = decrypt(&keygen(&pk, &msk, &att_matching).unwrap(), &ct_cp);
@@ -353,6 +621,9 @@ mod tests {
 
         let _no_match = decrypt(&keygen(&pk, &msk, &att_not_matching).unwrap(), &ct_cp);
         assert_eq!(_no_match.is_ok(), false);
+
+        // keygen reports why it failed
+        assert!(matches!(keygen(&pk, &msk, &[]), Err(RabeError::InvalidInput(_))));
     }
 
     #[test]
@@ -388,7 +659,7 @@ mod tests {
         }
         _policy.push_str(&String::from("}"));
         // cp-abe ciphertext
-        let ct_cp: CpAbeCiphertext = encrypt(&pk, &_policy, PolicyLanguage::JsonPolicy, &plaintext).unwrap();
+        let ct_cp: CpAbeCiphertext = encrypt(&pk, (&_policy, PolicyLanguage::JsonPolicy), &plaintext).unwrap();
 
         // and now decrypt again with mathcing sk
         let _match = decrypt(&keygen(&pk, &msk, &att).unwrap(), &ct_cp);
@@ -428,7 +699,7 @@ mod tests {
             policy = policy_str.clone();
         }
         // cp-abe ciphertext
-        let ct_cp: CpAbeCiphertext = encrypt(&pk, &policy, PolicyLanguage::JsonPolicy, &plaintext).unwrap();
+        let ct_cp: CpAbeCiphertext = encrypt(&pk, (&policy, PolicyLanguage::JsonPolicy), &plaintext).unwrap();
         // and now decrypt again with mathcing sk
         let _match = decrypt(&keygen(&pk, &msk, &attr).unwrap(), &ct_cp);
         assert_eq!(_match.is_ok(), true);
@@ -458,7 +729,7 @@ mod tests {
         let policy = String::from(r#"{"name": "or", "children": [{"name": "X"}, {"name": "Y"}, {"name": "A"}]}"#);
 
         // cp-abe ciphertext
-        let ct_cp: CpAbeCiphertext = encrypt(&pk, &policy, PolicyLanguage::JsonPolicy, &plaintext).unwrap();
+        let ct_cp: CpAbeCiphertext = encrypt(&pk, (&policy, PolicyLanguage::JsonPolicy), &plaintext).unwrap();
 
         // and now decrypt again with mathcing sk
         let _match = decrypt(&keygen(&pk, &msk, &att_matching).unwrap(), &ct_cp);
@@ -487,7 +758,7 @@ mod tests {
         // our policy
         let policy = String::from(r#"{"name": "and", "children":  [{"name": "A"}, {"name": "B"}]}"#);
         // cp-abe ciphertext
-        let ct_cp: CpAbeCiphertext = encrypt(&pk, &policy, PolicyLanguage::JsonPolicy, &plaintext).unwrap();
+        let ct_cp: CpAbeCiphertext = encrypt(&pk, (&policy, PolicyLanguage::JsonPolicy), &plaintext).unwrap();
         // and now decrypt again with mathcing sk
         let _match = decrypt(&keygen(&pk, &msk, &att_matching).unwrap(), &ct_cp);
         assert_eq!(_match.is_ok(), true);
@@ -514,7 +785,7 @@ mod tests {
         // our policy
         let policy = String::from(r#"{"name": "or", "children": [{"name": "and", "children":  [{"name": "A"}, {"name": "B"}]}, {"name": "and", "children":  [{"name": "B"}, {"name": "C"}]}]}"#);
         // cp-abe ciphertext
-        let ct_cp: CpAbeCiphertext = encrypt(&pk, &policy, PolicyLanguage::JsonPolicy, &plaintext).unwrap();
+        let ct_cp: CpAbeCiphertext = encrypt(&pk, (&policy, PolicyLanguage::JsonPolicy), &plaintext).unwrap();
         // and now decrypt again with mathcing sk
         let _match = decrypt(&keygen(&pk, &msk, &att_matching).unwrap(), &ct_cp);
         assert_eq!(_match.is_ok(), true);
@@ -542,7 +813,7 @@ mod tests {
         // our policy
         let policy = String::from(r#"{"name": "and", "children":  [{"name": "A"}, {"name": "B"}, {"name": "C"}]}"#);
         // cp-abe ciphertext
-        let ct_cp: CpAbeCiphertext = encrypt(&pk, &policy, PolicyLanguage::JsonPolicy, &plaintext).unwrap();
+        let ct_cp: CpAbeCiphertext = encrypt(&pk, (&policy, PolicyLanguage::JsonPolicy), &plaintext).unwrap();
         // and now decrypt again with mathcing sk
         let _match = decrypt(&keygen(&pk, &msk, &att_matching).unwrap(), &ct_cp);
         assert_eq!(_match.is_ok(), true);
@@ -566,7 +837,7 @@ mod tests {
         // our policy
         let policy = String::from(r#"{"name": "or", "children": [{"name": "and", "children":  [{"name": "A"}, {"name": "B"}]}, {"name": "and", "children":  [{"name": "C"}, {"name": "D"}]}]}"#);
         // cp-abe ciphertext
-        let ct_cp: CpAbeCiphertext = encrypt(&pk, &policy, PolicyLanguage::JsonPolicy, &plaintext).unwrap();
+        let ct_cp: CpAbeCiphertext = encrypt(&pk, (&policy, PolicyLanguage::JsonPolicy), &plaintext).unwrap();
         // and now decrypt again with mathcing sk
         let _match = decrypt(&keygen(&pk, &msk, &att_matching).unwrap(), &ct_cp);
         assert_eq!(_match.is_ok(), true);
@@ -589,7 +860,7 @@ mod tests {
         // our policy
         let policy = String::from(r#"{"name": "and", "children":  [{"name": "A"}, {"name": "B"}]}"#);
         // cp-abe ciphertext
-        let ct_cp: CpAbeCiphertext = encrypt(&pk, &policy, PolicyLanguage::JsonPolicy, &plaintext).unwrap();
+        let ct_cp: CpAbeCiphertext = encrypt(&pk, (&policy, PolicyLanguage::JsonPolicy), &plaintext).unwrap();
         // a cp-abe SK key matching
         let sk: CpAbeSecretKey = keygen(&pk, &msk, &att_matching).unwrap();
         // delegate a cp-abe SK
@@ -599,4 +870,69 @@ mod tests {
         assert_eq!(_match.is_ok(), true);
         assert_eq!(_match.unwrap(), plaintext);
     }
+
+    #[test]
+    fn prepared_public_key() {
+        let (pk, msk) = setup();
+        let prepared = CpAbePreparedPublicKey::new(&pk);
+        let policy = String::from(r#""A" and ("B" or "C")"#);
+        // the same randomness yields the same header with and without tables
+        let plain = encapsulate_with(&pk, (&policy, PolicyLanguage::HumanPolicy), EncryptOptions::new().rng(ChaCha20Rng::seed_from_u64(20))).unwrap();
+        let with_tables = encapsulate_with(&prepared, (&policy, PolicyLanguage::HumanPolicy), EncryptOptions::new().rng(ChaCha20Rng::seed_from_u64(20))).unwrap();
+        assert!(plain == with_tables);
+        let plaintext = String::from("dance like no one's watching, encrypt like everyone is!").into_bytes();
+        let ct = encrypt(&prepared, (&policy, PolicyLanguage::HumanPolicy), &plaintext).unwrap();
+        assert_eq!(decrypt(&keygen(&pk, &msk, &["A", "C"]).unwrap(), &ct).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn symmetric_cipher() {
+        let (pk, msk) = setup();
+        let policy = String::from(r#""A" and "B""#);
+        let plaintext = String::from("dance like no one's watching, encrypt like everyone is!").into_bytes();
+        let sk = keygen(&pk, &msk, &["A", "B"]).unwrap();
+        for cipher in [SymmetricCipher::ChaCha20Poly1305, SymmetricCipher::Aes256GcmSiv] {
+            let mut ct = encrypt_with(&pk, (&policy, PolicyLanguage::HumanPolicy), &plaintext, EncryptOptions::new().cipher(cipher)).unwrap();
+            // the cipher is recorded in the header
+            assert_eq!(ct.header.cipher, cipher);
+            assert_eq!(decrypt(&sk, &ct).unwrap(), plaintext);
+            // and bound to the key, so that switching it fails
+            ct.header.cipher = SymmetricCipher::Aes256Gcm;
+            assert!(decrypt(&sk, &ct).is_err());
+        }
+        let ct = encrypt(&pk, (&policy, PolicyLanguage::HumanPolicy), &plaintext).unwrap();
+        assert_eq!(ct.header.cipher, SymmetricCipher::Aes256Gcm);
+    }
+
+    #[test]
+    fn cp_abe() {
+        harness::cp_abe::<Bsw>().unwrap();
+    }
+
+    #[test]
+    fn header_binding() {
+        let plaintext = String::from("dance like no one's watching, encrypt like everyone is!").into_bytes();
+        let (pk, msk) = setup();
+        let sk = keygen(&pk, &msk, &["A", "B"]).unwrap();
+        let ct = encrypt(&pk, (r#""A" and "B""#, PolicyLanguage::HumanPolicy), &plaintext).unwrap();
+        let key = decapsulate(&sk, &ct.header).unwrap();
+        assert_eq!(decrypt_with_key(&key, &ct.data, &ct.header.associated_data(&[])).unwrap(), plaintext);
+        // even with the correct key, the data does not decrypt under a swapped policy
+        let mut header = ct.header.clone();
+        header.policy = (String::from(r#""A" or "B""#), PolicyLanguage::HumanPolicy);
+        assert!(decrypt_with_key(&key, &ct.data, &header.associated_data(&[])).is_err());
+        header.policy = (String::from(r#"{"name": "and", "children": [{"name": "A"}, {"name": "B"}]}"#), PolicyLanguage::JsonPolicy);
+        assert!(decrypt_with_key(&key, &ct.data, &header.associated_data(&[])).is_err());
+    }
+
+    #[test]
+    fn delegate_trait() {
+        let plaintext = String::from("dance like no one's watching, encrypt like everyone is!").into_bytes();
+        let (pk, msk) = <Bsw as CpAbe>::setup().unwrap();
+        let sk = <Bsw as CpAbe>::keygen(&pk, &msk, &["A", "B", "C"]).unwrap();
+        let del = Bsw::delegate(&pk, &sk, &["A", "B"]).unwrap();
+        let ct = <Bsw as CpAbe>::encrypt(&pk, (r#""A" and "B""#, PolicyLanguage::HumanPolicy), &plaintext).unwrap();
+        assert_eq!(<Bsw as CpAbe>::decrypt(&del, &ct).unwrap(), plaintext);
+        assert!(Bsw::delegate(&pk, &sk, &["D"]).is_err());
+    }
 }
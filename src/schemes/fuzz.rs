@@ -0,0 +1,382 @@
+//! Robustness tests for all schemes: malformed policies and attributes, mutated keys, headers and
+//! ciphertexts must be rejected with an error, never with a panic.
+use std::fmt::Debug;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use schemes::{ac17, aw11, bdabe, bsw, ghw11, lsw, mke08, yct14};
+use schemes::traits::{CpAbe, KpAbe, MultiAuthorityAbe};
+use utils::container::Container;
+use utils::policy::pest::PolicyLanguage;
+use error::RabeError;
+
+/// Number of mutated inputs per object and mutation strategy
+const ROUNDS: usize = 50;
+
+/// Policies that are malformed, degenerated or unusual. The secret keys of the tests hold A, B and C.
+const POLICIES: &[(&str, PolicyLanguage)] = &[
+    ("", PolicyLanguage::HumanPolicy),
+    ("A", PolicyLanguage::HumanPolicy),
+    (r#""A""#, PolicyLanguage::HumanPolicy),
+    (r#"("A")"#, PolicyLanguage::HumanPolicy),
+    (r#""A" and"#, PolicyLanguage::HumanPolicy),
+    (r#""A" and "B" and "C""#, PolicyLanguage::HumanPolicy),
+    (r#""A" or "B" or "D""#, PolicyLanguage::HumanPolicy),
+    (r#""A" and ("B" or "C" or "D") and "C""#, PolicyLanguage::HumanPolicy),
+    (r#""A" and 5"#, PolicyLanguage::HumanPolicy),
+    (r#""" and "A""#, PolicyLanguage::HumanPolicy),
+    (r#""ä" or "!A""#, PolicyLanguage::HumanPolicy),
+    (r#"(((("A"))))"#, PolicyLanguage::HumanPolicy),
+    (r#"{"name": "A"}"#, PolicyLanguage::JsonPolicy),
+    (r#"{"name": "and", "children": [{"name": "A"}]}"#, PolicyLanguage::JsonPolicy),
+    (r#"{"name": "or", "children": [{"name": "and", "children": [{"name": "B"}]}]}"#, PolicyLanguage::JsonPolicy),
+    (r#"{"name": "and", "children": []}"#, PolicyLanguage::JsonPolicy),
+    (r#"{"name": "or", "children": [{"name": "A"}, {"name": "B"}, {"name": "C"}]}"#, PolicyLanguage::JsonPolicy),
+    (r#"{"name": "and", "children": [{"name": "A"}, {"name": "B"}, {"name": "C"}]}"#, PolicyLanguage::JsonPolicy),
+    (r#"{"name": "and", "children": [{"name": "A"}, {"name": "or", "children": [{"name": "A"}]}]}"#, PolicyLanguage::JsonPolicy),
+    (r#"{"name": 1}"#, PolicyLanguage::JsonPolicy),
+    (r#"2 of ("A", "B", "D")"#, PolicyLanguage::HumanPolicy),
+    (r#"1 of ("A")"#, PolicyLanguage::HumanPolicy),
+    (r#"0 of ("A", "B")"#, PolicyLanguage::HumanPolicy),
+    (r#"4 of ("A", "B", "C")"#, PolicyLanguage::HumanPolicy),
+    (r#"99999999999999999999999 of ("A", "B")"#, PolicyLanguage::HumanPolicy),
+    (r#"6 of ("A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M", "N", "O", "P")"#, PolicyLanguage::HumanPolicy),
+    (r#"{"name": "threshold", "k": 2, "children": [{"name": "A"}, {"name": "threshold", "k": 1, "children": [{"name": "B"}]}]}"#, PolicyLanguage::JsonPolicy),
+    (r#"{"name": "threshold", "k": 0, "children": []}"#, PolicyLanguage::JsonPolicy),
+    (r#""A" and not "B""#, PolicyLanguage::HumanPolicy),
+    (r#""A" and age >= 18"#, PolicyLanguage::HumanPolicy),
+    (r#"age < 0"#, PolicyLanguage::HumanPolicy),
+    (r#"age in [9, 3]"#, PolicyLanguage::HumanPolicy),
+    (r#"{"name": "age", ">=": 18, ">": 1}"#, PolicyLanguage::JsonPolicy),
+    (r#"not not not ("A" or !"B")"#, PolicyLanguage::HumanPolicy),
+    (r#"not 2 of ("A", "B", not "C")"#, PolicyLanguage::HumanPolicy),
+    (r#"{"name": "not", "children": [{"name": "not", "children": [{"name": "A"}]}]}"#, PolicyLanguage::JsonPolicy),
+    (r#""A" and "B""#, PolicyLanguage::JsonPolicy),
+    (r#"{"name": "and", "children": [{"name": "A"}, {"name": "B"}]}"#, PolicyLanguage::HumanPolicy),
+];
+
+/// Attribute sets that are empty, unknown to the key or otherwise unusual
+const ATTRIBUTES: &[&[&str]] = &[
+    &[],
+    &[""],
+    &["A"],
+    &["A", "A"],
+    &["E"],
+    &["A", "E"],
+    &["ä", "B"],
+    &["!A", "B"],
+    &["A_1", "B"],
+];
+
+/// Runs `f` and fails the test with a description of the input if it panics
+fn no_panic<D: Debug, F: FnOnce()>(input: D, f: F) {
+    if catch_unwind(AssertUnwindSafe(f)).is_err() {
+        panic!("panic on input {:?}", input);
+    }
+}
+
+/// Mutates a container at the byte level: flipped and overwritten bytes, truncation, insertion and appended junk
+fn mutate_bytes<R: Rng>(bytes: &[u8], rng: &mut R) -> Vec<u8> {
+    let mut mutated = bytes.to_vec();
+    let pos = rng.gen_range(0..mutated.len());
+    match rng.gen_range(0..5) {
+        0 => mutated[pos] ^= 1 << rng.gen_range(0..8),
+        1 => mutated[pos] = rng.gen(),
+        2 => mutated.truncate(pos),
+        3 => mutated.insert(pos, rng.gen()),
+        _ => mutated.extend((0..rng.gen_range(1..16)).map(|_| rng.gen::<u8>())),
+    }
+    mutated
+}
+
+/// Mutates a random node of a decoded payload: emptied, shortened, duplicated and reordered arrays,
+/// replaced strings, integers and byte strings. This produces well-formed objects with inconsistent content.
+#[cfg(all(feature = "serde", not(feature = "borsh")))]
+fn mutate_structure<R: Rng>(value: &mut serde_cbor::Value, rng: &mut R) {
+    use serde_cbor::Value;
+    fn count(value: &Value) -> usize {
+        1 + match value {
+            Value::Array(a) => a.iter().map(count).sum(),
+            Value::Map(m) => m.values().map(count).sum(),
+            Value::Tag(_, v) => count(v),
+            _ => 0,
+        }
+    }
+    fn visit<R: Rng>(value: &mut Value, target: &mut usize, rng: &mut R) -> bool {
+        if *target == 0 {
+            match value {
+                Value::Array(a) if !a.is_empty() => match rng.gen_range(0..4) {
+                    0 => a.clear(),
+                    1 => { a.pop(); },
+                    2 => { let first = a[0].clone(); a.push(first); },
+                    _ => a.reverse(),
+                },
+                Value::Text(t) => {
+                    let texts = ["", "A", "\"A\" and", "\"A\" and \"B\" and \"C\"", "{\"name\": \"or\", \"children\": []}", "ä"];
+                    *t = texts[rng.gen_range(0..texts.len())].to_string();
+                },
+                Value::Integer(i) => *i = [0, 1, -1, 255, i128::from(u64::MAX)][rng.gen_range(0..5)],
+                Value::Bytes(b) if !b.is_empty() => {
+                    let pos = rng.gen_range(0..b.len());
+                    if rng.gen_bool(0.5) { b.truncate(pos) } else { b[pos] ^= 0xff }
+                },
+                _ => *value = Value::Null,
+            }
+            return true;
+        }
+        *target -= 1;
+        match value {
+            Value::Array(a) => a.iter_mut().any(|v| visit(v, target, rng)),
+            Value::Map(m) => m.values_mut().any(|v| visit(v, target, rng)),
+            Value::Tag(_, v) => visit(v, target, rng),
+            _ => false,
+        }
+    }
+    let mut target = rng.gen_range(0..count(value));
+    visit(value, &mut target, rng);
+}
+
+/// Feeds mutated versions of `object` into `f`, which must not panic
+fn fuzz<T: Container, F: Fn(&T)>(object: &T, seed: u64, f: F) {
+    let mut rng = ChaCha20Rng::seed_from_u64(seed);
+    let bytes = object.to_bytes().unwrap();
+    for _ in 0..ROUNDS {
+        let mutated = mutate_bytes(&bytes, &mut rng);
+        no_panic(&mutated, || {
+            if let Ok(parsed) = T::from_bytes(&mutated) {
+                f(&parsed);
+            }
+        });
+    }
+    #[cfg(all(feature = "serde", not(feature = "borsh")))]
+    {
+        let value: serde_cbor::Value = serde_cbor::from_slice(&object.encode().unwrap()).unwrap();
+        for _ in 0..ROUNDS {
+            let mut mutated = value.clone();
+            mutate_structure(&mut mutated, &mut rng);
+            no_panic(&mutated, || {
+                if let Ok(parsed) = T::decode(&serde_cbor::to_vec(&mutated).unwrap()) {
+                    f(&parsed);
+                }
+            });
+        }
+    }
+}
+
+/// Changes the z coordinate of every point and one coefficient of every element of Gt of `object` in turn. The
+/// results are well-formed and reduced, but not in their group, and must be rejected by `from_bytes`. Objects without
+/// points, like the secret key of YCT14, are not changed.
+#[cfg(all(feature = "serde", not(feature = "borsh")))]
+fn flip_coordinates<T: Container>(object: &T) {
+    use serde_cbor::Value;
+    use utils::container::HEADER_LEN;
+    fn field(value: &Value, name: &str) -> Option<usize> {
+        match value {
+            Value::Map(m) => m.keys().position(|key| *key == Value::Text(name.to_string())),
+            _ => None,
+        }
+    }
+    // the first limb of the first element of Fq below `value`
+    fn first_limb(value: &mut Value) -> Option<&mut i128> {
+        match value {
+            Value::Integer(i) => Some(i),
+            Value::Array(a) => a.iter_mut().find_map(first_limb),
+            Value::Map(m) => m.values_mut().find_map(first_limb),
+            _ => None,
+        }
+    }
+    // flips the target with the index `target`, a point (with a z coordinate) or an element of Fq12 (a pair of Fq6)
+    fn visit(value: &mut Value, target: &mut usize) -> bool {
+        let coordinate = match field(value, "z") {
+            Some(_) => Some("z"),
+            None => match value {
+                Value::Map(m) => m.values().next().and_then(|c0| field(c0, "c2")).map(|_| "c0"),
+                _ => None,
+            },
+        };
+        if let (Some(name), Value::Map(m)) = (coordinate, &mut *value) {
+            if *target == 0 {
+                let limb = first_limb(m.get_mut(&Value::Text(name.to_string())).unwrap()).unwrap();
+                *limb ^= 1;
+                return true;
+            }
+            *target -= 1;
+            return false;
+        }
+        match value {
+            Value::Array(a) => a.iter_mut().any(|v| visit(v, target)),
+            Value::Map(m) => m.values_mut().any(|v| visit(v, target)),
+            Value::Tag(_, v) => visit(v, target),
+            _ => false,
+        }
+    }
+    let bytes = object.to_bytes().unwrap();
+    let value: serde_cbor::Value = serde_cbor::from_slice(&object.encode().unwrap()).unwrap();
+    let container = |value: &Value| {
+        let payload = serde_cbor::to_vec(value).unwrap();
+        let mut container = bytes[..HEADER_LEN - 8].to_vec();
+        container.extend((payload.len() as u64).to_be_bytes());
+        container.extend(payload);
+        container
+    };
+    assert!(T::from_bytes(&container(&value)).is_ok());
+    let mut flipped = 0;
+    loop {
+        let mut mutated = value.clone();
+        if !visit(&mut mutated, &mut flipped.clone()) {
+            break;
+        }
+        assert!(
+            matches!(T::from_bytes(&container(&mutated)), Err(RabeError::Serialization { .. })),
+            "{} accepts an element outside of its group", std::any::type_name::<T>()
+        );
+        flipped += 1;
+    }
+}
+
+fn fuzz_cp<S: CpAbe>(seed: u64)
+where S::SecretKey: Container + Clone, S::Ciphertext: Container, S::Header: Container {
+    let plaintext = String::from("dance like no one's watching, encrypt like everyone is!").into_bytes();
+    let (pk, msk) = S::setup().unwrap();
+    for attributes in ATTRIBUTES {
+        no_panic(attributes, || { let _ = S::keygen(&pk, &msk, attributes); });
+    }
+    let sk = S::keygen(&pk, &msk, &["A", "B", "C"]).unwrap();
+    for (policy, language) in POLICIES {
+        no_panic(policy, || {
+            if let Ok(ct) = S::encrypt(&pk, (policy, *language), &plaintext) {
+                let _ = S::decrypt(&sk, &ct);
+            }
+        });
+    }
+    // n-ary and single child gates are supported
+    for policy in [r#""A" and "B" and "C""#, r#""A" and ("B" or "C" or "D") and "C""#, r#"2 of ("A", "D", "A" and "C")"#] {
+        let ct = S::encrypt(&pk, (policy, PolicyLanguage::HumanPolicy), &plaintext).unwrap();
+        assert_eq!(S::decrypt(&sk, &ct).ok(), Some(plaintext.clone()), "{} {}", std::any::type_name::<S>(), policy);
+    }
+    let ct = S::encrypt(&pk, (r#"{"name": "and", "children": [{"name": "A"}]}"#, PolicyLanguage::JsonPolicy), &plaintext).unwrap();
+    assert_eq!(S::decrypt(&sk, &ct).unwrap(), plaintext);
+    let ct = S::encrypt(&pk, (r#""A" and ("B" or "D")"#, PolicyLanguage::HumanPolicy), &plaintext).unwrap();
+    let (_, header) = S::encapsulate(&pk, (r#""A" and ("B" or "D")"#, PolicyLanguage::HumanPolicy)).unwrap();
+    fuzz(&ct, seed, |ct| { let _ = S::decrypt(&sk, ct); });
+    fuzz(&header, seed + 1, |header| { let _ = S::decapsulate(&sk, header); });
+    fuzz(&sk, seed + 2, |sk| { let _ = S::decrypt(sk, &ct); });
+    #[cfg(all(feature = "serde", not(feature = "borsh")))]
+    {
+        flip_coordinates(&ct);
+        flip_coordinates(&header);
+        flip_coordinates(&sk);
+    }
+}
+
+fn fuzz_kp<S: KpAbe>(seed: u64)
+where S::SecretKey: Container + Clone, S::Ciphertext: Container, S::Header: Container {
+    let plaintext = String::from("dance like no one's watching, encrypt like everyone is!").into_bytes();
+    let (pk, msk) = S::setup(&["A", "B", "C", "D"]).unwrap();
+    let sk = S::keygen(&pk, &msk, (r#""A" and ("B" or "D")"#, PolicyLanguage::HumanPolicy)).unwrap();
+    for (policy, language) in POLICIES {
+        no_panic(policy, || { let _ = S::keygen(&pk, &msk, (policy, *language)); });
+    }
+    for attributes in ATTRIBUTES {
+        no_panic(attributes, || {
+            if let Ok(ct) = S::encrypt(&pk, attributes, &plaintext) {
+                let _ = S::decrypt(&sk, &ct);
+            }
+        });
+    }
+    let sk3 = S::keygen(&pk, &msk, (r#""A" and "B" and "C""#, PolicyLanguage::HumanPolicy)).unwrap();
+    let ct = S::encrypt(&pk, &["A", "B", "C"], &plaintext).unwrap();
+    assert_eq!(S::decrypt(&sk3, &ct).unwrap(), plaintext);
+    let (_, header) = S::encapsulate(&pk, &["A", "B", "C"]).unwrap();
+    fuzz(&ct, seed, |ct| { let _ = S::decrypt(&sk, ct); });
+    fuzz(&header, seed + 1, |header| { let _ = S::decapsulate(&sk, header); });
+    fuzz(&sk, seed + 2, |sk| { let _ = S::decrypt(sk, &ct); });
+    #[cfg(all(feature = "serde", not(feature = "borsh")))]
+    {
+        flip_coordinates(&ct);
+        flip_coordinates(&header);
+        flip_coordinates(&sk);
+    }
+}
+
+fn fuzz_ma<S: MultiAuthorityAbe>(seed: u64)
+where S::SecretKey: Container + Clone, S::Ciphertext: Container, S::Header: Container {
+    let plaintext = String::from("dance like no one's watching, encrypt like everyone is!").into_bytes();
+    let (gk, msk) = S::setup().unwrap();
+    let auth1 = S::authgen(&gk, &msk, "auth1", &["auth1::A", "auth1::B"]).unwrap();
+    let auth2 = S::authgen(&gk, &msk, "auth2", &["auth2::C"]).unwrap();
+    let mut sk = S::keygen(&gk, &msk, &auth1, "bob", &["auth1::A"]).unwrap();
+    for attribute in ["", "auth1::A", "auth1::E", "auth2::C", "ä", "::", "auth2::C::D"] {
+        no_panic(attribute, || { let _ = S::attribute_public_key(&gk, &auth1, attribute); });
+        no_panic(attribute, || { let _ = S::add_attribute(&gk, &auth1, attribute, &mut sk.clone()); });
+    }
+    S::add_attribute(&gk, &auth2, "auth2::C", &mut sk).unwrap();
+    let pk_a = S::attribute_public_key(&gk, &auth1, "auth1::A").unwrap();
+    let pk_b = S::attribute_public_key(&gk, &auth1, "auth1::B").unwrap();
+    let pk_c = S::attribute_public_key(&gk, &auth2, "auth2::C").unwrap();
+    let policies = [
+        "", r#""auth1::A""#, r#""auth1::A" and "auth1::B" and "auth2::C""#, r#""auth1::A" or ("auth1::B" and "auth1::E")"#,
+        r#"("auth1::A" or "auth1::B") and "auth2::C""#, r#"{"name": "and", "children": [{"name": "auth1::A"}]}"#,
+    ];
+    for policy in policies {
+        for pks in [&[][..], &[&pk_a][..], &[&pk_a, &pk_b, &pk_c][..]] {
+            no_panic(policy, || {
+                if let Ok(ct) = S::encrypt(&gk, pks, (policy, PolicyLanguage::HumanPolicy), &plaintext) {
+                    let _ = S::decrypt(&gk, &sk, &ct);
+                }
+            });
+        }
+    }
+    let ct = S::encrypt(&gk, &[&pk_a, &pk_c], (r#""auth1::A" and "auth2::C""#, PolicyLanguage::HumanPolicy), &plaintext).unwrap();
+    let (_, header) = S::encapsulate(&gk, &[&pk_a, &pk_c], (r#""auth1::A" and "auth2::C""#, PolicyLanguage::HumanPolicy)).unwrap();
+    fuzz(&ct, seed, |ct| { let _ = S::decrypt(&gk, &sk, ct); });
+    fuzz(&header, seed + 1, |header| { let _ = S::decapsulate(&gk, &sk, header); });
+    fuzz(&sk, seed + 2, |sk| { let _ = S::decrypt(&gk, sk, &ct); });
+    #[cfg(all(feature = "serde", not(feature = "borsh")))]
+    {
+        flip_coordinates(&ct);
+        flip_coordinates(&header);
+        flip_coordinates(&sk);
+    }
+}
+
+#[test]
+fn cp_schemes() {
+    fuzz_cp::<ac17::Ac17Cp>(1);
+    fuzz_cp::<bsw::Bsw>(2);
+    fuzz_cp::<ghw11::Ghw11>(3);
+}
+
+#[test]
+fn kp_schemes() {
+    fuzz_kp::<ac17::Ac17Kp>(4);
+    fuzz_kp::<lsw::Lsw>(5);
+    fuzz_kp::<yct14::Yct14>(6);
+}
+
+#[test]
+fn multi_authority_schemes() {
+    fuzz_ma::<aw11::Aw11>(7);
+    fuzz_ma::<bdabe::Bdabe>(8);
+    fuzz_ma::<mke08::Mke08>(9);
+}
+
+#[test]
+fn malformed_headers() {
+    let plaintext = String::from("dance like no one's watching, encrypt like everyone is!").into_bytes();
+    let (pk, msk) = ac17::setup();
+    let sk = ac17::cp_keygen(&msk, &["A", "B"]).unwrap();
+    let ct = ac17::cp_encrypt(&pk, (r#""A" and "B""#, PolicyLanguage::HumanPolicy), &plaintext).unwrap();
+    let mut header = ct.header.clone();
+    header.c_0.pop();
+    assert!(matches!(ac17::cp_decapsulate(&sk, &header), Err(RabeError::InvalidInput(_))));
+    let mut header = ct.header.clone();
+    header.c[0].1.clear();
+    assert!(matches!(ac17::cp_decapsulate(&sk, &header), Err(RabeError::InvalidInput(_))));
+    let mut broken = sk.clone();
+    broken.sk.k_p.clear();
+    assert!(matches!(ac17::cp_decapsulate(&broken, &ct.header), Err(RabeError::InvalidKey(_))));
+    // attributes unknown to the attribute universe of yct14
+    let (pk, _) = yct14::setup(vec!["A", "B"]);
+    assert!(matches!(yct14::encrypt(&pk, &vec!["A", "C"], &plaintext), Err(RabeError::UnknownAttribute(_))));
+}
@@ -1,23 +1,38 @@
 use std::{
     string::String,
-    ops::Neg
+    ops::Neg,
+    borrow::Cow
 };
 use rabe_bn::{Group, Gt, G1, G2, Fr, pairing};
 
-use rand::Rng;
+use rand::{CryptoRng, Rng, RngCore};
 use utils::{
     tools::*,
     secretsharing::*,
     aes::*,
-    hash::sha3_hash
+    hash::{hash_to_g1, HashInput},
+    container::{Container, SchemeId, ObjectType},
+    pairing::multi_pairing,
+    fixed_base::FixedBase,
+    parallel,
 };
-use utils::policy::pest::{PolicyLanguage, parse, PolicyType};
+use utils::policy::pest::{PolicyLanguage, parse, check_monotone, PolicyType};
+use utils::policy::ast::PolicySource;
+use utils::policy::explain::not_satisfied;
 use crate::error::RabeError;
+use schemes::traits::CpAbe;
+use schemes::options::EncryptOptions;
+#[cfg(feature = "borsh")]
+use borsh::{BorshSerialize, BorshDeserialize};
 #[cfg(feature = "serde")]
 use serde::{Serialize, Deserialize};
 
+/// The decryption cost of the leaves of a policy: one pairing per leaf
+const LEAF_WEIGHTS: LeafWeights = LeafWeights { attribute: 1, negated: 1 };
+
 /// An Ghw11 Public Key (PK)
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Ghw11PublicKey {
     pub g1: G1,
@@ -27,16 +42,85 @@ pub struct Ghw11PublicKey {
     pub e_gg_alpha: Gt,
 }
 
+validate_fields!(Ghw11PublicKey { g1, g2, g1_a, g2_a, e_gg_alpha });
+
+impl Container for Ghw11PublicKey {
+    const SCHEME: SchemeId = SchemeId::Ghw11;
+    const OBJECT: ObjectType = ObjectType::PublicKey;
+}
+
+/// An Ghw11 Public Key (PK) with precomputed tables for all bases that are exponentiated during encryption, which
+/// speeds up encryption if the key is reused for many encryptions
+#[derive(Clone, PartialEq, Debug)]
+pub struct Ghw11PreparedPublicKey {
+    pub pk: Ghw11PublicKey,
+    g1: FixedBase<G1>,
+    g1_a: FixedBase<G1>,
+    g2: FixedBase<G2>,
+    e_gg_alpha: FixedBase<Gt>,
+}
+
+impl Ghw11PreparedPublicKey {
+    /// Precomputes the tables of the given Ghw11PublicKey
+    pub fn new(pk: &Ghw11PublicKey) -> Ghw11PreparedPublicKey {
+        Ghw11PreparedPublicKey {
+            pk: pk.clone(),
+            g1: FixedBase::prepare(pk.g1),
+            g1_a: FixedBase::prepare(pk.g1_a),
+            g2: FixedBase::prepare(pk.g2),
+            e_gg_alpha: FixedBase::prepare(pk.e_gg_alpha),
+        }
+    }
+
+    // the bases of the given key without tables
+    fn unprepared(pk: &Ghw11PublicKey) -> Ghw11PreparedPublicKey {
+        Ghw11PreparedPublicKey {
+            pk: pk.clone(),
+            g1: FixedBase::new(pk.g1),
+            g1_a: FixedBase::new(pk.g1_a),
+            g2: FixedBase::new(pk.g2),
+            e_gg_alpha: FixedBase::new(pk.e_gg_alpha),
+        }
+    }
+}
+
+/// A key that encrypts: a Ghw11PublicKey or a Ghw11PreparedPublicKey
+pub trait Ghw11EncryptionKey {
+    /// Returns the bases of the key, with tables if the key is prepared
+    fn prepared(&self) -> Cow<'_, Ghw11PreparedPublicKey>;
+}
+
+impl Ghw11EncryptionKey for Ghw11PublicKey {
+    fn prepared(&self) -> Cow<'_, Ghw11PreparedPublicKey> {
+        Cow::Owned(Ghw11PreparedPublicKey::unprepared(self))
+    }
+}
+
+impl Ghw11EncryptionKey for Ghw11PreparedPublicKey {
+    fn prepared(&self) -> Cow<'_, Ghw11PreparedPublicKey> {
+        Cow::Borrowed(self)
+    }
+}
+
 /// An Ghw11 Master Key (MSK)
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Ghw11MasterKey {
     pub g2_alpha: G2,
     pub pk: Ghw11PublicKey,
 }
 
+validate_fields!(Ghw11MasterKey { g2_alpha, pk });
+
+impl Container for Ghw11MasterKey {
+    const SCHEME: SchemeId = SchemeId::Ghw11;
+    const OBJECT: ObjectType = ObjectType::MasterKey;
+}
+
 /// An Ghw11 Secret Key (SK)
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Ghw11SecretKey {
     //g2^alpha * g2^ar
@@ -47,16 +131,27 @@ pub struct Ghw11SecretKey {
     pub attr_key: Vec<Ghw11Attribute>,
 }
 
+validate_fields!(Ghw11SecretKey { k, l, attr_key });
+
+impl Container for Ghw11SecretKey {
+    const SCHEME: SchemeId = SchemeId::Ghw11;
+    const OBJECT: ObjectType = ObjectType::SecretKey;
+}
+
 /// A Ghw11 Attribute
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Ghw11Attribute {
     pub string: String,
-    pub k_x: G2,
+    pub k_x: G1,
 }
 
+validate_fields!(Ghw11Attribute { string, k_x });
+
 /// An Ghw11 Transform Key (TK)
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Ghw11TransformKey {
     pub k_z: G2,
@@ -64,36 +159,97 @@ pub struct Ghw11TransformKey {
     pub attr_key_z: Vec<Ghw11Attribute>,
 }
 
+validate_fields!(Ghw11TransformKey { k_z, l_z, attr_key_z });
+
+impl Container for Ghw11TransformKey {
+    const SCHEME: SchemeId = SchemeId::Ghw11;
+    const OBJECT: ObjectType = ObjectType::TransformKey;
+}
+
 /// An Ghw11 Retrieve Key (RK)
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Ghw11RetrieveKey {
     pub z: Fr,
 }
 
-/// A Ghw11 Ciphertext (CT)
+validate_fields!(Ghw11RetrieveKey { z });
+
+impl Container for Ghw11RetrieveKey {
+    const SCHEME: SchemeId = SchemeId::Ghw11;
+    const OBJECT: ObjectType = ObjectType::RetrieveKey;
+}
+
+/// A Ghw11 Header, i.e. the encapsulated key under a policy
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub struct Ghw11Ciphertext {
+pub struct Ghw11Header {
     pub policy: (String, PolicyLanguage),
     pub c : Gt,
     pub c1: G1,
-    pub ci_di: Vec<(String, G1, G1)>,
+    pub ci_di: Vec<(String, G1, G2)>,
+    pub cipher: SymmetricCipher,
+}
+
+validate_fields!(Ghw11Header { policy, c, c1, ci_di, cipher });
+
+impl Container for Ghw11Header {
+    const SCHEME: SchemeId = SchemeId::Ghw11;
+    const OBJECT: ObjectType = ObjectType::Header;
+}
+
+impl Ghw11Header {
+    /// Returns the associated data that binds this header to the symmetrically encrypted data:
+    /// the scheme id, the format version, the policy and the caller supplied `aad`.
+    pub fn associated_data(&self, aad: &[u8]) -> Vec<u8> {
+        let language = [self.policy.1 as u8];
+        associated_data("GHW11", &[self.policy.0.as_bytes(), &language], aad)
+    }
+}
+
+/// A Ghw11 Ciphertext (CT), composed of a Ghw11Header and the symmetrically encrypted data
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Ghw11Ciphertext {
+    pub header: Ghw11Header,
     pub data: Vec<u8>,
 }
 
+validate_fields!(Ghw11Ciphertext { header, data });
+
+impl Container for Ghw11Ciphertext {
+    const SCHEME: SchemeId = SchemeId::Ghw11;
+    const OBJECT: ObjectType = ObjectType::Ciphertext;
+}
+
 /// A Ghw11 Transform Ciphertext (TCT)
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Ghw11TransformCiphertext {
     pub c : Gt,
     pub t : Gt,
+    // the digest of the header, which the shared key is bound to
+    pub header_digest: [u8; 32],
+}
+
+validate_fields!(Ghw11TransformCiphertext { c, t, header_digest });
+
+impl Container for Ghw11TransformCiphertext {
+    const SCHEME: SchemeId = SchemeId::Ghw11;
+    const OBJECT: ObjectType = ObjectType::TransformCiphertext;
 }
 
 /// The setup algorithm of Ghw11. Generates a Ghw11PublicKey and a Ghw11MasterKey.
 pub fn setup() -> (Ghw11PublicKey, Ghw11MasterKey) {
-    // random number generator
-    let mut rng = rand::thread_rng();
+    setup_with_rng(&mut rand::thread_rng())
+}
+
+/// Like `setup()`, but draws all randomness from the given random number generator `rng`.
+pub fn setup_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> (Ghw11PublicKey, Ghw11MasterKey) {
     let g1:G1 = rng.gen();
     let g2:G2 = rng.gen();
 
@@ -121,17 +277,25 @@ pub fn setup() -> (Ghw11PublicKey, Ghw11MasterKey) {
 ///	* `attributes` - A Vector of String attributes assigned to this user key
 ///
 pub fn keygen(
+    pk: &Ghw11PublicKey,
+    msk: &Ghw11MasterKey,
+    attributes: &[String]
+) -> Option<Ghw11SecretKey> {
+    keygen_with_rng(pk, msk, attributes, &mut rand::thread_rng())
+}
+
+/// Like `keygen()`, but draws all randomness from the given random number generator `rng`.
+pub fn keygen_with_rng<R: RngCore + CryptoRng>(
     pk: &Ghw11PublicKey,
     msk: &Ghw11MasterKey,
     attributes: &[String],
+    rng: &mut R
 ) -> Option<Ghw11SecretKey> {
     // if no attibutes or an empty policy
     // maybe add empty msk also here
     if attributes.is_empty() || attributes.len() == 0 {
         return None;
     }
-    // random number generator
-    let mut rng = rand::thread_rng();
     // generate random r
     let r:Fr = rng.gen();
 
@@ -145,7 +309,7 @@ pub fn keygen(
     for j in attributes {
         k_x.push(Ghw11Attribute {
             string: j.to_string(), // attribute name
-            k_x:  (sha3_hash(pk.g2, j.as_str()).expect("could not hash _j") * r), // K_x
+            k_x:  (hash_to_g1(SchemeId::Ghw11, &HashInput::new("attribute").string(j)).ok()? * r), // K_x
         });
     }
     return Some(Ghw11SecretKey { k, l: g2_r, attr_key: k_x });
@@ -154,14 +318,20 @@ pub fn keygen(
 /// The tansform key generation algorithm of Ghw11 CP-ABE. 
 /// Tansfrom Secretkey with a random RetrieveKey z to TransformKey, return (Ghw11TransformKey, Ghw11RetrieveKey).
 pub fn tkgen(
+    sk: Ghw11SecretKey
+) -> Option<(Ghw11TransformKey, Ghw11RetrieveKey)> {
+    tkgen_with_rng(sk, &mut rand::thread_rng())
+}
+
+/// Like `tkgen()`, but draws all randomness from the given random number generator `rng`.
+pub fn tkgen_with_rng<R: RngCore + CryptoRng>(
     sk: Ghw11SecretKey,
+    rng: &mut R
 ) -> Option<(Ghw11TransformKey, Ghw11RetrieveKey)> {
-        // random number generator
-        let mut rng = rand::thread_rng();
 
         // generate random z
         let z:Fr = rng.gen();
-        let z_inverse = z.inverse().unwrap();
+        let z_inverse = z.inverse()?;
 
         let k_z = sk.k * z_inverse;
 
@@ -181,51 +351,84 @@ pub fn tkgen(
 ///
 /// # Arguments
 ///
-///	* `pk` - A Public Key (PK), generated by the function setup()
-///	* `policy` - An access policy given as JSON String
-///	* `language` - The policy language
+///	* `pk` - A Public Key (PK), generated by the function setup(), or a Ghw11PreparedPublicKey
+///	* `policy` - An access policy, given as text and its PolicyLanguage or as Policy, see [PolicySource]
 ///	* `plaintext` - plaintext data given as a Vector of u8
 ///
-pub fn encrypt(
-    pk: &Ghw11PublicKey,
-    policy: &str,
-    language: PolicyLanguage,
+pub fn encrypt<K: Ghw11EncryptionKey + ?Sized, P: PolicySource>(
+    pk: &K,
+    policy: P,
+    plaintext: &[u8]
+) -> Result<Ghw11Ciphertext, RabeError> {
+    encrypt_with(pk, policy, plaintext, EncryptOptions::new())
+}
+
+/// Like `encrypt()`, but with the given [EncryptOptions], i.e. the random number generator, the associated data
+/// that has to be passed to `decrypt_with_aad()` and the symmetric cipher.
+pub fn encrypt_with<K: Ghw11EncryptionKey + ?Sized, P: PolicySource, R: RngCore + CryptoRng>(
+    pk: &K,
+    policy: P,
     plaintext: &[u8],
+    mut options: EncryptOptions<R>
 ) -> Result<Ghw11Ciphertext, RabeError> {
-    if plaintext.is_empty() || policy.is_empty() {
-        RabeError::new("Error in bsw/encrypt: data or policy is empty.");
-    }
-    let mut rng = rand::thread_rng();
+    let (key, header) = encapsulate_with(pk, policy, options.by_ref())?;
+    //Encrypt plaintext using the encapsulated key, binding the header as associated data
+    let data = header.cipher.encrypt(&key, plaintext, &header.associated_data(options.aad), &mut options.rng)?;
+    Ok(Ghw11Ciphertext { header, data })
+}
+
+/// The key encapsulation algorithm of Ghw11 CP-ABE. Generates a new SharedKey and a Ghw11Header that encapsulates it under an access policy given as String.
+///
+/// # Arguments
+///
+///	* `pk` - A Public Key (PK), generated by the function setup(), or a Ghw11PreparedPublicKey
+///	* `policy` - An access policy, given as text and its PolicyLanguage or as Policy, see [PolicySource]
+///
+pub fn encapsulate<K: Ghw11EncryptionKey + ?Sized, P: PolicySource>(
+    pk: &K,
+    policy: P
+) -> Result<(SharedKey, Ghw11Header), RabeError> {
+    encapsulate_with(pk, policy, EncryptOptions::new())
+}
+
+/// Like `encapsulate()`, but with the given [EncryptOptions], i.e. the random number generator and the symmetric
+/// cipher that is recorded in the header.
+pub fn encapsulate_with<K: Ghw11EncryptionKey + ?Sized, P: PolicySource, R: RngCore + CryptoRng>(
+    pk: &K,
+    policy: P,
+    mut options: EncryptOptions<R>
+) -> Result<(SharedKey, Ghw11Header), RabeError> {
+    let rng = &mut options.rng;
+    let pk = pk.prepared();
     // the shared root secret
     let secret:Fr = rng.gen();
 
     let msg: Gt = rng.gen();
 
-    match parse(policy, language) {
-        Ok(policy_value) => {
-            let shares: Vec<(String, Fr)> = gen_shares_policy(secret, &policy_value, None).unwrap();
-
-            let c = pk.e_gg_alpha.pow(secret) * msg;
-            let c1 = pk.g1 * secret;
-
-            let mut ci_di: Vec<(String, G1, G1)> = Vec::new();
-            for (node, i_val) in shares.clone() {
-                let t_i:Fr = rng.gen();
-                let j = remove_index(&node);
-                ci_di.push((node.clone(), pk.g1_a * i_val + sha3_hash(pk.g1, &j).unwrap() * (t_i.neg()), pk.g1 * t_i));
-            }
-            match encrypt_symmetric(msg, &plaintext.to_vec()) {
-                Ok(data) => Ok(Ghw11Ciphertext { policy: (policy.to_string(), language), c, c1, ci_di, data }),
-                Err(e) => Err(e)
-            }
+    match policy.value() {
+        Ok((policy_value, policy, language)) => {
+            check_monotone(&policy_value, "ghw11/encapsulate")?;
+            let shares: Vec<(String, Fr)> = gen_shares_policy_with_rng(secret, &policy_value, None, rng)?;
+
+            let c = pk.e_gg_alpha.exp(secret) * msg;
+            let c1 = pk.g1.exp(secret);
+
+            // the randomness is drawn first, in the order of the shares
+            let shares: Vec<(String, Fr, Fr)> = shares.into_iter().map(|(node, i_val)| (node, i_val, rng.gen())).collect();
+            let ci_di: Vec<(String, G1, G2)> = parallel::try_map(&shares, |(node, i_val, t_i)| {
+                let j = remove_index(node);
+                Ok((node.clone(), pk.g1_a.exp(*i_val) + hash_to_g1(SchemeId::Ghw11, &HashInput::new("attribute").string(&j))? * t_i.neg(), pk.g2.exp(*t_i)))
+            })?;
+            let header = Ghw11Header { policy: (policy.to_string(), language), c, c1, ci_di, cipher: options.cipher };
+            Ok((SharedKey::derive_for(msg, &header)?, header))
         }
         Err(e) => Err(e)
     }
 }
 
-/// The transform algorithm of Ghw11 CP-ABE. Generates a new Ghw11TransformCiphertext using an Ghw11TransformKey and Ghw11Ciphertext.
+/// The transform algorithm of Ghw11 CP-ABE. Generates a new Ghw11TransformCiphertext using an Ghw11TransformKey and the Ghw11Header of a Ghw11Ciphertext.
 pub fn transform(
-    ct: Ghw11Ciphertext,
+    ct: Ghw11Header,
     tk: Ghw11TransformKey,
 ) -> Result<Ghw11TransformCiphertext, RabeError> {
     let str_attr: Vec<String> = tk
@@ -239,51 +442,49 @@ pub fn transform(
     return match parse(ct.policy.0.as_ref(), ct.policy.1) {
         Ok(pol) => {
             return if traverse_policy(&str_attr, &pol, PolicyType::Leaf) == false {
-                Err(RabeError::new("Error: attributes in tk do not match policy in ct."))
+                Err(not_satisfied("ghw11/transform: attributes in tk do not match policy in ct", &str_attr, &pol))
             } else {
-                let _pruned = calc_pruned(&str_attr, &pol, None);
+                let _pruned = calc_pruned_minimal(&str_attr, &pol, LEAF_WEIGHTS);
                 match _pruned {
                     Err(e) => Err(e),
                     Ok(_p) => {
                         let (_match, _list) = _p;
                         let mut coeff_list: Vec<(String, Fr)> = Vec::new();
-                        coeff_list = calc_coefficients(&pol, Some(Fr::one()), coeff_list,None).unwrap();
+                        coeff_list = calc_coefficients(&pol, Some(Fr::one()), coeff_list, None, &_list)?;
                         if _match {
-                            let mut t = Gt::one();
+                            // t = e(c1, k_z) * (e(ci_wi, l_z) * prod e(k_x, d_i * w_i))^-1
+                            let mut pairs: Vec<(G1, G2)> = vec![(ct.c1, tk.k_z)];
                             let mut ci_wi = G1::zero();
                             for _current in _list.iter() {
                                 //w_i
                                 let _coeff = coeff_list
                                     .iter()
-                                    .filter(|_c| _c.0 == _current.1.to_string())
+                                    .find(|_c| _c.0 == _current.1.to_string())
                                     .map(|_c| _c.1)
-                                    .nth(0)
-                                    .unwrap();
+                                    .ok_or_else(|| RabeError::InvalidPolicy(format!("ghw11/transform: no coefficient for {}", _current.1)))?;
                                 //k_pi
                                 let _tk_attr = tk
                                     .attr_key_z
                                     .iter()
-                                    .filter(|_attr| _attr.string == _current.0.to_string())
-                                    .nth(0)
-                                    .unwrap();
+                                    .find(|_attr| _attr.string == _current.0.to_string())
+                                    .ok_or_else(|| RabeError::InvalidKey(format!("ghw11/transform: no key for attribute {}", _current.0)))?;
                                 //ci di
                                 let _ct_attr = ct
                                     .ci_di
                                     .iter()
-                                    .filter(|_attr| _attr.0 == _current.1.to_string())
-                                    .nth(0)
-                                    .unwrap();
+                                    .find(|_attr| _attr.0 == _current.1.to_string())
+                                    .ok_or_else(|| RabeError::InvalidInput(format!("ghw11/transform: header has no value for {}", _current.1)))?;
                                 //add ci^wi
                                 ci_wi = ci_wi + _ct_attr.1 * _coeff;
                                 //mul 
-                                t = t * pairing(_ct_attr.2 * _coeff, _tk_attr.k_x);
+                                pairs.push((-_tk_attr.k_x, _ct_attr.2 * _coeff));
                             }
-                            t = t * pairing(ci_wi, tk.l_z);
-                            t = pairing(ct.c1, tk.k_z) * t.inverse();
+                            pairs.push((-ci_wi, tk.l_z));
+                            let t = multi_pairing(&pairs);
 
-                            Ok(Ghw11TransformCiphertext{c: ct.c, t})
+                            Ok(Ghw11TransformCiphertext{c: ct.c, t, header_digest: header_digest(&ct)?})
                         } else {
-                            Err(RabeError::new("Error in Ghw11/decrypt: attributes in sk do not match policy in ct."))
+                            Err(not_satisfied("ghw11/decrypt: attributes in sk do not match policy in ct", &str_attr, &pol))
                         }
                     }
                 }
@@ -293,19 +494,113 @@ pub fn transform(
     }    
 }
 
-/// The decrypt_out algorithm of GHW11 CP-ABE. Reconstructs the original plaintext data as Vec<u8>, given a Ghw11TransformCiphertext with a matching Ghw11RetrieveKey.
+/// The decrypt_out algorithm of GHW11 CP-ABE. Reconstructs the original plaintext data of a Ghw11Ciphertext as Vec<u8>, given its Ghw11TransformCiphertext with a matching Ghw11RetrieveKey.
 pub fn decrypt_out(
     pct: Ghw11TransformCiphertext,
     rk: Ghw11RetrieveKey,
-    data: Vec<u8>,
+    ct: &Ghw11Ciphertext,
 ) -> Result<Vec<u8>, RabeError> {
+    decrypt_out_with_aad(pct, rk, ct, &[])
+}
+
+/// Like `decrypt_out()`, but for ciphertexts that were generated by `encrypt_with()` with the associated data `aad`.
+pub fn decrypt_out_with_aad(
+    pct: Ghw11TransformCiphertext,
+    rk: Ghw11RetrieveKey,
+    ct: &Ghw11Ciphertext,
+    aad: &[u8],
+) -> Result<Vec<u8>, RabeError> {
+    let key = decapsulate_out(pct, rk);
+    ct.header.cipher.decrypt(&key, &ct.data, &ct.header.associated_data(aad))
+}
+
+/// The decapsulate_out algorithm of GHW11 CP-ABE. Recovers the SharedKey, given a Ghw11TransformCiphertext with a matching Ghw11RetrieveKey.
+pub fn decapsulate_out(
+    pct: Ghw11TransformCiphertext,
+    rk: Ghw11RetrieveKey,
+) -> SharedKey {
     let msg = pct.c * (pct.t.pow(rk.z)).inverse();
-    decrypt_symmetric(msg, &data)
+    SharedKey::derive_with_digest(msg, SchemeId::Ghw11, &pct.header_digest)
+}
+
+/// The GHW11 CP-ABE scheme, to be used through the [`CpAbe`](../traits/trait.CpAbe.html) trait.
+///
+/// The trait performs the outsourced decryption locally, i.e. decrypt() runs tkgen(), transform() and decrypt_out(),
+/// and decapsulate() runs tkgen(), transform() and decapsulate_out().
+pub struct Ghw11;
+
+impl CpAbe for Ghw11 {
+    type PublicKey = Ghw11PublicKey;
+    type MasterKey = Ghw11MasterKey;
+    type SecretKey = Ghw11SecretKey;
+    type Ciphertext = Ghw11Ciphertext;
+    type Header = Ghw11Header;
+
+    fn setup_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> Result<(Ghw11PublicKey, Ghw11MasterKey), RabeError> {
+        Ok(setup_with_rng(rng))
+    }
+
+    fn keygen_with_rng<R: RngCore + CryptoRng>(
+        pk: &Ghw11PublicKey,
+        msk: &Ghw11MasterKey,
+        attributes: &[&str],
+        rng: &mut R
+    ) -> Result<Ghw11SecretKey, RabeError> {
+        let attributes: Vec<String> = attributes.iter().map(|a| a.to_string()).collect();
+        keygen_with_rng(pk, msk, &attributes, rng).ok_or_else(|| RabeError::InvalidInput(String::from("ghw11/keygen: attributes are empty")))
+    }
+
+    fn encrypt_with<P: PolicySource, R: RngCore + CryptoRng>(
+        pk: &Ghw11PublicKey,
+        policy: P,
+        plaintext: &[u8],
+        options: EncryptOptions<R>
+    ) -> Result<Ghw11Ciphertext, RabeError> {
+        encrypt_with(pk, policy, plaintext, options)
+    }
+
+    fn decrypt_with_aad(
+        sk: &Ghw11SecretKey,
+        ct: &Ghw11Ciphertext,
+        aad: &[u8]
+    ) -> Result<Vec<u8>, RabeError> {
+        match tkgen(sk.clone()) {
+            Some((tk, rk)) => {
+                let pct = transform(ct.header.clone(), tk)?;
+                decrypt_out_with_aad(pct, rk, ct, aad)
+            },
+            None => Err(RabeError::InvalidKey(String::from("ghw11/decrypt: could not generate transform key")))
+        }
+    }
+
+    fn encapsulate_with<P: PolicySource, R: RngCore + CryptoRng>(
+        pk: &Ghw11PublicKey,
+        policy: P,
+        options: EncryptOptions<R>
+    ) -> Result<(SharedKey, Ghw11Header), RabeError> {
+        encapsulate_with(pk, policy, options)
+    }
+
+    fn decapsulate(
+        sk: &Ghw11SecretKey,
+        header: &Ghw11Header
+    ) -> Result<SharedKey, RabeError> {
+        match tkgen(sk.clone()) {
+            Some((tk, rk)) => {
+                let pct = transform(header.clone(), tk)?;
+                Ok(decapsulate_out(pct, rk))
+            },
+            None => Err(RabeError::InvalidKey(String::from("ghw11/decapsulate: could not generate transform key")))
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use schemes::harness;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
 
     #[test]
     fn or() {
@@ -335,7 +630,7 @@ mod tests {
         let policy = String::from(r#"{"name": "or", "children": [{"name": "A"}, {"name": "B"}]}"#);
 
         // cp-abe ciphertext
-        let ct_cp = encrypt(&pk, &policy, PolicyLanguage::JsonPolicy, &plaintext).unwrap();
+        let ct_cp = encrypt(&pk, (&policy, PolicyLanguage::JsonPolicy), &plaintext).unwrap();
 
         //tk gen
         let (match_tk, match_rk) = tkgen(match_sk).unwrap();
@@ -343,12 +638,12 @@ mod tests {
         let (not_match_tk, _not_match_rk) = tkgen(not_match_sk).unwrap();
 
         //transform
-        let transform_ct = transform(ct_cp.clone(), match_tk).unwrap();
+        let transform_ct = transform(ct_cp.header.clone(), match_tk).unwrap();
 
-        let not_match_transform_ct = transform(ct_cp.clone(), not_match_tk);
+        let not_match_transform_ct = transform(ct_cp.header.clone(), not_match_tk);
         assert_eq!(not_match_transform_ct.is_ok(), false);
 
-        let _match = decrypt_out(transform_ct, match_rk, ct_cp.data.clone());
+        let _match = decrypt_out(transform_ct, match_rk, &ct_cp);
         assert_eq!(_match.is_ok(), true);
         assert_eq!(_match.unwrap(), plaintext);
     }
@@ -372,16 +667,16 @@ mod tests {
         let policy = String::from(r#"{"name": "and", "children": [{"name": "attr0"}, {"name": "attr1"}]}"#);
 
         // cp-abe ciphertext
-        let ct_cp = encrypt(&pk, &policy, PolicyLanguage::JsonPolicy, &plaintext).unwrap();
+        let ct_cp = encrypt(&pk, (&policy, PolicyLanguage::JsonPolicy), &plaintext).unwrap();
 
         //tk gen
         let (tk, rk) = tkgen(bob_sk).unwrap();
 
         //transform
-        let transform_ct = transform(ct_cp.clone(), tk).unwrap();
+        let transform_ct = transform(ct_cp.header.clone(), tk).unwrap();
 
         // and now decrypt again with mathcing tk
-        let _matching = decrypt_out(transform_ct, rk, ct_cp.data).unwrap();
+        let _matching = decrypt_out(transform_ct, rk, &ct_cp).unwrap();
 
         assert_eq!(_matching, plaintext);
 
@@ -429,7 +724,7 @@ mod tests {
         }
         _policy.push_str(&String::from("}"));
         // cp-abe ciphertext
-        let ct_cp = encrypt(&pk, &_policy, PolicyLanguage::JsonPolicy, &plaintext).unwrap();
+        let ct_cp = encrypt(&pk, (&_policy, PolicyLanguage::JsonPolicy), &plaintext).unwrap();
 
         //tk gen
         let (match_tk, match_rk) = tkgen(match_sk).unwrap();
@@ -437,14 +732,34 @@ mod tests {
         let (not_match_tk, _not_match_rk) = tkgen(not_match_sk).unwrap();
 
         //transform
-        let transform_ct = transform(ct_cp.clone(), match_tk).unwrap();
+        let transform_ct = transform(ct_cp.header.clone(), match_tk).unwrap();
 
-        let not_match_transform_ct = transform(ct_cp.clone(), not_match_tk);
+        let not_match_transform_ct = transform(ct_cp.header.clone(), not_match_tk);
         assert_eq!(not_match_transform_ct.is_ok(), false);
 
-        let _match = decrypt_out(transform_ct, match_rk, ct_cp.data.clone());
+        let _match = decrypt_out(transform_ct, match_rk, &ct_cp);
         assert_eq!(_match.is_ok(), true);
         assert_eq!(_match.unwrap(), plaintext);
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn prepared_public_key() {
+        let (pk, msk) = setup();
+        let prepared = Ghw11PreparedPublicKey::new(&pk);
+        let policy = String::from(r#""A" and ("B" or "C")"#);
+        // the same randomness yields the same header with and without tables
+        let plain = encapsulate_with(&pk, (&policy, PolicyLanguage::HumanPolicy), EncryptOptions::new().rng(ChaCha20Rng::seed_from_u64(20))).unwrap();
+        let with_tables = encapsulate_with(&prepared, (&policy, PolicyLanguage::HumanPolicy), EncryptOptions::new().rng(ChaCha20Rng::seed_from_u64(20))).unwrap();
+        assert!(plain == with_tables);
+        let plaintext = String::from("dance like no one's watching, encrypt like everyone is!").into_bytes();
+        let ct = encrypt(&prepared, (&policy, PolicyLanguage::HumanPolicy), &plaintext).unwrap();
+        let (tk, rk) = tkgen(keygen(&pk, &msk, &[String::from("A"), String::from("C")]).unwrap()).unwrap();
+        let transformed = transform(ct.header.clone(), tk).unwrap();
+        assert_eq!(decrypt_out(transformed, rk, &ct).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn cp_abe() {
+        harness::cp_abe::<Ghw11>().unwrap();
+    }
+}
@@ -0,0 +1,290 @@
+//! A generic test harness for the [CpAbe], [KpAbe] and [MultiAuthorityAbe] traits.
+//!
+//! Every scheme runs the harness of its traits in its own `mod tests`, e.g. `harness::cp_abe::<Bsw>()`. Cases that
+//! only apply to a single scheme are tested there as well.
+use std::fmt::Debug;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use schemes::traits::{CpAbe, KpAbe, MultiAuthorityAbe};
+use schemes::options::EncryptOptions;
+use error::RabeError;
+use utils::policy::ast::{Policy, PolicySource};
+use utils::policy::comparison::{expand_attributes, numeric_attributes};
+use utils::policy::pest::{encode_negations, negated_attributes, PolicyLanguage};
+
+const PLAINTEXT: &[u8] = b"dance like no one's watching, encrypt like everyone is!";
+
+/// Runs all cases of the CP-ABE harness
+pub(crate) fn cp_abe<S: CpAbe>() -> Result<(), RabeError>
+where S::PublicKey: PartialEq + Debug, S::SecretKey: PartialEq + Debug, S::Ciphertext: PartialEq + Debug {
+    cp_roundtrip::<S>()?;
+    cp_policies::<S>()?;
+    cp_negation::<S>()?;
+    cp_comparison::<S>()?;
+    cp_seeded::<S>()
+}
+
+/// Runs all cases of the KP-ABE harness, `negations` is set if the scheme supports negated attributes in keys
+pub(crate) fn kp_abe<S: KpAbe>(negations: bool) -> Result<(), RabeError>
+where S::PublicKey: PartialEq + Debug, S::SecretKey: PartialEq + Debug, S::Ciphertext: PartialEq + Debug {
+    kp_roundtrip::<S>()?;
+    kp_policies::<S>()?;
+    kp_negation::<S>(negations)?;
+    kp_comparison::<S>()?;
+    kp_seeded::<S>()
+}
+
+/// Runs all cases of the multi authority harness
+pub(crate) fn ma_abe<S: MultiAuthorityAbe>() -> Result<(), RabeError> {
+    ma_roundtrip::<S>()?;
+    ma_policies::<S>()
+}
+
+// checks that a decryption returns the plaintext if `satisfied`, and fails because of the policy otherwise
+fn check<C: Debug>(result: Result<Vec<u8>, RabeError>, satisfied: bool, case: C) {
+    match result {
+        Ok(pt) => assert!(satisfied && pt == PLAINTEXT, "{:?}", case),
+        Err(e) => assert!(!satisfied && matches!(e, RabeError::PolicyNotSatisfied(_)), "{:?} {:?}", case, e),
+    }
+}
+
+// checks that a decryption fails with an error that explains the missing attributes
+fn check_missing(result: Result<Vec<u8>, RabeError>, missing: &str) {
+    match result {
+        // attributes are case insensitive in AW11
+        Err(RabeError::PolicyNotSatisfied(message)) => assert!(message.to_lowercase().ends_with(&missing.to_lowercase()), "{}", message),
+        other => panic!("expected PolicyNotSatisfied, got {:?}", other.err()),
+    }
+}
+
+fn cp_roundtrip<S: CpAbe>() -> Result<(), RabeError> {
+    let (pk, msk) = S::setup()?;
+    let sk = S::keygen(&pk, &msk, &["A", "B", "C"])?;
+    let ct = S::encrypt(&pk, (r#""A" and ("B" or "D")"#, PolicyLanguage::HumanPolicy), PLAINTEXT)?;
+    assert_eq!(S::decrypt(&sk, &ct)?, PLAINTEXT);
+    let ct = S::encrypt(&pk, (r#""A" and "D""#, PolicyLanguage::HumanPolicy), PLAINTEXT)?;
+    check_missing(S::decrypt(&sk, &ct), r#"missing one of ["D"]"#);
+    let ct = S::encrypt_with(&pk, (r#""A" and ("B" or "D")"#, PolicyLanguage::HumanPolicy), PLAINTEXT, EncryptOptions::new().aad(b"context"))?;
+    assert_eq!(S::decrypt_with_aad(&sk, &ct, b"context")?, PLAINTEXT);
+    assert!(S::decrypt_with_aad(&sk, &ct, b"other context").is_err());
+    assert!(matches!(S::decrypt(&sk, &ct), Err(RabeError::SymmetricDecryption)));
+    let (key, header) = S::encapsulate(&pk, (r#""A" and ("B" or "D")"#, PolicyLanguage::HumanPolicy))?;
+    assert_eq!(S::decapsulate(&sk, &header)?, key);
+    let (key, header) = S::encapsulate(&pk, (r#""A" and "D""#, PolicyLanguage::HumanPolicy))?;
+    assert_ne!(S::decapsulate(&sk, &header).ok(), Some(key));
+    Ok(())
+}
+
+fn kp_roundtrip<S: KpAbe>() -> Result<(), RabeError> {
+    let (pk, msk) = S::setup(&["A", "B", "C", "D"])?;
+    let sk = S::keygen(&pk, &msk, (r#""A" and ("B" or "D")"#, PolicyLanguage::HumanPolicy))?;
+    let ct = S::encrypt(&pk, &["A", "B", "C"], PLAINTEXT)?;
+    assert_eq!(S::decrypt(&sk, &ct)?, PLAINTEXT);
+    let ct = S::encrypt(&pk, &["A", "C"], PLAINTEXT)?;
+    check_missing(S::decrypt(&sk, &ct), r#"missing one of ["B"], ["D"]"#);
+    let ct = S::encrypt_with(&pk, &["A", "B", "C"], PLAINTEXT, EncryptOptions::new().aad(b"context"))?;
+    assert_eq!(S::decrypt_with_aad(&sk, &ct, b"context")?, PLAINTEXT);
+    assert!(S::decrypt_with_aad(&sk, &ct, b"other context").is_err());
+    assert!(matches!(S::decrypt(&sk, &ct), Err(RabeError::SymmetricDecryption)));
+    let (key, header) = S::encapsulate(&pk, &["A", "B", "C"])?;
+    assert_eq!(S::decapsulate(&sk, &header)?, key);
+    let (key, header) = S::encapsulate(&pk, &["A", "C"])?;
+    assert_ne!(S::decapsulate(&sk, &header).ok(), Some(key));
+    Ok(())
+}
+
+fn ma_roundtrip<S: MultiAuthorityAbe>() -> Result<(), RabeError> {
+    let (gk, msk) = S::setup()?;
+    let auth1 = S::authgen(&gk, &msk, "auth1", &["auth1::A", "auth1::B"])?;
+    let auth2 = S::authgen(&gk, &msk, "auth2", &["auth2::C"])?;
+    let mut sk = S::keygen(&gk, &msk, &auth1, "bob", &["auth1::A"])?;
+    S::add_attribute(&gk, &auth2, "auth2::C", &mut sk)?;
+    let pk_a = S::attribute_public_key(&gk, &auth1, "auth1::A")?;
+    let pk_b = S::attribute_public_key(&gk, &auth1, "auth1::B")?;
+    let pk_c = S::attribute_public_key(&gk, &auth2, "auth2::C")?;
+    let ct = S::encrypt(&gk, &[&pk_a, &pk_b, &pk_c], (r#"("auth1::A" and "auth2::C") or "auth1::B""#, PolicyLanguage::HumanPolicy), PLAINTEXT)?;
+    assert_eq!(S::decrypt(&gk, &sk, &ct)?, PLAINTEXT);
+    let ct = S::encrypt(&gk, &[&pk_a, &pk_b], (r#""auth1::A" and "auth1::B""#, PolicyLanguage::HumanPolicy), PLAINTEXT)?;
+    check_missing(S::decrypt(&gk, &sk, &ct), r#"missing one of ["auth1::B"]"#);
+    let ct = S::encrypt_with(&gk, &[&pk_a, &pk_c], (r#""auth1::A" and "auth2::C""#, PolicyLanguage::HumanPolicy), PLAINTEXT, EncryptOptions::new().aad(b"context"))?;
+    assert_eq!(S::decrypt_with_aad(&gk, &sk, &ct, b"context")?, PLAINTEXT);
+    assert!(S::decrypt_with_aad(&gk, &sk, &ct, b"other context").is_err());
+    assert!(matches!(S::decrypt(&gk, &sk, &ct), Err(RabeError::SymmetricDecryption)));
+    let (key, header) = S::encapsulate(&gk, &[&pk_a, &pk_c], (r#""auth1::A" and "auth2::C""#, PolicyLanguage::HumanPolicy))?;
+    assert_eq!(S::decapsulate(&gk, &sk, &header)?, key);
+    let (key, header) = S::encapsulate(&gk, &[&pk_a, &pk_b], (r#""auth1::A" and "auth1::B""#, PolicyLanguage::HumanPolicy))?;
+    assert_ne!(S::decapsulate(&gk, &sk, &header).ok(), Some(key));
+    Ok(())
+}
+
+// threshold gates in both policy languages and policy trees, for a key of the attributes A and C
+fn cp_policies<S: CpAbe>() -> Result<(), RabeError> {
+    let (pk, msk) = S::setup()?;
+    let sk = S::keygen(&pk, &msk, &["A", "C"])?;
+    let policies: [(&dyn PolicySource, bool); 7] = [
+        (&(r#"2 of ("A", "B", "C")"#, PolicyLanguage::HumanPolicy), true),
+        (&(r#"2 of ("A", "B", "D")"#, PolicyLanguage::HumanPolicy), false),
+        (&(r#""C" and 2 of ("B", "A" or "D", ("C" and "A"))"#, PolicyLanguage::HumanPolicy), true),
+        (&(r#"{"name": "threshold", "k": 2, "children": [{"name": "B"}, {"name": "C"}, {"name": "A"}]}"#, PolicyLanguage::JsonPolicy), true),
+        (&(r#"{"name": "threshold", "k": 3, "children": [{"name": "B"}, {"name": "C"}, {"name": "A"}]}"#, PolicyLanguage::JsonPolicy), false),
+        (&Policy::and([Policy::attr("A")?, Policy::threshold(1, [Policy::attr("B")?, Policy::attr("C")?])]), true),
+        (&Policy::and([Policy::attr("A")?, Policy::attr("B")?]), false),
+    ];
+    for (i, &(policy, satisfied)) in policies.iter().enumerate() {
+        check(S::decrypt(&sk, &S::encrypt(&pk, policy, PLAINTEXT)?), satisfied, i);
+    }
+    Ok(())
+}
+
+// threshold gates in both policy languages and policy trees, for ciphertexts of several attribute sets
+fn kp_policies<S: KpAbe>() -> Result<(), RabeError> {
+    let (pk, msk) = S::setup(&["A", "B", "C", "D"])?;
+    let policies: [&dyn PolicySource; 3] = [
+        &(r#""D" or 2 of ("A", "B", "C")"#, PolicyLanguage::HumanPolicy),
+        &(r#"{"name": "or", "children": [{"name": "D"}, {"name": "threshold", "k": 2, "children": [{"name": "A"}, {"name": "B"}, {"name": "C"}]}]}"#, PolicyLanguage::JsonPolicy),
+        &Policy::or([Policy::attr("D")?, Policy::threshold(2, [Policy::attr("A")?, Policy::attr("B")?, Policy::attr("C")?])]),
+    ];
+    for policy in policies {
+        let sk = S::keygen(&pk, &msk, policy)?;
+        for (attributes, satisfied) in [(&["B", "C"][..], true), (&["A", "C"][..], true), (&["C"][..], false), (&["D"][..], true)] {
+            check(S::decrypt(&sk, &S::encrypt(&pk, attributes, PLAINTEXT)?), satisfied, attributes);
+        }
+    }
+    Ok(())
+}
+
+// threshold gates and policies that are not in DNF, which the DNF schemes convert
+fn ma_policies<S: MultiAuthorityAbe>() -> Result<(), RabeError> {
+    let (gk, msk) = S::setup()?;
+    let attributes = ["auth::A", "auth::B", "auth::C", "auth::D"];
+    let auth = S::authgen(&gk, &msk, "auth", &attributes)?;
+    let sk = S::keygen(&gk, &msk, &auth, "bob", &["auth::A", "auth::C"])?;
+    let pks = attributes.iter().map(|a| S::attribute_public_key(&gk, &auth, a)).collect::<Result<Vec<_>, _>>()?;
+    let pks: Vec<_> = pks.iter().collect();
+    let policies: [(&dyn PolicySource, bool); 5] = [
+        (&(r#"2 of ("auth::A", "auth::B", "auth::C")"#, PolicyLanguage::HumanPolicy), true),
+        (&(r#"3 of ("auth::A", "auth::B", "auth::C")"#, PolicyLanguage::HumanPolicy), false),
+        (&(r#"("auth::A" or "auth::B") and ("auth::C" or "auth::D")"#, PolicyLanguage::HumanPolicy), true),
+        (&(r#"("auth::A" and "auth::B") or ("auth::A" and "auth::D")"#, PolicyLanguage::HumanPolicy), false),
+        (&Policy::or([Policy::attr("auth::B")?, Policy::and([Policy::attr("auth::A")?, Policy::attr("auth::C")?])]), true),
+    ];
+    for (i, &(policy, satisfied)) in policies.iter().enumerate() {
+        check(S::decrypt(&gk, &sk, &S::encrypt(&gk, &pks, policy, PLAINTEXT)?), satisfied, i);
+    }
+    Ok(())
+}
+
+fn cp_negation<S: CpAbe>() -> Result<(), RabeError> {
+    let (pk, msk) = S::setup()?;
+    let policy = r#""A" and not "B""#;
+    assert!(matches!(S::encrypt(&pk, (policy, PolicyLanguage::HumanPolicy), PLAINTEXT), Err(RabeError::InvalidPolicy(_))));
+    // negations are encoded as dummy attributes, which the keys of all users lacking an attribute hold
+    let universe = ["A", "B", "C"];
+    let ct = S::encrypt(&pk, (&encode_negations(policy, PolicyLanguage::HumanPolicy)?, PolicyLanguage::JsonPolicy), PLAINTEXT)?;
+    for (attributes, satisfied) in [(&["A", "C"][..], true), (&["A", "B"][..], false), (&["C"][..], false)] {
+        let dummies = negated_attributes(&universe, attributes);
+        let mut all: Vec<&str> = attributes.to_vec();
+        all.extend(dummies.iter().map(|a| a.as_str()));
+        check(S::decrypt(&S::keygen(&pk, &msk, &all)?, &ct), satisfied, attributes);
+    }
+    Ok(())
+}
+
+fn kp_negation<S: KpAbe>(supported: bool) -> Result<(), RabeError> {
+    let (pk, msk) = S::setup(&["A", "B", "C"])?;
+    let sk = match S::keygen(&pk, &msk, (r#""A" and not "B""#, PolicyLanguage::HumanPolicy)) {
+        Err(e) => {
+            assert!(!supported && matches!(e, RabeError::InvalidPolicy(_)), "{:?}", e);
+            return Ok(());
+        },
+        Ok(sk) => sk,
+    };
+    assert!(supported);
+    for (attributes, satisfied) in [(&["A", "C"][..], true), (&["A"][..], true), (&["A", "B"][..], false), (&["C"][..], false)] {
+        check(S::decrypt(&sk, &S::encrypt(&pk, attributes, PLAINTEXT)?), satisfied, attributes);
+    }
+    Ok(())
+}
+
+fn cp_comparison<S: CpAbe>() -> Result<(), RabeError> {
+    let (pk, msk) = S::setup()?;
+    let ct = S::encrypt(&pk, (r#""A" and age >= 18 and level > 1"#, PolicyLanguage::HumanPolicy), PLAINTEXT)?;
+    for (attributes, satisfied) in [(["A", "age = 42", "level = 2"], true), (["A", "age = 17", "level = 3"], false), (["A", "age = 18", "level = 1"], false)] {
+        let attributes = expand_attributes(&attributes)?;
+        let attributes: Vec<&str> = attributes.iter().map(|a| a.as_str()).collect();
+        check(S::decrypt(&S::keygen(&pk, &msk, &attributes)?, &ct), satisfied, attributes);
+    }
+    // the width is part of the name, a key with a different width than the policy is rejected
+    let ct = S::encrypt(&pk, (r#""A" and age:8 >= 18"#, PolicyLanguage::HumanPolicy), PLAINTEXT)?;
+    for (attributes, satisfied) in [(["A", "age:8 = 42"], true), (["A", "age:8 = 17"], false)] {
+        let attributes = expand_attributes(&attributes)?;
+        let attributes: Vec<&str> = attributes.iter().map(|a| a.as_str()).collect();
+        check(S::decrypt(&S::keygen(&pk, &msk, &attributes)?, &ct), satisfied, attributes);
+    }
+    let attributes = expand_attributes(&["A", "age = 42"])?;
+    let attributes: Vec<&str> = attributes.iter().map(|a| a.as_str()).collect();
+    assert!(matches!(S::decrypt(&S::keygen(&pk, &msk, &attributes)?, &ct), Err(RabeError::InvalidPolicy(_))));
+    Ok(())
+}
+
+fn kp_comparison<S: KpAbe>() -> Result<(), RabeError> {
+    let mut universe = numeric_attributes("age", 0)?;
+    universe.extend(numeric_attributes("age", u64::MAX)?);
+    universe.extend(numeric_attributes("age:8", 0)?);
+    universe.extend(numeric_attributes("age:8", 255)?);
+    let universe: Vec<&str> = universe.iter().map(|a| a.as_str()).collect();
+    let (pk, msk) = S::setup(&universe)?;
+    // an upper bound on a small value is an AND of all higher bits, which is expensive for the MSP of AC17
+    let sk = S::keygen(&pk, &msk, (r#"{"name": "age", ">": 17}"#, PolicyLanguage::JsonPolicy))?;
+    for (age, satisfied) in [("age = 18", true), ("age = 64", true), ("age = 17", false), ("age = 0", false)] {
+        let attributes = expand_attributes(&[age])?;
+        let attributes: Vec<&str> = attributes.iter().map(|a| a.as_str()).collect();
+        check(S::decrypt(&sk, &S::encrypt(&pk, &attributes, PLAINTEXT)?), satisfied, age);
+    }
+    // the width is part of the name, a ciphertext with a different width than the key is rejected
+    let sk = S::keygen(&pk, &msk, (r#"{"name": "age:8", ">": 17}"#, PolicyLanguage::JsonPolicy))?;
+    for (age, satisfied) in [("age:8 = 18", true), ("age:8 = 17", false)] {
+        let attributes = expand_attributes(&[age])?;
+        let attributes: Vec<&str> = attributes.iter().map(|a| a.as_str()).collect();
+        check(S::decrypt(&sk, &S::encrypt(&pk, &attributes, PLAINTEXT)?), satisfied, age);
+    }
+    let attributes = expand_attributes(&["age = 18"])?;
+    let attributes: Vec<&str> = attributes.iter().map(|a| a.as_str()).collect();
+    assert!(matches!(S::decrypt(&sk, &S::encrypt(&pk, &attributes, PLAINTEXT)?), Err(RabeError::InvalidPolicy(_))));
+    Ok(())
+}
+
+// the same seed yields the same keys and ciphertexts
+fn cp_seeded<S: CpAbe>() -> Result<(), RabeError>
+where S::PublicKey: PartialEq + Debug, S::SecretKey: PartialEq + Debug, S::Ciphertext: PartialEq + Debug {
+    let run = |seed: u64| -> Result<_, RabeError> {
+        let mut rng = ChaCha20Rng::seed_from_u64(seed);
+        let (pk, msk) = S::setup_with_rng(&mut rng)?;
+        let sk = S::keygen_with_rng(&pk, &msk, &["A", "B"], &mut rng)?;
+        let ct = S::encrypt_with(&pk, (r#""A" and "B""#, PolicyLanguage::HumanPolicy), PLAINTEXT, EncryptOptions::new().rng(&mut rng))?;
+        Ok((pk, sk, ct))
+    };
+    let (pk, sk, ct) = run(42)?;
+    let (pk2, sk2, ct2) = run(42)?;
+    assert_eq!((pk, &sk, &ct), (pk2, &sk2, &ct2));
+    assert_ne!(ct, run(43)?.2);
+    assert_eq!(S::decrypt(&sk, &ct)?, PLAINTEXT);
+    Ok(())
+}
+
+fn kp_seeded<S: KpAbe>() -> Result<(), RabeError>
+where S::PublicKey: PartialEq + Debug, S::SecretKey: PartialEq + Debug, S::Ciphertext: PartialEq + Debug {
+    let run = |seed: u64| -> Result<_, RabeError> {
+        let mut rng = ChaCha20Rng::seed_from_u64(seed);
+        let (pk, msk) = S::setup_with_rng(&["A", "B"], &mut rng)?;
+        let sk = S::keygen_with_rng(&pk, &msk, (r#""A" and "B""#, PolicyLanguage::HumanPolicy), &mut rng)?;
+        let ct = S::encrypt_with(&pk, &["A", "B"], PLAINTEXT, EncryptOptions::new().rng(&mut rng))?;
+        Ok((pk, sk, ct))
+    };
+    let (pk, sk, ct) = run(42)?;
+    let (pk2, sk2, ct2) = run(42)?;
+    assert_eq!((pk, &sk, &ct), (pk2, &sk2, &ct2));
+    assert_ne!(ct, run(43)?.2);
+    assert_eq!(S::decrypt(&sk, &ct)?, PLAINTEXT);
+    Ok(())
+}
@@ -3,7 +3,7 @@
 //! * Developped by Allison Lewko, Amit Sahai and Brent Waters, "Revocation Systems with Very Small Private Keys"
 //! * Published in Security and Privacy, 2010. SP'10. IEEE Symposium on. IEEE
 //! * Available from <http://eprint.iacr.org/2008/309.pdf>
-//! * Type: encryption (key-policy attribute-based)
+//! * Type: encryption (key-policy attribute-based, non-monotone)
 //! * Setting: bilinear groups (asymmetric)
 //! * Authors: Georg Bramm
 //! * Date:	04/2018
@@ -17,26 +17,39 @@
 //! let plaintext = String::from("our plaintext!").into_bytes();
 //! let policy = String::from(r#""B" or "C""#);
 //! let ct_kp: KpAbeCiphertext = encrypt(&pk, &vec!["A", "B"], &plaintext).unwrap();
-//! let sk: KpAbeSecretKey = keygen(&pk, &msk, &policy, PolicyLanguage::HumanPolicy).unwrap();
+//! let sk: KpAbeSecretKey = keygen(&pk, &msk, (&policy, PolicyLanguage::HumanPolicy)).unwrap();
+//! assert_eq!(decrypt(&sk, &ct_kp).unwrap(), plaintext);
+//! // key policies may contain negations
+//! let sk: KpAbeSecretKey = keygen(&pk, &msk, (r#""A" and not "C""#, PolicyLanguage::HumanPolicy)).unwrap();
 //! assert_eq!(decrypt(&sk, &ct_kp).unwrap(), plaintext);
 //! ```
 use rabe_bn::{Group, Fr, G1, G2, Gt, pairing};
 use std::ops::Neg;
 use utils::{
     tools::*,
-    secretsharing::{gen_shares_policy, calc_coefficients, calc_pruned},
+    secretsharing::{gen_shares_policy_with_rng, calc_coefficients, calc_pruned_minimal, LeafWeights},
+    pairing::multi_pairing,
     aes::*,
-    hash::{sha3_hash_fr, sha3_hash}
+    hash::{hash_to_fr, hash_to_g1, HashInput},
+    container::{Container, SchemeId, ObjectType},
 };
-use rand::Rng;
-use utils::policy::pest::{PolicyLanguage, parse};
+use rand::{CryptoRng, Rng, RngCore};
+use utils::policy::pest::{PolicyLanguage, PolicyValue, parse, negation_normal_form};
+use utils::policy::ast::PolicySource;
+use utils::policy::explain::not_satisfied;
 use crate::error::RabeError;
+use schemes::traits::KpAbe;
+use schemes::options::EncryptOptions;
 #[cfg(feature = "serde")]
 use serde::{Serialize, Deserialize};
 #[cfg(feature = "borsh")]
 use borsh::{BorshSerialize, BorshDeserialize};
 use utils::secretsharing::remove_index;
 
+/// The decryption cost of the leaves of a policy: one pairing per attribute, two pairings per negated attribute. The
+/// pairings with e2 of all leaves are merged into one.
+const LEAF_WEIGHTS: LeafWeights = LeafWeights { attribute: 1, negated: 2 };
+
 /// A LSW Public Key (PK)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -50,6 +63,13 @@ pub struct KpAbePublicKey {
     e_gg_alpha: Gt,
 }
 
+validate_fields!(KpAbePublicKey { g1, g2, g1_b, g1_b2, h_b, e_gg_alpha });
+
+impl Container for KpAbePublicKey {
+    const SCHEME: SchemeId = SchemeId::Lsw;
+    const OBJECT: ObjectType = ObjectType::PublicKey;
+}
+
 /// A LSW Master Key (MSK)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -62,30 +82,79 @@ pub struct KpAbeMasterKey {
     h_g2: G2,
 }
 
+validate_fields!(KpAbeMasterKey { alpha1, alpha2, b, h_g1, h_g2 });
+
+impl Container for KpAbeMasterKey {
+    const SCHEME: SchemeId = SchemeId::Lsw;
+    const OBJECT: ObjectType = ObjectType::MasterKey;
+}
+
 /// A LSW Secret User Key (SK)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct KpAbeSecretKey {
     policy: (String, PolicyLanguage),
-    dj: Vec<(String, G1, G2, G1, G1, G1)>,
+    dj: Vec<(String, G1, G2, G1, G2, G2)>,
+}
+
+validate_fields!(KpAbeSecretKey { policy, dj });
+
+impl Container for KpAbeSecretKey {
+    const SCHEME: SchemeId = SchemeId::Lsw;
+    const OBJECT: ObjectType = ObjectType::SecretKey;
 }
 
-/// A LSW Ciphertext (CT)
+/// A LSW Header, i.e. the encapsulated key under a set of attributes
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub struct KpAbeCiphertext {
+pub struct KpAbeHeader {
     e1: Gt,
     e2: G2,
     ej: Vec<(String, G1, G1, G1)>,
+    pub cipher: SymmetricCipher,
+}
+
+validate_fields!(KpAbeHeader { e1, e2, ej, cipher });
+
+impl Container for KpAbeHeader {
+    const SCHEME: SchemeId = SchemeId::Lsw;
+    const OBJECT: ObjectType = ObjectType::Header;
+}
+
+impl KpAbeHeader {
+    /// Returns the associated data that binds this header to the symmetrically encrypted data:
+    /// the scheme id, the format version, the attributes and the caller supplied `aad`.
+    pub fn associated_data(&self, aad: &[u8]) -> Vec<u8> {
+        let attributes: Vec<&[u8]> = self.ej.iter().map(|(a, _, _, _)| a.as_bytes()).collect();
+        associated_data("LSW", &attributes, aad)
+    }
+}
+
+/// A LSW Ciphertext (CT), composed of a KpAbeHeader and the symmetrically encrypted data
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct KpAbeCiphertext {
+    header: KpAbeHeader,
     ct: Vec<u8>,
 }
 
+validate_fields!(KpAbeCiphertext { header, ct });
+
+impl Container for KpAbeCiphertext {
+    const SCHEME: SchemeId = SchemeId::Lsw;
+    const OBJECT: ObjectType = ObjectType::Ciphertext;
+}
+
 /// The setup algorithm of LSW KP-ABE. Generates a new KpAbePublicKey and a new KpAbeMasterKey.
 pub fn setup() -> (KpAbePublicKey, KpAbeMasterKey) {
-    // random number generator
-    let mut rng = rand::thread_rng();
+    setup_with_rng(&mut rand::thread_rng())
+}
+
+/// Like `setup()`, but draws all randomness from the given random number generator `rng`.
+pub fn setup_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> (KpAbePublicKey, KpAbeMasterKey) {
     // generate random alpha1, alpha2 and b
     let alpha1:Fr = rng.gen();
     let alpha2:Fr = rng.gen();
@@ -94,8 +163,10 @@ pub fn setup() -> (KpAbePublicKey, KpAbeMasterKey) {
     let alpha = alpha1 * alpha2;
     let g1:G1 = rng.gen();
     let g2:G2 = rng.gen();
-    let h_g1:G1 = rng.gen();
-    let h_g2:G2 = rng.gen();
+    // h has the same discrete logarithm in both groups, as required by the decryption of negated attributes
+    let h:Fr = rng.gen();
+    let h_g1 = g1 * h;
+    let h_g2 = g2 * h;
     let g1_b = g1 * b;
     let g1_b2 = g1_b * b;
     let h_b = h_g1 * b;
@@ -110,50 +181,57 @@ pub fn setup() -> (KpAbePublicKey, KpAbeMasterKey) {
 
 /// The key generation algorithm of LSW KP-ABE.
 /// Generates a KpAbeSecretKey using a KpAbePublicKey, a KpAbeMasterKey and a policy given as JSON String.
+/// The policy may contain negations (`not "A"`, or the attribute `"!A"`), which are satisfied if a ciphertext lacks the attribute.
 ///
 /// # Arguments
 ///
 ///	* `pk` - A Public Key (PK), generated by the function setup()
 ///	* `msk` - A Master Key (MSK), generated by the function setup()
-///	* `policy` - An access policy given as JSON String
-///	* `language` - The policy language
+///	* `policy` - An access policy, given as text and its PolicyLanguage or as Policy, see [PolicySource]
 ///
-pub fn keygen(
+pub fn keygen<P: PolicySource>(
     pk: &KpAbePublicKey,
     msk: &KpAbeMasterKey,
-    policy: &str,
-    language: PolicyLanguage,
+    policy: P
 ) -> Result<KpAbeSecretKey, RabeError> {
-    // random number generator
-    let mut rng = rand::thread_rng();
-    match parse(policy, language) {
-        Ok(policy_value) => {
-            match gen_shares_policy(msk.alpha1, &policy_value, None) {
-                Some(shares) => {
-                    let mut dj: Vec<(String, G1, G2, G1, G1, G1)> = Vec::new();
+    keygen_with_rng(pk, msk, policy, &mut rand::thread_rng())
+}
+
+/// Like `keygen()`, but draws all randomness from the given random number generator `rng`.
+pub fn keygen_with_rng<P: PolicySource, R: RngCore + CryptoRng>(
+    pk: &KpAbePublicKey,
+    msk: &KpAbeMasterKey,
+    policy: P,
+    rng: &mut R
+) -> Result<KpAbeSecretKey, RabeError> {
+    match policy.value() {
+        Ok((policy_value, policy, language)) => {
+            match gen_shares_policy_with_rng(msk.alpha1, &lsw_policy(policy_value), None, rng) {
+                Ok(shares) => {
+                    let mut dj: Vec<(String, G1, G2, G1, G2, G2)> = Vec::new();
                     for (share_str, share_value) in shares.into_iter() {
                         let striped = remove_index(&share_str);
                         let random:Fr = rng.gen();
                         if is_negative(&striped) {
-                            let share_hash = sha3_hash_fr(&striped).expect("could not hash _share_str");
+                            let share_hash = hash_to_fr(SchemeId::Lsw, &HashInput::new("attribute").string(&striped[1..]))?;
                             dj.push((
                                 striped,
                                 G1::zero(),
                                 G2::zero(),
-                                (pk.g1 * share_value) + (pk.g1_b2 * random),
-                                pk.g1_b * (share_hash * random) + (msk.h_g1 * random),
-                                pk.g1 * random.neg(),
+                                (pk.g1 * (msk.alpha2 * share_value)) + (pk.g1_b2 * random),
+                                (pk.g2 * (msk.b * share_hash * random)) + (msk.h_g2 * random),
+                                pk.g2 * random.neg(),
                             ));
                         } else {
-                            let share_hash = sha3_hash(pk.g1, &striped).expect("could not hash _share_str");
+                            let share_hash = hash_to_g1(SchemeId::Lsw, &HashInput::new("attribute").string(&striped))?;
                             dj.push((
                                 striped,
                                 (pk.g1 * (msk.alpha2 * share_value))
                                     + (share_hash * random),
                                 pk.g2 * random,
                                 G1::zero(),
-                                G1::zero(),
-                                G1::zero(),
+                                G2::zero(),
+                                G2::zero(),
                             ));
                         }
                     }
@@ -162,7 +240,7 @@ pub fn keygen(
                         dj,
                     });
                 },
-                None => Err(RabeError::new("could not share secret over policy"))
+                Err(e) => Err(e)
             }
         }
         Err(e) => Err(e)
@@ -178,43 +256,77 @@ pub fn keygen(
 ///	* `plaintext` - plaintext data given as a Vector of u8
 ///
 pub fn encrypt(
+    pk: &KpAbePublicKey,
+    attributes: &[&str],
+    plaintext: &[u8]
+) -> Result<KpAbeCiphertext, RabeError> {
+    encrypt_with(pk, attributes, plaintext, EncryptOptions::new())
+}
+
+/// Like `encrypt()`, but with the given [EncryptOptions], i.e. the random number generator, the associated data
+/// that has to be passed to `decrypt_with_aad()` and the symmetric cipher.
+pub fn encrypt_with<R: RngCore + CryptoRng>(
     pk: &KpAbePublicKey,
     attributes: &[&str],
     plaintext: &[u8],
+    mut options: EncryptOptions<R>
 ) -> Result<KpAbeCiphertext, RabeError> {
     if attributes.is_empty() || plaintext.is_empty() {
-        Err(RabeError::new("attributes or data empty"))
+        Err(RabeError::InvalidInput(String::from("lsw/encrypt: attributes or data empty")))
+    } else {
+        let (key, header) = encapsulate_with(pk, attributes, options.by_ref())?;
+        //Encrypt plaintext using the encapsulated key, binding the header as associated data
+        let ct = header.cipher.encrypt(&key, plaintext, &header.associated_data(options.aad), &mut options.rng)?;
+        Ok(KpAbeCiphertext { header, ct })
+    }
+}
+
+/// The key encapsulation algorithm of LSW KP-ABE. Generates a new SharedKey and a KpAbeHeader that encapsulates it under a set of attributes given as String Vector.
+///
+/// # Arguments
+///
+///	* `pk` - A Public Key (PK), generated by the function setup()
+///	* `attributes` - A set of attributes given as String Vector
+///
+pub fn encapsulate(
+    pk: &KpAbePublicKey,
+    attributes: &[&str]
+) -> Result<(SharedKey, KpAbeHeader), RabeError> {
+    encapsulate_with(pk, attributes, EncryptOptions::new())
+}
+
+/// Like `encapsulate()`, but with the given [EncryptOptions], i.e. the random number generator and the symmetric
+/// cipher that is recorded in the header.
+pub fn encapsulate_with<R: RngCore + CryptoRng>(
+    pk: &KpAbePublicKey,
+    attributes: &[&str],
+    mut options: EncryptOptions<R>
+) -> Result<(SharedKey, KpAbeHeader), RabeError> {
+    let rng = &mut options.rng;
+    if attributes.is_empty() {
+        Err(RabeError::InvalidInput(String::from("lsw/encapsulate: attributes are empty")))
     } else {
-        // random number generator
-        let mut rng = rand::thread_rng();
         // attribute vector
         let mut ej: Vec<(String, G1, G1, G1)> = Vec::new();
         // random secret
         let secret:Fr = rng.gen();
-        // sx vector
-        let mut sx: Vec<Fr> = Vec::new();
-        sx.push(secret);
-        for (_i, _attr) in attributes.iter().enumerate() {
-            sx.push(rng.gen());
-            sx[0] = sx[0] - sx[_i];
-        }
+        // sx vector, a random sharing of the secret: the sum of all sx is the secret
+        let mut sx: Vec<Fr> = attributes.iter().skip(1).map(|_| rng.gen()).collect();
+        sx.push(sx.iter().fold(secret, |rest, s| rest - *s));
         for (_i, _attr) in attributes.into_iter().enumerate() {
             ej.push((
                 _attr.to_string(),
-                sha3_hash(pk.g1, &_attr).expect("could not hash _attr") * secret,
+                hash_to_g1(SchemeId::Lsw, &HashInput::new("attribute").string(_attr))? * secret,
                 pk.g1_b * sx[_i.clone()],
-                (pk.g1_b2 * (sx[_i.clone()] * sha3_hash_fr(&_attr.to_string()).expect("could not hash _attr"))) + (pk.h_b * sx[_i]),
+                (pk.g1_b2 * (sx[_i.clone()] * hash_to_fr(SchemeId::Lsw, &HashInput::new("attribute").string(_attr))?)) + (pk.h_b * sx[_i]),
             ));
         }
         // random message
         let msg: Gt = rng.gen();
         let e1: Gt = pk.e_gg_alpha.pow(secret) * msg;
         let e2: G2 = pk.g2 * secret;
-        //Encrypt plaintext using derived key from secret
-        match encrypt_symmetric(msg, &plaintext.to_vec()) {
-            Ok(ct) => Ok(KpAbeCiphertext { e1, e2, ej, ct }),
-            Err(e) => Err(e)
-        }
+        let header = KpAbeHeader { e1, e2, ej, cipher: options.cipher };
+        Ok((SharedKey::derive_for(msg, &header)?, header))
     }
 }
 
@@ -229,58 +341,85 @@ pub fn decrypt(
     sk: &KpAbeSecretKey,
     ct: &KpAbeCiphertext
 ) -> Result<Vec<u8>, RabeError> {
-    let attr = ct
+    decrypt_with_aad(sk, ct, &[])
+}
+
+/// Like `decrypt()`, but for ciphertexts that were generated by `encrypt_with()` with the associated data `aad`.
+pub fn decrypt_with_aad(
+    sk: &KpAbeSecretKey,
+    ct: &KpAbeCiphertext,
+    aad: &[u8]
+) -> Result<Vec<u8>, RabeError> {
+    let key = decapsulate(sk, &ct.header)?;
+    ct.header.cipher.decrypt(&key, &ct.ct, &ct.header.associated_data(aad))
+}
+
+/// The key decapsulation algorithm of LSW KP-ABE. Recovers the SharedKey of a KpAbeHeader with a matching KpAbeSecretKey.
+///
+/// # Arguments
+///
+///	* `sk` - A Secret Key (SK), generated by the function keygen()
+///	* `header` - A LSW KP-ABE Header
+///
+pub fn decapsulate(
+    sk: &KpAbeSecretKey,
+    header: &KpAbeHeader
+) -> Result<SharedKey, RabeError> {
+    let attr = header
         .ej
         .iter()
         .map(|a| a.clone().0.to_string())
         .collect::<Vec<_>>();
     match parse(sk.policy.0.as_ref(), sk.policy.1) {
         Ok(policy_value) => {
-            return match calc_pruned(&attr, &policy_value, None) {
+            let policy_value = lsw_policy(policy_value);
+            return match calc_pruned_minimal(&attr, &policy_value, LEAF_WEIGHTS) {
                 Err(e) => Err(e),
                 Ok((matches, list)) => {
                     if matches {
-                        let mut prod_t = Gt::one();
-                        let mut _z_y = Gt::one();
+                        // the pairings of the leaves, weighted by their inverse coefficients
+                        let mut pairs: Vec<(G1, G2)> = Vec::new();
                         let mut coeff_list: Vec<(String, Fr)> = Vec::new();
-                        coeff_list = calc_coefficients(&policy_value, Some(Fr::one()), coeff_list, None).unwrap();
+                        coeff_list = calc_coefficients(&policy_value, Some(Fr::one()), coeff_list, None, &list)?;
                         for attr_str in list.iter() {
                             let sk_attr = sk
                                 .dj
                                 .iter()
-                                .filter(|_attr| { _attr.0 == attr_str.0.to_string() })
-                                .nth(0)
-                                .unwrap();
-                            let ct_attr = ct
-                                .ej
-                                .iter()
-                                .filter(|_attr| _attr.0 == attr_str.0.to_string())
-                                .nth(0)
-                                .unwrap();
+                                .find(|_attr| { _attr.0 == attr_str.0.to_string() })
+                                .ok_or_else(|| RabeError::InvalidKey(format!("lsw/decapsulate: no key for attribute {}", attr_str.0)))?;
                             let coeff = coeff_list
                                 .iter()
-                                .filter(|_attr| _attr.0 == attr_str.1.to_string())
-                                .nth(0)
-                                .unwrap();
+                                .find(|_attr| _attr.0 == attr_str.1.to_string())
+                                .ok_or_else(|| RabeError::InvalidPolicy(format!("lsw/decapsulate: no coefficient for {}", attr_str.1)))?;
                             if is_negative(&attr_str.0) {
-                                // TODO !!
-                                /*let _sum_e4 = G2::zero();
-                                let _sum_e5 = G2::zero();
-                                _prod_t = _prod_t *
-                                    (pairing(sk._d_i[_i].3, ct._e2) *
-                                         (pairing(sk._d_i[_i].4, _sum_e4) * pairing(sk._d_i[_i].5, _sum_e5))
-                                             .inverse());
-                                */
+                                // interpolate over all attributes of the ciphertext, which all differ from the negated one
+                                let negated = hash_to_fr(SchemeId::Lsw, &HashInput::new("attribute").string(&attr_str.0[1..]))?;
+                                let mut sum_e2 = G1::zero();
+                                let mut sum_e3 = G1::zero();
+                                for ct_attr in header.ej.iter() {
+                                    let omega = (negated - hash_to_fr(SchemeId::Lsw, &HashInput::new("attribute").string(&ct_attr.0))?)
+                                        .inverse()
+                                        .ok_or_else(|| RabeError::PolicyNotSatisfied(format!("lsw/decapsulate: negated attribute {} is present", ct_attr.0)))?;
+                                    sum_e2 = sum_e2 + (ct_attr.2 * omega);
+                                    sum_e3 = sum_e3 + (ct_attr.3 * omega);
+                                }
+                                pairs.push((-(sk_attr.3 * coeff.1), header.e2));
+                                pairs.push((sum_e2 * coeff.1, sk_attr.4));
+                                pairs.push((sum_e3 * coeff.1, sk_attr.5));
                             } else {
-                                _z_y = pairing(sk_attr.1, ct.e2)
-                                    * pairing(ct_attr.1, sk_attr.2).inverse();
+                                let ct_attr = header
+                                    .ej
+                                    .iter()
+                                    .find(|_attr| _attr.0 == attr_str.0.to_string())
+                                    .ok_or_else(|| RabeError::UnknownAttribute(attr_str.0.to_string()))?;
+                                pairs.push((-(sk_attr.1 * coeff.1), header.e2));
+                                pairs.push((ct_attr.1 * coeff.1, sk_attr.2));
                             }
-                            prod_t = prod_t * _z_y.pow(coeff.1);
                         }
-                        let msg: Gt = ct.e1 * prod_t.inverse();
-                        decrypt_symmetric(msg, &ct.ct)
+                        let msg: Gt = header.e1 * multi_pairing(&pairs);
+                        SharedKey::derive_for(msg, header)
                     } else {
-                        Err(RabeError::new("Error in lsw/decrypt: attributes do not match policy."))
+                        Err(not_satisfied("lsw/decrypt: attributes do not match policy", &attr, &policy_value))
                     }
                 }
             }
@@ -289,10 +428,82 @@ pub fn decrypt(
     }
 }
 
+// the negation normal form of a key policy, attributes of the form "!A" are negations, too
+fn lsw_policy(policy: PolicyValue) -> PolicyValue {
+    negation_normal_form(attribute_negations(policy))
+}
+
+fn attribute_negations(policy: PolicyValue) -> PolicyValue {
+    match policy {
+        PolicyValue::String((name, position)) if name.starts_with('!') => PolicyValue::Not(Box::new(PolicyValue::String((name[1..].to_string().into(), position)))),
+        PolicyValue::Array(children) => PolicyValue::Array(children.into_iter().map(attribute_negations).collect()),
+        PolicyValue::Object((policy_type, child)) => PolicyValue::Object((policy_type, Box::new(attribute_negations(*child)))),
+        PolicyValue::Not(child) => PolicyValue::Not(Box::new(attribute_negations(*child))),
+        PolicyValue::String(node) => PolicyValue::String(node),
+    }
+}
+
+/// The LSW KP-ABE scheme, to be used through the [`KpAbe`](../traits/trait.KpAbe.html) trait.
+pub struct Lsw;
+
+impl KpAbe for Lsw {
+    type PublicKey = KpAbePublicKey;
+    type MasterKey = KpAbeMasterKey;
+    type SecretKey = KpAbeSecretKey;
+    type Ciphertext = KpAbeCiphertext;
+    type Header = KpAbeHeader;
+
+    fn setup_with_rng<R: RngCore + CryptoRng>(_attributes: &[&str], rng: &mut R) -> Result<(KpAbePublicKey, KpAbeMasterKey), RabeError> {
+        Ok(setup_with_rng(rng))
+    }
+
+    fn keygen_with_rng<P: PolicySource, R: RngCore + CryptoRng>(
+        pk: &KpAbePublicKey,
+        msk: &KpAbeMasterKey,
+        policy: P,
+        rng: &mut R
+    ) -> Result<KpAbeSecretKey, RabeError> {
+        keygen_with_rng(pk, msk, policy, rng)
+    }
+
+    fn encrypt_with<R: RngCore + CryptoRng>(
+        pk: &KpAbePublicKey,
+        attributes: &[&str],
+        plaintext: &[u8],
+        options: EncryptOptions<R>
+    ) -> Result<KpAbeCiphertext, RabeError> {
+        encrypt_with(pk, attributes, plaintext, options)
+    }
+
+    fn decrypt_with_aad(
+        sk: &KpAbeSecretKey,
+        ct: &KpAbeCiphertext,
+        aad: &[u8]
+    ) -> Result<Vec<u8>, RabeError> {
+        decrypt_with_aad(sk, ct, aad)
+    }
+
+    fn encapsulate_with<R: RngCore + CryptoRng>(
+        pk: &KpAbePublicKey,
+        attributes: &[&str],
+        options: EncryptOptions<R>
+    ) -> Result<(SharedKey, KpAbeHeader), RabeError> {
+        encapsulate_with(pk, attributes, options)
+    }
+
+    fn decapsulate(
+        sk: &KpAbeSecretKey,
+        header: &KpAbeHeader
+    ) -> Result<SharedKey, RabeError> {
+        decapsulate(sk, header)
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use schemes::harness;
 
     #[test]
     fn and() {
@@ -308,7 +519,7 @@ mod tests {
         // kp-abe ciphertext
         let ct_kp_matching: KpAbeCiphertext = encrypt(&pk, &att_matching, &plaintext).unwrap();
         // a kp-abe SK key
-        let sk: KpAbeSecretKey = keygen(&pk, &msk, &policy, PolicyLanguage::JsonPolicy).unwrap();
+        let sk: KpAbeSecretKey = keygen(&pk, &msk, (&policy, PolicyLanguage::JsonPolicy)).unwrap();
         // and now decrypt again with matching sk
         assert_eq!(decrypt(&sk, &ct_kp_matching).unwrap(), plaintext);
     }
@@ -327,7 +538,7 @@ mod tests {
         // kp-abe ciphertext
         let ct_kp_matching: KpAbeCiphertext = encrypt(&pk, &att_matching, &plaintext).unwrap();
         // a kp-abe SK key
-        let sk: KpAbeSecretKey = keygen(&pk, &msk, &policy, PolicyLanguage::JsonPolicy).unwrap();
+        let sk: KpAbeSecretKey = keygen(&pk, &msk, (&policy, PolicyLanguage::JsonPolicy)).unwrap();
         // and now decrypt again with matching sk
         assert_eq!(decrypt(&sk, &ct_kp_matching).unwrap(), plaintext);
     }
@@ -347,7 +558,7 @@ mod tests {
         // kp-abe ciphertext
         let ct: KpAbeCiphertext = encrypt(&pk, &att_matching, &plaintext).unwrap();
         // a kp-abe SK key
-        let sk: KpAbeSecretKey = keygen(&pk, &msk, &policy, PolicyLanguage::JsonPolicy).unwrap();
+        let sk: KpAbeSecretKey = keygen(&pk, &msk, (&policy, PolicyLanguage::JsonPolicy)).unwrap();
         // and now decrypt again with matching sk
         assert_eq!(decrypt(&sk, &ct).unwrap(), plaintext);
     }
@@ -366,9 +577,38 @@ mod tests {
         // kp-abe ciphertext
         let ct_kp_matching: KpAbeCiphertext = encrypt(&pk, &att_matching, &plaintext).unwrap();
         // a kp-abe SK key
-        let sk: KpAbeSecretKey = keygen(&pk, &msk, &policy, PolicyLanguage::JsonPolicy).unwrap();
+        let sk: KpAbeSecretKey = keygen(&pk, &msk, (&policy, PolicyLanguage::JsonPolicy)).unwrap();
         // and now decrypt again with matching sk
         let res = decrypt(&sk, &ct_kp_matching);
         assert_eq!(res.is_ok(), false);
     }
+
+    #[test]
+    fn negation() {
+        // setup scheme
+        let (pk, msk) = setup();
+        // our plaintext
+        let plaintext =
+            String::from("dance like no one's watching, encrypt like everyone is!").into_bytes();
+        // policies with negated attributes and gates, and the legacy "!" attributes
+        let policies = [
+            (r#""A" and not "C""#, PolicyLanguage::HumanPolicy),
+            (r#""A" and "!C""#, PolicyLanguage::HumanPolicy),
+            (r#"not ("C" or "D") and !"X""#, PolicyLanguage::HumanPolicy),
+            (r#"not 2 of ("B", "C", "D")"#, PolicyLanguage::HumanPolicy),
+            (r#"{"name": "and", "children": [{"name": "B"}, {"name": "not", "children": [{"name": "C"}]}]}"#, PolicyLanguage::JsonPolicy),
+        ];
+        for (policy, language) in policies {
+            let sk: KpAbeSecretKey = keygen(&pk, &msk, (policy, language)).unwrap();
+            let ct_matching: KpAbeCiphertext = encrypt(&pk, &["A", "B"], &plaintext).unwrap();
+            assert_eq!(decrypt(&sk, &ct_matching).unwrap(), plaintext, "{}", policy);
+            let ct_revoked: KpAbeCiphertext = encrypt(&pk, &["A", "B", "C", "D"], &plaintext).unwrap();
+            assert!(decrypt(&sk, &ct_revoked).is_err(), "{}", policy);
+        }
+    }
+
+    #[test]
+    fn kp_abe() {
+        harness::kp_abe::<Lsw>(true).unwrap();
+    }
 }
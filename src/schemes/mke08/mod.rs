@@ -27,21 +27,26 @@
 //! let plaintext = String::from("our plaintext!").into_bytes();
 //! let policy = String::from(r#""aa1::A" and "aa2::B""#);
 //! let attr_vec: Vec<&Mke08PublicAttributeKey> = vec!(&_att1_pk, &_att2_pk);
-//! let _ct: Mke08Ciphertext = encrypt(&_pk, &attr_vec.as_slice(), &policy, PolicyLanguage::HumanPolicy, &plaintext).unwrap();
+//! let _ct: Mke08Ciphertext = encrypt(&_pk, &attr_vec.as_slice(), (&policy, PolicyLanguage::HumanPolicy), &plaintext).unwrap();
 //! assert_eq!(decrypt(&sk, &_ct).unwrap(), plaintext);
 //! ```
 use rabe_bn::{Group, Fr, G1, G2, Gt, pairing};
-use rand::Rng;
+use rand::{CryptoRng, Rng, RngCore};
 use std::string::String;
 use utils::{
     aes::*,
-    hash::sha3_hash_fr,
+    hash::{hash_to_fr, HashInput},
     policy::dnf::DnfPolicy,
-    tools::*
+    tools::*,
+    container::{Container, SchemeId, ObjectType},
+    pairing::multi_pairing,
 };
-use utils::policy::pest::{PolicyLanguage, parse, PolicyType};
-use utils::policy::dnf::policy_in_dnf;
+use utils::policy::pest::{PolicyLanguage, parse, check_monotone, PolicyType};
+use utils::policy::ast::PolicySource;
+use utils::policy::explain::not_satisfied;
 use crate::error::RabeError;
+use schemes::traits::MultiAuthorityAbe;
+use schemes::options::EncryptOptions;
 #[cfg(feature = "serde")]
 use serde::{Serialize, Deserialize};
 #[cfg(feature = "borsh")]
@@ -60,6 +65,13 @@ pub struct Mke08PublicKey {
     pub e_gg_y2: Gt,
 }
 
+validate_fields!(Mke08PublicKey { g1, g2, p1, p2, e_gg_y1, e_gg_y2 });
+
+impl Container for Mke08PublicKey {
+    const SCHEME: SchemeId = SchemeId::Mke08;
+    const OBJECT: ObjectType = ObjectType::PublicKey;
+}
+
 /// A MKE08 Master Key (MK)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -69,6 +81,13 @@ pub struct Mke08MasterKey {
     pub g2: G2,
 }
 
+validate_fields!(Mke08MasterKey { g1, g2 });
+
+impl Container for Mke08MasterKey {
+    const SCHEME: SchemeId = SchemeId::Mke08;
+    const OBJECT: ObjectType = ObjectType::MasterKey;
+}
+
 /// A MKE08 User Key (SK), consisting of a Secret User Key (SKu), a Public User Key (PKu) and a Vector of Secret Attribute Keys (SKau)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -79,6 +98,13 @@ pub struct Mke08UserKey {
     pub sk_a: Vec<Mke08SecretAttributeKey>,
 }
 
+validate_fields!(Mke08UserKey { sk, pk, sk_a });
+
+impl Container for Mke08UserKey {
+    const SCHEME: SchemeId = SchemeId::Mke08;
+    const OBJECT: ObjectType = ObjectType::SecretKey;
+}
+
 /// A MKE08 Public User Key (PKu)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -89,6 +115,8 @@ pub struct Mke08PublicUserKey {
     pub g2: G2,
 }
 
+validate_fields!(Mke08PublicUserKey { name, g1, g2 });
+
 /// A MKE08 Secret User Key (SKu)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -98,6 +126,8 @@ pub struct Mke08SecretUserKey {
     pub g2: G2,
 }
 
+validate_fields!(Mke08SecretUserKey { g1, g2 });
+
 /// A MKE08 Secret Authrotiy Key (SKauth)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -107,6 +137,13 @@ pub struct Mke08SecretAuthorityKey {
     pub r: Fr,
 }
 
+validate_fields!(Mke08SecretAuthorityKey { name, r });
+
+impl Container for Mke08SecretAuthorityKey {
+    const SCHEME: SchemeId = SchemeId::Mke08;
+    const OBJECT: ObjectType = ObjectType::AuthorityKey;
+}
+
 /// A MKE08 Public Attribute Key (PKa)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -119,6 +156,13 @@ pub struct Mke08PublicAttributeKey {
     pub gt2: Gt,
 }
 
+validate_fields!(Mke08PublicAttributeKey { attr, g1, g2, gt1, gt2 });
+
+impl Container for Mke08PublicAttributeKey {
+    const SCHEME: SchemeId = SchemeId::Mke08;
+    const OBJECT: ObjectType = ObjectType::AttributeKey;
+}
+
 /// A MKE08 Secret Attribute Key (SKa)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -129,16 +173,55 @@ pub struct Mke08SecretAttributeKey {
     pub g2: G2,
 }
 
-/// A MKE08 Ciphertext (CT) consisting of the AES encrypted data as well as a Vector of all Conjunctions of the access policy
+validate_fields!(Mke08SecretAttributeKey { attr, g1, g2 });
+
+impl Container for Mke08SecretAttributeKey {
+    const SCHEME: SchemeId = SchemeId::Mke08;
+    const OBJECT: ObjectType = ObjectType::AttributeSecretKey;
+}
+
+/// A MKE08 Header consisting of the access policy and a Vector of all its Conjunctions, i.e. the encapsulated key
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub struct Mke08Ciphertext {
+pub struct Mke08Header {
     pub policy: (String, PolicyLanguage),
     pub e: Vec<Mke08CTConjunction>,
+    pub cipher: SymmetricCipher,
+}
+
+validate_fields!(Mke08Header { policy, e, cipher });
+
+impl Container for Mke08Header {
+    const SCHEME: SchemeId = SchemeId::Mke08;
+    const OBJECT: ObjectType = ObjectType::Header;
+}
+
+impl Mke08Header {
+    /// Returns the associated data that binds this header to the symmetrically encrypted data:
+    /// the scheme id, the format version, the policy and the caller supplied `aad`.
+    pub fn associated_data(&self, aad: &[u8]) -> Vec<u8> {
+        let language = [self.policy.1 as u8];
+        associated_data("MKE08", &[self.policy.0.as_bytes(), &language], aad)
+    }
+}
+
+/// A MKE08 Ciphertext (CT) consisting of the AES encrypted data as well as a Mke08Header
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Mke08Ciphertext {
+    pub header: Mke08Header,
     pub ct: Vec<u8>,
 }
 
+validate_fields!(Mke08Ciphertext { header, ct });
+
+impl Container for Mke08Ciphertext {
+    const SCHEME: SchemeId = SchemeId::Mke08;
+    const OBJECT: ObjectType = ObjectType::Ciphertext;
+}
+
 /// A MKE08 Ciphertext Conjunction (CTcon)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -153,10 +236,15 @@ pub struct Mke08CTConjunction {
     pub j6: G2,
 }
 
+validate_fields!(Mke08CTConjunction { str, j1, j2, j3, j4, j5, j6 });
+
 /// The setup algorithm of MKE08. Generates a Mke08PublicKey and a Mke08PublicKey.
 pub fn setup() -> (Mke08PublicKey, Mke08MasterKey) {
-    // random number generator
-    let mut rng = rand::thread_rng();
+    setup_with_rng(&mut rand::thread_rng())
+}
+
+/// Like `setup()`, but draws all randomness from the given random number generator `rng`.
+pub fn setup_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> (Mke08PublicKey, Mke08MasterKey) {
     let g1:G1 = rng.gen();
     let g2:G2 = rng.gen();
     let p1:G1 = rng.gen();
@@ -187,8 +275,16 @@ pub fn keygen(
     msk: &Mke08MasterKey,
     name: &str
 ) -> Mke08UserKey {
-    // random number generator
-    let mut rng = rand::thread_rng();
+    keygen_with_rng(pk, msk, name, &mut rand::thread_rng())
+}
+
+/// Like `keygen()`, but draws all randomness from the given random number generator `rng`.
+pub fn keygen_with_rng<R: RngCore + CryptoRng>(
+    pk: &Mke08PublicKey,
+    msk: &Mke08MasterKey,
+    name: &str,
+    rng: &mut R
+) -> Mke08UserKey {
     let mk_u:Fr = rng.gen();
     // return pk_u and sk_u
     return Mke08UserKey {
@@ -214,11 +310,19 @@ pub fn keygen(
 ///
 pub fn authgen(
     name: &str
+) -> Mke08SecretAuthorityKey {
+    authgen_with_rng(name, &mut rand::thread_rng())
+}
+
+/// Like `authgen()`, but draws all randomness from the given random number generator `rng`.
+pub fn authgen_with_rng<R: RngCore + CryptoRng>(
+    name: &str,
+    rng: &mut R
 ) -> Mke08SecretAuthorityKey {
     // return secret authority key
     return Mke08SecretAuthorityKey {
         name: name.to_string(),
-        r: rand::thread_rng().gen(),
+        r: rng.gen(),
     };
 }
 
@@ -237,9 +341,9 @@ pub fn request_authority_pk(
 ) -> Result<Mke08PublicAttributeKey, RabeError> {
     // if attribute a is from authority sk_a
     return if from_authority(attribute, &sk_a.name) {
-        match sha3_hash_fr(attribute) {
+        match hash_to_fr(SchemeId::Mke08, &HashInput::new("attribute").string(attribute)) {
             Ok(hash_1) => {
-                match sha3_hash_fr(&sk_a.name) {
+                match hash_to_fr(SchemeId::Mke08, &HashInput::new("authority").string(&sk_a.name)) {
                     Ok(hash_2) => {
                         let exp = hash_1 * hash_2 * sk_a.r;
                         // return PK and mke
@@ -257,7 +361,7 @@ pub fn request_authority_pk(
             Err(e) => Err(e)
         }
     } else {
-        Err(RabeError::new(&format!("attribute {} is not from_authority() or !is_eligible()", attribute.to_string())))
+        Err(RabeError::UnknownAttribute(attribute.to_string()))
     }
 }
 
@@ -276,9 +380,9 @@ pub fn request_authority_sk(
 ) -> Result<Mke08SecretAttributeKey, RabeError> {
     // if attribute a is from authority sk_a
     return if from_authority(attr, &sk_a.name) && is_eligible(attr, &pk_u.name) {
-        match sha3_hash_fr(attr) {
+        match hash_to_fr(SchemeId::Mke08, &HashInput::new("attribute").string(attr)) {
             Ok(hash_1) => {
-                match sha3_hash_fr(&sk_a.name) {
+                match hash_to_fr(SchemeId::Mke08, &HashInput::new("authority").string(&sk_a.name)) {
                     Ok(hash_2) => {
                         let exp = hash_1 * hash_2 * sk_a.r;
                         // return PK and mke
@@ -294,7 +398,7 @@ pub fn request_authority_sk(
             Err(e) => Err(e)
         }
     } else {
-        Err(RabeError::new(&format!("attribute {} is not from_authority() or !is_eligible()", attr.to_string())))
+        Err(RabeError::UnknownAttribute(attr.to_string()))
     }
 }
 
@@ -305,51 +409,85 @@ pub fn request_authority_sk(
 ///
 ///	* `pk` - A Public Key (PK), generated by the function setup()
 ///	* `attr_pks` - A Vector of all Mke08PublicAttributeKey that are involded in the policy
-///	* `policy` - An access policy given as JSON &str
+///	* `policy` - An access policy, given as text and its PolicyLanguage or as Policy, see [PolicySource]
 ///	* `plaintext` - plaintext data given as a Vector of u8
 ///
-pub fn encrypt(
+pub fn encrypt<P: PolicySource>(
     pk: &Mke08PublicKey,
     attr_pks: &[&Mke08PublicAttributeKey],
-    policy: &str,
-    language: PolicyLanguage,
+    policy: P,
+    plaintext: &[u8]
+) -> Result<Mke08Ciphertext, RabeError> {
+    encrypt_with(pk, attr_pks, policy, plaintext, EncryptOptions::new())
+}
+
+/// Like `encrypt()`, but with the given [EncryptOptions], i.e. the random number generator, the associated data
+/// that has to be passed to `decrypt_with_aad()` and the symmetric cipher.
+pub fn encrypt_with<P: PolicySource, R: RngCore + CryptoRng>(
+    pk: &Mke08PublicKey,
+    attr_pks: &[&Mke08PublicAttributeKey],
+    policy: P,
     plaintext: &[u8],
+    mut options: EncryptOptions<R>
 ) -> Result<Mke08Ciphertext, RabeError> {
-    match parse(policy, language) {
-        Ok(pol) => {
-            // if policy is in DNF
-            return if policy_in_dnf(&pol, false, None) {
-                // random number generator
-                let mut rng = rand::thread_rng();
-                // an DNF policy from the given String
-                let policy_dnf = DnfPolicy::from_string(&policy, attr_pks, language).unwrap();
-                // random Gt msgs
-                let msg1 = pairing(rng.gen(), rng.gen());
-                let msg2 = msg1.pow(rng.gen());
-                let msg = msg1 * msg2;
-                // CT result vectors
-                let mut e: Vec<Mke08CTConjunction> = Vec::new();
-                // now add randomness using _r_j
-                for term in policy_dnf.terms.into_iter() {
-                    let r_j: Fr = rng.gen();
-                    e.push(Mke08CTConjunction {
-                        str: term.0,
-                        j1: term.1.pow(r_j) * msg1,
-                        j2: term.2.pow(r_j) * msg2,
-                        j3: pk.p1 * r_j,
-                        j4: pk.p2 * r_j,
-                        j5: term.3 * r_j,
-                        j6: term.4 * r_j,
-                    });
-                }
-                //Encrypt plaintext using derived key from secret
-                match encrypt_symmetric(msg, &plaintext.to_vec()) {
-                    Ok(ct) => Ok(Mke08Ciphertext { policy: (policy.to_string(), language), e, ct }),
-                    Err(e) => Err(e)
-                }
-            } else {
-                Err(RabeError::new("Error in mke08/encrypt: policy is not in dnf"))
+    let (key, header) = encapsulate_with(pk, attr_pks, policy, options.by_ref())?;
+    //Encrypt plaintext using the encapsulated key, binding the header as associated data
+    let ct = header.cipher.encrypt(&key, plaintext, &header.associated_data(options.aad), &mut options.rng)?;
+    Ok(Mke08Ciphertext { header, ct })
+}
+
+/// The key encapsulation algorithm of MKE08. Generates a new SharedKey and a Mke08Header using an Mke08PublicKey,
+/// a Vector of Mke08PublicAttributeKeys and an access policy given as String.
+///
+/// # Arguments
+///
+///	* `pk` - A Public Key (PK), generated by the function setup()
+///	* `attr_pks` - A Vector of all Mke08PublicAttributeKey that are involded in the policy
+///	* `policy` - An access policy, given as text and its PolicyLanguage or as Policy, see [PolicySource]
+///
+pub fn encapsulate<P: PolicySource>(
+    pk: &Mke08PublicKey,
+    attr_pks: &[&Mke08PublicAttributeKey],
+    policy: P
+) -> Result<(SharedKey, Mke08Header), RabeError> {
+    encapsulate_with(pk, attr_pks, policy, EncryptOptions::new())
+}
+
+/// Like `encapsulate()`, but with the given [EncryptOptions], i.e. the random number generator and the symmetric
+/// cipher that is recorded in the header.
+pub fn encapsulate_with<P: PolicySource, R: RngCore + CryptoRng>(
+    pk: &Mke08PublicKey,
+    attr_pks: &[&Mke08PublicAttributeKey],
+    policy: P,
+    mut options: EncryptOptions<R>
+) -> Result<(SharedKey, Mke08Header), RabeError> {
+    let rng = &mut options.rng;
+    match policy.value() {
+        Ok((pol, policy, language)) => {
+            check_monotone(&pol, "mke08/encapsulate")?;
+            // the policy converted to DNF
+            let policy_dnf = DnfPolicy::from_policy(&pol, attr_pks)?;
+            // random Gt msgs
+            let msg1 = pairing(rng.gen(), rng.gen());
+            let msg2 = msg1.pow(rng.gen());
+            let msg = msg1 * msg2;
+            // CT result vectors
+            let mut e: Vec<Mke08CTConjunction> = Vec::new();
+            // now add randomness using _r_j
+            for term in policy_dnf.terms.into_iter() {
+                let r_j: Fr = rng.gen();
+                e.push(Mke08CTConjunction {
+                    str: term.0,
+                    j1: term.1.pow(r_j) * msg1,
+                    j2: term.2.pow(r_j) * msg2,
+                    j3: pk.p1 * r_j,
+                    j4: pk.p2 * r_j,
+                    j5: term.3 * r_j,
+                    j6: term.4 * r_j,
+                });
             }
+            let header = Mke08Header { policy: (policy.to_string(), language), e, cipher: options.cipher };
+            Ok((SharedKey::derive_for(msg, &header)?, header))
         },
         Err(e) => Err(e)
     }
@@ -366,6 +504,31 @@ pub fn decrypt(
     sk: &Mke08UserKey,
     ct: &Mke08Ciphertext
 ) -> Result<Vec<u8>, RabeError> {
+    decrypt_with_aad(sk, ct, &[])
+}
+
+/// Like `decrypt()`, but for ciphertexts that were generated by `encrypt_with()` with the associated data `aad`.
+pub fn decrypt_with_aad(
+    sk: &Mke08UserKey,
+    ct: &Mke08Ciphertext,
+    aad: &[u8]
+) -> Result<Vec<u8>, RabeError> {
+    let key = decapsulate(sk, &ct.header)?;
+    // Decrypt plaintext using the key recovered from the mke08 scheme
+    ct.header.cipher.decrypt(&key, &ct.ct, &ct.header.associated_data(aad))
+}
+
+/// The key decapsulation algorithm of MKE08. Recovers the SharedKey of a Mke08Header with a matching Mke08UserKey.
+///
+/// # Arguments
+///
+///	* `sk` - A Mke08UserKey (SK), generated by the function keygen()
+///	* `header` - A Mke08Header
+///
+pub fn decapsulate(
+    sk: &Mke08UserKey,
+    header: &Mke08Header
+) -> Result<SharedKey, RabeError> {
     let attr_str = sk.sk_a
         .iter()
         .map(|triple| {
@@ -373,24 +536,25 @@ pub fn decrypt(
             _a.attr.to_string()
         })
         .collect::<Vec<_>>();
-    match parse(ct.policy.0.as_ref(), ct.policy.1) {
+    match parse(header.policy.0.as_ref(), header.policy.1) {
         Ok(pol) => {
             return if traverse_policy(&attr_str, &pol, PolicyType::Leaf) == false {
-                Err(RabeError::new("Error in mke08/decrypt: attributes in sk do not match policy in ct."))
+                Err(not_satisfied("mke08/decrypt: attributes in sk do not match policy in ct", &attr_str, &pol))
             } else {
                 let mut msg = Gt::one();
-                for (_i, _e_j) in ct.e.iter().enumerate() {
+                for (_i, _e_j) in header.e.iter().enumerate() {
                     if is_satisfiable(&_e_j.str, &sk.sk_a) {
                         let _sk_sum = calc_satisfiable(&_e_j.str, &sk.sk_a);
-                        msg = _e_j.j1 * _e_j.j2 * pairing(_e_j.j3, _sk_sum.1) *
-                            pairing(_sk_sum.0, _e_j.j4) *
-                            (pairing(_e_j.j5, sk.sk.g2) * pairing(sk.sk.g1, _e_j.j6))
-                                .inverse();
+                        msg = _e_j.j1 * _e_j.j2 * multi_pairing(&[
+                            (_e_j.j3, _sk_sum.1),
+                            (_sk_sum.0, _e_j.j4),
+                            (-_e_j.j5, sk.sk.g2),
+                            (-sk.sk.g1, _e_j.j6),
+                        ]);
                         break;
                     }
                 }
-                // Decrypt plaintext using derived secret from mke08 scheme
-                decrypt_symmetric(msg, &ct.ct)
+                SharedKey::derive_for(msg, header)
             }
         },
         Err(e) => Err(e)
@@ -472,7 +636,7 @@ fn from_authority(
 ) -> bool {
     let v: Vec<_> = attr.match_indices("::").collect();
     if v.len() == 1 {
-        return attr.get(0..v[0].0).unwrap().to_string() == authority.to_string();
+        return attr.get(0..v[0].0) == Some(authority);
     }
     return false;
 }
@@ -494,9 +658,109 @@ fn is_eligible(
     return true;
 }
 
+/// The MKE08 multi-authority scheme, to be used through the [`MultiAuthorityAbe`](../traits/trait.MultiAuthorityAbe.html) trait.
+///
+/// Attribute keys are derived on request, so the attributes given to authgen() are ignored.
+pub struct Mke08;
+
+impl MultiAuthorityAbe for Mke08 {
+    type GlobalKey = Mke08PublicKey;
+    type MasterKey = Mke08MasterKey;
+    type AuthorityKey = Mke08SecretAuthorityKey;
+    type AttributePublicKey = Mke08PublicAttributeKey;
+    type SecretKey = Mke08UserKey;
+    type Ciphertext = Mke08Ciphertext;
+    type Header = Mke08Header;
+
+    fn setup_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> Result<(Mke08PublicKey, Mke08MasterKey), RabeError> {
+        Ok(setup_with_rng(rng))
+    }
+
+    fn authgen_with_rng<R: RngCore + CryptoRng>(
+        _pk: &Mke08PublicKey,
+        _msk: &Mke08MasterKey,
+        name: &str,
+        _attributes: &[&str],
+        rng: &mut R
+    ) -> Result<Mke08SecretAuthorityKey, RabeError> {
+        Ok(authgen_with_rng(name, rng))
+    }
+
+    fn attribute_public_key(
+        pk: &Mke08PublicKey,
+        authority: &Mke08SecretAuthorityKey,
+        attribute: &str
+    ) -> Result<Mke08PublicAttributeKey, RabeError> {
+        request_authority_pk(pk, attribute, authority)
+    }
+
+    fn keygen_with_rng<R: RngCore + CryptoRng>(
+        pk: &Mke08PublicKey,
+        msk: &Mke08MasterKey,
+        authority: &Mke08SecretAuthorityKey,
+        name: &str,
+        attributes: &[&str],
+        rng: &mut R
+    ) -> Result<Mke08UserKey, RabeError> {
+        let mut sk = keygen_with_rng(pk, msk, name, rng);
+        for attribute in attributes {
+            <Mke08 as MultiAuthorityAbe>::add_attribute(pk, authority, attribute, &mut sk)?;
+        }
+        Ok(sk)
+    }
+
+    fn add_attribute(
+        _pk: &Mke08PublicKey,
+        authority: &Mke08SecretAuthorityKey,
+        attribute: &str,
+        sk: &mut Mke08UserKey
+    ) -> Result<(), RabeError> {
+        let sk_a = request_authority_sk(&sk.pk, attribute, authority)?;
+        sk.sk_a.push(sk_a);
+        Ok(())
+    }
+
+    fn encrypt_with<P: PolicySource, R: RngCore + CryptoRng>(
+        pk: &Mke08PublicKey,
+        attr_pks: &[&Mke08PublicAttributeKey],
+        policy: P,
+        plaintext: &[u8],
+        options: EncryptOptions<R>
+    ) -> Result<Mke08Ciphertext, RabeError> {
+        encrypt_with(pk, attr_pks, policy, plaintext, options)
+    }
+
+    fn decrypt_with_aad(
+        _pk: &Mke08PublicKey,
+        sk: &Mke08UserKey,
+        ct: &Mke08Ciphertext,
+        aad: &[u8]
+    ) -> Result<Vec<u8>, RabeError> {
+        decrypt_with_aad(sk, ct, aad)
+    }
+
+    fn encapsulate_with<P: PolicySource, R: RngCore + CryptoRng>(
+        pk: &Mke08PublicKey,
+        attr_pks: &[&Mke08PublicAttributeKey],
+        policy: P,
+        options: EncryptOptions<R>
+    ) -> Result<(SharedKey, Mke08Header), RabeError> {
+        encapsulate_with(pk, attr_pks, policy, options)
+    }
+
+    fn decapsulate(
+        _pk: &Mke08PublicKey,
+        sk: &Mke08UserKey,
+        header: &Mke08Header
+    ) -> Result<SharedKey, RabeError> {
+        decapsulate(sk, header)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use schemes::harness;
 
     #[test]
     fn and() {
@@ -526,7 +790,7 @@ mod tests {
         let policy = String::from(r#"{"name": "and", "children": [{"name": "auth1::A"}, {"name": "auth2::B"}]}"#);
         let att_pk: Vec<&Mke08PublicAttributeKey> = vec![&_att1_pk, &_att2_pk];
         // cp-abe ciphertext
-        let _ct: Mke08Ciphertext = encrypt(&pk, &att_pk.as_slice(), &policy, PolicyLanguage::JsonPolicy, &plaintext)
+        let _ct: Mke08Ciphertext = encrypt(&pk, &att_pk.as_slice(), (&policy, PolicyLanguage::JsonPolicy), &plaintext)
             .unwrap();
         // and now decrypt again with mathcing sk
         let _match = decrypt(&sk, &_ct);
@@ -559,10 +823,10 @@ mod tests {
         let plaintext = String::from("dance like no one's watching, encrypt like everyone is!")
             .into_bytes();
         // our policy
-        let policy = String::from(r#"{"name": "or", "children": [{"name": "auth1::A"}, {"name": "auth2::B"}]}"#);
+        let policy = String::from(r#"{"name": "or", "children": [{"name": "auth1::C"}, {"name": "auth2::B"}]}"#);
         let att_pks: Vec<&Mke08PublicAttributeKey> = vec![&_att1_pk, &_att2_pk];
         // cp-abe ciphertext
-        let ct: Mke08Ciphertext = encrypt(&pk, att_pks.as_slice(), &policy, PolicyLanguage::JsonPolicy, &plaintext)
+        let ct: Mke08Ciphertext = encrypt(&pk, att_pks.as_slice(), (&policy, PolicyLanguage::JsonPolicy), &plaintext)
             .unwrap();
         // and now decrypt again with mathcing sk
         let ct_decrypted = decrypt(&sk, &ct);
@@ -607,8 +871,7 @@ mod tests {
         let ct: Mke08Ciphertext = encrypt(
             &pk,
             &attr_pks.as_slice(),
-            &policy,
-            PolicyLanguage::JsonPolicy,
+            (&policy, PolicyLanguage::JsonPolicy),
             &plaintext,
         ).unwrap();
         // and now decrypt again with mathcing sk
@@ -616,4 +879,9 @@ mod tests {
         assert_eq!(ct_decrypted.is_ok(), true);
         assert_eq!(ct_decrypted.unwrap(), plaintext);
     }
+
+    #[test]
+    fn ma_abe() {
+        harness::ma_abe::<Mke08>().unwrap();
+    }
 }
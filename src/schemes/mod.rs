@@ -7,4 +7,13 @@ pub mod bsw;
 pub mod lsw;
 pub mod mke08;
 pub mod yct14;
-pub mod ghw11;
\ No newline at end of file
+pub mod ghw11;
+pub mod traits;
+pub mod options;
+#[cfg(test)]
+mod fuzz;
+#[cfg(test)]
+mod harness;
+
+pub use self::traits::{CpAbe, DelegatableCpAbe, KpAbe, MultiAuthorityAbe};
+pub use self::options::EncryptOptions;
@@ -0,0 +1,92 @@
+//! Options of the encrypt and encapsulate algorithms of all schemes.
+//!
+//! Every scheme exposes a plain `encrypt` (and `encapsulate`) function that uses the defaults, and a single
+//! `encrypt_with` (and `encapsulate_with`) function that takes an [`EncryptOptions`], which is built by chaining
+//! the setters of the options that differ from the defaults.
+//!
+//! # Examples
+//!
+//! ```
+//! use rabe::schemes::{EncryptOptions, bsw::*};
+//! use rabe::utils::aes::SymmetricCipher;
+//! use rabe::utils::policy::pest::PolicyLanguage;
+//! let (pk, msk) = setup();
+//! let plaintext = String::from("our plaintext!").into_bytes();
+//! let policy = String::from(r#""A" and "B""#);
+//! let options = EncryptOptions::new().aad(b"context").cipher(SymmetricCipher::ChaCha20Poly1305);
+//! let ct: CpAbeCiphertext = encrypt_with(&pk, (&policy, PolicyLanguage::HumanPolicy), &plaintext, options).unwrap();
+//! let sk: CpAbeSecretKey = keygen(&pk, &msk, &vec!["A", "B"]).unwrap();
+//! assert_eq!(decrypt_with_aad(&sk, &ct, b"context").unwrap(), plaintext);
+//! ```
+use rand::{rngs::ThreadRng, CryptoRng, RngCore};
+use utils::aes::SymmetricCipher;
+
+/// The options of `encrypt_with()` and `encapsulate_with()`.
+///
+/// The defaults are the thread local random number generator, no associated data, AES-256-GCM and no bound on the
+/// reuse of attributes in a policy.
+#[derive(Debug)]
+pub struct EncryptOptions<'a, R = ThreadRng> {
+    pub(crate) rng: R,
+    pub(crate) aad: &'a [u8],
+    pub(crate) cipher: SymmetricCipher,
+    pub(crate) max_reuse: usize,
+}
+
+impl<'a> EncryptOptions<'a> {
+    /// Returns the default options
+    pub fn new() -> EncryptOptions<'a> {
+        EncryptOptions {
+            rng: rand::thread_rng(),
+            aad: &[],
+            cipher: SymmetricCipher::default(),
+            max_reuse: usize::MAX,
+        }
+    }
+}
+
+impl<'a> Default for EncryptOptions<'a> {
+    fn default() -> EncryptOptions<'a> {
+        EncryptOptions::new()
+    }
+}
+
+impl<'a, R: RngCore + CryptoRng> EncryptOptions<'a, R> {
+    /// Draws all randomness from the given random number generator `rng`, e.g. `&mut rng` to keep using it afterwards.
+    pub fn rng<S: RngCore + CryptoRng>(self, rng: S) -> EncryptOptions<'a, S> {
+        EncryptOptions {
+            rng,
+            aad: self.aad,
+            cipher: self.cipher,
+            max_reuse: self.max_reuse,
+        }
+    }
+
+    /// Additionally authenticates the caller supplied associated data `aad`. The same `aad` has to be passed to
+    /// `decrypt_with_aad()`. Encapsulation ignores it, since the header does not contain any payload.
+    pub fn aad(self, aad: &'a [u8]) -> EncryptOptions<'a, R> {
+        EncryptOptions { aad, ..self }
+    }
+
+    /// Encrypts the data with the symmetric `cipher` instead of AES-256-GCM. The cipher is recorded in the header,
+    /// from where `decrypt()` picks it up.
+    pub fn cipher(self, cipher: SymmetricCipher) -> EncryptOptions<'a, R> {
+        EncryptOptions { cipher, ..self }
+    }
+
+    /// Rejects policies in which an attribute is used more than `max_reuse` times. Only the schemes whose security
+    /// proof bounds the reuse of attributes (AC17 CP-ABE and AW11) check it.
+    pub fn max_reuse(self, max_reuse: usize) -> EncryptOptions<'a, R> {
+        EncryptOptions { max_reuse, ..self }
+    }
+
+    // the same options, but with a borrowed random number generator, so that the caller can use it afterwards
+    pub(crate) fn by_ref(&mut self) -> EncryptOptions<'a, &mut R> {
+        EncryptOptions {
+            rng: &mut self.rng,
+            aad: self.aad,
+            cipher: self.cipher,
+            max_reuse: self.max_reuse,
+        }
+    }
+}
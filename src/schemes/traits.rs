@@ -0,0 +1,425 @@
+//! Common interfaces implemented by the rabe schemes.
+//!
+//! Every scheme module still exposes its own free functions, but additionally provides a unit
+//! struct (e.g. [`ac17::Ac17Cp`](../ac17/struct.Ac17Cp.html) or [`bsw::Bsw`](../bsw/struct.Bsw.html))
+//! that implements one of the traits below. The traits use the same argument order and always
+//! return a `Result`, so that an application can switch schemes through a generic parameter.
+//!
+//! # Examples
+//!
+//! ```
+//! use rabe::schemes::{CpAbe, ac17::Ac17Cp, bsw::Bsw};
+//! use rabe::error::RabeError;
+//! use rabe::utils::policy::pest::PolicyLanguage;
+//!
+//! fn roundtrip<S: CpAbe>(plaintext: &[u8]) -> Result<Vec<u8>, RabeError> {
+//!     let (pk, msk) = S::setup()?;
+//!     let sk = S::keygen(&pk, &msk, &["A", "B"])?;
+//!     let ct = S::encrypt(&pk, (r#""A" and "B""#, PolicyLanguage::HumanPolicy), plaintext)?;
+//!     S::decrypt(&sk, &ct)
+//! }
+//! let plaintext = String::from("our plaintext!").into_bytes();
+//! assert_eq!(roundtrip::<Ac17Cp>(&plaintext).unwrap(), plaintext);
+//! assert_eq!(roundtrip::<Bsw>(&plaintext).unwrap(), plaintext);
+//! ```
+use rand::{CryptoRng, RngCore};
+use utils::policy::ast::PolicySource;
+use utils::aes::SharedKey;
+use schemes::options::EncryptOptions;
+use crate::error::RabeError;
+
+/// A Ciphertext-Policy ABE scheme: secret keys carry attributes, ciphertexts carry a policy.
+pub trait CpAbe {
+    /// The Public Key (PK)
+    type PublicKey;
+    /// The Master Key (MSK)
+    type MasterKey;
+    /// The Secret Key (SK)
+    type SecretKey;
+    /// The Ciphertext (CT)
+    type Ciphertext;
+    /// The Header of a ciphertext, i.e. the encapsulated key without any payload
+    type Header;
+
+    /// Generates a new key pair (PK, MSK).
+    fn setup() -> Result<(Self::PublicKey, Self::MasterKey), RabeError> {
+        Self::setup_with_rng(&mut rand::thread_rng())
+    }
+
+    /// Like `setup()`, but draws all randomness from the given random number generator `rng`.
+    fn setup_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> Result<(Self::PublicKey, Self::MasterKey), RabeError>;
+
+    /// Generates a secret key for the given set of attributes.
+    ///
+    /// # Arguments
+    ///
+    ///	* `pk` - A Public Key (PK), generated by setup()
+    ///	* `msk` - A Master Key (MSK), generated by setup()
+    ///	* `attributes` - A set of attributes that is assigned to the key
+    fn keygen(
+        pk: &Self::PublicKey,
+        msk: &Self::MasterKey,
+        attributes: &[&str]
+    ) -> Result<Self::SecretKey, RabeError> {
+        Self::keygen_with_rng(pk, msk, attributes, &mut rand::thread_rng())
+    }
+
+    /// Like `keygen()`, but draws all randomness from the given random number generator `rng`.
+    fn keygen_with_rng<R: RngCore + CryptoRng>(
+        pk: &Self::PublicKey,
+        msk: &Self::MasterKey,
+        attributes: &[&str],
+        rng: &mut R
+    ) -> Result<Self::SecretKey, RabeError>;
+
+    /// Encrypts a plaintext under a policy.
+    ///
+    /// # Arguments
+    ///
+    ///	* `pk` - A Public Key (PK), generated by setup()
+    ///	* `policy` - An access policy, given as text and its PolicyLanguage or as Policy, see [PolicySource]
+    ///	* `plaintext` - The plaintext data given as a slice of u8
+    fn encrypt<P: PolicySource>(
+        pk: &Self::PublicKey,
+        policy: P,
+        plaintext: &[u8]
+    ) -> Result<Self::Ciphertext, RabeError> {
+        Self::encrypt_with(pk, policy, plaintext, EncryptOptions::new())
+    }
+
+    /// Like `encrypt()`, but with the given [EncryptOptions], i.e. the random number generator, the associated data
+    /// that has to be passed to `decrypt_with_aad()`, the symmetric cipher and the maximal reuse of an attribute.
+    fn encrypt_with<P: PolicySource, R: RngCore + CryptoRng>(
+        pk: &Self::PublicKey,
+        policy: P,
+        plaintext: &[u8],
+        options: EncryptOptions<R>
+    ) -> Result<Self::Ciphertext, RabeError>;
+
+    /// Decrypts a ciphertext if the attributes of the secret key satisfy its policy.
+    fn decrypt(
+        sk: &Self::SecretKey,
+        ct: &Self::Ciphertext
+    ) -> Result<Vec<u8>, RabeError> {
+        Self::decrypt_with_aad(sk, ct, &[])
+    }
+
+    /// Like `decrypt()`, but for ciphertexts that were generated by `encrypt_with()` with the associated data `aad`.
+    fn decrypt_with_aad(
+        sk: &Self::SecretKey,
+        ct: &Self::Ciphertext,
+        aad: &[u8]
+    ) -> Result<Vec<u8>, RabeError>;
+
+    /// Encapsulates a fresh symmetric key under a policy, without encrypting any payload.
+    fn encapsulate<P: PolicySource>(
+        pk: &Self::PublicKey,
+        policy: P
+    ) -> Result<(SharedKey, Self::Header), RabeError> {
+        Self::encapsulate_with(pk, policy, EncryptOptions::new())
+    }
+
+    /// Like `encapsulate()`, but with the given [EncryptOptions], i.e. the random number generator, the symmetric
+    /// cipher that is recorded in the header and the maximal reuse of an attribute.
+    fn encapsulate_with<P: PolicySource, R: RngCore + CryptoRng>(
+        pk: &Self::PublicKey,
+        policy: P,
+        options: EncryptOptions<R>
+    ) -> Result<(SharedKey, Self::Header), RabeError>;
+
+    /// Recovers the symmetric key from a header if the attributes of the secret key satisfy its policy.
+    fn decapsulate(
+        sk: &Self::SecretKey,
+        header: &Self::Header
+    ) -> Result<SharedKey, RabeError>;
+}
+
+/// A CP-ABE scheme that allows to derive a key for a subset of the attributes of a given key.
+pub trait DelegatableCpAbe: CpAbe {
+    /// Delegates a new secret key for a subset of the attributes of `sk`.
+    fn delegate(
+        pk: &Self::PublicKey,
+        sk: &Self::SecretKey,
+        subset: &[&str]
+    ) -> Result<Self::SecretKey, RabeError> {
+        Self::delegate_with_rng(pk, sk, subset, &mut rand::thread_rng())
+    }
+
+    /// Like `delegate()`, but draws all randomness from the given random number generator `rng`.
+    fn delegate_with_rng<R: RngCore + CryptoRng>(
+        pk: &Self::PublicKey,
+        sk: &Self::SecretKey,
+        subset: &[&str],
+        rng: &mut R
+    ) -> Result<Self::SecretKey, RabeError>;
+}
+
+/// A Key-Policy ABE scheme: secret keys carry a policy, ciphertexts carry attributes.
+pub trait KpAbe {
+    /// The Public Key (PK)
+    type PublicKey;
+    /// The Master Key (MSK)
+    type MasterKey;
+    /// The Secret Key (SK)
+    type SecretKey;
+    /// The Ciphertext (CT)
+    type Ciphertext;
+    /// The Header of a ciphertext, i.e. the encapsulated key without any payload
+    type Header;
+
+    /// Generates a new key pair (PK, MSK).
+    ///
+    /// # Arguments
+    ///
+    ///	* `attributes` - The attribute universe. Only small universe schemes (YCT14) require it, all other schemes ignore it.
+    fn setup(attributes: &[&str]) -> Result<(Self::PublicKey, Self::MasterKey), RabeError> {
+        Self::setup_with_rng(attributes, &mut rand::thread_rng())
+    }
+
+    /// Like `setup()`, but draws all randomness from the given random number generator `rng`.
+    fn setup_with_rng<R: RngCore + CryptoRng>(attributes: &[&str], rng: &mut R) -> Result<(Self::PublicKey, Self::MasterKey), RabeError>;
+
+    /// Generates a secret key for the given policy.
+    ///
+    /// # Arguments
+    ///
+    ///	* `pk` - A Public Key (PK), generated by setup()
+    ///	* `msk` - A Master Key (MSK), generated by setup()
+    ///	* `policy` - An access policy, given as text and its PolicyLanguage or as Policy, see [PolicySource]
+    fn keygen<P: PolicySource>(
+        pk: &Self::PublicKey,
+        msk: &Self::MasterKey,
+        policy: P
+    ) -> Result<Self::SecretKey, RabeError> {
+        Self::keygen_with_rng(pk, msk, policy, &mut rand::thread_rng())
+    }
+
+    /// Like `keygen()`, but draws all randomness from the given random number generator `rng`.
+    fn keygen_with_rng<P: PolicySource, R: RngCore + CryptoRng>(
+        pk: &Self::PublicKey,
+        msk: &Self::MasterKey,
+        policy: P,
+        rng: &mut R
+    ) -> Result<Self::SecretKey, RabeError>;
+
+    /// Encrypts a plaintext under a set of attributes.
+    ///
+    /// # Arguments
+    ///
+    ///	* `pk` - A Public Key (PK), generated by setup()
+    ///	* `attributes` - A set of attributes the ciphertext is labeled with
+    ///	* `plaintext` - The plaintext data given as a slice of u8
+    fn encrypt(
+        pk: &Self::PublicKey,
+        attributes: &[&str],
+        plaintext: &[u8]
+    ) -> Result<Self::Ciphertext, RabeError> {
+        Self::encrypt_with(pk, attributes, plaintext, EncryptOptions::new())
+    }
+
+    /// Like `encrypt()`, but with the given [EncryptOptions], i.e. the random number generator, the associated data
+    /// that has to be passed to `decrypt_with_aad()` and the symmetric cipher.
+    fn encrypt_with<R: RngCore + CryptoRng>(
+        pk: &Self::PublicKey,
+        attributes: &[&str],
+        plaintext: &[u8],
+        options: EncryptOptions<R>
+    ) -> Result<Self::Ciphertext, RabeError>;
+
+    /// Decrypts a ciphertext if its attributes satisfy the policy of the secret key.
+    fn decrypt(
+        sk: &Self::SecretKey,
+        ct: &Self::Ciphertext
+    ) -> Result<Vec<u8>, RabeError> {
+        Self::decrypt_with_aad(sk, ct, &[])
+    }
+
+    /// Like `decrypt()`, but for ciphertexts that were generated by `encrypt_with()` with the associated data `aad`.
+    fn decrypt_with_aad(
+        sk: &Self::SecretKey,
+        ct: &Self::Ciphertext,
+        aad: &[u8]
+    ) -> Result<Vec<u8>, RabeError>;
+
+    /// Encapsulates a fresh symmetric key under a set of attributes, without encrypting any payload.
+    fn encapsulate(
+        pk: &Self::PublicKey,
+        attributes: &[&str]
+    ) -> Result<(SharedKey, Self::Header), RabeError> {
+        Self::encapsulate_with(pk, attributes, EncryptOptions::new())
+    }
+
+    /// Like `encapsulate()`, but with the given [EncryptOptions], i.e. the random number generator and the symmetric
+    /// cipher that is recorded in the header.
+    fn encapsulate_with<R: RngCore + CryptoRng>(
+        pk: &Self::PublicKey,
+        attributes: &[&str],
+        options: EncryptOptions<R>
+    ) -> Result<(SharedKey, Self::Header), RabeError>;
+
+    /// Recovers the symmetric key from a header if its attributes satisfy the policy of the secret key.
+    fn decapsulate(
+        sk: &Self::SecretKey,
+        header: &Self::Header
+    ) -> Result<SharedKey, RabeError>;
+}
+
+/// A multi-authority CP-ABE scheme, where attributes are managed by independent authorities.
+///
+/// Attributes should be given as "authority::attribute", since some schemes (BDABE, MKE08) check that an
+/// attribute belongs to the authority that issues it.
+pub trait MultiAuthorityAbe {
+    /// The Global Parameters shared by all parties (GP or PK)
+    type GlobalKey;
+    /// The Master Key of the central authority (MSK), `()` if the scheme has none
+    type MasterKey;
+    /// The key material of a single attribute authority
+    type AuthorityKey;
+    /// The public key of an attribute, used for encryption
+    type AttributePublicKey;
+    /// The Secret Key (SK) of a user
+    type SecretKey;
+    /// The Ciphertext (CT)
+    type Ciphertext;
+    /// The Header of a ciphertext, i.e. the encapsulated key without any payload
+    type Header;
+
+    /// Generates the global parameters and the master key.
+    fn setup() -> Result<(Self::GlobalKey, Self::MasterKey), RabeError> {
+        Self::setup_with_rng(&mut rand::thread_rng())
+    }
+
+    /// Like `setup()`, but draws all randomness from the given random number generator `rng`.
+    fn setup_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> Result<(Self::GlobalKey, Self::MasterKey), RabeError>;
+
+    /// Generates a new attribute authority.
+    ///
+    /// # Arguments
+    ///
+    ///	* `gk` - The Global Parameters, generated by setup()
+    ///	* `msk` - The Master Key, generated by setup()
+    ///	* `name` - The name of the authority
+    ///	* `attributes` - The attributes handled by this authority. Schemes that derive attribute keys on request ignore it.
+    fn authgen(
+        gk: &Self::GlobalKey,
+        msk: &Self::MasterKey,
+        name: &str,
+        attributes: &[&str]
+    ) -> Result<Self::AuthorityKey, RabeError> {
+        Self::authgen_with_rng(gk, msk, name, attributes, &mut rand::thread_rng())
+    }
+
+    /// Like `authgen()`, but draws all randomness from the given random number generator `rng`.
+    fn authgen_with_rng<R: RngCore + CryptoRng>(
+        gk: &Self::GlobalKey,
+        msk: &Self::MasterKey,
+        name: &str,
+        attributes: &[&str],
+        rng: &mut R
+    ) -> Result<Self::AuthorityKey, RabeError>;
+
+    /// Returns the public key of an attribute handled by the given authority.
+    fn attribute_public_key(
+        gk: &Self::GlobalKey,
+        authority: &Self::AuthorityKey,
+        attribute: &str
+    ) -> Result<Self::AttributePublicKey, RabeError>;
+
+    /// Generates a secret key for a user (identified by `name`), holding the given attributes of an authority.
+    fn keygen(
+        gk: &Self::GlobalKey,
+        msk: &Self::MasterKey,
+        authority: &Self::AuthorityKey,
+        name: &str,
+        attributes: &[&str]
+    ) -> Result<Self::SecretKey, RabeError> {
+        Self::keygen_with_rng(gk, msk, authority, name, attributes, &mut rand::thread_rng())
+    }
+
+    /// Like `keygen()`, but draws all randomness from the given random number generator `rng`.
+    fn keygen_with_rng<R: RngCore + CryptoRng>(
+        gk: &Self::GlobalKey,
+        msk: &Self::MasterKey,
+        authority: &Self::AuthorityKey,
+        name: &str,
+        attributes: &[&str],
+        rng: &mut R
+    ) -> Result<Self::SecretKey, RabeError>;
+
+    /// Adds an attribute of (possibly another) authority to an existing secret key.
+    fn add_attribute(
+        gk: &Self::GlobalKey,
+        authority: &Self::AuthorityKey,
+        attribute: &str,
+        sk: &mut Self::SecretKey
+    ) -> Result<(), RabeError>;
+
+    /// Encrypts a plaintext under a policy, using the public keys of all attributes in the policy.
+    ///
+    /// # Arguments
+    ///
+    ///	* `gk` - The Global Parameters, generated by setup()
+    ///	* `attr_pks` - The public keys of the attributes that occur in the policy
+    ///	* `policy` - An access policy, given as text and its PolicyLanguage or as Policy, see [PolicySource]
+    ///	* `plaintext` - The plaintext data given as a slice of u8
+    fn encrypt<P: PolicySource>(
+        gk: &Self::GlobalKey,
+        attr_pks: &[&Self::AttributePublicKey],
+        policy: P,
+        plaintext: &[u8]
+    ) -> Result<Self::Ciphertext, RabeError> {
+        Self::encrypt_with(gk, attr_pks, policy, plaintext, EncryptOptions::new())
+    }
+
+    /// Like `encrypt()`, but with the given [EncryptOptions], i.e. the random number generator, the associated data
+    /// that has to be passed to `decrypt_with_aad()`, the symmetric cipher and the maximal reuse of an attribute.
+    fn encrypt_with<P: PolicySource, R: RngCore + CryptoRng>(
+        gk: &Self::GlobalKey,
+        attr_pks: &[&Self::AttributePublicKey],
+        policy: P,
+        plaintext: &[u8],
+        options: EncryptOptions<R>
+    ) -> Result<Self::Ciphertext, RabeError>;
+
+    /// Decrypts a ciphertext if the attributes of the secret key satisfy its policy.
+    fn decrypt(
+        gk: &Self::GlobalKey,
+        sk: &Self::SecretKey,
+        ct: &Self::Ciphertext
+    ) -> Result<Vec<u8>, RabeError> {
+        Self::decrypt_with_aad(gk, sk, ct, &[])
+    }
+
+    /// Like `decrypt()`, but for ciphertexts that were generated by `encrypt_with()` with the associated data `aad`.
+    fn decrypt_with_aad(
+        gk: &Self::GlobalKey,
+        sk: &Self::SecretKey,
+        ct: &Self::Ciphertext,
+        aad: &[u8]
+    ) -> Result<Vec<u8>, RabeError>;
+
+    /// Encapsulates a fresh symmetric key under a policy, without encrypting any payload.
+    fn encapsulate<P: PolicySource>(
+        gk: &Self::GlobalKey,
+        attr_pks: &[&Self::AttributePublicKey],
+        policy: P
+    ) -> Result<(SharedKey, Self::Header), RabeError> {
+        Self::encapsulate_with(gk, attr_pks, policy, EncryptOptions::new())
+    }
+
+    /// Like `encapsulate()`, but with the given [EncryptOptions], i.e. the random number generator, the symmetric
+    /// cipher that is recorded in the header and the maximal reuse of an attribute.
+    fn encapsulate_with<P: PolicySource, R: RngCore + CryptoRng>(
+        gk: &Self::GlobalKey,
+        attr_pks: &[&Self::AttributePublicKey],
+        policy: P,
+        options: EncryptOptions<R>
+    ) -> Result<(SharedKey, Self::Header), RabeError>;
+
+    /// Recovers the symmetric key from a header if the attributes of the secret key satisfy its policy.
+    fn decapsulate(
+        gk: &Self::GlobalKey,
+        sk: &Self::SecretKey,
+        header: &Self::Header
+    ) -> Result<SharedKey, RabeError>;
+}
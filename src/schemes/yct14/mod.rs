@@ -26,17 +26,22 @@
 //! let plaintext = String::from("our plaintext!").into_bytes();
 //! let policy = String::from(r#""A" or "B""#);
 //! let ct_kp: Yct14AbeCiphertext = encrypt(&pk, &vec!["A", "B"], &plaintext).unwrap();
-//! let sk: Yct14AbeSecretKey = keygen(&msk, &policy, PolicyLanguage::HumanPolicy).unwrap();
+//! let sk: Yct14AbeSecretKey = keygen(&msk, (&policy, PolicyLanguage::HumanPolicy)).unwrap();
 //! assert_eq!(decrypt(&sk, &ct_kp).unwrap(), plaintext);
 //! ```
 use rabe_bn::{Fr, Gt};
 use utils::{
-    secretsharing::{gen_shares_policy, calc_coefficients, calc_pruned},
-    aes::*
+    secretsharing::{gen_shares_policy_with_rng, calc_coefficients, calc_pruned_minimal, LeafWeights},
+    aes::*,
+    container::{Container, SchemeId, ObjectType, Validate},
 };
-use rand::Rng;
-use utils::policy::pest::{PolicyLanguage, parse};
+use rand::{CryptoRng, Rng, RngCore};
+use utils::policy::pest::{PolicyLanguage, parse, check_monotone};
+use utils::policy::ast::PolicySource;
+use utils::policy::explain::not_satisfied;
 use crate::error::RabeError;
+use schemes::traits::KpAbe;
+use schemes::options::EncryptOptions;
 use std::ops::Mul;
 use utils::secretsharing::remove_index;
 #[cfg(feature = "serde")]
@@ -44,6 +49,9 @@ use serde::{Serialize, Deserialize};
 #[cfg(feature = "borsh")]
 use borsh::{BorshSerialize, BorshDeserialize};
 
+/// The decryption cost of the leaves of a policy: one exponentiation in Gt per leaf
+const LEAF_WEIGHTS: LeafWeights = LeafWeights { attribute: 1, negated: 1 };
+
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, PartialEq, Debug)]
@@ -55,6 +63,8 @@ pub struct Yct14Attribute {
     node: Option<Yct14Type>,
 }
 
+validate_fields!(Yct14Attribute { name, node });
+
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -63,25 +73,37 @@ pub enum Yct14Type {
     Private(Fr),
 }
 
+impl Validate for Yct14Type {
+    fn validate(&self) -> Result<(), RabeError> {
+        match self {
+            Yct14Type::Public(gt) => gt.validate(),
+            Yct14Type::Private(fr) => fr.validate(),
+        }
+    }
+}
+
 impl Yct14Type {
     pub fn public(&self) -> Result<Gt, RabeError> {
         match self {
             Yct14Type::Public(g) => Ok(g.clone()),
-            _ => Err(RabeError::new("no public value (Gt) found"))
+            _ => Err(RabeError::InvalidKey(String::from("yct14: no public value (Gt) found")))
         }
     }
     pub fn  private(&self) -> Result<Fr, RabeError> {
         match self {
             Yct14Type::Private(fr) => Ok(fr.clone()),
-            _ => Err(RabeError::new("no private value (Fr) found"))
+            _ => Err(RabeError::InvalidKey(String::from("yct14: no private value (Fr) found")))
         }
     }
 }
 
 impl Yct14Attribute {
     pub fn new(name: String, g: Gt) -> (Yct14Attribute, Yct14Attribute) {
+        Yct14Attribute::new_with_rng(name, g, &mut rand::thread_rng())
+    }
+    pub fn new_with_rng<R: RngCore + CryptoRng>(name: String, g: Gt, rng: &mut R) -> (Yct14Attribute, Yct14Attribute) {
         // random fr
-        let si: Fr = rand::thread_rng().gen();
+        let si: Fr = rng.gen();
         (
             // public attribute part
             Yct14Attribute {
@@ -97,41 +119,29 @@ impl Yct14Attribute {
     }
     pub fn private_from(input: (String, Fr), msk: &Yct14AbeMasterKey) -> Result<Yct14Attribute, RabeError> {
         match msk.get_private(&input.0) {
-            Ok(si) => Ok(
-                Yct14Attribute {
-                    name: input.0,
-                    node: Some(
-                        Yct14Type::Private(
-                            input.1.mul(si.inverse().unwrap())
+            Ok(si) => match si.inverse() {
+                Some(si_inverse) => Ok(
+                    Yct14Attribute {
+                        name: input.0,
+                        node: Some(
+                            Yct14Type::Private(
+                                input.1.mul(si_inverse)
+                            )
                         )
-                    )
-                }
-            ),
+                    }
+                ),
+                None => Err(RabeError::InvalidKey(format!("yct14: private value of attribute {} is zero", input.0)))
+            },
             Err(e) => Err(e)
         }
 
     }
-    pub fn public_from(name: &String, pk: &Yct14AbePublicKey, k: Fr) -> Yct14Attribute {
-        Yct14Attribute {
+    pub fn public_from(name: &String, pk: &Yct14AbePublicKey, k: Fr) -> Result<Yct14Attribute, RabeError> {
+        let public = pk.get_public(name)?;
+        Ok(Yct14Attribute {
             name: name.to_string(),
-            node: pk.attributes
-                .clone()
-                .into_iter()
-                .filter(|attribute| attribute.name.as_str() == name)
-                .map(|attribute| match attribute.node {
-                    Some(node) => {
-                        match node {
-                            Yct14Type::Public(public) => {
-                                Some(Yct14Type::Public(public.pow(k)))
-                            },
-                            _ => panic!("attribute {} has no public node value", attribute.name),
-                        }
-                    },
-                    None => panic!("attribute {} has no public node", attribute.name),
-                })
-                .nth(0)
-                .unwrap()
-        }
+            node: Some(Yct14Type::Public(public.pow(k)))
+        })
     }
 }
 
@@ -144,6 +154,30 @@ pub struct Yct14AbePublicKey {
     attributes: Vec<Yct14Attribute>
 }
 
+validate_fields!(Yct14AbePublicKey { g, attributes });
+
+impl Container for Yct14AbePublicKey {
+    const SCHEME: SchemeId = SchemeId::Yct14;
+    const OBJECT: ObjectType = ObjectType::PublicKey;
+}
+
+impl Yct14AbePublicKey {
+    pub fn get_public(&self, attribute: &String) -> Result<Gt, RabeError> {
+        find_node(&self.attributes, attribute)?.public()
+    }
+}
+
+/// Returns the node of the first attribute with the given name
+fn find_node<'a>(attributes: &'a [Yct14Attribute], attribute: &String) -> Result<&'a Yct14Type, RabeError> {
+    attributes
+        .iter()
+        .find(|a| a.name.as_str() == attribute)
+        .ok_or_else(|| RabeError::UnknownAttribute(attribute.to_string()))?
+        .node
+        .as_ref()
+        .ok_or_else(|| RabeError::InvalidKey(format!("yct14: attribute {} has no value", attribute)))
+}
+
 /// A Master Key (MSK)
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
@@ -153,18 +187,16 @@ pub struct Yct14AbeMasterKey {
     attributes: Vec<Yct14Attribute>
 }
 
+validate_fields!(Yct14AbeMasterKey { s, attributes });
+
+impl Container for Yct14AbeMasterKey {
+    const SCHEME: SchemeId = SchemeId::Yct14;
+    const OBJECT: ObjectType = ObjectType::MasterKey;
+}
+
 impl Yct14AbeMasterKey {
     pub fn get_private(&self, attribute: &String) -> Result<Fr, RabeError> {
-        let res: Option<Fr> = self.attributes
-            .clone()
-            .into_iter()
-            .filter(|a| a.name.as_str() == attribute && a.node.is_some())
-            .map(|a| match a.node.unwrap().private() {
-                Ok(node_value) => node_value,
-                Err(e) => panic!("no private node value: {}",e)
-            } )
-            .nth(0);
-        res.ok_or(RabeError::new(&format!("no private key found for {}", attribute)))
+        find_node(&self.attributes, attribute)?.private()
     }
 }
 
@@ -177,42 +209,67 @@ pub struct Yct14AbeSecretKey {
     du: Vec<Yct14Attribute>,
 }
 
+validate_fields!(Yct14AbeSecretKey { policy, du });
+
+impl Container for Yct14AbeSecretKey {
+    const SCHEME: SchemeId = SchemeId::Yct14;
+    const OBJECT: ObjectType = ObjectType::SecretKey;
+}
+
 impl Yct14AbeSecretKey {
     pub fn get_private(&self, attribute: &String) -> Result<Fr, RabeError> {
-        let res: Option<Fr> = self.du
-            .clone()
-            .into_iter()
-            .filter(|a| a.name.as_str() == attribute)
-            .map(|a| match a.node.unwrap().private() {
-                Ok(node_value) => node_value,
-                Err(e) => panic!("no private node value: {}",e)
-            } )
-            .nth(0);
-        res.ok_or(RabeError::new(&format!("no private key found for {}", attribute)))
+        find_node(&self.du, attribute)?.private()
     }
 }
 
-/// A Ciphertext (CT)
+/// A Header, i.e. the encapsulated key under a set of attributes
 #[derive(Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub struct Yct14AbeCiphertext {
+pub struct Yct14AbeHeader {
     attributes: Vec<Yct14Attribute>,
+    pub cipher: SymmetricCipher,
+}
+
+validate_fields!(Yct14AbeHeader { attributes, cipher });
+
+impl Container for Yct14AbeHeader {
+    const SCHEME: SchemeId = SchemeId::Yct14;
+    const OBJECT: ObjectType = ObjectType::Header;
+}
+
+/// A Ciphertext (CT), composed of a Yct14AbeHeader and the symmetrically encrypted data
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Yct14AbeCiphertext {
+    header: Yct14AbeHeader,
     ct: Vec<u8>,
 }
 
+validate_fields!(Yct14AbeCiphertext { header, ct });
+
+impl Container for Yct14AbeCiphertext {
+    const SCHEME: SchemeId = SchemeId::Yct14;
+    const OBJECT: ObjectType = ObjectType::Ciphertext;
+}
+
 impl Yct14AbeCiphertext {
     pub fn get_public(&self, attribute: &String) -> Result<Gt, RabeError> {
-        let res: Option<Gt> = self.attributes
-            .clone()
-            .into_iter()
-            .filter(|a| a.name.as_str() == attribute)
-            .map(|a| match a.node.unwrap().public() {
-                Ok(node_value) => node_value,
-                Err(e) => panic!("no public node value: {}",e)
-            } )
-            .nth(0);
-        res.ok_or(RabeError::new(&format!("no private key found for {}", attribute)))
+        self.header.get_public(attribute)
+    }
+}
+
+impl Yct14AbeHeader {
+    /// Returns the associated data that binds this header to the symmetrically encrypted data:
+    /// the scheme id, the format version, the attributes and the caller supplied `aad`.
+    pub fn associated_data(&self, aad: &[u8]) -> Vec<u8> {
+        let attributes: Vec<&[u8]> = self.attributes.iter().map(|a| a.name.as_bytes()).collect();
+        associated_data("YCT14", &attributes, aad)
+    }
+
+    pub fn get_public(&self, attribute: &String) -> Result<Gt, RabeError> {
+        find_node(&self.attributes, attribute)?.public()
     }
 }
 
@@ -222,17 +279,23 @@ impl Yct14AbeCiphertext {
 pub fn setup(
     attributes: Vec<&str>
 ) -> (Yct14AbePublicKey, Yct14AbeMasterKey) {
-    // random number generator
-    let mut _rng = rand::thread_rng();
+    setup_with_rng(attributes, &mut rand::thread_rng())
+}
+
+/// Like `setup()`, but draws all randomness from the given random number generator `rng`.
+pub fn setup_with_rng<R: RngCore + CryptoRng>(
+    attributes: Vec<&str>,
+    rng: &mut R
+) -> (Yct14AbePublicKey, Yct14AbeMasterKey) {
     // attribute vec
     let mut private: Vec<Yct14Attribute> = Vec::new();
     let mut public: Vec<Yct14Attribute> = Vec::new();
     // generate random values
-    let s: Fr = _rng.gen();
-    let g: Gt = _rng.gen();
+    let s: Fr = rng.gen();
+    let g: Gt = rng.gen();
     // generate randomized attributes
     for attribute in attributes {
-        let attribute_pair = Yct14Attribute::new(attribute.to_string(), g);
+        let attribute_pair = Yct14Attribute::new_with_rng(attribute.to_string(), g, rng);
         public.push(attribute_pair.0);
         private.push(attribute_pair.1);
     }
@@ -251,32 +314,36 @@ pub fn setup(
 /// # Arguments
 ///
 ///	* `msk` - A Master Key (MSK), generated by the function setup()
-///	* `policy` - An access policy given as PolicyLanguage
-///	* `language` - The PolicyLanguage
+///	* `policy` - An access policy, given as text and its PolicyLanguage or as Policy, see [PolicySource]
 ///
-pub fn keygen(
+pub fn keygen<P: PolicySource>(
     msk: &Yct14AbeMasterKey,
-    policy: &String,
-    language: PolicyLanguage,
+    policy: P
 ) -> Result<Yct14AbeSecretKey, RabeError> {
-    match parse(policy, language) {
-        Ok(pol) => {
+    keygen_with_rng(msk, policy, &mut rand::thread_rng())
+}
+
+/// Like `keygen()`, but draws all randomness from the given random number generator `rng`.
+pub fn keygen_with_rng<P: PolicySource, R: RngCore + CryptoRng>(
+    msk: &Yct14AbeMasterKey,
+    policy: P,
+    rng: &mut R
+) -> Result<Yct14AbeSecretKey, RabeError> {
+    match policy.value() {
+        Ok((pol, policy, language)) => {
+            check_monotone(&pol, "yct14/keygen")?;
             let mut du: Vec<Yct14Attribute> = Vec::new();
-            match gen_shares_policy(msk.s, &pol, None) {
-                Some(shares) => {
+            match gen_shares_policy_with_rng(msk.s, &pol, None, rng) {
+                Ok(shares) => {
                     for share in shares.into_iter() {
-                        //println!("share {}", serde_json::to_string(&share.clone()).unwrap());
-                        match Yct14Attribute::private_from((remove_index(&share.0), share.1), msk) {
-                            Ok(attribute) => du.push(attribute),
-                            Err(e) => println!("Error: {:?}", e)
-                        }
+                        du.push(Yct14Attribute::private_from((remove_index(&share.0), share.1), msk)?);
                     }
                     Ok(Yct14AbeSecretKey {
-                        policy: (policy.clone(), language),
+                        policy: (policy.to_string(), language),
                         du
                     })
                 },
-                None => Err(RabeError::new("could not generate shares during keygen()"))
+                Err(e) => Err(e)
             }
         },
         Err(e) => Err(e)
@@ -290,31 +357,67 @@ pub fn keygen(
 ///	* `plaintext` - plaintext data given as a vec<u8>
 ///
 pub fn encrypt(
+    pk: &Yct14AbePublicKey,
+    attributes: &Vec<&str>,
+    plaintext: &[u8]
+) -> Result<Yct14AbeCiphertext, RabeError> {
+    encrypt_with(pk, attributes, plaintext, EncryptOptions::new())
+}
+
+/// Like `encrypt()`, but with the given [EncryptOptions], i.e. the random number generator, the associated data
+/// that has to be passed to `decrypt_with_aad()` and the symmetric cipher.
+pub fn encrypt_with<R: RngCore + CryptoRng>(
     pk: &Yct14AbePublicKey,
     attributes: &Vec<&str>,
     plaintext: &[u8],
+    mut options: EncryptOptions<R>
 ) -> Result<Yct14AbeCiphertext, RabeError> {
+    if plaintext.is_empty() {
+        return Err(RabeError::InvalidInput(String::from("yct14/encrypt: plaintext empty")));
+    }
+    let (key, header) = encapsulate_with(pk, attributes, options.by_ref())?;
+    //Encrypt plaintext using the encapsulated key, binding the header as associated data
+    let ct = header.cipher.encrypt(&key, plaintext, &header.associated_data(options.aad), &mut options.rng)?;
+    Ok(Yct14AbeCiphertext { header, ct })
+}
+
+/// Generates a new SharedKey and a Yct14AbeHeader that encapsulates it under a set of attributes.
+///
+/// # Arguments
+///
+///	* `pk` - A Public Key (PK), generated by the function setup()
+///	* `attributes` - A set of attributes given as String Vector
+///
+pub fn encapsulate(
+    pk: &Yct14AbePublicKey,
+    attributes: &Vec<&str>
+) -> Result<(SharedKey, Yct14AbeHeader), RabeError> {
+    encapsulate_with(pk, attributes, EncryptOptions::new())
+}
+
+/// Like `encapsulate()`, but with the given [EncryptOptions], i.e. the random number generator and the symmetric
+/// cipher that is recorded in the header.
+pub fn encapsulate_with<R: RngCore + CryptoRng>(
+    pk: &Yct14AbePublicKey,
+    attributes: &Vec<&str>,
+    mut options: EncryptOptions<R>
+) -> Result<(SharedKey, Yct14AbeHeader), RabeError> {
+    let rng = &mut options.rng;
     if attributes.is_empty() {
-        return Err(RabeError::new("attributes empty"));
-    } 
-    else if plaintext.is_empty() {
-        return Err(RabeError::new("plaintext empty"));
+        return Err(RabeError::InvalidInput(String::from("yct14/encapsulate: attributes are empty")));
     }
     else {
         // attribute vector
         let mut attrs: Vec<Yct14Attribute> = Vec::new();
         // random secret
-        let k: Fr = rand::thread_rng().gen();
+        let k: Fr = rng.gen();
         // aes secret = public g ** random k
         let _cs: Gt = pk.g.pow(k);
         for attr in attributes.into_iter() {
-            attrs.push(Yct14Attribute::public_from(&attr.to_string(), pk, k));
-        }
-        //Encrypt plaintext using aes secret
-        match encrypt_symmetric(_cs, &plaintext.to_vec()) {
-            Ok(ct) => Ok(Yct14AbeCiphertext { attributes: attrs, ct }),
-            Err(e) => Err(e)
+            attrs.push(Yct14Attribute::public_from(&attr.to_string(), pk, k)?);
         }
+        let header = Yct14AbeHeader { attributes: attrs, cipher: options.cipher };
+        Ok((SharedKey::derive_for(_cs, &header)?, header))
     }
 }
 
@@ -327,35 +430,57 @@ pub fn decrypt(
     sk: &Yct14AbeSecretKey,
     ct: &Yct14AbeCiphertext
 ) -> Result<Vec<u8>, RabeError> {
-    let attr = ct
+    decrypt_with_aad(sk, ct, &[])
+}
+
+/// Like `decrypt()`, but for ciphertexts that were generated by `encrypt_with()` with the associated data `aad`.
+pub fn decrypt_with_aad(
+    sk: &Yct14AbeSecretKey,
+    ct: &Yct14AbeCiphertext,
+    aad: &[u8]
+) -> Result<Vec<u8>, RabeError> {
+    let key = decapsulate(sk, &ct.header)?;
+    ct.header.cipher.decrypt(&key, &ct.ct, &ct.header.associated_data(aad))
+}
+
+/// Recovers the SharedKey of a Yct14AbeHeader with a matching Yct14AbeSecretKey.
+///
+/// # Arguments
+///
+///	* `sk` - A Secret Key (SK), generated by keygen()
+///	* `header` - A Header, generated by encapsulate()
+///
+pub fn decapsulate(
+    sk: &Yct14AbeSecretKey,
+    header: &Yct14AbeHeader
+) -> Result<SharedKey, RabeError> {
+    let attr = header
         .attributes
         .iter()
         .map(|value| value.name.clone())
         .collect::<Vec<String>>();
     match parse(sk.policy.0.as_ref(), sk.policy.1) {
         Ok(policy_value) => {
-            return match calc_pruned(&attr, &policy_value, None) {
+            return match calc_pruned_minimal(&attr, &policy_value, LEAF_WEIGHTS) {
                 Err(e) => Err(e),
                 Ok(_p) => {
                     let (_match, _list) = _p;
                     if _match {
                         let mut _prod_t = Gt::one();
                         let mut coeff_list: Vec<(String, Fr)> = Vec::new();
-                        coeff_list = calc_coefficients(&policy_value, Some(Fr::one()), coeff_list, None).unwrap();
+                        coeff_list = calc_coefficients(&policy_value, Some(Fr::one()), coeff_list, None, &_list)?;
                         for _attr in _list.into_iter() {
-                            let z = ct.get_public(&_attr.0).unwrap().pow(sk.get_private(&_attr.0).unwrap());
+                            let z = header.get_public(&_attr.0)?.pow(sk.get_private(&_attr.0)?);
                             let coeff = coeff_list
-                                .clone()
-                                .into_iter()
-                                .filter(|a| a.0 == _attr.1)
+                                .iter()
+                                .find(|a| a.0 == _attr.1)
                                 .map(|a| a.1 )
-                                .nth(0)
-                                .unwrap();
+                                .ok_or_else(|| RabeError::InvalidPolicy(format!("yct14/decapsulate: no coefficient for {}", _attr.1)))?;
                             _prod_t = _prod_t * z.pow(coeff);
                         }
-                        decrypt_symmetric(_prod_t, &ct.ct)
+                        SharedKey::derive_for(_prod_t, header)
                     } else {
-                        Err(RabeError::new("Error in decrypt: attributes do not match policy."))
+                        Err(not_satisfied("yct14/decrypt: attributes do not match policy", &attr, &policy_value))
                     }
                 }
             }
@@ -364,10 +489,72 @@ pub fn decrypt(
     }
 }
 
+/// The YCT14 KP-ABE scheme, to be used through the [`KpAbe`](../traits/trait.KpAbe.html) trait.
+pub struct Yct14;
+
+impl KpAbe for Yct14 {
+    type PublicKey = Yct14AbePublicKey;
+    type MasterKey = Yct14AbeMasterKey;
+    type SecretKey = Yct14AbeSecretKey;
+    type Ciphertext = Yct14AbeCiphertext;
+    type Header = Yct14AbeHeader;
+
+    fn setup_with_rng<R: RngCore + CryptoRng>(attributes: &[&str], rng: &mut R) -> Result<(Yct14AbePublicKey, Yct14AbeMasterKey), RabeError> {
+        if attributes.is_empty() {
+            Err(RabeError::InvalidInput(String::from("yct14/setup: the attribute universe is empty")))
+        }
+        else {
+            Ok(setup_with_rng(attributes.to_vec(), rng))
+        }
+    }
+
+    fn keygen_with_rng<P: PolicySource, R: RngCore + CryptoRng>(
+        _pk: &Yct14AbePublicKey,
+        msk: &Yct14AbeMasterKey,
+        policy: P,
+        rng: &mut R
+    ) -> Result<Yct14AbeSecretKey, RabeError> {
+        keygen_with_rng(msk, policy, rng)
+    }
+
+    fn encrypt_with<R: RngCore + CryptoRng>(
+        pk: &Yct14AbePublicKey,
+        attributes: &[&str],
+        plaintext: &[u8],
+        options: EncryptOptions<R>
+    ) -> Result<Yct14AbeCiphertext, RabeError> {
+        encrypt_with(pk, &attributes.to_vec(), plaintext, options)
+    }
+
+    fn decrypt_with_aad(
+        sk: &Yct14AbeSecretKey,
+        ct: &Yct14AbeCiphertext,
+        aad: &[u8]
+    ) -> Result<Vec<u8>, RabeError> {
+        decrypt_with_aad(sk, ct, aad)
+    }
+
+    fn encapsulate_with<R: RngCore + CryptoRng>(
+        pk: &Yct14AbePublicKey,
+        attributes: &[&str],
+        options: EncryptOptions<R>
+    ) -> Result<(SharedKey, Yct14AbeHeader), RabeError> {
+        encapsulate_with(pk, &attributes.to_vec(), options)
+    }
+
+    fn decapsulate(
+        sk: &Yct14AbeSecretKey,
+        header: &Yct14AbeHeader
+    ) -> Result<SharedKey, RabeError> {
+        decapsulate(sk, header)
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use schemes::harness;
 
     #[test]
     fn or() {
@@ -386,10 +573,14 @@ mod tests {
         let ct: Yct14AbeCiphertext = encrypt(&pk, &attributes, &plaintext).unwrap();
         //println!("ct: {:?}", serde_json::to_string(&ct).unwrap());
         // a kp-abe SK key
-        let sk: Yct14AbeSecretKey = keygen(&msk, &policy, PolicyLanguage::JsonPolicy).unwrap();
+        let sk: Yct14AbeSecretKey = keygen(&msk, (&policy, PolicyLanguage::JsonPolicy)).unwrap();
         //println!("sk: {:?}", serde_json::to_string(&sk).unwrap());
         // and now decrypt again with matching sk
         assert_eq!(decrypt(&sk, &ct).unwrap(), plaintext);
     }
 
+    #[test]
+    fn kp_abe() {
+        harness::kp_abe::<Yct14>(false).unwrap();
+    }
 }
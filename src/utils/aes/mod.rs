@@ -1,59 +1,247 @@
-use aes_gcm::{Aes256Gcm, Key, Nonce, KeyInit}; // Or `Aes128Gcm`
-use aes_gcm::aead::Aead;
+use aes_gcm::Aes256Gcm;
+use aes_gcm::aead::{Aead, KeyInit, Nonce, Payload};
+use aes_gcm_siv::Aes256GcmSiv;
+use chacha20poly1305::ChaCha20Poly1305;
+
+use hkdf::Hkdf;
+use sha3::{Digest, Sha3_256};
+use std::fmt;
+use subtle::{Choice, ConstantTimeEq};
+use zeroize::Zeroize;
 
 use crate::error::RabeError;
-use std::convert::TryInto;
-use rand::thread_rng;
-use rand::Rng;
+use rand::{thread_rng, CryptoRng, Rng, RngCore};
+use utils::container::{Container, SchemeId, FORMAT_VERSION};
+#[cfg(feature = "borsh")]
+use borsh::{BorshSerialize, BorshDeserialize};
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+/// Chunked encryption of large payloads with `Read`/`Write` adapters
+pub mod stream;
+
+/// The salt of the HKDF extraction
+const KDF_SALT: &[u8] = b"RABE-HKDF-SHA3-256";
+/// The label of the AEAD key, see [SharedKey::as_bytes]
+const AEAD_KEY: &str = "aead key";
+
+/// A 256 bit symmetric key, derived from the secret that is encapsulated by an ABE scheme.
+///
+/// Returned by the `encapsulate` and `decapsulate` functions of every scheme, e.g. to wrap data keys of
+/// an existing envelope encryption or to key a different data encapsulation mechanism (DEM).
+///
+/// The key schedule is HKDF with SHA3-256: the encapsulated secret is extracted once, and all keys are expanded
+/// with an info string of the scheme, the container format version, the digest of the header and a label. Besides
+/// the AEAD key, [SharedKey::expand] derives further independent keys, e.g. a MAC or nonce key.
+///
+/// Keys are compared in constant time, their `Debug` output does not contain key material and the key material is
+/// zeroized on drop.
+#[derive(Clone)]
+pub struct SharedKey {
+    // the pseudorandom key of the extraction
+    prk: Vec<u8>,
+    // the scheme, format version and header digest, the prefix of all info strings
+    context: Vec<u8>,
+    // the AEAD key
+    key: [u8; 32],
+}
+
+impl SharedKey {
+    /// Derives a shared key from anything implementing the `Into<Vec<u8>>` trait (usually a `Gt` element), without
+    /// binding it to a scheme or header
+    pub fn derive<G: std::convert::Into<Vec<u8>>>(msg: G) -> SharedKey {
+        SharedKey::schedule(msg.into(), Vec::new())
+    }
+
+    /// Derives the shared key of the secret `msg` that is encapsulated by `header`, bound to the scheme, the
+    /// container format version and the digest of the header. The digest covers the encoding of the header, i.e. a
+    /// header decapsulates to the same key after serialization and deserialization, but not after its points have
+    /// been recomputed in other coordinates.
+    pub fn derive_for<G: std::convert::Into<Vec<u8>>, H: Container>(msg: G, header: &H) -> Result<SharedKey, RabeError> {
+        Ok(SharedKey::derive_with_digest(msg, H::SCHEME, &header_digest(header)?))
+    }
+
+    /// Like `derive_for()`, given the scheme and the digest of the header (see `header_digest()`), e.g. if only the
+    /// digest is available at decryption
+    pub fn derive_with_digest<G: std::convert::Into<Vec<u8>>>(msg: G, scheme: SchemeId, digest: &[u8]) -> SharedKey {
+        let version = [FORMAT_VERSION];
+        SharedKey::schedule(msg.into(), length_prefixed(&[scheme.name().as_bytes(), &version, digest]))
+    }
+
+    // extracts the secret and expands the AEAD key
+    fn schedule(secret: Vec<u8>, context: Vec<u8>) -> SharedKey {
+        let (prk, _) = Hkdf::<Sha3_256>::extract(Some(KDF_SALT), &secret);
+        let mut shared = SharedKey { prk: prk.to_vec(), context, key: [0u8; 32] };
+        let mut key = shared.expand(AEAD_KEY, 32).expect("kdf: 32 bytes are below the output limit of hkdf");
+        shared.key.copy_from_slice(&key);
+        key.zeroize();
+        shared
+    }
+
+    /// Returns the raw bytes of the AEAD key
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.key
+    }
+
+    /// Derives a key of `len` bytes (at most 8160) for the given `label`. Keys of different labels are independent
+    /// of each other and of the AEAD key.
+    pub fn expand(&self, label: &str, len: usize) -> Result<Vec<u8>, RabeError> {
+        let hkdf = Hkdf::<Sha3_256>::from_prk(&self.prk)
+            .map_err(|_| RabeError::InvalidKey(String::from("kdf: invalid pseudorandom key")))?;
+        let info = [&self.context[..], &length_prefixed(&[label.as_bytes()])].concat();
+        let mut okm = vec![0u8; len];
+        hkdf.expand(&info, &mut okm)
+            .map_err(|_| RabeError::InvalidInput(format!("kdf: cannot derive {} bytes", len)))?;
+        Ok(okm)
+    }
+}
+
+impl ConstantTimeEq for SharedKey {
+    fn ct_eq(&self, other: &SharedKey) -> Choice {
+        self.prk.ct_eq(&other.prk) & self.context.ct_eq(&other.context) & self.key.ct_eq(&other.key)
+    }
+}
+
+impl PartialEq for SharedKey {
+    fn eq(&self, other: &SharedKey) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl Eq for SharedKey {}
+
+// the context is public, the keys are not printed
+impl fmt::Debug for SharedKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedKey")
+            .field("context", &self.context)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for SharedKey {
+    fn drop(&mut self) {
+        self.prk.zeroize();
+        self.key.zeroize();
+    }
+}
+
+/// Returns the SHA3-256 digest of the container of a header, which binds the shared key to all elements of the header
+pub fn header_digest<H: Container>(header: &H) -> Result<[u8; 32], RabeError> {
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(&Sha3_256::digest(header.to_bytes()?));
+    Ok(digest)
+}
 
 /// Key Encapsulation Mechanism (AES-256 Encryption Function)
-pub fn encrypt_symmetric<G: std::convert::Into<Vec<u8>>>(msg: G, data: &Vec<u8>) -> Result<Vec<u8>, RabeError> {
-    let mut rng = thread_rng();
-    // 256bit key hashed/derived from _msg G
-    let kdf = kdf(msg);
-    let key = Key::<Aes256Gcm>::from_slice(kdf.as_slice());
-    let cipher = Aes256Gcm::new(key);
-    // 96bit random noise
-    let nonce_vec: Vec<u8> = (0..12).into_iter().map(|_| rng.gen()).collect(); // 12*u8 = 96 Bit
-    let nonce = Nonce::from_slice(nonce_vec.as_ref());
-    match cipher.encrypt(nonce, data.as_ref()) {
-        Ok(mut ct) => {
-            ct.splice(0..0, nonce.iter().cloned()); // first 12 bytes are nonce i.e. [nonce|ciphertext]
-            Ok(ct)
+pub fn encrypt_symmetric<G: std::convert::Into<Vec<u8>>>(msg: G, data: &[u8]) -> Result<Vec<u8>, RabeError> {
+    encrypt_symmetric_with_rng(msg, data, &mut thread_rng())
+}
+
+/// Key Encapsulation Mechanism (AES-256 Encryption Function), using the given random number generator for the nonce
+pub fn encrypt_symmetric_with_rng<G: std::convert::Into<Vec<u8>>, R: RngCore + CryptoRng>(msg: G, data: &[u8], rng: &mut R) -> Result<Vec<u8>, RabeError> {
+    encrypt_with_key(&SharedKey::derive(msg), data, &[], rng)
+}
+
+/// AES-256 Encryption Function using a `SharedKey` directly, authenticating the associated data `aad`. The output has the form [nonce|ciphertext]
+pub fn encrypt_with_key<R: RngCore + CryptoRng>(key: &SharedKey, data: &[u8], aad: &[u8], rng: &mut R) -> Result<Vec<u8>, RabeError> {
+    SymmetricCipher::Aes256Gcm.encrypt(key, data, aad, rng)
+}
+
+/// Key Encapsulation Mechanism (AES-256 Decryption Function)
+pub fn decrypt_symmetric<G: std::convert::Into<Vec<u8>>>(msg: G, _nonce_ct: &[u8]) -> Result<Vec<u8>, RabeError> {
+    decrypt_with_key(&SharedKey::derive(msg), _nonce_ct, &[])
+}
+
+/// AES-256 Decryption Function using a `SharedKey` directly, expects input of the form [nonce|ciphertext] and the associated data `aad` used during encryption
+pub fn decrypt_with_key(key: &SharedKey, _nonce_ct: &[u8], aad: &[u8]) -> Result<Vec<u8>, RabeError> {
+    SymmetricCipher::Aes256Gcm.decrypt(key, _nonce_ct, aad)
+}
+
+/// The length of the nonces of all symmetric ciphers
+const NONCE_LEN: usize = 12;
+
+/// The symmetric cipher, i.e. the data encapsulation mechanism (DEM), that encrypts the data of a ciphertext with
+/// the shared key of an ABE scheme. It is recorded in the header of the ciphertext, so decryption picks it up.
+///
+/// All ciphers take a 256 bit key and a random 96 bit nonce and produce output of the form [nonce|ciphertext].
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SymmetricCipher {
+    /// AES-256-GCM, the default
+    #[default]
+    Aes256Gcm,
+    /// ChaCha20-Poly1305 (RFC 8439), which is fast on processors without AES instructions
+    ChaCha20Poly1305,
+    /// AES-256-GCM-SIV (RFC 8452), which stays secure if a nonce repeats, e.g. for deterministic re-encryption with
+    /// a seeded random number generator
+    Aes256GcmSiv,
+}
+
+impl SymmetricCipher {
+    /// Encrypts `data` with `key`, authenticating the associated data `aad`. The output has the form [nonce|ciphertext]
+    pub fn encrypt<R: RngCore + CryptoRng>(&self, key: &SharedKey, data: &[u8], aad: &[u8], rng: &mut R) -> Result<Vec<u8>, RabeError> {
+        // 96bit random noise
+        let nonce: Vec<u8> = (0..NONCE_LEN).map(|_| rng.gen()).collect();
+        let ct = match self {
+            SymmetricCipher::Aes256Gcm => seal::<Aes256Gcm>(key, &nonce, data, aad),
+            SymmetricCipher::ChaCha20Poly1305 => seal::<ChaCha20Poly1305>(key, &nonce, data, aad),
+            SymmetricCipher::Aes256GcmSiv => seal::<Aes256GcmSiv>(key, &nonce, data, aad),
+        }?;
+        Ok([nonce, ct].concat()) // first 12 bytes are nonce i.e. [nonce|ciphertext]
+    }
+
+    /// Decrypts input of the form [nonce|ciphertext] with `key` and the associated data `aad` used during encryption
+    pub fn decrypt(&self, key: &SharedKey, nonce_ct: &[u8], aad: &[u8]) -> Result<Vec<u8>, RabeError> {
+        if nonce_ct.len() < NONCE_LEN {
+            return Err(RabeError::SymmetricDecryption);
+        }
+        let (nonce, ciphertext) = nonce_ct.split_at(NONCE_LEN);
+        match self {
+            SymmetricCipher::Aes256Gcm => open::<Aes256Gcm>(key, nonce, ciphertext, aad),
+            SymmetricCipher::ChaCha20Poly1305 => open::<ChaCha20Poly1305>(key, nonce, ciphertext, aad),
+            SymmetricCipher::Aes256GcmSiv => open::<Aes256GcmSiv>(key, nonce, ciphertext, aad),
         }
-        Err(e) => Err(RabeError::new(&format!("encryption error: {:?}", e.to_string())))
     }
 }
 
-/// Key Encapsulation Mechanism (AES-256 Decryption Function)
-pub fn decrypt_symmetric<G: std::convert::Into<Vec<u8>>>(msg: G, _nonce_ct: &Vec<u8>) -> Result<Vec<u8>, RabeError> {
-    let ciphertext = _nonce_ct.clone().split_off(12); // 12*u8 = 96 Bit
-    let nonce_vec: [u8; 12] = match _nonce_ct[..12].try_into() { // first 12 bytes are nonce i.e. [nonce|ciphertext]
-        Ok(iv) => iv,
-        Err(_) => return Err(RabeError::new("Error extracting IV from ciphertext: Expected an IV of 16 bytes")), // this REALLY shouldn't happen.
-    };
-    // 256bit key hashed/derived from _msg G
-    let kdf = kdf(msg);
-    let key = Key::<Aes256Gcm>::from_slice(kdf.as_slice());
-    let cipher = Aes256Gcm::new(key);
-    let nonce = Nonce::from_slice(nonce_vec.as_ref());
-    match cipher.decrypt(nonce, ciphertext.as_ref()) {
-        Ok(data) => Ok(data),
-        Err(e) => Err(RabeError::new(&format!("decryption error: {:?}", e.to_string())))
-    }
-}
-
-/// Key derivation function - turns anything implementing the `Into<Vec<u8>` trait into a key for AES-256
-fn kdf<T: std::convert::Into<Vec<u8>>>(data: T) -> Vec<u8> {
-    use sha3::{
-        Digest,
-        Sha3_256
-    };
-    let mut hasher = Sha3_256::new();
-    hasher.update(data.into());
-    hasher.finalize().to_vec()
+// encrypts with one of the ciphers, all of them have 96 bit nonces
+fn seal<C: Aead + KeyInit>(key: &SharedKey, nonce: &[u8], data: &[u8], aad: &[u8]) -> Result<Vec<u8>, RabeError> {
+    let cipher = C::new_from_slice(key.as_bytes()).map_err(|_| RabeError::SymmetricEncryption)?;
+    cipher.encrypt(Nonce::<C>::from_slice(nonce), Payload { msg: data, aad }).map_err(|_| RabeError::SymmetricEncryption)
+}
+
+// decrypts with one of the ciphers, all of them have 96 bit nonces
+fn open<C: Aead + KeyInit>(key: &SharedKey, nonce: &[u8], ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>, RabeError> {
+    let cipher = C::new_from_slice(key.as_bytes()).map_err(|_| RabeError::SymmetricDecryption)?;
+    cipher.decrypt(Nonce::<C>::from_slice(nonce), Payload { msg: ciphertext, aad }).map_err(|_| RabeError::SymmetricDecryption)
+}
+
+/// Version of the layout produced by `associated_data()`
+pub const AAD_VERSION: u8 = 1;
+
+/// Builds the associated data (AAD) that binds a ciphertext header to the symmetrically encrypted data.
+///
+/// The result contains the `scheme` id, the `AAD_VERSION`, the given header `parts` (e.g. policy and policy language,
+/// or the attributes) and the caller supplied `aad`, each of them prefixed by its length.
+pub fn associated_data(scheme: &str, parts: &[&[u8]], aad: &[u8]) -> Vec<u8> {
+    let version = [AAD_VERSION];
+    let all: Vec<&[u8]> = [scheme.as_bytes(), &version[..]].iter().chain(parts.iter()).chain([aad].iter()).cloned().collect();
+    length_prefixed(&all)
 }
 
+// the concatenation of the parts, each prefixed by its length
+fn length_prefixed(parts: &[&[u8]]) -> Vec<u8> {
+    let mut data: Vec<u8> = Vec::new();
+    for part in parts {
+        data.extend_from_slice(&(part.len() as u64).to_be_bytes());
+        data.extend_from_slice(part);
+    }
+    data
+}
+
+#[cfg(test)]
 mod tests {
 
     #[test]
@@ -66,4 +254,96 @@ mod tests {
         let reconstruct = decrypt_symmetric(key, &ciphertext).unwrap();
         assert_eq!(plaintext.into_bytes(), reconstruct);
     }
+
+    #[test]
+    fn seeded_rng_test() {
+        use crate::utils::aes::{encrypt_symmetric_with_rng, decrypt_symmetric};
+        use rand::SeedableRng;
+        use rand_chacha::ChaCha20Rng;
+        let key = "7h15 15 4 v3ry 53cr37 k3ysdfsfsdfsdfdsfdsf1896957848";
+        let plaintext =
+            String::from("dance like no one's watching, encrypt like everyone is!").into_bytes();
+        let ct1 = encrypt_symmetric_with_rng(key, &plaintext, &mut ChaCha20Rng::seed_from_u64(42)).unwrap();
+        let ct2 = encrypt_symmetric_with_rng(key, &plaintext, &mut ChaCha20Rng::seed_from_u64(42)).unwrap();
+        let ct3 = encrypt_symmetric_with_rng(key, &plaintext, &mut ChaCha20Rng::seed_from_u64(43)).unwrap();
+        assert_eq!(ct1, ct2);
+        assert_ne!(ct1, ct3);
+        assert_eq!(decrypt_symmetric(key, &ct1).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn aad_test() {
+        use crate::utils::aes::{associated_data, encrypt_with_key, decrypt_with_key, SharedKey};
+        let key = SharedKey::derive("7h15 15 4 v3ry 53cr37 k3ysdfsfsdfsdfdsfdsf1896957848");
+        let plaintext =
+            String::from("dance like no one's watching, encrypt like everyone is!").into_bytes();
+        let aad = associated_data("TEST", &[b"A and B"], b"extra");
+        let ct = encrypt_with_key(&key, &plaintext, &aad, &mut rand::thread_rng()).unwrap();
+        assert_eq!(decrypt_with_key(&key, &ct, &aad).unwrap(), plaintext);
+        assert!(decrypt_with_key(&key, &ct, &associated_data("TEST", &[b"A or B"], b"extra")).is_err());
+        assert!(decrypt_with_key(&key, &ct, &associated_data("TEST", &[b"A and B"], b"")).is_err());
+        // the length prefixes keep the parts apart
+        assert_ne!(associated_data("TEST", &[b"ab", b"c"], b""), associated_data("TEST", &[b"a", b"bc"], b""));
+    }
+
+    #[test]
+    fn key_schedule_test() {
+        use crate::utils::aes::{header_digest, SharedKey};
+        use crate::utils::container::SchemeId;
+        use crate::utils::policy::pest::PolicyLanguage;
+        use crate::schemes::bsw;
+        let (pk, _msk) = bsw::setup();
+        let (key, header) = bsw::encapsulate(&pk, (r#""A" and "B""#, PolicyLanguage::HumanPolicy)).unwrap();
+        let msg = "7h15 15 4 v3ry 53cr37 k3ysdfsfsdfsdfdsfdsf1896957848";
+        // the key is bound to the scheme and the header
+        let bound = SharedKey::derive_for(msg, &header).unwrap();
+        assert_eq!(bound, SharedKey::derive_with_digest(msg, SchemeId::Bsw, &header_digest(&header).unwrap()));
+        assert_ne!(bound, SharedKey::derive_with_digest(msg, SchemeId::Ac17, &header_digest(&header).unwrap()));
+        assert_ne!(bound.as_bytes(), SharedKey::derive(msg).as_bytes());
+        let (_, other) = bsw::encapsulate(&pk, (r#""A" and "B""#, PolicyLanguage::HumanPolicy)).unwrap();
+        assert_ne!(bound.as_bytes(), SharedKey::derive_for(msg, &other).unwrap().as_bytes());
+        // further keys of configurable lengths, independent of the aead key and of each other
+        let mac = key.expand("mac key", 64).unwrap();
+        assert_eq!(mac.len(), 64);
+        assert_eq!(mac, key.expand("mac key", 64).unwrap());
+        assert_eq!(&mac[..32], &key.expand("mac key", 32).unwrap()[..]);
+        assert_ne!(&mac[..32], key.as_bytes());
+        assert_ne!(mac, key.expand("nonce key", 64).unwrap());
+        assert!(key.expand("mac key", 255 * 32 + 1).is_err());
+    }
+
+    #[test]
+    fn shared_key_secrecy_test() {
+        use crate::utils::aes::SharedKey;
+        let key = SharedKey::derive("7h15 15 4 v3ry 53cr37 k3ysdfsfsdfsdfdsfdsf1896957848");
+        assert_eq!(key, key.clone());
+        assert_ne!(key, SharedKey::derive("another key"));
+        // neither the pseudorandom key nor the aead key is printed
+        let debug = format!("{:?}", key);
+        let hex: String = key.as_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+        assert!(debug.starts_with("SharedKey") && !debug.contains("prk") && !debug.contains("key:"));
+        assert!(!debug.contains(&format!("{:?}", key.as_bytes())) && !debug.contains(&hex));
+    }
+
+    #[test]
+    fn symmetric_cipher_test() {
+        use crate::utils::aes::{SharedKey, SymmetricCipher};
+        let key = SharedKey::derive("7h15 15 4 v3ry 53cr37 k3ysdfsfsdfsdfdsfdsf1896957848");
+        let other = SharedKey::derive("another key");
+        let plaintext =
+            String::from("dance like no one's watching, encrypt like everyone is!").into_bytes();
+        let ciphers = [SymmetricCipher::Aes256Gcm, SymmetricCipher::ChaCha20Poly1305, SymmetricCipher::Aes256GcmSiv];
+        assert_eq!(SymmetricCipher::default(), SymmetricCipher::Aes256Gcm);
+        for cipher in ciphers {
+            let ct = cipher.encrypt(&key, &plaintext, b"aad", &mut rand::thread_rng()).unwrap();
+            assert_eq!(cipher.decrypt(&key, &ct, b"aad").unwrap(), plaintext);
+            assert!(cipher.decrypt(&other, &ct, b"aad").is_err());
+            assert!(cipher.decrypt(&key, &ct, b"other").is_err());
+            assert!(cipher.decrypt(&key, &ct[..8], b"aad").is_err());
+            // a ciphertext only opens with the cipher that sealed it
+            for wrong in ciphers.iter().filter(|c| **c != cipher) {
+                assert!(wrong.decrypt(&key, &ct, b"aad").is_err());
+            }
+        }
+    }
 }
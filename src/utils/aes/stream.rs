@@ -0,0 +1,437 @@
+//! Chunked encryption (STREAM construction) for large payloads.
+//!
+//! The payload is split into chunks of [`CHUNK_SIZE`] bytes, each of them is sealed with its own
+//! nonce derived from a random prefix and a chunk counter. The final chunk is flagged, so truncating,
+//! reordering or extending the stream is detected on decryption.
+//!
+//! The encrypted stream has the form `[nonce prefix | chunk_0 | chunk_1 | ... | chunk_n]` and contains
+//! no ABE data at all. It is keyed with a key of its own, expanded from the [`SharedKey`](../struct.SharedKey.html)
+//! returned by the `encapsulate()` function of a scheme, so a stream and a one-shot ciphertext of the same shared key
+//! never share a key. It is encrypted with any [`SymmetricCipher`](../enum.SymmetricCipher.html).
+//! Every chunk authenticates the same associated data, which should be the digest of the ABE header (see
+//! [`header_digest()`](../fn.header_digest.html)), so a streamed body cannot be stored with a different header.
+//!
+//! ```
+//! use rabe::schemes::bsw;
+//! use rabe::utils::aes::header_digest;
+//! use rabe::utils::aes::stream::{encrypt_stream, decrypt_stream};
+//! use rabe::utils::policy::pest::PolicyLanguage;
+//! let (pk, msk) = bsw::setup();
+//! let sk = bsw::keygen(&pk, &msk, &["A", "B"]).unwrap();
+//! let (key, header) = bsw::encapsulate(&pk, (r#""A" and "B""#, PolicyLanguage::HumanPolicy)).unwrap();
+//! let plaintext = vec![42u8; 200_000];
+//! let mut ciphertext: Vec<u8> = Vec::new();
+//! encrypt_stream(&key, header.cipher, &header_digest(&header).unwrap(), &mut plaintext.as_slice(), &mut ciphertext).unwrap();
+//! let key = bsw::decapsulate(&sk, &header).unwrap();
+//! let mut decrypted: Vec<u8> = Vec::new();
+//! decrypt_stream(&key, header.cipher, &header_digest(&header).unwrap(), &mut ciphertext.as_slice(), &mut decrypted).unwrap();
+//! assert_eq!(decrypted, plaintext);
+//! ```
+use aes_gcm::Aes256Gcm;
+use aes_gcm::aead::{AeadInPlace, KeyInit, Payload};
+use aes_gcm::aead::stream::{DecryptorBE32, EncryptorBE32};
+use aes_gcm_siv::Aes256GcmSiv;
+use chacha20poly1305::ChaCha20Poly1305;
+use std::io::{self, Read, Write};
+use rand::{thread_rng, CryptoRng, Rng, RngCore};
+use zeroize::Zeroize;
+use crate::error::RabeError;
+use super::{SharedKey, SymmetricCipher};
+
+/// Size of a plaintext chunk (64 KiB)
+pub const CHUNK_SIZE: usize = 64 * 1024;
+/// Size of the random nonce prefix written in front of the stream (96 bit nonce minus 40 bit counter and flag)
+pub const NONCE_PREFIX_SIZE: usize = 7;
+/// Size of the authentication tag appended to every chunk
+const TAG_SIZE: usize = 16;
+/// The label of the STREAM key, see [SharedKey::expand]
+const STREAM_KEY: &str = "stream key";
+
+// the STREAM encryptor of one of the symmetric ciphers
+enum Encryptor {
+    Aes256Gcm(EncryptorBE32<Aes256Gcm>),
+    ChaCha20Poly1305(EncryptorBE32<ChaCha20Poly1305>),
+    Aes256GcmSiv(EncryptorBE32<Aes256GcmSiv>),
+}
+
+impl Encryptor {
+    fn new(cipher: SymmetricCipher, key: &SharedKey, nonce: &[u8]) -> Result<Encryptor, RabeError> {
+        Ok(match cipher {
+            SymmetricCipher::Aes256Gcm => Encryptor::Aes256Gcm(EncryptorBE32::from_aead(aead(key)?, nonce.into())),
+            SymmetricCipher::ChaCha20Poly1305 => Encryptor::ChaCha20Poly1305(EncryptorBE32::from_aead(aead(key)?, nonce.into())),
+            SymmetricCipher::Aes256GcmSiv => Encryptor::Aes256GcmSiv(EncryptorBE32::from_aead(aead(key)?, nonce.into())),
+        })
+    }
+
+    fn next(&mut self, msg: &[u8], aad: &[u8]) -> Result<Vec<u8>, RabeError> {
+        match self {
+            Encryptor::Aes256Gcm(e) => e.encrypt_next(Payload { msg, aad }),
+            Encryptor::ChaCha20Poly1305(e) => e.encrypt_next(Payload { msg, aad }),
+            Encryptor::Aes256GcmSiv(e) => e.encrypt_next(Payload { msg, aad }),
+        }.map_err(|_| RabeError::SymmetricEncryption)
+    }
+
+    fn last(self, msg: &[u8], aad: &[u8]) -> Result<Vec<u8>, RabeError> {
+        match self {
+            Encryptor::Aes256Gcm(e) => e.encrypt_last(Payload { msg, aad }),
+            Encryptor::ChaCha20Poly1305(e) => e.encrypt_last(Payload { msg, aad }),
+            Encryptor::Aes256GcmSiv(e) => e.encrypt_last(Payload { msg, aad }),
+        }.map_err(|_| RabeError::SymmetricEncryption)
+    }
+}
+
+// the STREAM decryptor of one of the symmetric ciphers
+enum Decryptor {
+    Aes256Gcm(DecryptorBE32<Aes256Gcm>),
+    ChaCha20Poly1305(DecryptorBE32<ChaCha20Poly1305>),
+    Aes256GcmSiv(DecryptorBE32<Aes256GcmSiv>),
+}
+
+impl Decryptor {
+    fn new(cipher: SymmetricCipher, key: &SharedKey, nonce: &[u8]) -> Result<Decryptor, RabeError> {
+        Ok(match cipher {
+            SymmetricCipher::Aes256Gcm => Decryptor::Aes256Gcm(DecryptorBE32::from_aead(aead(key)?, nonce.into())),
+            SymmetricCipher::ChaCha20Poly1305 => Decryptor::ChaCha20Poly1305(DecryptorBE32::from_aead(aead(key)?, nonce.into())),
+            SymmetricCipher::Aes256GcmSiv => Decryptor::Aes256GcmSiv(DecryptorBE32::from_aead(aead(key)?, nonce.into())),
+        })
+    }
+
+    fn next(&mut self, msg: &[u8], aad: &[u8]) -> Result<Vec<u8>, RabeError> {
+        match self {
+            Decryptor::Aes256Gcm(d) => d.decrypt_next(Payload { msg, aad }),
+            Decryptor::ChaCha20Poly1305(d) => d.decrypt_next(Payload { msg, aad }),
+            Decryptor::Aes256GcmSiv(d) => d.decrypt_next(Payload { msg, aad }),
+        }.map_err(|_| RabeError::SymmetricDecryption)
+    }
+
+    fn last(self, msg: &[u8], aad: &[u8]) -> Result<Vec<u8>, RabeError> {
+        match self {
+            Decryptor::Aes256Gcm(d) => d.decrypt_last(Payload { msg, aad }),
+            Decryptor::ChaCha20Poly1305(d) => d.decrypt_last(Payload { msg, aad }),
+            Decryptor::Aes256GcmSiv(d) => d.decrypt_last(Payload { msg, aad }),
+        }.map_err(|_| RabeError::SymmetricDecryption)
+    }
+}
+
+// keys one of the ciphers with the STREAM key, which is independent of the AEAD key of the one-shot encryption
+fn aead<C: AeadInPlace + KeyInit>(key: &SharedKey) -> Result<C, RabeError> {
+    let mut stream_key = key.expand(STREAM_KEY, 32)?;
+    let aead = C::new_from_slice(&stream_key).map_err(|_| RabeError::InvalidKey(String::from("stream: invalid key length")));
+    stream_key.zeroize();
+    aead
+}
+
+/// A `Write` adapter that encrypts everything written to it and passes the ciphertext to `writer`.
+///
+/// [`finish()`](#method.finish) must be called after the last write, otherwise the stream is incomplete
+/// and cannot be decrypted.
+pub struct StreamEncryptor<W: Write> {
+    writer: W,
+    encryptor: Encryptor,
+    aad: Vec<u8>,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> StreamEncryptor<W> {
+    /// Creates a new encrypting stream, keyed by `key` and authenticating `aad` with every chunk, and writes the
+    /// nonce prefix to `writer`.
+    pub fn new(key: &SharedKey, cipher: SymmetricCipher, aad: &[u8], writer: W) -> Result<StreamEncryptor<W>, RabeError> {
+        StreamEncryptor::new_with_rng(key, cipher, aad, writer, &mut thread_rng())
+    }
+
+    /// Like `new()`, but draws the nonce prefix from the given random number generator `rng`.
+    pub fn new_with_rng<R: RngCore + CryptoRng>(key: &SharedKey, cipher: SymmetricCipher, aad: &[u8], mut writer: W, rng: &mut R) -> Result<StreamEncryptor<W>, RabeError> {
+        let nonce: [u8; NONCE_PREFIX_SIZE] = rng.gen();
+        let encryptor = Encryptor::new(cipher, key, &nonce)?;
+        writer.write_all(&nonce)?;
+        Ok(StreamEncryptor {
+            writer,
+            encryptor,
+            aad: aad.to_vec(),
+            buffer: Vec::with_capacity(CHUNK_SIZE + 1),
+        })
+    }
+
+    /// Encrypts the remaining data as final chunk and returns the inner writer.
+    pub fn finish(mut self) -> Result<W, RabeError> {
+        let ct = self.encryptor.last(self.buffer.as_slice(), &self.aad)?;
+        self.writer.write_all(&ct)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> Write for StreamEncryptor<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(data);
+        // a full chunk is only sealed once more data follows, since the last chunk is sealed differently
+        while self.buffer.len() > CHUNK_SIZE {
+            let rest = self.buffer.split_off(CHUNK_SIZE);
+            let ct = self.encryptor
+                .next(self.buffer.as_slice(), &self.aad)
+                .map_err(io::Error::other)?;
+            self.writer.write_all(&ct)?;
+            self.buffer = rest;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// A `Read` adapter that decrypts a stream produced by [`StreamEncryptor`] from `reader`.
+///
+/// Reading fails with `io::ErrorKind::InvalidData` if the stream was modified or truncated, or if the
+/// associated data differs from the one used during encryption.
+pub struct StreamDecryptor<R: Read> {
+    reader: R,
+    decryptor: Option<Decryptor>,
+    aad: Vec<u8>,
+    ciphertext: Vec<u8>,
+    plaintext: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> StreamDecryptor<R> {
+    /// Creates a new decrypting stream, keyed by `key` and expecting `aad` with every chunk, and reads the nonce
+    /// prefix from `reader`.
+    pub fn new(key: &SharedKey, cipher: SymmetricCipher, aad: &[u8], mut reader: R) -> Result<StreamDecryptor<R>, RabeError> {
+        let mut nonce = [0u8; NONCE_PREFIX_SIZE];
+        if reader.read_exact(&mut nonce).is_err() {
+            return Err(RabeError::SymmetricDecryption);
+        }
+        Ok(StreamDecryptor {
+            reader,
+            decryptor: Some(Decryptor::new(cipher, key, &nonce)?),
+            aad: aad.to_vec(),
+            ciphertext: Vec::with_capacity(CHUNK_SIZE + TAG_SIZE + 1),
+            plaintext: Vec::new(),
+            position: 0,
+        })
+    }
+    /// Decrypts the next chunk into the plaintext buffer. Returns `false` once the final chunk was consumed.
+    fn next_chunk(&mut self) -> io::Result<bool> {
+        if self.decryptor.is_none() {
+            return Ok(false);
+        }
+        // read one byte ahead to find out whether this is the final chunk
+        let mut eof = false;
+        while self.ciphertext.len() <= CHUNK_SIZE + TAG_SIZE {
+            let len = self.ciphertext.len();
+            self.ciphertext.resize(CHUNK_SIZE + TAG_SIZE + 1, 0);
+            let read = self.reader.read(&mut self.ciphertext[len..]);
+            match read {
+                Ok(0) => {
+                    self.ciphertext.truncate(len);
+                    eof = true;
+                    break;
+                }
+                Ok(read) => self.ciphertext.truncate(len + read),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => self.ciphertext.truncate(len),
+                Err(e) => {
+                    self.ciphertext.truncate(len);
+                    return Err(e);
+                }
+            }
+        }
+        let mut decryptor = match self.decryptor.take() {
+            Some(decryptor) => decryptor,
+            None => return Ok(false),
+        };
+        let result = if eof {
+            decryptor.last(self.ciphertext.as_slice(), &self.aad)
+        } else {
+            let rest = self.ciphertext.split_off(CHUNK_SIZE + TAG_SIZE);
+            let chunk = std::mem::replace(&mut self.ciphertext, rest);
+            let result = decryptor.next(chunk.as_slice(), &self.aad);
+            self.decryptor = Some(decryptor);
+            result
+        };
+        match result {
+            Ok(pt) => {
+                if eof {
+                    self.ciphertext.clear();
+                }
+                self.plaintext = pt;
+                self.position = 0;
+                Ok(true)
+            }
+            Err(e) => {
+                self.decryptor = None;
+                Err(io::Error::new(io::ErrorKind::InvalidData, e))
+            }
+        }
+    }
+}
+
+impl<R: Read> Read for StreamDecryptor<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.plaintext.len() {
+            if !self.next_chunk()? {
+                return Ok(0);
+            }
+        }
+        let len = buf.len().min(self.plaintext.len() - self.position);
+        buf[..len].copy_from_slice(&self.plaintext[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+/// Encrypts everything read from `reader` with `cipher`, authenticating `aad`, and writes the encrypted stream to
+/// `writer`. Returns the number of plaintext bytes.
+pub fn encrypt_stream<R: Read, W: Write>(key: &SharedKey, cipher: SymmetricCipher, aad: &[u8], reader: &mut R, writer: &mut W) -> Result<u64, RabeError> {
+    encrypt_stream_with_rng(key, cipher, aad, reader, writer, &mut thread_rng())
+}
+
+/// Like `encrypt_stream()`, but draws the nonce prefix from the given random number generator `rng`.
+pub fn encrypt_stream_with_rng<R: Read, W: Write, T: RngCore + CryptoRng>(key: &SharedKey, cipher: SymmetricCipher, aad: &[u8], reader: &mut R, writer: &mut W, rng: &mut T) -> Result<u64, RabeError> {
+    let mut encryptor = StreamEncryptor::new_with_rng(key, cipher, aad, writer, rng)?;
+    let len = io::copy(reader, &mut encryptor)?;
+    encryptor.finish()?;
+    Ok(len)
+}
+
+/// Decrypts an encrypted stream read from `reader` with `cipher` and the associated data `aad` used during
+/// encryption, and writes the plaintext to `writer`. Returns the number of plaintext bytes.
+pub fn decrypt_stream<R: Read, W: Write>(key: &SharedKey, cipher: SymmetricCipher, aad: &[u8], reader: &mut R, writer: &mut W) -> Result<u64, RabeError> {
+    let mut decryptor = StreamDecryptor::new(key, cipher, aad, reader)?;
+    Ok(io::copy(&mut decryptor, writer)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    const AAD: &[u8] = b"header digest";
+
+    fn key() -> SharedKey {
+        SharedKey::derive("7h15 15 4 v3ry 53cr37 k3ysdfsfsdfsdfdsfdsf1896957848")
+    }
+
+    fn roundtrip(cipher: SymmetricCipher, len: usize) {
+        let plaintext: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        let mut ciphertext: Vec<u8> = Vec::new();
+        assert_eq!(encrypt_stream(&key(), cipher, AAD, &mut plaintext.as_slice(), &mut ciphertext).unwrap(), len as u64);
+        let chunks = len.max(1).div_ceil(CHUNK_SIZE);
+        assert_eq!(ciphertext.len(), NONCE_PREFIX_SIZE + len + chunks * TAG_SIZE);
+        let mut decrypted: Vec<u8> = Vec::new();
+        assert_eq!(decrypt_stream(&key(), cipher, AAD, &mut ciphertext.as_slice(), &mut decrypted).unwrap(), len as u64);
+        assert_eq!(decrypted, plaintext);
+    }
+
+    #[test]
+    fn correctness() {
+        for len in [0, 1, CHUNK_SIZE - 1, CHUNK_SIZE, CHUNK_SIZE + 1, 3 * CHUNK_SIZE, 3 * CHUNK_SIZE + 17].iter() {
+            roundtrip(SymmetricCipher::Aes256Gcm, *len);
+        }
+        for cipher in [SymmetricCipher::ChaCha20Poly1305, SymmetricCipher::Aes256GcmSiv] {
+            roundtrip(cipher, CHUNK_SIZE + 1);
+        }
+    }
+
+    #[test]
+    fn small_writes() {
+        let plaintext: Vec<u8> = (0..2 * CHUNK_SIZE + 5).map(|i| (i % 251) as u8).collect();
+        let mut encryptor = StreamEncryptor::new(&key(), SymmetricCipher::Aes256Gcm, AAD, Vec::new()).unwrap();
+        for piece in plaintext.chunks(1000) {
+            encryptor.write_all(piece).unwrap();
+        }
+        let ciphertext = encryptor.finish().unwrap();
+        let mut decryptor = StreamDecryptor::new(&key(), SymmetricCipher::Aes256Gcm, AAD, ciphertext.as_slice()).unwrap();
+        let mut decrypted = Vec::new();
+        let mut buf = [0u8; 777];
+        loop {
+            match decryptor.read(&mut buf).unwrap() {
+                0 => break,
+                n => decrypted.extend_from_slice(&buf[..n]),
+            }
+        }
+        assert_eq!(decrypted, plaintext);
+    }
+
+    #[test]
+    fn seeded_rng() {
+        let plaintext = vec![7u8; CHUNK_SIZE + 3];
+        let mut ct1: Vec<u8> = Vec::new();
+        let mut ct2: Vec<u8> = Vec::new();
+        encrypt_stream_with_rng(&key(), SymmetricCipher::Aes256Gcm, AAD, &mut plaintext.as_slice(), &mut ct1, &mut ChaCha20Rng::seed_from_u64(42)).unwrap();
+        encrypt_stream_with_rng(&key(), SymmetricCipher::Aes256Gcm, AAD, &mut plaintext.as_slice(), &mut ct2, &mut ChaCha20Rng::seed_from_u64(42)).unwrap();
+        assert_eq!(ct1, ct2);
+    }
+
+    #[test]
+    fn tampering() {
+        let plaintext = vec![1u8; 2 * CHUNK_SIZE + 100];
+        let mut ciphertext: Vec<u8> = Vec::new();
+        encrypt_stream(&key(), SymmetricCipher::Aes256Gcm, AAD, &mut plaintext.as_slice(), &mut ciphertext).unwrap();
+        let mut sink: Vec<u8> = Vec::new();
+        // wrong key
+        assert!(matches!(decrypt_stream(&SharedKey::derive("wrong key"), SymmetricCipher::Aes256Gcm, AAD, &mut ciphertext.as_slice(), &mut sink), Err(RabeError::SymmetricDecryption)));
+        // body stored with a different header
+        assert!(matches!(decrypt_stream(&key(), SymmetricCipher::Aes256Gcm, b"other header digest", &mut ciphertext.as_slice(), &mut sink), Err(RabeError::SymmetricDecryption)));
+        // wrong cipher
+        assert!(decrypt_stream(&key(), SymmetricCipher::ChaCha20Poly1305, AAD, &mut ciphertext.as_slice(), &mut sink).is_err());
+        // modified byte
+        let mut modified = ciphertext.clone();
+        modified[NONCE_PREFIX_SIZE + CHUNK_SIZE + 5] ^= 1;
+        assert!(decrypt_stream(&key(), SymmetricCipher::Aes256Gcm, AAD, &mut modified.as_slice(), &mut sink).is_err());
+        // truncated after a full chunk
+        let truncated = &ciphertext[..NONCE_PREFIX_SIZE + 2 * (CHUNK_SIZE + TAG_SIZE)];
+        assert!(decrypt_stream(&key(), SymmetricCipher::Aes256Gcm, AAD, &mut &truncated[..], &mut sink).is_err());
+        // extended
+        let mut extended = ciphertext.clone();
+        extended.extend_from_slice(&[0u8; 20]);
+        assert!(decrypt_stream(&key(), SymmetricCipher::Aes256Gcm, AAD, &mut extended.as_slice(), &mut sink).is_err());
+        // missing nonce
+        assert!(decrypt_stream(&key(), SymmetricCipher::Aes256Gcm, AAD, &mut &ciphertext[..3], &mut sink).is_err());
+    }
+
+    // yields the nonce [prefix | counter 0 | last flag] of the only chunk of a stream with the prefix [9; 7]
+    struct LastChunkNonce(usize);
+
+    impl RngCore for LastChunkNonce {
+        fn next_u32(&mut self) -> u32 {
+            let byte = [9, 9, 9, 9, 9, 9, 9, 0, 0, 0, 0, 1][self.0 % 12];
+            self.0 += 1;
+            byte
+        }
+        fn next_u64(&mut self) -> u64 {
+            self.next_u32() as u64
+        }
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            dest.iter_mut().for_each(|byte| *byte = self.next_u32() as u8);
+        }
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    impl CryptoRng for LastChunkNonce {}
+
+    #[test]
+    fn separate_keys() {
+        use aes_gcm::aead::Aead;
+        let plaintext = b"a single chunk".to_vec();
+        let mut sink: Vec<u8> = Vec::new();
+        // a stream of one chunk is a one-shot ciphertext with the nonce [prefix | 0 | 1], but under the STREAM key
+        let mut stream: Vec<u8> = Vec::new();
+        encrypt_stream_with_rng(&key(), SymmetricCipher::Aes256Gcm, AAD, &mut plaintext.as_slice(), &mut stream, &mut LastChunkNonce(0)).unwrap();
+        let (prefix, chunk) = stream.split_at(NONCE_PREFIX_SIZE);
+        let nonce = [prefix, &[0, 0, 0, 0, 1]].concat();
+        let stream_aead: Aes256Gcm = aead(&key()).unwrap();
+        assert_eq!(stream_aead.decrypt(nonce.as_slice().into(), Payload { msg: chunk, aad: AAD }).unwrap(), plaintext);
+        assert!(SymmetricCipher::Aes256Gcm.decrypt(&key(), &[&nonce[..], chunk].concat(), AAD).is_err());
+        // and a one-shot ciphertext with that nonce does not decrypt as stream
+        let one_shot = SymmetricCipher::Aes256Gcm.encrypt(&key(), &plaintext, AAD, &mut LastChunkNonce(0)).unwrap();
+        assert_eq!(&one_shot[..12], &nonce[..]);
+        let as_stream = [prefix, &one_shot[12..]].concat();
+        assert!(matches!(decrypt_stream(&key(), SymmetricCipher::Aes256Gcm, AAD, &mut as_stream.as_slice(), &mut sink), Err(RabeError::SymmetricDecryption)));
+        assert_eq!(decrypt_stream(&key(), SymmetricCipher::Aes256Gcm, AAD, &mut stream.as_slice(), &mut sink).unwrap(), plaintext.len() as u64);
+    }
+}
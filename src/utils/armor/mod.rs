@@ -0,0 +1,205 @@
+//! PEM-style ASCII armor for rabe keys and ciphertexts.
+//!
+//! An armored object consists of a `-----BEGIN <label>-----` line, optional `Key: Value` headers
+//! terminated by an empty line, the Base64 encoded data wrapped at 64 columns and a matching
+//! `-----END <label>-----` line:
+//!
+//! ```text
+//! -----BEGIN RABE SECRET KEY-----
+//! Scheme: AC17CP
+//!
+//! UkFCRQEBAQIDAAAAAAAAAKOiYWuDomF...
+//! -----END RABE SECRET KEY-----
+//! ```
+//!
+//! The data of keys and ciphertexts is their [`Container`](../container/trait.Container.html) encoding.
+//!
+//! ```
+//! use rabe::schemes::ac17::*;
+//! use rabe::utils::container::Container;
+//! let (pk, _msk) = setup();
+//! let pem = pk.to_pem().unwrap();
+//! assert!(pem.starts_with("-----BEGIN RABE PUBLIC KEY-----\nScheme: AC17\n"));
+//! assert_eq!(Ac17PublicKey::from_pem(&pem).unwrap(), pk);
+//! ```
+use base64::{Engine, engine::general_purpose::STANDARD};
+use error::RabeError;
+use utils::container::Container;
+
+/// Prefix of every label
+pub const LABEL_PREFIX: &str = "RABE ";
+/// The header naming the scheme of an armored object
+pub const SCHEME_HEADER: &str = "Scheme";
+/// Number of Base64 characters per line
+pub const LINE_WIDTH: usize = 64;
+
+const BEGIN: &str = "-----BEGIN ";
+const END: &str = "-----END ";
+const DASHES: &str = "-----";
+
+/// A parsed armored block
+#[derive(Clone, PartialEq, Debug)]
+pub struct Armor {
+    pub label: String,
+    pub headers: Vec<(String, String)>,
+    pub data: Vec<u8>,
+}
+
+/// Returns the label of armored objects of a given Container type, i.e. `RABE SECRET KEY`
+pub fn label<T: Container>() -> String {
+    [LABEL_PREFIX, T::OBJECT.name()].concat()
+}
+
+/// Encodes data as an armored block.
+///
+/// # Arguments
+///
+///	* `label` - The label used in the BEGIN and END lines
+///	* `headers` - Optional `Key: Value` headers
+///	* `data` - The data to encode
+///
+pub fn encode(label: &str, headers: &[(&str, &str)], data: &[u8]) -> String {
+    let encoded = STANDARD.encode(data);
+    let mut armor = String::with_capacity(encoded.len() + encoded.len() / LINE_WIDTH + 2 * label.len() + 64);
+    armor.push_str(&[BEGIN, label, DASHES, "\n"].concat());
+    for (key, value) in headers {
+        armor.push_str(&[key, ": ", value, "\n"].concat());
+    }
+    if !headers.is_empty() {
+        armor.push('\n');
+    }
+    for line in encoded.as_bytes().chunks(LINE_WIDTH) {
+        // Base64 output is plain ASCII, so every chunk is valid UTF-8
+        armor.push_str(std::str::from_utf8(line).unwrap_or_default());
+        armor.push('\n');
+    }
+    armor.push_str(&[END, label, DASHES, "\n"].concat());
+    armor
+}
+
+/// Decodes the first armored block found in `text`. Text before the BEGIN line is ignored,
+/// line endings may be `\n` or `\r\n` and the body may be wrapped at any width.
+///
+/// # Arguments
+///
+///	* `text` - The armored text
+///
+pub fn decode(text: &str) -> Result<Armor, RabeError> {
+    let mut lines = text.lines().map(|line| line.trim());
+    let label = loop {
+        match lines.next() {
+            Some(line) if line.starts_with(BEGIN) && line.ends_with(DASHES) && line.len() >= BEGIN.len() + DASHES.len() => {
+                break line[BEGIN.len()..line.len() - DASHES.len()].to_string();
+            }
+            Some(_) => continue,
+            None => return Err(RabeError::serialization("armor: no BEGIN line found")),
+        }
+    };
+    let end = [END, &label, DASHES].concat();
+    let mut headers: Vec<(String, String)> = Vec::new();
+    let mut body = String::new();
+    let mut in_headers = true;
+    for line in lines {
+        if line == end {
+            let data = STANDARD
+                .decode(body.as_bytes())
+                .map_err(|e| RabeError::serialization_from("armor: invalid base64 data", e))?;
+            return Ok(Armor { label, headers, data });
+        }
+        if line.starts_with(END) {
+            return Err(RabeError::serialization(&format!("armor: END line does not match label {}", label)));
+        }
+        if in_headers {
+            if line.is_empty() {
+                in_headers = false;
+                continue;
+            }
+            if let Some(pos) = line.find(':') {
+                headers.push((line[..pos].trim().to_string(), line[pos + 1..].trim().to_string()));
+                continue;
+            }
+            in_headers = false;
+        }
+        body.push_str(line);
+    }
+    Err(RabeError::serialization(&format!("armor: no END line found for label {}", label)))
+}
+
+/// Armors a key or ciphertext, adding a `Scheme` header.
+pub fn to_pem<T: Container>(object: &T) -> Result<String, RabeError> {
+    let bytes = object.to_bytes()?;
+    Ok(encode(&label::<T>(), &[(SCHEME_HEADER, T::SCHEME.name())], &bytes))
+}
+
+/// Parses an armored key or ciphertext, rejecting blocks with a foreign label or scheme header.
+pub fn from_pem<T: Container>(text: &str) -> Result<T, RabeError> {
+    let armor = decode(text)?;
+    let expected = label::<T>();
+    if armor.label != expected {
+        return Err(RabeError::serialization(&format!("armor: found a {} where a {} was expected", armor.label, expected)));
+    }
+    for (key, value) in armor.headers.iter() {
+        if key.eq_ignore_ascii_case(SCHEME_HEADER) && value != T::SCHEME.name() {
+            return Err(RabeError::serialization(&format!("armor: found scheme {} where {} was expected", value, T::SCHEME.name())));
+        }
+    }
+    T::from_bytes(&armor.data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schemes::{ac17, bsw};
+
+    #[test]
+    fn encoding() {
+        let data: Vec<u8> = (0..200u8).collect();
+        let armor = encode("RABE TEST", &[("Scheme", "TEST"), ("Comment", "a: b")], &data);
+        let lines: Vec<&str> = armor.lines().collect();
+        assert_eq!(lines[0], "-----BEGIN RABE TEST-----");
+        assert_eq!(lines[1], "Scheme: TEST");
+        assert_eq!(lines[3], "");
+        assert!(lines[4..lines.len() - 1].iter().all(|l| l.len() <= LINE_WIDTH));
+        assert_eq!(lines[lines.len() - 1], "-----END RABE TEST-----");
+        let decoded = decode(&armor).unwrap();
+        assert_eq!(decoded.label, "RABE TEST");
+        assert_eq!(decoded.headers, vec![
+            (String::from("Scheme"), String::from("TEST")),
+            (String::from("Comment"), String::from("a: b"))
+        ]);
+        assert_eq!(decoded.data, data);
+        // no headers, other line endings and wrapping, leading text
+        let armor = encode("RABE TEST", &[], &data);
+        assert_eq!(decode(&armor).unwrap().data, data);
+        let rewrapped = format!(
+            "some text\r\n{}",
+            armor.replace('\n', "").replace("-----BEGIN RABE TEST-----", "-----BEGIN RABE TEST-----\r\n")
+                .replace("-----END", "\r\n-----END")
+        );
+        assert_eq!(decode(&rewrapped).unwrap().data, data);
+        assert!(decode("-----BEGIN RABE TEST-----\nAAAA\n").is_err());
+        assert!(decode("-----BEGIN RABE TEST-----\nAAAA\n-----END RABE OTHER-----\n").is_err());
+        assert!(decode("-----BEGIN RABE TEST-----\nA?AA\n-----END RABE TEST-----\n").is_err());
+        assert!(decode("AAAA").is_err());
+    }
+
+    #[test]
+    fn objects() {
+        let plaintext = String::from("dance like no one's watching, encrypt like everyone is!").into_bytes();
+        let (pk, msk) = ac17::setup();
+        let sk = ac17::cp_keygen(&msk, &["A", "B"]).unwrap();
+        let ct = ac17::cp_encrypt(&pk, (r#""A" and "B""#, ::utils::policy::pest::PolicyLanguage::HumanPolicy), &plaintext).unwrap();
+        let sk_pem = to_pem(&sk).unwrap();
+        assert!(sk_pem.starts_with("-----BEGIN RABE SECRET KEY-----\nScheme: AC17CP\n\n"));
+        // the raw data of the file excludes the Scheme header
+        assert_eq!(::utils::file::read_raw(&sk_pem).unwrap(), sk.to_bytes().unwrap());
+        let ct_pem = ct.to_pem().unwrap();
+        let sk: ac17::Ac17CpSecretKey = from_pem(&sk_pem).unwrap();
+        let ct = ac17::Ac17CpCiphertext::from_pem(&ct_pem).unwrap();
+        assert_eq!(ac17::cp_decrypt(&sk, &ct).unwrap(), plaintext);
+        // wrong label, wrong scheme
+        assert!(ac17::Ac17CpSecretKey::from_pem(&ct_pem).is_err());
+        assert!(bsw::CpAbeSecretKey::from_pem(&sk_pem).is_err());
+        assert!(ac17::Ac17CpSecretKey::from_pem(&sk_pem.replace("Scheme: AC17CP", "Scheme: BSW")).is_err());
+    }
+}
@@ -0,0 +1,421 @@
+//! Versioned, self-describing binary container for rabe keys and ciphertexts.
+//!
+//! Every object is wrapped in an envelope of the form
+//!
+//! | bytes   | content                                   |
+//! |---------|-------------------------------------------|
+//! | 4       | magic bytes `RABE`                        |
+//! | 1       | format version ([`FORMAT_VERSION`])       |
+//! | 1       | payload encoding ([`Encoding`])           |
+//! | 1       | curve id ([`CurveId`])                    |
+//! | 1       | scheme id ([`SchemeId`])                  |
+//! | 1       | object type ([`ObjectType`])              |
+//! | 8       | payload length (big endian)               |
+//! | n       | payload                                   |
+//!
+//! The payload is the CBOR (feature `serde`) or borsh (feature `borsh`) encoding of the object.
+//! `from_bytes` validates the complete envelope before decoding the payload, so that objects of a different
+//! scheme, type, encoding or format version are rejected with an error instead of being misinterpreted. The decoded
+//! payload is checked with [`Validate`], so that field elements that are not reduced and points or elements of Gt
+//! outside of their group are rejected as well.
+//!
+//! ```
+//! use rabe::schemes::ac17::*;
+//! use rabe::utils::container::Container;
+//! let (pk, _msk) = setup();
+//! let bytes = pk.to_bytes().unwrap();
+//! assert_eq!(Ac17PublicKey::from_bytes(&bytes).unwrap(), pk);
+//! assert!(Ac17MasterKey::from_bytes(&bytes).is_err());
+//! ```
+use std::convert::TryInto;
+use error::RabeError;
+use utils::armor;
+#[cfg(all(feature = "serde", not(feature = "borsh")))]
+use serde::{Serialize, de::DeserializeOwned};
+#[cfg(feature = "borsh")]
+use borsh::{BorshSerialize, BorshDeserialize};
+
+#[macro_use]
+mod validate;
+pub use self::validate::Validate;
+pub(crate) use self::validate::{Coordinate, Coordinate2, Jacobian};
+
+/// Magic bytes at the start of every container
+pub const MAGIC: [u8; 4] = *b"RABE";
+/// The current container format version
+///
+/// Version 2 hashes attributes and global identifiers to the curve with [`hash_to_g1`](crate::utils::hash::hash_to_g1)
+/// and [`hash_to_g2`](crate::utils::hash::hash_to_g2), and to scalars with [`hash_to_fr`](crate::utils::hash::hash_to_fr),
+/// all of them with length-prefixed [`HashInput`](crate::utils::hash::HashInput)s, and derives the
+/// [`SharedKey`](crate::utils::aes::SharedKey) with HKDF, bound to the scheme and the header. Ciphertext headers record
+/// the [`SymmetricCipher`](crate::utils::aes::SymmetricCipher) that encrypts the data. Keys and ciphertexts of
+/// version 1 do not work with version 2 objects and have to be generated again.
+pub const FORMAT_VERSION: u8 = 2;
+/// Length of the envelope preceding the payload
+pub const HEADER_LEN: usize = 17;
+
+/// The encoding of the container payload
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Encoding {
+    /// CBOR encoding of the serde data model
+    Cbor = 1,
+    /// Borsh encoding
+    Borsh = 2,
+}
+
+/// The pairing friendly curve the objects are defined on
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum CurveId {
+    /// The 254-bit Barreto-Naehrig curve of `rabe-bn`
+    Bn254 = 1,
+}
+
+/// The scheme an object belongs to
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SchemeId {
+    /// Keys shared by AC17 CP-ABE and KP-ABE
+    Ac17 = 1,
+    /// AC17 CP-ABE
+    Ac17Cp = 2,
+    /// AC17 KP-ABE
+    Ac17Kp = 3,
+    /// AW11 multi authority CP-ABE
+    Aw11 = 4,
+    /// BDABE multi authority CP-ABE
+    Bdabe = 5,
+    /// BSW CP-ABE
+    Bsw = 6,
+    /// GHW11 outsourced CP-ABE
+    Ghw11 = 7,
+    /// LSW KP-ABE
+    Lsw = 8,
+    /// MKE08 multi authority CP-ABE
+    Mke08 = 9,
+    /// YCT14 KP-ABE
+    Yct14 = 10,
+}
+
+/// The kind of object stored in a container
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ObjectType {
+    /// A public key, or the public key of an authority
+    PublicKey = 1,
+    /// A master key, or the master key of an authority
+    MasterKey = 2,
+    /// A user secret key
+    SecretKey = 3,
+    /// A ciphertext, i.e. a header and the symmetrically encrypted data
+    Ciphertext = 4,
+    /// An encapsulated key
+    Header = 5,
+    /// The global parameters of a multi authority scheme
+    GlobalKey = 6,
+    /// The secret key of an attribute authority
+    AuthorityKey = 7,
+    /// The public key of a single attribute
+    AttributeKey = 8,
+    /// A GHW11 transform key
+    TransformKey = 9,
+    /// A GHW11 retrieve key
+    RetrieveKey = 10,
+    /// A GHW11 partially decrypted (transformed) ciphertext
+    TransformCiphertext = 11,
+    /// The secret key of a single attribute, issued to a user
+    AttributeSecretKey = 12,
+}
+
+impl Encoding {
+    fn from_u8(value: u8) -> Result<Encoding, RabeError> {
+        match value {
+            1 => Ok(Encoding::Cbor),
+            2 => Ok(Encoding::Borsh),
+            _ => Err(RabeError::serialization(&format!("unknown container encoding {}", value))),
+        }
+    }
+}
+
+impl CurveId {
+    fn from_u8(value: u8) -> Result<CurveId, RabeError> {
+        match value {
+            1 => Ok(CurveId::Bn254),
+            _ => Err(RabeError::serialization(&format!("unknown container curve id {}", value))),
+        }
+    }
+}
+
+impl SchemeId {
+    /// The name of the scheme, as used in armor headers
+    pub fn name(&self) -> &'static str {
+        match self {
+            SchemeId::Ac17 => "AC17",
+            SchemeId::Ac17Cp => "AC17CP",
+            SchemeId::Ac17Kp => "AC17KP",
+            SchemeId::Aw11 => "AW11",
+            SchemeId::Bdabe => "BDABE",
+            SchemeId::Bsw => "BSW",
+            SchemeId::Ghw11 => "GHW11",
+            SchemeId::Lsw => "LSW",
+            SchemeId::Mke08 => "MKE08",
+            SchemeId::Yct14 => "YCT14",
+        }
+    }
+
+    fn from_u8(value: u8) -> Result<SchemeId, RabeError> {
+        match value {
+            1 => Ok(SchemeId::Ac17),
+            2 => Ok(SchemeId::Ac17Cp),
+            3 => Ok(SchemeId::Ac17Kp),
+            4 => Ok(SchemeId::Aw11),
+            5 => Ok(SchemeId::Bdabe),
+            6 => Ok(SchemeId::Bsw),
+            7 => Ok(SchemeId::Ghw11),
+            8 => Ok(SchemeId::Lsw),
+            9 => Ok(SchemeId::Mke08),
+            10 => Ok(SchemeId::Yct14),
+            _ => Err(RabeError::serialization(&format!("unknown container scheme id {}", value))),
+        }
+    }
+}
+
+impl ObjectType {
+    /// The name of the object type, as used in armor labels
+    pub fn name(&self) -> &'static str {
+        match self {
+            ObjectType::PublicKey => "PUBLIC KEY",
+            ObjectType::MasterKey => "MASTER KEY",
+            ObjectType::SecretKey => "SECRET KEY",
+            ObjectType::Ciphertext => "CIPHERTEXT",
+            ObjectType::Header => "HEADER",
+            ObjectType::GlobalKey => "GLOBAL KEY",
+            ObjectType::AuthorityKey => "AUTHORITY KEY",
+            ObjectType::AttributeKey => "ATTRIBUTE KEY",
+            ObjectType::TransformKey => "TRANSFORM KEY",
+            ObjectType::RetrieveKey => "RETRIEVE KEY",
+            ObjectType::TransformCiphertext => "TRANSFORM CIPHERTEXT",
+            ObjectType::AttributeSecretKey => "ATTRIBUTE SECRET KEY",
+        }
+    }
+
+    fn from_u8(value: u8) -> Result<ObjectType, RabeError> {
+        match value {
+            1 => Ok(ObjectType::PublicKey),
+            2 => Ok(ObjectType::MasterKey),
+            3 => Ok(ObjectType::SecretKey),
+            4 => Ok(ObjectType::Ciphertext),
+            5 => Ok(ObjectType::Header),
+            6 => Ok(ObjectType::GlobalKey),
+            7 => Ok(ObjectType::AuthorityKey),
+            8 => Ok(ObjectType::AttributeKey),
+            9 => Ok(ObjectType::TransformKey),
+            10 => Ok(ObjectType::RetrieveKey),
+            11 => Ok(ObjectType::TransformCiphertext),
+            12 => Ok(ObjectType::AttributeSecretKey),
+            _ => Err(RabeError::serialization(&format!("unknown container object type {}", value))),
+        }
+    }
+}
+
+/// The parsed envelope of a container
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct ContainerInfo {
+    pub version: u8,
+    pub encoding: Encoding,
+    pub curve: CurveId,
+    pub scheme: SchemeId,
+    pub object: ObjectType,
+}
+
+/// Parses and validates the envelope of a container without decoding its payload.
+/// Returns the ContainerInfo together with the payload.
+///
+/// # Arguments
+///
+///	* `bytes` - A container as produced by [`Container::to_bytes`]
+///
+pub fn inspect(bytes: &[u8]) -> Result<(ContainerInfo, &[u8]), RabeError> {
+    if bytes.len() < HEADER_LEN {
+        return Err(RabeError::serialization("container is too short"));
+    }
+    if bytes[0..4] != MAGIC {
+        return Err(RabeError::serialization("not a rabe container (magic bytes mismatch)"));
+    }
+    if bytes[4] != FORMAT_VERSION {
+        return Err(RabeError::serialization(&format!("unsupported container format version {}, expected {}", bytes[4], FORMAT_VERSION)));
+    }
+    let info = ContainerInfo {
+        version: bytes[4],
+        encoding: Encoding::from_u8(bytes[5])?,
+        curve: CurveId::from_u8(bytes[6])?,
+        scheme: SchemeId::from_u8(bytes[7])?,
+        object: ObjectType::from_u8(bytes[8])?,
+    };
+    let len = u64::from_be_bytes(bytes[9..HEADER_LEN].try_into()?);
+    let payload = &bytes[HEADER_LEN..];
+    if payload.len() as u64 != len {
+        return Err(RabeError::serialization("container payload length mismatch"));
+    }
+    Ok((info, payload))
+}
+
+/// Encoding of a container payload, implemented for every type that can be (de)serialized with the enabled
+/// serialization feature.
+pub trait Payload: Sized {
+    /// The encoding used for the payload
+    const ENCODING: Encoding;
+    /// Encodes the object
+    fn encode(&self) -> Result<Vec<u8>, RabeError>;
+    /// Decodes the object and validates its field elements, the complete input has to be consumed
+    fn decode(bytes: &[u8]) -> Result<Self, RabeError>;
+}
+
+#[cfg(all(feature = "serde", not(feature = "borsh")))]
+impl<T: Serialize + DeserializeOwned + Validate> Payload for T {
+    const ENCODING: Encoding = Encoding::Cbor;
+
+    fn encode(&self) -> Result<Vec<u8>, RabeError> {
+        serde_cbor::to_vec(self).map_err(|e| RabeError::serialization_from("could not encode payload", e))
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, RabeError> {
+        let object: T = decode_unchecked(bytes)?;
+        object.validate()?;
+        Ok(object)
+    }
+}
+
+/// Decodes an object without validating it, e.g. a point of the twist that is mapped to G2 afterwards
+#[cfg(all(feature = "serde", not(feature = "borsh")))]
+pub(crate) fn decode_unchecked<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, RabeError> {
+    serde_cbor::from_slice(bytes).map_err(|e| RabeError::serialization_from("could not decode payload", e))
+}
+
+#[cfg(feature = "borsh")]
+impl<T: BorshSerialize + BorshDeserialize + Validate> Payload for T {
+    const ENCODING: Encoding = Encoding::Borsh;
+
+    fn encode(&self) -> Result<Vec<u8>, RabeError> {
+        // borsh::io::Error does not implement std::error::Error without the std feature
+        borsh::to_vec(self).map_err(|e| RabeError::serialization(&format!("could not encode payload: {}", e)))
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, RabeError> {
+        let object: T = decode_unchecked(bytes)?;
+        object.validate()?;
+        Ok(object)
+    }
+}
+
+/// Decodes an object without validating it, e.g. a point of the twist that is mapped to G2 afterwards
+#[cfg(feature = "borsh")]
+pub(crate) fn decode_unchecked<T: BorshDeserialize>(bytes: &[u8]) -> Result<T, RabeError> {
+    borsh::from_slice(bytes).map_err(|e| RabeError::serialization(&format!("could not decode payload: {}", e)))
+}
+
+/// A key or ciphertext that can be stored in a self-describing container.
+///
+/// Implementors only need to name their scheme and object type, `to_bytes` and `from_bytes` are provided.
+pub trait Container: Payload {
+    /// The scheme of the object
+    const SCHEME: SchemeId;
+    /// The type of the object
+    const OBJECT: ObjectType;
+
+    /// Serializes the object into a container
+    fn to_bytes(&self) -> Result<Vec<u8>, RabeError> {
+        let payload = self.encode()?;
+        let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(FORMAT_VERSION);
+        bytes.push(Self::ENCODING as u8);
+        bytes.push(CurveId::Bn254 as u8);
+        bytes.push(Self::SCHEME as u8);
+        bytes.push(Self::OBJECT as u8);
+        bytes.extend_from_slice(&(payload.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(&payload);
+        Ok(bytes)
+    }
+
+    /// Deserializes an object from a container, rejecting containers of a different scheme,
+    /// object type, encoding, curve or format version.
+    fn from_bytes(bytes: &[u8]) -> Result<Self, RabeError> {
+        let (info, payload) = inspect(bytes)?;
+        if info.encoding != Self::ENCODING {
+            return Err(RabeError::serialization(&format!("container payload is encoded as {:?}, expected {:?}", info.encoding, Self::ENCODING)));
+        }
+        if info.scheme != Self::SCHEME || info.object != Self::OBJECT {
+            return Err(RabeError::serialization(&format!(
+                "container holds a {:?} {:?}, expected a {:?} {:?}",
+                info.scheme, info.object, Self::SCHEME, Self::OBJECT
+            )));
+        }
+        Self::decode(payload)
+    }
+
+    /// Serializes the object into an ASCII armored container, see [`armor`](../armor/index.html)
+    fn to_pem(&self) -> Result<String, RabeError> {
+        armor::to_pem(self)
+    }
+
+    /// Deserializes an object from an ASCII armored container, see [`armor`](../armor/index.html)
+    fn from_pem(text: &str) -> Result<Self, RabeError> {
+        armor::from_pem(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schemes::{ac17, bsw, lsw};
+    use utils::policy::pest::PolicyLanguage;
+
+    #[test]
+    fn roundtrip() {
+        let plaintext = String::from("dance like no one's watching, encrypt like everyone is!").into_bytes();
+        let (pk, msk) = ac17::setup();
+        let sk = ac17::cp_keygen(&msk, &["A", "B"]).unwrap();
+        let ct = ac17::cp_encrypt(&pk, (r#""A" and "B""#, PolicyLanguage::HumanPolicy), &plaintext).unwrap();
+        assert_eq!(ac17::Ac17PublicKey::from_bytes(&pk.to_bytes().unwrap()).unwrap(), pk);
+        assert_eq!(ac17::Ac17MasterKey::from_bytes(&msk.to_bytes().unwrap()).unwrap(), msk);
+        assert_eq!(ac17::Ac17CpSecretKey::from_bytes(&sk.to_bytes().unwrap()).unwrap(), sk);
+        let ct = ac17::Ac17CpCiphertext::from_bytes(&ct.to_bytes().unwrap()).unwrap();
+        assert_eq!(ac17::cp_decrypt(&sk, &ct).unwrap(), plaintext);
+        let (info, _) = inspect(&ct.to_bytes().unwrap()).unwrap();
+        assert_eq!(info.version, FORMAT_VERSION);
+        assert_eq!(info.curve, CurveId::Bn254);
+        assert_eq!(info.scheme, SchemeId::Ac17Cp);
+        assert_eq!(info.object, ObjectType::Ciphertext);
+    }
+
+    #[test]
+    fn rejects_mismatch() {
+        let plaintext = String::from("dance like no one's watching, encrypt like everyone is!").into_bytes();
+        let (pk, msk) = bsw::setup();
+        let ct = bsw::encrypt(&pk, (r#""A" and "B""#, PolicyLanguage::HumanPolicy), &plaintext).unwrap();
+        let bytes = ct.to_bytes().unwrap();
+        // wrong object type of the same scheme
+        assert!(bsw::CpAbeSecretKey::from_bytes(&bytes).is_err());
+        assert!(bsw::CpAbeMasterKey::from_bytes(&pk.to_bytes().unwrap()).is_err());
+        // wrong scheme
+        assert!(lsw::KpAbeCiphertext::from_bytes(&bytes).is_err());
+        assert!(lsw::KpAbeMasterKey::from_bytes(&msk.to_bytes().unwrap()).is_err());
+        // corrupted envelope
+        let mut magic = bytes.clone();
+        magic[0] = b'X';
+        assert!(bsw::CpAbeCiphertext::from_bytes(&magic).is_err());
+        let mut version = bytes.clone();
+        version[4] = FORMAT_VERSION + 1;
+        assert!(bsw::CpAbeCiphertext::from_bytes(&version).is_err());
+        let mut encoding = bytes.clone();
+        encoding[5] = 0xff;
+        assert!(bsw::CpAbeCiphertext::from_bytes(&encoding).is_err());
+        let mut curve = bytes.clone();
+        curve[6] = 0xff;
+        assert!(bsw::CpAbeCiphertext::from_bytes(&curve).is_err());
+        assert!(bsw::CpAbeCiphertext::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(bsw::CpAbeCiphertext::from_bytes(&bytes[..HEADER_LEN - 1]).is_err());
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(bsw::CpAbeCiphertext::from_bytes(&trailing).is_err());
+        assert!(bsw::CpAbeCiphertext::from_bytes(&bytes).is_ok());
+    }
+}
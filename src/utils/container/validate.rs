@@ -0,0 +1,267 @@
+//! Validation of decoded keys and ciphertexts.
+//!
+//! rabe-bn decodes field elements without checking that they are reduced, i.e. smaller than the modulus. Its
+//! arithmetic debug-asserts this, so an unreduced element of an attacker-supplied key or ciphertext panics in debug
+//! builds and silently computes with a wrong value in release builds. It does not check points either, so a point
+//! that is not on the curve, a point of the twist outside of G2 or an element of Fq12 outside of Gt decodes as well
+//! and breaks the security of the schemes (small subgroup and invalid curve attacks). [`Validate`] checks every
+//! element of a decoded object, and [`Payload::decode`](super::Payload::decode) rejects invalid elements with an
+//! error.
+use rabe_bn::{Fr, G1, G2, Gt, Group, arith::U256};
+use utils::aes::SymmetricCipher;
+use utils::container::Payload;
+use utils::hash::{on_curve_g1, on_twist, MODULUS};
+use utils::policy::pest::PolicyLanguage;
+use error::RabeError;
+#[cfg(feature = "borsh")]
+use borsh::{BorshSerialize, BorshDeserialize};
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+/// r, the order of the groups and the modulus of Fr
+const ORDER: [u64; 4] = [0x43e1f593f0000001, 0x2833e84879b97091, 0xb85045b68181585d, 0x30644e72e131a029];
+
+/// An object whose elements can be checked after decoding
+pub trait Validate {
+    /// Returns an error if a field element of the object is not reduced, or a point or element of Gt is not in its
+    /// group
+    fn validate(&self) -> Result<(), RabeError>;
+}
+
+/// Implements [`Validate`] for a struct by validating all of its fields. The struct is destructured, so a field
+/// that is missing from the list does not compile.
+macro_rules! validate_fields {
+    ($name:ident { $($field:ident),* }) => {
+        impl ::utils::container::Validate for $name {
+            fn validate(&self) -> Result<(), ::error::RabeError> {
+                let $name { $($field),* } = self;
+                $( ::utils::container::Validate::validate($field)?; )*
+                Ok(())
+            }
+        }
+    };
+}
+
+// rabe-bn does not expose the coordinates of its points. These types have the same encoding as the field elements
+// and jacobian points of rabe-bn, in montgomery form with little endian 64 bit limbs.
+
+/// An element of Fq
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct Coordinate(pub(crate) U256);
+
+/// An element of Fq2
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct Coordinate2 {
+    pub(crate) c0: Coordinate,
+    pub(crate) c1: Coordinate,
+}
+
+/// An element of Fq6
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct Coordinate6 {
+    c0: Coordinate2,
+    c1: Coordinate2,
+    c2: Coordinate2,
+}
+
+/// An element of Fq12, i.e. of Gt
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct Coordinate12 {
+    c0: Coordinate6,
+    c1: Coordinate6,
+}
+
+/// A point of G1 (with Coordinate) or G2 (with Coordinate2) in jacobian coordinates
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct Jacobian<C> {
+    pub(crate) x: C,
+    pub(crate) y: C,
+    pub(crate) z: C,
+}
+
+/// An element of Fr
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct Scalar(U256);
+
+impl Validate for Coordinate {
+    fn validate(&self) -> Result<(), RabeError> {
+        match self.0 < MODULUS {
+            true => Ok(()),
+            false => Err(RabeError::serialization("element of Fq is not reduced")),
+        }
+    }
+}
+
+validate_fields!(Coordinate2 { c0, c1 });
+validate_fields!(Coordinate6 { c0, c1, c2 });
+validate_fields!(Coordinate12 { c0, c1 });
+
+impl<C: Validate> Validate for Jacobian<C> {
+    fn validate(&self) -> Result<(), RabeError> {
+        self.x.validate()?;
+        self.y.validate()?;
+        self.z.validate()
+    }
+}
+
+impl Validate for Scalar {
+    fn validate(&self) -> Result<(), RabeError> {
+        match (self.0) < U256(ORDER) {
+            true => Ok(()),
+            false => Err(RabeError::serialization("element of Fr is not reduced")),
+        }
+    }
+}
+
+// decoding the encoding of an element as its mirror type validates the mirror
+impl Validate for Fr {
+    fn validate(&self) -> Result<(), RabeError> {
+        Scalar::decode(&self.encode()?).map(|_| ())
+    }
+}
+
+// G1 is the whole curve, so a point of G1 only has to be on the curve
+impl Validate for G1 {
+    fn validate(&self) -> Result<(), RabeError> {
+        match on_curve_g1(&Jacobian::<Coordinate>::decode(&self.encode()?)?) {
+            true => Ok(()),
+            false => Err(RabeError::serialization("point is not on the curve")),
+        }
+    }
+}
+
+// G2 is the subgroup of order r of the twist. Fr cannot hold r, so p * r = 0 is checked as p * (r - 1) + p = 0.
+impl Validate for G2 {
+    fn validate(&self) -> Result<(), RabeError> {
+        if !on_twist(&Jacobian::<Coordinate2>::decode(&self.encode()?)?) {
+            return Err(RabeError::serialization("point is not on the twist"));
+        }
+        match (*self * -Fr::one() + *self).is_zero() {
+            true => Ok(()),
+            false => Err(RabeError::serialization("point is not in the subgroup of order r")),
+        }
+    }
+}
+
+// Gt is the subgroup of order r of the multiplicative group of Fq12, checked like G2
+impl Validate for Gt {
+    fn validate(&self) -> Result<(), RabeError> {
+        Coordinate12::decode(&self.encode()?)?;
+        match self.pow(-Fr::one()) * *self == Gt::one() {
+            true => Ok(()),
+            false => Err(RabeError::serialization("element of Fq12 is not in Gt")),
+        }
+    }
+}
+
+// types without field elements
+macro_rules! validate_nothing {
+    ($($name:ty),*) => {
+        $(
+            impl Validate for $name {
+                fn validate(&self) -> Result<(), RabeError> {
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+validate_nothing!(String, u8, bool, PolicyLanguage, SymmetricCipher);
+
+impl<T: Validate> Validate for Vec<T> {
+    fn validate(&self) -> Result<(), RabeError> {
+        self.iter().try_for_each(Validate::validate)
+    }
+}
+
+impl<T: Validate, const N: usize> Validate for [T; N] {
+    fn validate(&self) -> Result<(), RabeError> {
+        self.iter().try_for_each(Validate::validate)
+    }
+}
+
+impl<T: Validate> Validate for Option<T> {
+    fn validate(&self) -> Result<(), RabeError> {
+        self.as_ref().map_or(Ok(()), Validate::validate)
+    }
+}
+
+// tuples, as stored by the schemes
+macro_rules! validate_tuple {
+    ($($element:ident),*) => {
+        impl<$($element: Validate),*> Validate for ($($element,)*) {
+            #[allow(non_snake_case)]
+            fn validate(&self) -> Result<(), RabeError> {
+                let ($($element,)*) = self;
+                $( $element.validate()?; )*
+                Ok(())
+            }
+        }
+    };
+}
+
+validate_tuple!(A, B);
+validate_tuple!(A, B, C);
+validate_tuple!(A, B, C, D);
+validate_tuple!(A, B, C, D, E, F);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rabe_bn::{pairing, Group};
+    use schemes::bsw;
+    use utils::container::{Container, decode_unchecked};
+
+    fn point(x: [u64; 4]) -> Jacobian<Coordinate> {
+        Jacobian { x: Coordinate(U256(x)), y: Coordinate(U256([1, 0, 0, 0])), z: Coordinate(U256([1, 0, 0, 0])) }
+    }
+
+    #[test]
+    fn unreduced_elements() {
+        let [q0, q1, q2, q3] = MODULUS.0;
+        assert!(Coordinate(U256([q0 - 1, q1, q2, q3])).validate().is_ok());
+        for limbs in [MODULUS.0, [u64::MAX; 4]] {
+            assert!(Coordinate(U256(limbs)).validate().is_err());
+            assert!(matches!(G1::decode(&point(limbs).encode().unwrap()), Err(RabeError::Serialization { .. })));
+        }
+        assert!(Fr::decode(&Scalar(U256([ORDER[0] - 1, ORDER[1], ORDER[2], ORDER[3]])).encode().unwrap()).is_ok());
+        assert!(Fr::decode(&Scalar(U256(ORDER)).encode().unwrap()).is_err());
+        // an unreduced element anywhere in a key is rejected by the container
+        let (mut pk, _msk) = bsw::setup();
+        assert!(bsw::CpAbePublicKey::from_bytes(&pk.to_bytes().unwrap()).is_ok());
+        pk.h = decode_unchecked(&point(MODULUS.0).encode().unwrap()).unwrap();
+        assert!(matches!(bsw::CpAbePublicKey::from_bytes(&pk.to_bytes().unwrap()), Err(RabeError::Serialization { .. })));
+    }
+
+    #[test]
+    fn elements_outside_of_groups() {
+        let g1 = G1::one() * Fr::from_str("5").unwrap();
+        let g2 = G2::one() * Fr::from_str("7").unwrap();
+        let gt = pairing(g1, g2);
+        for bytes in [g1.encode().unwrap(), G1::zero().encode().unwrap()] {
+            assert!(G1::decode(&bytes).is_ok());
+        }
+        for bytes in [g2.encode().unwrap(), G2::zero().encode().unwrap()] {
+            assert!(G2::decode(&bytes).is_ok());
+        }
+        assert!(Gt::decode(&gt.encode().unwrap()).is_ok());
+        // a changed coordinate moves the point off the curve
+        let mut point = Jacobian::<Coordinate>::decode(&g1.encode().unwrap()).unwrap();
+        (point.y.0).0[0] ^= 1;
+        assert!(matches!(G1::decode(&point.encode().unwrap()), Err(RabeError::Serialization { .. })));
+        let mut point = Jacobian::<Coordinate2>::decode(&g2.encode().unwrap()).unwrap();
+        (point.x.c1.0).0[0] ^= 1;
+        assert!(matches!(G2::decode(&point.encode().unwrap()), Err(RabeError::Serialization { .. })));
+        // elements of Fq12 other than one that lie in Fq have an order coprime to r
+        let mut element = Coordinate12::decode(&Gt::one().encode().unwrap()).unwrap();
+        (element.c0.c0.c0.0).0[0] ^= 1;
+        assert!(matches!(Gt::decode(&element.encode().unwrap()), Err(RabeError::Serialization { .. })));
+    }
+}
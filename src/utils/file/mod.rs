@@ -1,99 +1,48 @@
 use std::path::Path;
 use std::fs::File;
 use std::io::{Read, Write};
+use error::RabeError;
+use utils::armor;
 
 pub fn read_file(
     path: &Path
-) -> String {
-    let display = path.display();
-    let mut file = match File::open(path) {
-        Err(why) => {
-            panic!(
-                "sorry, couldn't open {}: {}",
-                path.display(),
-                why.to_string()
-            )
-        }
-        Ok(file) => file,
-    };
+) -> Result<String, RabeError> {
+    let mut file = File::open(path)?;
     let mut s = String::new();
-    match file.read_to_string(&mut s) {
-        Err(why) => panic!("sorry, couldn't read {}: {}", display, why.to_string()),
-        Ok(_) => {},
-    }
-    return s;
+    file.read_to_string(&mut s)?;
+    return Ok(s);
 }
 
 pub fn read_to_vec(
     path: &Path
-) -> Vec<u8> {
+) -> Result<Vec<u8>, RabeError> {
     let mut data: Vec<u8> = Vec::new();
-    let display = path.display();
-    let mut file = match File::open(path) {
-        Err(why) => panic!("sorry, couldn't open {}: {}", display, why.to_string()),
-        Ok(file) => file,
-    };
-    match file.read_to_end(&mut data) {
-        Ok(bytes) => {
-            println!("parsed {:?} bytes", bytes);
-            return data;
-        }
-        Err(e) => {
-            println!("error parsing: {:?}", e);
-            return Vec::new();
-        }
-    }
-
+    let mut file = File::open(path)?;
+    file.read_to_end(&mut data)?;
+    return Ok(data);
 }
 
 pub fn write_from_vec(
     path: &Path,
     data: &Vec<u8>
-) {
-    let display = path.display();
-    let mut file = match File::open(path) {
-        Err(why) => panic!("sorry, couldn't open {}: {}", display, why.to_string()),
-        Ok(file) => file,
-    };
-    match file.write_all(data) {
-        Err(why) => {
-            panic!(
-                "sorry, couldn't write to {}: {}",
-                display,
-                why.to_string()
-            )
-        }
-        Ok(_) => println!("successfully wrote to {}", display),
-    }
+) -> Result<(), RabeError> {
+    let mut file = File::create(path)?;
+    file.write_all(data)?;
+    return Ok(());
 }
-
+/// Returns the decoded data of an armored file, without its label and headers.
+/// See [`armor::decode`](../armor/fn.decode.html) for the label and headers.
 pub fn read_raw(
-    raw: &String
-) -> String {
-    let lines = &mut raw.lines();
-    let middle = lines.nth(1).unwrap().to_string();
-    return middle;
+    raw: &str
+) -> Result<Vec<u8>, RabeError> {
+    return Ok(armor::decode(raw)?.data);
 }
 
 pub fn write_file(
     path: &Path,
     content: String
-) -> bool {
-    let display = path.display();
-    let mut file = match File::create(path) {
-        Err(why) => panic!("couldn't create {}: {}", display, why.to_string()),
-        Ok(file) => file,
-    };
-    let mut _ret: bool = false;
-    match file.write_all(content.as_bytes()) {
-        Err(why) => {
-            _ret = false;
-            panic!("couldn't write to {}: {}", display, why.to_string());
-        }
-        Ok(_) => {
-            _ret = true;
-            println!("successfully wrote to {}", display);
-        }
-    }
-    return _ret;
-}
\ No newline at end of file
+) -> Result<(), RabeError> {
+    let mut file = File::create(path)?;
+    file.write_all(content.as_bytes())?;
+    return Ok(());
+}
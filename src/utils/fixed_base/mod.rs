@@ -0,0 +1,171 @@
+use rabe_bn::{Fr, Group, Gt, G1, G2};
+
+/// The number of bits of a scalar that are handled by one lookup in a [FixedBase] table
+const WINDOW: usize = 4;
+/// The number of windows of a 256 bit scalar
+const WINDOWS: usize = 256 / WINDOW;
+
+/// An element of [`G1`], [`G2`] or [`Gt`] that can be used as fixed base
+pub trait FixedBaseElement: Copy {
+    /// The neutral element
+    fn identity() -> Self;
+    /// The group operation, i.e. the addition in [`G1`] and [`G2`] and the multiplication in [`Gt`]
+    fn combine(self, other: Self) -> Self;
+    /// The scalar multiplication in [`G1`] and [`G2`] and the exponentiation in [`Gt`]
+    fn exp(self, x: Fr) -> Self;
+    /// The unique representation of the element, i.e. affine coordinates in [`G1`] and [`G2`]
+    fn normalized(self) -> Self;
+}
+
+impl FixedBaseElement for G1 {
+    fn identity() -> Self {
+        G1::zero()
+    }
+    fn combine(self, other: Self) -> Self {
+        self + other
+    }
+    fn exp(self, x: Fr) -> Self {
+        self * x
+    }
+    fn normalized(mut self) -> Self {
+        self.normalize();
+        self
+    }
+}
+
+impl FixedBaseElement for G2 {
+    fn identity() -> Self {
+        G2::zero()
+    }
+    fn combine(self, other: Self) -> Self {
+        self + other
+    }
+    fn exp(self, x: Fr) -> Self {
+        self * x
+    }
+    fn normalized(mut self) -> Self {
+        self.normalize();
+        self
+    }
+}
+
+impl FixedBaseElement for Gt {
+    fn identity() -> Self {
+        Gt::one()
+    }
+    fn combine(self, other: Self) -> Self {
+        self * other
+    }
+    fn exp(self, x: Fr) -> Self {
+        self.pow(x)
+    }
+    fn normalized(self) -> Self {
+        self
+    }
+}
+
+/// A base that is multiplied (or exponentiated) with many scalars, e.g. a generator of a public key.
+///
+/// A prepared base holds a table of all multiples d * 2^(4 * i) * base of the 4 bit digits d, so that a multiplication
+/// takes 64 group operations instead of 256 doublings and about 128 additions. A table takes 1024 elements, i.e.
+/// about 100KB for [`G1`], 200KB for [`G2`] and 400KB for [`Gt`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct FixedBase<T: FixedBaseElement> {
+    base: T,
+    table: Vec<Vec<T>>,
+    // multiplying a scalar with the inverse montgomery factor yields its canonical bytes, see digits()
+    r_inv: Fr,
+}
+
+impl<T: FixedBaseElement> FixedBase<T> {
+    /// Returns a base without table, which multiplies as usual
+    pub fn new(base: T) -> FixedBase<T> {
+        FixedBase { base, table: Vec::new(), r_inv: Fr::one() }
+    }
+
+    /// Returns a base with a precomputed table
+    pub fn prepare(base: T) -> FixedBase<T> {
+        let mut table: Vec<Vec<T>> = Vec::with_capacity(WINDOWS);
+        let mut window_base = base;
+        for _ in 0..WINDOWS {
+            let mut row: Vec<T> = Vec::with_capacity(1 << WINDOW);
+            let mut multiple = T::identity();
+            for _ in 0..(1 << WINDOW) {
+                row.push(multiple);
+                multiple = multiple.combine(window_base);
+            }
+            // 2^WINDOW * window_base
+            window_base = multiple;
+            table.push(row);
+        }
+        // the montgomery factor R = 2^256 mod r
+        let mut r = Fr::one();
+        for _ in 0..256 {
+            r = r + r;
+        }
+        FixedBase { base, table, r_inv: r.inverse().unwrap_or_else(Fr::one) }
+    }
+
+    /// Returns true if the base has a precomputed table
+    pub fn is_prepared(&self) -> bool {
+        !self.table.is_empty()
+    }
+
+    /// Returns the base
+    pub fn base(&self) -> T {
+        self.base
+    }
+
+    /// Returns base * x in [`G1`] and [`G2`], and base^x in [`Gt`]. The result is normalized, so that prepared and
+    /// unprepared bases yield the same encoding, which the shared keys of the schemes are bound to.
+    pub fn exp(&self, x: Fr) -> T {
+        if !self.is_prepared() {
+            return self.base.exp(x).normalized();
+        }
+        self.digits(x)
+            .into_iter()
+            .zip(self.table.iter())
+            .filter(|(digit, _)| *digit != 0)
+            .fold(T::identity(), |product, (digit, row)| product.combine(row[digit]))
+            .normalized()
+    }
+
+    // the 4 bit digits of the canonical value of x, least significant first. rabe-bn only exposes the montgomery form
+    // x * R of a scalar, which is why (x * R^-1) * R = x is serialized. The limbs are serialized least significant
+    // first, each limb in big endian byte order.
+    fn digits(&self, x: Fr) -> Vec<usize> {
+        let bytes = (x * self.r_inv).into_bytes();
+        let mut digits: Vec<usize> = Vec::with_capacity(WINDOWS);
+        for limb in bytes.chunks(8) {
+            for byte in limb.iter().rev() {
+                digits.push((byte & 0x0f) as usize);
+                digits.push((byte >> 4) as usize);
+            }
+        }
+        digits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn test_fixed_base() {
+        let mut rng = rand::thread_rng();
+        let (g1, g2, gt): (G1, G2, Gt) = (rng.gen(), rng.gen(), rng.gen());
+        let (p1, p2, pt) = (FixedBase::prepare(g1), FixedBase::prepare(g2), FixedBase::prepare(gt));
+        assert!(p1.is_prepared() && !FixedBase::new(g1).is_prepared());
+        let mut scalars: Vec<Fr> = vec![Fr::zero(), Fr::one(), -Fr::one(), Fr::from_str("16").unwrap()];
+        scalars.extend((0..8).map(|_| rng.gen::<Fr>()));
+        for x in scalars {
+            assert!(p1.exp(x) == g1 * x);
+            assert!(p2.exp(x) == g2 * x);
+            assert!(pt.exp(x) == gt.pow(x));
+            assert!(FixedBase::new(g1).exp(x) == g1 * x);
+            assert_eq!(p2.exp(x).into_bytes(), FixedBase::new(g2).exp(x).into_bytes());
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! The inputs of [hash_to_g1](super::hash_to_g1), [hash_to_g2](super::hash_to_g2) and [hash_to_fr](super::hash_to_fr).
+//!
+//! An input consists of a label, which names the purpose of the hash (e.g. "attribute" or "column"), followed by
+//! strings and indices. Every part is tagged with its kind and strings are prefixed with their length, so that two
+//! different inputs never have the same encoding: attribute "A1" with index 0 differs from attribute "A" with index 10,
+//! and an attribute named "01" differs from the column 1.
+
+// the tags of the parts
+const LABEL: u8 = 0x4c;
+const STRING: u8 = 0x53;
+const INDEX: u8 = 0x49;
+
+/// A structured hash input, whose encoding is unambiguous
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct HashInput {
+    bytes: Vec<u8>,
+}
+
+impl HashInput {
+    /// Starts an input with the given label
+    pub fn new(label: &str) -> HashInput {
+        HashInput { bytes: Vec::new() }.part(LABEL, label.as_bytes())
+    }
+
+    /// Appends a string, e.g. an attribute
+    pub fn string(self, value: &str) -> HashInput {
+        self.part(STRING, value.as_bytes())
+    }
+
+    /// Appends an index, e.g. a row or column of a matrix
+    pub fn index(mut self, value: usize) -> HashInput {
+        self.bytes.push(INDEX);
+        self.bytes.extend_from_slice(&(value as u64).to_be_bytes());
+        self
+    }
+
+    /// Returns the encoded input
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    // the tag, the length as 8 bytes big endian and the value
+    fn part(mut self, tag: u8, value: &[u8]) -> HashInput {
+        self.bytes.push(tag);
+        self.bytes.extend_from_slice(&(value.len() as u64).to_be_bytes());
+        self.bytes.extend_from_slice(value);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_hash_input() {
+        // the collisions of the former string concatenation, attribute + l + t and "0" + column + l + t
+        assert_ne!(
+            HashInput::new("attribute").string("A1").index(0).index(0),
+            HashInput::new("attribute").string("A").index(10).index(0)
+        );
+        assert_ne!(
+            HashInput::new("attribute").string("A").index(1).index(10),
+            HashInput::new("attribute").string("A").index(11).index(0)
+        );
+        assert_ne!(
+            HashInput::new("attribute").string("01").index(0).index(1),
+            HashInput::new("column").index(1).index(0).index(1)
+        );
+        // strings and indices, labels and strings, and the split of strings
+        assert_ne!(HashInput::new("attribute").string("1"), HashInput::new("attribute").index(1));
+        assert_ne!(HashInput::new("attribute").string(""), HashInput::new("attributeS"));
+        assert_ne!(
+            HashInput::new("gid").string("ab").string("c"),
+            HashInput::new("gid").string("a").string("bc")
+        );
+        assert_eq!(
+            HashInput::new("gid").string("alice").as_bytes(),
+            [&[LABEL][..], &3u64.to_be_bytes(), b"gid", &[STRING], &5u64.to_be_bytes(), b"alice"].concat().as_slice()
+        );
+    }
+}
@@ -1,12 +1,26 @@
-use rabe_bn::Fr;
+use rabe_bn::{Fr, G1, G2};
 use sha3::{
     Digest,
     Sha3_256
 };
 use crate::error::RabeError;
+use utils::container::SchemeId;
 use std::ops::Mul;
 
+mod input;
+mod to_curve;
+pub use self::input::HashInput;
+pub use self::to_curve::{expand_message_xmd, hash_to_curve_g1, hash_to_curve_g2, SUITE_G1, SUITE_G2};
+pub(crate) use self::to_curve::{on_curve_g1, on_twist, MODULUS};
+
+/// The suite of [hash_to_fr], hash_to_field of RFC 9380 with 512 bits reduced modulo the group order
+const SUITE_FR: &str = "BN254FR_XMD:SHA3-256_";
+
 /// Hash to a &String to [`rabe-bn::G1`] or [`rabe-bn::G2`] using Base g
+///
+/// The result is a known multiple of `g`, which is not a random oracle into the group. The schemes use
+/// [hash_to_g1] and [hash_to_g2] since container format version 2.
+#[deprecated(note = "the discrete logarithm of the result is known, use hash_to_g1() or hash_to_g2()")]
 pub fn sha3_hash<T: Mul<Fr, Output = T>>(
     g: T,
     data: &str
@@ -20,6 +34,9 @@ pub fn sha3_hash<T: Mul<Fr, Output = T>>(
 }
 
 /// Hash to a &String to [`rabe-bn::Fr`]
+///
+/// The input is not domain separated. The schemes use [hash_to_fr] since container format version 2.
+#[deprecated(note = "the input is not domain separated, use hash_to_fr()")]
 pub fn sha3_hash_fr(
     data: &str
 ) -> Result<Fr, RabeError> {
@@ -29,4 +46,58 @@ pub fn sha3_hash_fr(
         Ok(fr) => Ok(fr),
         Err(e) => Err(e.into())
     }
-}
\ No newline at end of file
+}
+
+// the domain separation tag of a scheme, the version is the container format version that introduced it
+fn domain(scheme: SchemeId, suite: &str) -> String {
+    format!("RABE-V02-{}-with-{}", scheme.name(), suite)
+}
+
+/// Hash a [HashInput] to [`rabe-bn::G1`], separated from the hashes of all other schemes
+pub fn hash_to_g1(
+    scheme: SchemeId,
+    data: &HashInput
+) -> Result<G1, RabeError> {
+    hash_to_curve_g1(data.as_bytes(), domain(scheme, SUITE_G1).as_bytes())
+}
+
+/// Hash a [HashInput] to [`rabe-bn::G2`], separated from the hashes of all other schemes
+pub fn hash_to_g2(
+    scheme: SchemeId,
+    data: &HashInput
+) -> Result<G2, RabeError> {
+    hash_to_curve_g2(data.as_bytes(), domain(scheme, SUITE_G2).as_bytes())
+}
+
+/// Hash a [HashInput] to [`rabe-bn::Fr`], separated from the hashes of all other schemes
+pub fn hash_to_fr(
+    scheme: SchemeId,
+    data: &HashInput
+) -> Result<Fr, RabeError> {
+    let bytes = expand_message_xmd(data.as_bytes(), domain(scheme, SUITE_FR).as_bytes(), 64)?;
+    let mut wide = [0u8; 64];
+    wide.copy_from_slice(&bytes);
+    Ok(Fr::interpret(&wide))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_scheme_hashes() {
+        // ac17 hashed attribute "01" with l and t to the same point as the first column before
+        let attribute = HashInput::new("attribute").string("01").index(0).index(1);
+        let column = HashInput::new("column").index(1).index(0).index(1);
+        assert!(hash_to_g1(SchemeId::Ac17, &attribute).unwrap() != hash_to_g1(SchemeId::Ac17, &column).unwrap());
+        // the same input yields the same values within and different values across schemes
+        let input = HashInput::new("attribute").string("A");
+        assert!(hash_to_g1(SchemeId::Lsw, &input).unwrap() == hash_to_g1(SchemeId::Lsw, &input).unwrap());
+        assert!(hash_to_g1(SchemeId::Lsw, &input).unwrap() != hash_to_g1(SchemeId::Ghw11, &input).unwrap());
+        assert!(hash_to_g2(SchemeId::Bsw, &input).unwrap() != hash_to_g2(SchemeId::Ac17, &input).unwrap());
+        assert_eq!(hash_to_fr(SchemeId::Mke08, &input).unwrap(), hash_to_fr(SchemeId::Mke08, &input).unwrap());
+        assert!(hash_to_fr(SchemeId::Mke08, &input).unwrap() != hash_to_fr(SchemeId::Bdabe, &input).unwrap());
+        assert!(hash_to_fr(SchemeId::Mke08, &input).unwrap() != hash_to_fr(SchemeId::Mke08, &HashInput::new("authority").string("A")).unwrap());
+    }
+}
@@ -0,0 +1,568 @@
+//! Hashing to [`G1`] and [`G2`] with the constructions of RFC 9380, "Hashing to Elliptic Curves".
+//!
+//! A message is expanded with expand_message_xmd to two field elements, each is mapped to the curve with the
+//! Shallue-van de Woestijne map and the sum of both points is returned. The sum is uniformly distributed and nobody
+//! knows its discrete logarithm, as required by schemes that model the hash as random oracle. [`G2`] points are
+//! multiplied with the cofactor of the twist to land in the subgroup of prime order, the cofactor of [`G1`] is one.
+//!
+//! The suites `BN254G1_XMD:SHA3-256_SVDW_RO_` and `BN254G2_XMD:SHA3-256_SVDW_RO_` are not interoperable: RFC 9380
+//! defines no suite for BN254, these suites use SHA3-256 where the RFC uses SHA-2, and the schemes hash with domain
+//! separation tags of their own. The only known answer values are the ones of tools/to_curve_vectors.py.
+//!
+//! rabe-bn does not expose its field elements, so Fq and Fq2 are built on the montgomery arithmetic of
+//! [`rabe_bn::arith::U256`], which rabe-bn uses for its own field elements. Like rabe-bn, it does not run in constant
+//! time. Only public values pass through it, the hashed attribute names and identities and the coordinates of decoded
+//! points.
+use rabe_bn::{Group, G1, G2, arith::{U256, U512}};
+use sha3::{Digest, Sha3_256};
+use std::ops::{Add, Mul, Neg, Sub};
+use std::sync::OnceLock;
+use utils::container::{decode_unchecked, Coordinate, Coordinate2, Jacobian, Payload};
+use crate::error::RabeError;
+
+/// The suite of hashing to [`G1`]
+pub const SUITE_G1: &str = "BN254G1_XMD:SHA3-256_SVDW_RO_";
+/// The suite of hashing to [`G2`]
+pub const SUITE_G2: &str = "BN254G2_XMD:SHA3-256_SVDW_RO_";
+// the output and block size of SHA3-256 in bytes
+const HASH_LEN: usize = 32;
+const BLOCK_LEN: usize = 136;
+// bytes per field element: ceil((ceil(log2(q)) + k) / 8) with the security parameter k = 128
+const ELEMENT_LEN: usize = 48;
+/// q, the modulus of Fq
+pub(crate) const MODULUS: U256 = U256([0x3c208c16d87cfd47, 0x97816a916871ca8d, 0xb85045b68181585d, 0x30644e72e131a029]);
+// -q^-1 mod 2^64, the montgomery constant of q
+const INV: u64 = montgomery_inv(MODULUS.0[0]);
+// (q - 1) / 2 and (q + 1) / 4, the exponents of the legendre symbol and of the square root, as q = 3 mod 4
+const Q_MINUS_1_HALF: U256 = U256([0x9e10460b6c3e7ea3, 0xcbc0b548b438e546, 0xdc2822db40c0ac2e, 0x183227397098d014]);
+const Q_PLUS_1_QUARTER: U256 = U256([0x4f082305b61f3f52, 0x65e05aa45a1c72a3, 0x6e14116da0605617, 0x0c19139cb84c680a]);
+// 2q - r, the cofactor of the twist
+const COFACTOR_G2: U256 = U256([0x345f2299c0f9fa8d, 0x06ceecda572a2489, 0xb85045b68181585e, 0x30644e72e131a029]);
+// the constants c3 = sqrt(-g(z) * 3z^2) of the maps to G1 and G2 with sgn0(c3) = 0, checked by test_svdw_constants
+const C3_G1: U256 = U256([0x5d8d1cc5dffffffa, 0x53c98fc6b36d713d, 0x6789af3a83522eb3, 0x0000000000000001]);
+const C3_G2: [U256; 2] = [
+    U256([0xfcbe57377b5ca1ec, 0x2e6da55f90a3e510, 0xb801fa95b21af64e, 0x29fd332ab7260112]),
+    U256([0xb1e9154d01565034, 0x5e76f77b1267a846, 0xf8408aee24ba0b86, 0x303d1eff1426764b]),
+];
+
+// q0^(2^63 - 1) = q0^-1 mod 2^64 for an odd q0, as the units modulo 2^64 have order 2^63
+const fn montgomery_inv(q0: u64) -> u64 {
+    let mut inv = 1u64;
+    let mut i = 0;
+    while i < 63 {
+        inv = inv.wrapping_mul(inv).wrapping_mul(q0);
+        i += 1;
+    }
+    inv.wrapping_neg()
+}
+
+/// The operations of Fq and Fq2 that are needed to map to a curve
+pub(crate) trait Field: Copy + PartialEq + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Neg<Output = Self> {
+    fn from_u64(value: u64) -> Self;
+    fn is_zero(&self) -> bool;
+    /// The inverse, or zero for zero
+    fn inverse(&self) -> Self;
+    fn is_square(&self) -> bool;
+    fn sqrt(&self) -> Option<Self>;
+    /// The sign of the element as defined by RFC 9380, Section 4.1
+    fn sgn0(&self) -> bool;
+
+    fn square(&self) -> Self {
+        *self * *self
+    }
+}
+
+/// An element of Fq in the montgomery form x * 2^256 mod q, which is also the encoding of rabe-bn
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) struct Fq(pub(crate) U256);
+
+/// An element c0 + c1 * u of Fq2 = Fq[u] / (u^2 + 1)
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) struct Fq2 {
+    pub(crate) c0: Fq,
+    pub(crate) c1: Fq,
+}
+
+impl Fq {
+    // the element that represents an integer below 2^256
+    fn from_integer(x: U256) -> Fq {
+        let [x0, x1, x2, x3] = x.0;
+        Fq(U512([0, 0, 0, 0, x0, x1, x2, x3]).divrem(&MODULUS).1)
+    }
+
+    // reduces a big endian integer of at most 64 bytes modulo q
+    fn from_be_bytes(bytes: &[u8]) -> Fq {
+        let mut wide = [0u8; 64];
+        wide[64 - bytes.len()..].copy_from_slice(bytes);
+        Fq::from_integer(U512::interpret(&wide).divrem(&MODULUS).1)
+    }
+
+    // the integer below q that the element represents
+    fn to_integer(self) -> U256 {
+        let mut x = self.0;
+        x.mul(&U256::one(), &MODULUS, INV);
+        x
+    }
+
+    fn pow(&self, exponent: &U256) -> Fq {
+        exponent.bits().fold(Fq::from_u64(1), |power, bit| match bit {
+            true => power.square() * *self,
+            false => power.square(),
+        })
+    }
+}
+
+impl Add for Fq {
+    type Output = Fq;
+    fn add(mut self, other: Fq) -> Fq {
+        self.0.add(&other.0, &MODULUS);
+        self
+    }
+}
+
+impl Sub for Fq {
+    type Output = Fq;
+    fn sub(mut self, other: Fq) -> Fq {
+        self.0.sub(&other.0, &MODULUS);
+        self
+    }
+}
+
+impl Mul for Fq {
+    type Output = Fq;
+    fn mul(mut self, other: Fq) -> Fq {
+        self.0.mul(&other.0, &MODULUS, INV);
+        self
+    }
+}
+
+impl Neg for Fq {
+    type Output = Fq;
+    fn neg(mut self) -> Fq {
+        self.0.neg(&MODULUS);
+        self
+    }
+}
+
+impl Field for Fq {
+    fn from_u64(value: u64) -> Fq {
+        Fq::from_integer(U256([value, 0, 0, 0]))
+    }
+    fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+    fn inverse(&self) -> Fq {
+        if self.is_zero() {
+            return *self;
+        }
+        let mut x = self.to_integer();
+        x.invert(&MODULUS);
+        Fq::from_integer(x)
+    }
+    fn is_square(&self) -> bool {
+        self.is_zero() || self.pow(&Q_MINUS_1_HALF) == Fq::from_u64(1)
+    }
+    fn sqrt(&self) -> Option<Fq> {
+        let root = self.pow(&Q_PLUS_1_QUARTER);
+        if root.square() == *self { Some(root) } else { None }
+    }
+    fn sgn0(&self) -> bool {
+        !self.to_integer().is_even()
+    }
+}
+
+impl Add for Fq2 {
+    type Output = Fq2;
+    fn add(self, other: Fq2) -> Fq2 {
+        Fq2 { c0: self.c0 + other.c0, c1: self.c1 + other.c1 }
+    }
+}
+
+impl Sub for Fq2 {
+    type Output = Fq2;
+    fn sub(self, other: Fq2) -> Fq2 {
+        Fq2 { c0: self.c0 - other.c0, c1: self.c1 - other.c1 }
+    }
+}
+
+impl Mul for Fq2 {
+    type Output = Fq2;
+    fn mul(self, other: Fq2) -> Fq2 {
+        Fq2 {
+            c0: self.c0 * other.c0 - self.c1 * other.c1,
+            c1: self.c0 * other.c1 + self.c1 * other.c0,
+        }
+    }
+}
+
+impl Neg for Fq2 {
+    type Output = Fq2;
+    fn neg(self) -> Fq2 {
+        Fq2 { c0: -self.c0, c1: -self.c1 }
+    }
+}
+
+impl Fq2 {
+    // c0^2 + c1^2, an element is a square if and only if its norm is
+    fn norm(&self) -> Fq {
+        self.c0.square() + self.c1.square()
+    }
+}
+
+impl Field for Fq2 {
+    fn from_u64(value: u64) -> Fq2 {
+        Fq2 { c0: Fq::from_u64(value), c1: Fq::from_u64(0) }
+    }
+    fn is_zero(&self) -> bool {
+        self.c0.is_zero() && self.c1.is_zero()
+    }
+    fn inverse(&self) -> Fq2 {
+        let t = self.norm().inverse();
+        Fq2 { c0: self.c0 * t, c1: -(self.c1 * t) }
+    }
+    fn is_square(&self) -> bool {
+        self.norm().is_square()
+    }
+    // the complex method: (x0 + x1 * u)^2 = c0 + c1 * u with x0^2 = (c0 +- sqrt(norm)) / 2 and x1 = c1 / (2 * x0)
+    fn sqrt(&self) -> Option<Fq2> {
+        let zero = Fq::from_u64(0);
+        if self.c1.is_zero() {
+            return match self.c0.sqrt() {
+                Some(root) => Some(Fq2 { c0: root, c1: zero }),
+                None => (-self.c0).sqrt().map(|root| Fq2 { c0: zero, c1: root }),
+            };
+        }
+        let alpha = self.norm().sqrt()?;
+        let half = Fq::from_u64(2).inverse();
+        let mut delta = (self.c0 + alpha) * half;
+        if !delta.is_square() {
+            delta = (self.c0 - alpha) * half;
+        }
+        let x0 = delta.sqrt()?;
+        let root = Fq2 { c0: x0, c1: self.c1 * (x0 + x0).inverse() };
+        if root.square() == *self { Some(root) } else { None }
+    }
+    fn sgn0(&self) -> bool {
+        self.c0.sgn0() || (self.c0.is_zero() && self.c1.sgn0())
+    }
+}
+
+/// expand_message_xmd of RFC 9380, Section 5.3.1, with SHA3-256. Expands `msg` to `len` uniformly random bytes,
+/// which depend on the domain separation tag `dst`.
+pub fn expand_message_xmd(msg: &[u8], dst: &[u8], len: usize) -> Result<Vec<u8>, RabeError> {
+    let blocks = len.div_ceil(HASH_LEN);
+    if blocks > 255 || len > u16::MAX as usize || dst.len() > 255 {
+        return Err(RabeError::InvalidInput(String::from("expand_message_xmd: output or domain separation tag too long")));
+    }
+    let mut dst_prime = dst.to_vec();
+    dst_prime.push(dst.len() as u8);
+    let b_0 = Sha3_256::new()
+        .chain_update([0u8; BLOCK_LEN])
+        .chain_update(msg)
+        .chain_update((len as u16).to_be_bytes())
+        .chain_update([0u8])
+        .chain_update(&dst_prime)
+        .finalize();
+    let mut b_i = Sha3_256::new().chain_update(b_0).chain_update([1u8]).chain_update(&dst_prime).finalize();
+    let mut uniform: Vec<u8> = b_i.to_vec();
+    for i in 2..=blocks {
+        let xored: Vec<u8> = b_0.iter().zip(b_i.iter()).map(|(a, b)| a ^ b).collect();
+        b_i = Sha3_256::new().chain_update(xored).chain_update([i as u8]).chain_update(&dst_prime).finalize();
+        uniform.extend_from_slice(&b_i);
+    }
+    uniform.truncate(len);
+    Ok(uniform)
+}
+
+// hash_to_field of RFC 9380, Section 5.2, returns two elements of Fq^degree
+fn hash_to_field(msg: &[u8], dst: &[u8], degree: usize) -> Result<Vec<Vec<Fq>>, RabeError> {
+    let uniform = expand_message_xmd(msg, dst, 2 * degree * ELEMENT_LEN)?;
+    Ok(uniform
+        .chunks(degree * ELEMENT_LEN)
+        .map(|element| element.chunks(ELEMENT_LEN).map(Fq::from_be_bytes).collect())
+        .collect())
+}
+
+// the constants of the Shallue-van de Woestijne map to y^2 = x^3 + b, see RFC 9380, Section 6.6.1
+struct Svdw<F: Field> {
+    b: F,
+    z: F,
+    c1: F,
+    c2: F,
+    c3: F,
+    c4: F,
+}
+
+impl<F: Field> Svdw<F> {
+    // z = 1 is the first candidate of find_z_svdw (RFC 9380, Appendix H.1) that fits both curves
+    fn new(b: F, c3: F) -> Svdw<F> {
+        let z = F::from_u64(1);
+        let g_z = z.square() * z + b;
+        let three_z_squared = F::from_u64(3) * z.square();
+        Svdw {
+            b,
+            z,
+            c1: g_z,
+            c2: -(z * F::from_u64(2).inverse()),
+            c3,
+            c4: -(F::from_u64(4) * g_z) * three_z_squared.inverse(),
+        }
+    }
+
+    fn curve(&self, x: F) -> F {
+        x.square() * x + self.b
+    }
+
+    // the jacobian point (x, y, z) is the point at infinity or y^2 = x^3 + b * z^6
+    fn contains(&self, x: F, y: F, z: F) -> bool {
+        z.is_zero() || y.square() == x.square() * x + self.b * (z.square() * z).square()
+    }
+
+    // the straight line implementation of RFC 9380, Appendix F.1
+    fn map(&self, u: F) -> Result<(F, F), RabeError> {
+        let one = F::from_u64(1);
+        let tv1 = u.square() * self.c1;
+        let tv2 = one + tv1;
+        let tv1 = one - tv1;
+        let tv3 = (tv1 * tv2).inverse();
+        let tv4 = u * tv1 * tv3 * self.c3;
+        let x1 = self.c2 - tv4;
+        let x2 = self.c2 + tv4;
+        let x3 = (tv2.square() * tv3).square() * self.c4 + self.z;
+        let x = if self.curve(x1).is_square() {
+            x1
+        } else if self.curve(x2).is_square() {
+            x2
+        } else {
+            x3
+        };
+        // g(x1) * g(x2) * g(x3) is a square for every u (RFC 9380, Section 6.6.1), so if neither g(x1) nor g(x2) is a
+        // square, g(x3) is one and the error cannot occur for the constants of this module
+        let y = self.curve(x).sqrt().ok_or_else(|| RabeError::InvalidInput(String::from("svdw: no candidate is on the curve")))?;
+        Ok(if u.sgn0() == y.sgn0() { (x, y) } else { (x, -y) })
+    }
+}
+
+fn svdw_g1() -> &'static Svdw<Fq> {
+    static SVDW: OnceLock<Svdw<Fq>> = OnceLock::new();
+    SVDW.get_or_init(|| Svdw::new(Fq::from_u64(3), Fq::from_integer(C3_G1)))
+}
+
+fn svdw_g2() -> &'static Svdw<Fq2> {
+    static SVDW: OnceLock<Svdw<Fq2>> = OnceLock::new();
+    // the twist y^2 = x^3 + 3 / (9 + u)
+    SVDW.get_or_init(|| Svdw::new(
+        Fq2::from_u64(3) * Fq2 { c0: Fq::from_u64(9), c1: Fq::from_u64(1) }.inverse(),
+        Fq2 { c0: Fq::from_integer(C3_G2[0]), c1: Fq::from_integer(C3_G2[1]) },
+    ))
+}
+
+// rabe-bn does not construct points from coordinates, but decodes them from the encoding of the mirror types
+fn coordinate2(x: Fq2) -> Coordinate2 {
+    Coordinate2 { c0: Coordinate(x.c0.0), c1: Coordinate(x.c1.0) }
+}
+
+/// Returns true if the encoded jacobian point is the point at infinity or on the curve of [`G1`]
+pub(crate) fn on_curve_g1(point: &Jacobian<Coordinate>) -> bool {
+    svdw_g1().contains(Fq(point.x.0), Fq(point.y.0), Fq(point.z.0))
+}
+
+/// Returns true if the encoded jacobian point is the point at infinity or on the twist, which contains [`G2`]
+pub(crate) fn on_twist(point: &Jacobian<Coordinate2>) -> bool {
+    let fq2 = |c: &Coordinate2| Fq2 { c0: Fq(c.c0.0), c1: Fq(c.c1.0) };
+    svdw_g2().contains(fq2(&point.x), fq2(&point.y), fq2(&point.z))
+}
+
+/// Returns the [`G1`] point with the affine coordinates (x, y), or an error if it is not on the curve
+pub(crate) fn g1_from_affine(x: Fq, y: Fq) -> Result<G1, RabeError> {
+    G1::decode(&Jacobian { x: Coordinate(x.0), y: Coordinate(y.0), z: Coordinate(Fq::from_u64(1).0) }.encode()?)
+}
+
+// the point of the twist with the affine coordinates (x, y), which is not checked to be in G2
+fn twist_from_affine(x: Fq2, y: Fq2) -> Result<G2, RabeError> {
+    decode_unchecked(&Jacobian { x: coordinate2(x), y: coordinate2(y), z: coordinate2(Fq2::from_u64(1)) }.encode()?)
+}
+
+// point * k for an integer k, which may exceed the group order
+fn mul_integer<T: Group>(point: T, k: &U256) -> T {
+    k.bits().fold(T::zero(), |result, bit| match bit {
+        true => result + result + point,
+        false => result + result,
+    })
+}
+
+/// Hashes `msg` to [`G1`] with the domain separation tag `dst`, see the suite [SUITE_G1]
+pub fn hash_to_curve_g1(msg: &[u8], dst: &[u8]) -> Result<G1, RabeError> {
+    let mut sum = G1::zero();
+    for u in hash_to_field(msg, dst, 1)? {
+        let (x, y) = svdw_g1().map(u[0])?;
+        sum = sum + g1_from_affine(x, y)?;
+    }
+    Ok(sum)
+}
+
+/// Hashes `msg` to [`G2`] with the domain separation tag `dst`, see the suite [SUITE_G2]
+pub fn hash_to_curve_g2(msg: &[u8], dst: &[u8]) -> Result<G2, RabeError> {
+    // the mapped points are on the twist, but not necessarily in G2. Clearing the cofactor of the sum gives the same
+    // point as clearing the cofactor of each point.
+    let mut sum = G2::zero();
+    for u in hash_to_field(msg, dst, 2)? {
+        let (x, y) = svdw_g2().map(Fq2 { c0: u[0], c1: u[1] })?;
+        sum = sum + twist_from_affine(x, y)?;
+    }
+    Ok(mul_integer(sum, &COFACTOR_G2))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use rabe_bn::{pairing, Fr};
+    use rand::Rng;
+
+    /// r, the order of [`G1`] and [`G2`]
+    const ORDER: U256 = U256([0x43e1f593f0000001, 0x2833e84879b97091, 0xb85045b68181585d, 0x30644e72e131a029]);
+
+    fn bytes(hex: &str) -> Vec<u8> {
+        (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect()
+    }
+
+    fn fq(hex: &str) -> Fq {
+        Fq::from_be_bytes(&bytes(hex))
+    }
+
+    fn in_g2(point: G2) -> bool {
+        G2::decode(&point.encode().unwrap()).is_ok()
+    }
+
+    #[test]
+    fn test_constants() {
+        assert_eq!(MODULUS.0[0].wrapping_mul(INV), u64::MAX);
+        // 2 * (q - 1) / 2 + 1 = q, 4 * (q + 1) / 4 = q + 1 and (2q - r) + r = 2q
+        let q = U512::from(&U256::zero(), &MODULUS, &MODULUS);
+        assert!(U512::from(&U256::from([2, 0, 0, 0]), &U256::one(), &Q_MINUS_1_HALF) == q);
+        let mut q_plus_1 = MODULUS;
+        q_plus_1.0[0] += 1;
+        assert!(U512::from(&U256::from([4, 0, 0, 0]), &U256::zero(), &Q_PLUS_1_QUARTER) == U512::from(&U256::zero(), &q_plus_1, &MODULUS));
+        assert!(U512::from(&U256::one(), &ORDER, &COFACTOR_G2) == U512::from(&U256::from([2, 0, 0, 0]), &U256::zero(), &MODULUS));
+    }
+
+    #[test]
+    fn test_field() {
+        let (a, b) = (Fq::from_u64(6), Fq::from_u64(7));
+        assert_eq!(a * b, Fq::from_u64(42));
+        assert_eq!(a - b + b, a);
+        let mut minus_one = MODULUS;
+        minus_one.0[0] -= 1;
+        assert_eq!((a - b).to_integer(), minus_one);
+        assert_eq!(a * a.inverse(), Fq::from_u64(1));
+        assert_eq!(Fq::from_u64(0).inverse(), Fq::from_u64(0));
+        // -1 is not a square, as q = 3 mod 4
+        assert!(!(-Fq::from_u64(1)).is_square() && (-Fq::from_u64(1)).sqrt().is_none());
+        assert_eq!(Fq::from_u64(49).sqrt().map(|root| root.square()), Some(Fq::from_u64(49)));
+        // 2^256 + 5 and q + 42
+        let mut bytes = vec![0u8; 33];
+        bytes[0] = 1;
+        bytes[32] = 5;
+        assert_eq!(Fq::from_be_bytes(&bytes), Fq::from_integer(U256([u64::MAX; 4])) + Fq::from_u64(6));
+        let mut q_plus_42 = MODULUS;
+        q_plus_42.0[0] += 42;
+        let q_plus_42: Vec<u8> = q_plus_42.0.iter().rev().flat_map(|limb| limb.to_be_bytes()).collect();
+        assert_eq!(Fq::from_be_bytes(&q_plus_42), Fq::from_u64(42));
+        let u = Fq2 { c0: Fq::from_u64(0), c1: Fq::from_u64(1) };
+        assert_eq!(u * u, -Fq2::from_u64(1));
+        let x = Fq2 { c0: Fq::from_u64(3), c1: Fq::from_u64(5) };
+        assert_eq!(x * x.inverse(), Fq2::from_u64(1));
+        for y in [x, u, -Fq2::from_u64(1), Fq2::from_u64(7)] {
+            let square = y.square();
+            assert!(square.is_square());
+            assert_eq!(square.sqrt().map(|root| root.square()), Some(square));
+        }
+        assert!(x.sgn0() && !(-x).sgn0() && u.sgn0() && !(-u).sgn0());
+    }
+
+    // the elements are compared with the field elements of rabe-bn by converting random points to affine coordinates
+    #[test]
+    fn test_field_matches_rabe_bn() {
+        let mut rng = rand::thread_rng();
+        let g1: G1 = rng.gen();
+        let point = Jacobian::<Coordinate>::decode(&g1.encode().unwrap()).unwrap();
+        let (x, y, z) = (Fq(point.x.0), Fq(point.y.0), Fq(point.z.0));
+        let z_inverse = z.inverse();
+        let (x, y) = (x * z_inverse.square(), y * z_inverse.square() * z_inverse);
+        assert!(g1_from_affine(x, y).unwrap() == g1);
+        let g2: G2 = rng.gen();
+        let point = Jacobian::<Coordinate2>::decode(&g2.encode().unwrap()).unwrap();
+        let fq2 = |c: Coordinate2| Fq2 { c0: Fq(c.c0.0), c1: Fq(c.c1.0) };
+        let (x, y, z) = (fq2(point.x), fq2(point.y), fq2(point.z));
+        let z_inverse = z.inverse();
+        let (x, y) = (x * z_inverse.square(), y * z_inverse.square() * z_inverse);
+        assert!(twist_from_affine(x, y).unwrap() == g2);
+        assert!(y == svdw_g2().curve(x).sqrt().map(|root| if root.sgn0() == y.sgn0() { root } else { -root }).unwrap());
+    }
+
+    fn check_c3<F: Field>(svdw: &Svdw<F>) {
+        let three_z_squared = F::from_u64(3) * svdw.z.square();
+        assert!(svdw.c3.square() == -(svdw.c1 * three_z_squared));
+        assert!(!svdw.c3.sgn0());
+    }
+
+    #[test]
+    fn test_svdw_constants() {
+        check_c3(svdw_g1());
+        check_c3(svdw_g2());
+    }
+
+    #[test]
+    fn test_points_from_coordinates() {
+        let (x, y) = svdw_g1().map(Fq::from_u64(7)).unwrap();
+        assert!(g1_from_affine(x, y).is_ok());
+        assert!(matches!(g1_from_affine(x, y + Fq::from_u64(1)), Err(RabeError::Serialization { .. })));
+        // the mapped point is on the twist, but not in G2 before its cofactor is cleared
+        let (x, y) = svdw_g2().map(Fq2::from_u64(7)).unwrap();
+        let point = twist_from_affine(x, y).unwrap();
+        assert!(!in_g2(point));
+        let g2 = mul_integer(point, &COFACTOR_G2);
+        assert!(in_g2(g2) && mul_integer(g2, &ORDER).is_zero());
+        assert!(mul_integer(twist_from_affine(x, -y).unwrap(), &COFACTOR_G2) == -g2);
+    }
+
+    // the reference values are printed by tools/to_curve_vectors.py, an implementation of RFC 9380 in python that shares
+    // no code with this module
+    #[test]
+    fn test_hash_to_curve() {
+        let dst_g1 = format!("RABE-TEST-with-{}", SUITE_G1);
+        let dst_g2 = format!("RABE-TEST-with-{}", SUITE_G2);
+        assert_eq!(
+            expand_message_xmd(b"abc", dst_g1.as_bytes(), 32).unwrap(),
+            bytes("7b95ecbd33bdc63b3d79929359dff14b3186aa2aa75be59787fc5e8313744c9b")
+        );
+        let g1 = hash_to_curve_g1(b"abc", dst_g1.as_bytes()).unwrap();
+        let expected = g1_from_affine(
+            fq("0d6eaeee796d4432544bf3f7146db80d9e945ceb3e26047d1d95ddbb5086a602"),
+            fq("24b65e415295387e58d56cb5a6818f56cfb751e4cb3f87a5257efbd061d892ed"),
+        ).unwrap();
+        assert!(g1 == expected);
+        let g2 = hash_to_curve_g2(b"abc", dst_g2.as_bytes()).unwrap();
+        let expected = twist_from_affine(
+            Fq2 {
+                c0: fq("1001df906d5fbafe4e5371af85b0818c3475908d7dea0f4090674fc4778e8637"),
+                c1: fq("1bf11f93dd3540f9b05df6f6a0ec0638e57220b1d18b7c96c7ca80ecbbde83b0"),
+            },
+            Fq2 {
+                c0: fq("18b1ed05193f0ce7efab7959236e8bc4ee875a21f14ad694575fa7401db8a944"),
+                c1: fq("02a30565cd73a892189bb7db1a31fa02ea7effb4940698c84d315eb89a6c0889"),
+            },
+        ).unwrap();
+        assert!(g2 == expected && in_g2(g2));
+        let empty = hash_to_curve_g2(b"", dst_g2.as_bytes()).unwrap();
+        assert!(empty != g2 && !empty.is_zero());
+        // both points have order r
+        assert!(mul_integer(g1, &ORDER).is_zero() && mul_integer(g2, &ORDER).is_zero());
+        // the points are compatible with the group operations and the pairing of rabe-bn
+        let x: Fr = rand::thread_rng().gen();
+        assert!(pairing(g1 * x, g2) == pairing(g1, g2 * x));
+        // other domain, other point
+        assert!(hash_to_curve_g1(b"abc", dst_g2.as_bytes()).unwrap() != g1);
+    }
+}
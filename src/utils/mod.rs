@@ -7,9 +7,20 @@ pub mod aes;
 pub mod hash;
 /// Language (human and json) and Policy parsers (in msp and dnf)
 pub mod policy;
+/// Products of pairings
+pub mod pairing;
+/// Precomputed tables for fixed-base multiplication and exponentiation
+pub mod fixed_base;
+/// Loops that run in parallel with the `parallel` feature
+pub mod parallel;
 /// Secret sharing utilities
 pub mod secretsharing;
 /// various functions
 pub mod tools;
 /// File operations
 pub mod file;
+/// Versioned container format for keys and ciphertexts
+#[macro_use]
+pub mod container;
+/// PEM-style ASCII armor for keys and ciphertexts
+pub mod armor;
@@ -0,0 +1,70 @@
+use rabe_bn::{Group, Gt, G1, G2, pairing};
+use utils::parallel;
+
+/// Calculates the product of pairings e(p_1, q_1) · … · e(p_n, q_n) after merging pairs by bilinearity.
+///
+/// rabe-bn does not expose its miller loop and final exponentiation, so every remaining pairing is computed in full.
+/// The saving comes from merging the pairs first: pairs with the same [`G2`] element are merged to e(p_1 + p_2, q),
+/// pairs with the same [`G1`] element to e(p, q_1 + q_2), and pairs with a zero element are left out. Exponents and
+/// inverses of pairings should be applied to the [`G1`] element, i.e. e(p, q)^x is e(p * x, q) and e(p, q)^-1 is
+/// e(-p, q), which is much cheaper than an exponentiation in [`Gt`]. With the `parallel` feature, the remaining
+/// pairings are computed in parallel. No miller loop result is unwrapped here, so there is no failure to return.
+///
+/// # Arguments
+///
+///	* `pairs` - The pairs (p_i, q_i) of the product
+///
+pub fn multi_pairing(pairs: &[(G1, G2)]) -> Gt {
+    let mut same_q: Vec<(G1, G2)> = Vec::new();
+    for (p, q) in pairs.iter().filter(|(p, q)| !p.is_zero() && !q.is_zero()) {
+        match same_q.iter_mut().find(|pair| pair.1 == *q) {
+            Some(pair) => pair.0 = pair.0 + *p,
+            None => same_q.push((*p, *q)),
+        }
+    }
+    let mut same_p: Vec<(G1, G2)> = Vec::new();
+    for (p, q) in same_q.into_iter().filter(|(p, _)| !p.is_zero()) {
+        match same_p.iter_mut().find(|pair| pair.0 == p) {
+            Some(pair) => pair.1 = pair.1 + q,
+            None => same_p.push((p, q)),
+        }
+    }
+    same_p.retain(|(_, q)| !q.is_zero());
+    parallel::map(&same_p, |(p, q)| pairing(*p, *q))
+        .into_iter()
+        .fold(Gt::one(), |product, e| product * e)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use rabe_bn::Fr;
+    use rand::Rng;
+
+    #[test]
+    fn test_multi_pairing() {
+        let mut rng = rand::thread_rng();
+        let (p, q): (Vec<G1>, Vec<G2>) = (0..4).map(|_| (rng.gen::<G1>(), rng.gen::<G2>())).unzip();
+        let x: Fr = rng.gen();
+        let pairs = vec![
+            (p[0], q[0]),
+            (p[1], q[1]),
+            // same G2 element, same G1 element and an inverse
+            (p[2], q[0]),
+            (p[1], q[3]),
+            (-(p[3] * x), q[2]),
+            // left out
+            (G1::zero(), q[1]),
+            (p[0], G2::zero()),
+        ];
+        let expected = pairing(p[0], q[0])
+            * pairing(p[1], q[1])
+            * pairing(p[2], q[0])
+            * pairing(p[1], q[3])
+            * pairing(p[3], q[2]).pow(x).inverse();
+        assert!(multi_pairing(&pairs) == expected);
+        assert!(multi_pairing(&[]) == Gt::one());
+        assert!(multi_pairing(&[(p[0], q[0]), (-p[0], q[0])]) == Gt::one());
+    }
+}
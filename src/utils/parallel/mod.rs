@@ -0,0 +1,51 @@
+//! Loops over independent items, e.g. the rows of an msp or the leaves of a policy.
+//!
+//! With the `parallel` feature, the items are processed on the rayon thread pool, otherwise one after the other. The
+//! results are returned in the order of the items in both cases. Randomness has to be drawn before, so that a seeded
+//! random number generator yields the same keys and ciphertexts with and without the `parallel` feature.
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use crate::error::RabeError;
+
+/// Applies `f` to all `items` and returns the results in the order of the items
+pub fn map<T, U, F>(items: &[T], f: F) -> Vec<U>
+where
+    T: Sync,
+    U: Send,
+    F: Fn(&T) -> U + Sync + Send,
+{
+    #[cfg(feature = "parallel")]
+    let results: Vec<U> = items.par_iter().map(f).collect();
+    #[cfg(not(feature = "parallel"))]
+    let results: Vec<U> = items.iter().map(f).collect();
+    results
+}
+
+/// Like [map], but returns an error if `f` fails for any of the items
+pub fn try_map<T, U, F>(items: &[T], f: F) -> Result<Vec<U>, RabeError>
+where
+    T: Sync,
+    U: Send,
+    F: Fn(&T) -> Result<U, RabeError> + Sync + Send,
+{
+    #[cfg(feature = "parallel")]
+    let results: Result<Vec<U>, RabeError> = items.par_iter().map(f).collect();
+    #[cfg(not(feature = "parallel"))]
+    let results: Result<Vec<U>, RabeError> = items.iter().map(f).collect();
+    results
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_map() {
+        let items: Vec<usize> = (0..1000).collect();
+        assert_eq!(map(&items, |i| i * 2), (0..2000).step_by(2).collect::<Vec<usize>>());
+        assert_eq!(try_map(&items, |i| Ok(*i)).unwrap(), items);
+        let failing = try_map(&items, |i| if *i % 500 != 499 { Ok(*i) } else { Err(RabeError::InvalidInput(i.to_string())) });
+        assert!(failing.is_err());
+    }
+}
@@ -0,0 +1,359 @@
+//! An owned policy tree, which can be built programmatically instead of formatting and parsing policy strings.
+//!
+//! All encrypt and keygen functions accept a [Policy] wherever they accept the text of a policy, see [PolicySource].
+//! A [Policy] is converted to a policy tree directly and stored in the JSON policy language.
+//!
+//! # Examples
+//!
+//! ```
+//! use rabe::schemes::bsw::*;
+//! use rabe::utils::policy::ast::Policy;
+//! let policy = Policy::and([Policy::attr("A").unwrap(), Policy::or([Policy::attr("B").unwrap(), Policy::attr("C").unwrap()])]);
+//! assert_eq!(policy.to_string(), r#"("A" and ("B" or "C"))"#);
+//! let (pk, msk) = setup();
+//! let plaintext = String::from("our plaintext!").into_bytes();
+//! let ct: CpAbeCiphertext = encrypt(&pk, &policy, &plaintext).unwrap();
+//! let sk: CpAbeSecretKey = keygen(&pk, &msk, &vec!["A", "C"]).unwrap();
+//! assert_eq!(decrypt(&sk, &ct).unwrap(), plaintext);
+//! ```
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter, Result as FormatResult};
+use std::ops::Not;
+use utils::policy::pest::{PolicyLanguage, PolicyValue, PolicyType, parse, threshold};
+use utils::policy::comparison::{self, Comparator};
+use utils::policy::normalize::normalize;
+use crate::error::RabeError;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+#[cfg(feature = "borsh")]
+use borsh::{BorshSerialize, BorshDeserialize};
+
+/// An owned policy tree. Attribute names must not contain quotes, backslashes or control characters, so that the policy
+/// can be written to and parsed from both policy languages, see [Policy::attr]. Use [Policy::normalize] to compare or
+/// hash policies independent of their formatting.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Policy {
+    /// An attribute, the name is not checked when the variant is built directly
+    Attribute(String),
+    /// Satisfied if all children are satisfied
+    And(Vec<Policy>),
+    /// Satisfied if one of the children is satisfied
+    Or(Vec<Policy>),
+    /// Satisfied if at least `k` of the children are satisfied
+    Threshold(usize, Vec<Policy>),
+    /// Satisfied if the child is not satisfied
+    Not(Box<Policy>),
+}
+
+impl Policy {
+    /// Returns the attribute `name`, or an error if `name` contains a quote, a backslash or a control character
+    pub fn attr<S: Into<String>>(name: S) -> Result<Policy, RabeError> {
+        let name = name.into();
+        check_name(&name)?;
+        Ok(Policy::Attribute(name))
+    }
+
+    /// Returns an AND gate of `children`
+    pub fn and<I: IntoIterator<Item = Policy>>(children: I) -> Policy {
+        Policy::And(children.into_iter().collect())
+    }
+
+    /// Returns an OR gate of `children`
+    pub fn or<I: IntoIterator<Item = Policy>>(children: I) -> Policy {
+        Policy::Or(children.into_iter().collect())
+    }
+
+    /// Returns a threshold gate, which is satisfied if `k` of the `children` are satisfied
+    pub fn threshold<I: IntoIterator<Item = Policy>>(k: usize, children: I) -> Policy {
+        Policy::Threshold(k, children.into_iter().collect())
+    }
+
+    /// Returns the numeric comparison `name comparator value`, compiled to the bit attributes of `name`
+    pub fn comparison(name: &str, comparator: Comparator, value: u64) -> Result<Policy, RabeError> {
+        Policy::from_value(&comparison::comparison(name, comparator, value, 0)?)
+    }
+
+    /// Returns the numeric range `min <= name <= max`, compiled to the bit attributes of `name`
+    pub fn range(name: &str, min: u64, max: u64) -> Result<Policy, RabeError> {
+        Policy::from_value(&comparison::range(name, min, max, 0)?)
+    }
+
+    /// Parses a policy given in a [PolicyLanguage]
+    pub fn parse(policy: &str, language: PolicyLanguage) -> Result<Policy, RabeError> {
+        Policy::from_value(&parse(policy, language)?)
+    }
+
+    /// Converts a parsed policy tree to an owned policy
+    pub fn from_value(value: &PolicyValue) -> Result<Policy, RabeError> {
+        match value {
+            PolicyValue::String(node) => Policy::attr(node.0.to_string()),
+            PolicyValue::Not(child) => Ok(!Policy::from_value(child)?),
+            PolicyValue::Object((PolicyType::Leaf, child)) => Policy::from_value(child),
+            PolicyValue::Object((policy_type, child)) => {
+                let children = match child.as_ref() {
+                    PolicyValue::Array(children) => children.iter().map(Policy::from_value).collect::<Result<Vec<_>, _>>()?,
+                    _ => return Err(RabeError::InvalidPolicy(String::from("policy: AND, OR or THRESHOLD without children"))),
+                };
+                match policy_type {
+                    PolicyType::And => Ok(Policy::And(children)),
+                    PolicyType::Or => Ok(Policy::Or(children)),
+                    PolicyType::Threshold(k) => Ok(Policy::Threshold(*k, children)),
+                    PolicyType::Leaf => Err(RabeError::InvalidPolicy(String::from("policy: leaf with children"))),
+                }
+            },
+            PolicyValue::Array(_) => Err(RabeError::InvalidPolicy(String::from("policy: children without AND, OR or THRESHOLD"))),
+        }
+    }
+
+    /// Returns the canonical form of the policy, see [normalize]
+    pub fn normalize(&self) -> Policy {
+        normalize(self)
+    }
+
+    /// Returns a value that displays the policy in `language`
+    pub fn display(&self, language: PolicyLanguage) -> PolicyDisplay<'_> {
+        PolicyDisplay { policy: self, language }
+    }
+
+    /// Converts the policy to a parsed policy tree and its text in the JSON policy language, without parsing the text.
+    /// The positions of the attributes in the tree are their positions in the text, as if the text was parsed.
+    /// Fails like [parse] on invalid attribute names, AND and OR gates without children and invalid thresholds.
+    pub fn to_value(&self) -> Result<(PolicyValue<'static>, String), RabeError> {
+        let mut text = String::new();
+        let mut column = 1;
+        let value = self.write_value(&mut text, &mut column)?;
+        Ok((value, text))
+    }
+
+    // `column` is the (1-based) column in characters at the end of `text`, like the positions of the parser
+    fn write_value(&self, text: &mut String, column: &mut usize) -> Result<PolicyValue<'static>, RabeError> {
+        let (name, k, children) = match self {
+            Policy::Attribute(name) => {
+                check_name(name)?;
+                let position = push(text, column, "{\"name\": \"");
+                push(text, column, name);
+                push(text, column, "\"}");
+                return Ok(PolicyValue::String((Cow::Owned(name.clone()), position)));
+            },
+            Policy::Not(child) => ("not", None, std::slice::from_ref(child.as_ref())),
+            Policy::And(children) => ("and", None, &children[..]),
+            Policy::Or(children) => ("or", None, &children[..]),
+            Policy::Threshold(k, children) => ("threshold", Some(*k), &children[..]),
+        };
+        match k {
+            Some(k) => push(text, column, &format!("{{\"name\": \"{}\", \"k\": {}, \"children\": [", name, k)),
+            None => push(text, column, &format!("{{\"name\": \"{}\", \"children\": [", name)),
+        };
+        let mut values = Vec::new();
+        for (i, child) in children.iter().enumerate() {
+            if i > 0 {
+                push(text, column, ", ");
+            }
+            values.push(child.write_value(text, column)?);
+        }
+        push(text, column, "]}");
+        match (self, k) {
+            (Policy::Not(_), _) => Ok(PolicyValue::Not(Box::new(values.remove(0)))),
+            (_, Some(k)) => threshold(&k.to_string(), values),
+            (_, None) if values.is_empty() => Err(RabeError::InvalidPolicy(format!("policy: {} without children", name))),
+            (Policy::And(_), None) => Ok(PolicyValue::Object((PolicyType::And, Box::new(PolicyValue::Array(values))))),
+            (_, None) => Ok(PolicyValue::Object((PolicyType::Or, Box::new(PolicyValue::Array(values))))),
+        }
+    }
+
+    fn fmt_language(&self, f: &mut Formatter<'_>, language: PolicyLanguage) -> FormatResult {
+        let (name, k, children) = match self {
+            // names of directly built attributes are escaped, so that they cannot change the structure of the policy
+            Policy::Attribute(name) => return match language {
+                PolicyLanguage::JsonPolicy => write!(f, "{{\"name\": \"{}\"}}", escape(name)),
+                PolicyLanguage::HumanPolicy => write!(f, "\"{}\"", escape(name)),
+            },
+            Policy::Not(child) => match language {
+                PolicyLanguage::JsonPolicy => ("not", None, std::slice::from_ref(child.as_ref())),
+                PolicyLanguage::HumanPolicy => {
+                    write!(f, "not ")?;
+                    return child.fmt_language(f, language);
+                }
+            },
+            Policy::And(children) => ("and", None, &children[..]),
+            Policy::Or(children) => ("or", None, &children[..]),
+            Policy::Threshold(k, children) => ("threshold", Some(*k), &children[..]),
+        };
+        let separator = match (language, name) {
+            (PolicyLanguage::HumanPolicy, "and") => " and ",
+            (PolicyLanguage::HumanPolicy, "or") => " or ",
+            _ => ", ",
+        };
+        match (language, k) {
+            (PolicyLanguage::JsonPolicy, Some(k)) => write!(f, "{{\"name\": \"{}\", \"k\": {}, \"children\": [", name, k)?,
+            (PolicyLanguage::JsonPolicy, None) => write!(f, "{{\"name\": \"{}\", \"children\": [", name)?,
+            (PolicyLanguage::HumanPolicy, Some(k)) => write!(f, "{} of (", k)?,
+            (PolicyLanguage::HumanPolicy, None) => write!(f, "(")?,
+        }
+        for (i, child) in children.iter().enumerate() {
+            if i > 0 {
+                write!(f, "{}", separator)?;
+            }
+            child.fmt_language(f, language)?;
+        }
+        match language {
+            PolicyLanguage::JsonPolicy => write!(f, "]}}"),
+            PolicyLanguage::HumanPolicy => write!(f, ")"),
+        }
+    }
+}
+
+// appends `part` to `text` and returns the column after it
+fn push(text: &mut String, column: &mut usize, part: &str) -> usize {
+    text.push_str(part);
+    *column += part.chars().count();
+    *column
+}
+
+fn check_name(name: &str) -> Result<(), RabeError> {
+    match name.chars().find(|c| *c == '"' || *c == '\\' || c.is_control()) {
+        Some(c) => Err(RabeError::InvalidPolicy(format!("policy: attribute name {:?} contains {:?}", name, c))),
+        None => Ok(()),
+    }
+}
+
+fn escape(name: &str) -> Cow<'_, str> {
+    if name.contains(['"', '\\']) {
+        Cow::Owned(name.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        Cow::Borrowed(name)
+    }
+}
+
+/// Returns the negation of a policy, `!Policy::attr("A")?` is the policy `not "A"`
+impl Not for Policy {
+    type Output = Policy;
+
+    fn not(self) -> Policy {
+        Policy::Not(Box::new(self))
+    }
+}
+
+/// Displays the policy in the human policy language
+impl Display for Policy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        self.fmt_language(f, PolicyLanguage::HumanPolicy)
+    }
+}
+
+/// Displays a [Policy] in a [PolicyLanguage], see [Policy::display]
+pub struct PolicyDisplay<'a> {
+    policy: &'a Policy,
+    language: PolicyLanguage,
+}
+
+impl Display for PolicyDisplay<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        self.policy.fmt_language(f, self.language)
+    }
+}
+
+/// A policy that is passed to an encrypt or keygen function, either as text in a [PolicyLanguage], e.g.
+/// `(r#""A" and "B""#, PolicyLanguage::HumanPolicy)`, or as [Policy]
+pub trait PolicySource {
+    /// Returns the parsed policy, together with its text and the language of the text, which are stored in the
+    /// ciphertext or key
+    fn value(&self) -> Result<(PolicyValue<'_>, Cow<'_, str>, PolicyLanguage), RabeError>;
+}
+
+impl<S: AsRef<str>> PolicySource for (S, PolicyLanguage) {
+    fn value(&self) -> Result<(PolicyValue<'_>, Cow<'_, str>, PolicyLanguage), RabeError> {
+        let policy = self.0.as_ref();
+        Ok((parse(policy, self.1)?, Cow::Borrowed(policy), self.1))
+    }
+}
+
+/// A [Policy] is stored in the JSON policy language, see [Policy::to_value]
+impl PolicySource for Policy {
+    fn value(&self) -> Result<(PolicyValue<'_>, Cow<'_, str>, PolicyLanguage), RabeError> {
+        let (value, text) = self.to_value()?;
+        Ok((value, Cow::Owned(text), PolicyLanguage::JsonPolicy))
+    }
+}
+
+impl<T: PolicySource + ?Sized> PolicySource for &T {
+    fn value(&self) -> Result<(PolicyValue<'_>, Cow<'_, str>, PolicyLanguage), RabeError> {
+        (**self).value()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use utils::tools::traverse_policy;
+    use utils::policy::comparison::expand_attributes;
+
+    #[test]
+    fn test_policy_roundtrip() {
+        let policies = [
+            Policy::attr("A").unwrap(),
+            Policy::and([Policy::attr("A").unwrap(), Policy::attr("B").unwrap(), Policy::attr("and").unwrap()]),
+            Policy::or([Policy::attr("A").unwrap(), Policy::and([Policy::attr("B").unwrap(), !Policy::attr("C").unwrap()])]),
+            Policy::threshold(2, [Policy::attr("A").unwrap(), !Policy::or([Policy::attr("B").unwrap(), Policy::attr("C").unwrap()]), Policy::attr("5").unwrap()]),
+            !!Policy::attr("A").unwrap(),
+        ];
+        for policy in policies.iter() {
+            for language in [PolicyLanguage::HumanPolicy, PolicyLanguage::JsonPolicy] {
+                let text = policy.display(language).to_string();
+                assert_eq!(&Policy::parse(&text, language).unwrap(), policy, "{}", text);
+            }
+            // the tree is built without parsing, with the positions the parser would give the attributes
+            let (value, text) = policy.to_value().unwrap();
+            assert_eq!(text, policy.display(PolicyLanguage::JsonPolicy).to_string());
+            assert_eq!(parse(&text, PolicyLanguage::JsonPolicy).unwrap(), value, "{}", text);
+        }
+        assert_eq!(policies[2].to_string(), r#"("A" or ("B" and not "C"))"#);
+        assert_eq!(policies[3].display(PolicyLanguage::JsonPolicy).to_string(), r#"{"name": "threshold", "k": 2, "children": [{"name": "A"}, {"name": "not", "children": [{"name": "or", "children": [{"name": "B"}, {"name": "C"}]}]}, {"name": "5"}]}"#);
+        // invalid policies can be built, but not parsed
+        assert!(Policy::parse(&Policy::and([]).to_string(), PolicyLanguage::HumanPolicy).is_err());
+        assert!(Policy::parse(&Policy::threshold(3, [Policy::attr("A").unwrap()]).display(PolicyLanguage::JsonPolicy).to_string(), PolicyLanguage::JsonPolicy).is_err());
+        for policy in [Policy::and([]), Policy::or([]), Policy::threshold(0, [Policy::attr("A").unwrap()]), Policy::threshold(3, [Policy::attr("A").unwrap()])] {
+            assert!(matches!(policy.to_value(), Err(RabeError::InvalidPolicy(_))), "{:?}", policy);
+        }
+    }
+
+    #[test]
+    fn test_policy_attribute_names() {
+        for name in [r#"A" or "X"#, r#"A\"#, "A\nB"] {
+            assert!(matches!(Policy::attr(name), Err(RabeError::InvalidPolicy(_))), "{:?}", name);
+        }
+        // a directly built attribute stays a single attribute, which is rejected instead of parsed as another policy
+        let policy = Policy::Attribute(String::from(r#"A" or "X"#));
+        for language in [PolicyLanguage::HumanPolicy, PolicyLanguage::JsonPolicy] {
+            let text = policy.display(language).to_string();
+            assert!(matches!(Policy::parse(&text, language), Err(RabeError::InvalidPolicy(_))), "{}", text);
+            assert!(matches!(parse(&text, language).unwrap(), PolicyValue::String(_)), "{}", text);
+        }
+        assert!(matches!(policy.to_value(), Err(RabeError::InvalidPolicy(_))));
+    }
+
+    #[test]
+    fn test_policy_comparison() {
+        let policy = Policy::and([Policy::attr("A").unwrap(), Policy::comparison("age", Comparator::GreaterOrEqual, 18).unwrap(), Policy::range("level", 2, 5).unwrap()]);
+        let parsed = Policy::parse(r#""A" and age >= 18 and level in [2, 5]"#, PolicyLanguage::HumanPolicy).unwrap();
+        let text = policy.to_string();
+        let value = parse(&text, PolicyLanguage::HumanPolicy).unwrap();
+        for (attributes, satisfied) in [(["A", "age = 18", "level = 3"], true), (["A", "age = 17", "level = 3"], false), (["A", "age = 18", "level = 6"], false)] {
+            let attributes = expand_attributes(&attributes).unwrap();
+            assert_eq!(traverse_policy(&attributes, &value, PolicyType::Leaf), satisfied);
+            assert_eq!(traverse_policy(&attributes, &parse(&parsed.to_string(), PolicyLanguage::HumanPolicy).unwrap(), PolicyType::Leaf), satisfied);
+        }
+        assert!(Policy::comparison("age", Comparator::Less, 0).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_policy_serde() {
+        let policy = Policy::threshold(1, [Policy::attr("A").unwrap(), !Policy::attr("B").unwrap()]);
+        let bytes = serde_cbor::to_vec(&policy).unwrap();
+        assert_eq!(serde_cbor::from_slice::<Policy>(&bytes).unwrap(), policy);
+    }
+}
@@ -0,0 +1,408 @@
+//! Numeric comparisons in policies, using the bag-of-bits encoding of Bethencourt, Sahai and Waters.
+//!
+//! A numeric attribute `age = 42` is given to a user as one attribute per bit of its value, see
+//! [numeric_attributes]. A comparison `age >= 18` in a policy is compiled to a sub-policy over these bit
+//! attributes, which is satisfied if and only if the bits of the user encode a value that is at least 18.
+//!
+//! The width of a numeric attribute is part of its name: `age:8 = 42` and `age:8 >= 18` use 8 bits, which makes
+//! keys and policies smaller, while a name without width, like `age`, uses 64 bits. Attributes and policies that use
+//! the same name with different widths are rejected by [check_widths] instead of silently never matching.
+//!
+//! # Examples
+//!
+//! ```
+//! use rabe::utils::policy::comparison::expand_attributes;
+//! use rabe::utils::policy::pest::{parse, PolicyLanguage, PolicyType};
+//! use rabe::utils::tools::traverse_policy;
+//! let policy = parse(r#""A" and age >= 18"#, PolicyLanguage::HumanPolicy).unwrap();
+//! let adult = expand_attributes(&["A", "age = 42"]).unwrap();
+//! let child = expand_attributes(&["A", "age = 12"]).unwrap();
+//! assert!(traverse_policy(&adult, &policy, PolicyType::Leaf));
+//! assert!(!traverse_policy(&child, &policy, PolicyType::Leaf));
+//! let policy = parse(r#""A" and age:8 >= 18"#, PolicyLanguage::HumanPolicy).unwrap();
+//! let adult = expand_attributes(&["A", "age:8 = 42"]).unwrap();
+//! assert_eq!(adult.len(), 9);
+//! assert!(traverse_policy(&adult, &policy, PolicyType::Leaf));
+//! ```
+use utils::policy::pest::{PolicyValue, PolicyType};
+use crate::error::RabeError;
+use std::fmt::{Display, Formatter, Result as FormatResult};
+
+/// The width of numeric attributes without a width in their name, which is also the largest width
+const MAX_BITS: usize = 64;
+
+/// The comparison operators of a policy
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Comparator {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+}
+
+impl Comparator {
+    /// Parses `<`, `<=`, `>`, `>=`, `==` and `=`
+    pub fn parse(comparator: &str) -> Result<Comparator, RabeError> {
+        match comparator {
+            "<" => Ok(Comparator::Less),
+            "<=" => Ok(Comparator::LessOrEqual),
+            ">" => Ok(Comparator::Greater),
+            ">=" => Ok(Comparator::GreaterOrEqual),
+            "==" | "=" => Ok(Comparator::Equal),
+            _ => Err(RabeError::InvalidPolicy(format!("comparison: unknown comparator {}", comparator))),
+        }
+    }
+}
+
+impl Display for Comparator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        let symbol = match self {
+            Comparator::Less => "<",
+            Comparator::LessOrEqual => "<=",
+            Comparator::Greater => ">",
+            Comparator::GreaterOrEqual => ">=",
+            Comparator::Equal => "==",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+/// Splits the name of a numeric attribute `name:bits` into the name and its width between 1 and 64 bits. A name
+/// without width, i.e. that does not end with `:` and a number, has 64 bits.
+pub fn numeric_width(name: &str) -> Result<(&str, usize), RabeError> {
+    match name.rsplit_once(':') {
+        Some((base, bits)) if is_digits(bits) => match bits.parse() {
+            Ok(bits) if (1..=MAX_BITS).contains(&bits) => Ok((base, bits)),
+            _ => Err(RabeError::InvalidPolicy(format!("comparison: the width of {} is not between 1 and {} bits", name, MAX_BITS))),
+        },
+        _ => Ok((name, MAX_BITS)),
+    }
+}
+
+/// Returns the attribute of bit `bit` of the numeric attribute `name` with `bits` bits, if this bit is set to `value`
+pub fn bit_attribute(name: &str, bits: usize, bit: usize, value: bool) -> String {
+    match bits {
+        MAX_BITS => format!("{}#{}={}", name, bit, value as u8),
+        _ => format!("{}:{}#{}={}", name, bits, bit, value as u8),
+    }
+}
+
+/// Returns the bit attributes that encode the numeric attribute `name` (or `name:bits`) with value `value`
+pub fn numeric_attributes(name: &str, value: u64) -> Result<Vec<String>, RabeError> {
+    let (name, bits) = numeric_width(name)?;
+    check_value(value, bits)?;
+    Ok((0..bits).map(|bit| bit_attribute(name, bits, bit, (value >> bit) & 1 == 1)).collect())
+}
+
+/// Rejects `attributes` and a `policy` that use the same numeric attribute with different widths, e.g. a key with
+/// `age:8 = 42` and a policy with `age >= 18`, whose bit attributes would never match.
+pub fn check_widths(attributes: &[String], policy: &PolicyValue) -> Result<(), RabeError> {
+    let widths: Vec<(&str, usize)> = attributes.iter().filter_map(|attribute| bit_width(attribute)).collect();
+    let mut leaves: Vec<&str> = Vec::new();
+    collect_leaves(policy, &mut leaves);
+    for (name, bits) in leaves.into_iter().filter_map(bit_width) {
+        if let Some((_, other)) = widths.iter().find(|(other_name, other)| *other_name == name && *other != bits) {
+            return Err(RabeError::InvalidPolicy(format!("comparison: {} has {} bits in the policy, but {} bits in the attributes", name, bits, other)));
+        }
+    }
+    Ok(())
+}
+
+// the name and width of a bit attribute, see bit_attribute
+fn bit_width(attribute: &str) -> Option<(&str, usize)> {
+    let (name, bit) = attribute.rsplit_once('#')?;
+    match bit.split_once('=') {
+        Some((bit, "0" | "1")) if is_digits(bit) => numeric_width(name).ok(),
+        _ => None,
+    }
+}
+
+fn collect_leaves<'a>(policy: &'a PolicyValue, leaves: &mut Vec<&'a str>) {
+    match policy {
+        PolicyValue::String(node) => leaves.push(&node.0),
+        PolicyValue::Not(child) | PolicyValue::Object((_, child)) => collect_leaves(child, leaves),
+        PolicyValue::Array(children) => children.iter().for_each(|child| collect_leaves(child, leaves)),
+    }
+}
+
+/// Expands all numeric attributes of the form `name = value` (or `name:bits = value`) with an integer `value` to their
+/// bit attributes, all other attributes, like `dept=hr`, are kept as they are
+pub fn expand_attributes(attributes: &[&str]) -> Result<Vec<String>, RabeError> {
+    let mut expanded: Vec<String> = Vec::new();
+    for attribute in attributes {
+        match attribute.split_once('=') {
+            Some((name, value)) if is_integer(value.trim()) => {
+                let name = name.trim();
+                expanded.extend(numeric_attributes(name, parse_value(value.trim(), numeric_width(name)?.1)?)?)
+            },
+            _ => expanded.push(attribute.to_string()),
+        }
+    }
+    Ok(expanded)
+}
+
+// an optional sign followed by decimal digits, integers outside of the range of the width are rejected by parse_value
+fn is_integer(value: &str) -> bool {
+    is_digits(value.strip_prefix(['-', '+']).unwrap_or(value))
+}
+
+fn is_digits(value: &str) -> bool {
+    !value.is_empty() && value.bytes().all(|digit| digit.is_ascii_digit())
+}
+
+// the largest value of `bits` bits
+fn max_value(bits: usize) -> u64 {
+    u64::MAX >> (MAX_BITS - bits)
+}
+
+fn check_value(value: u64, bits: usize) -> Result<(), RabeError> {
+    match value <= max_value(bits) {
+        true => Ok(()),
+        false => Err(RabeError::InvalidPolicy(format!("comparison: {} is not an unsigned {} bit integer", value, bits))),
+    }
+}
+
+fn parse_value(value: &str, bits: usize) -> Result<u64, RabeError> {
+    match value.parse() {
+        Ok(parsed) => check_value(parsed, bits).map(|_| parsed),
+        Err(_) => Err(RabeError::InvalidPolicy(format!("comparison: {} is not an unsigned {} bit integer", value, bits))),
+    }
+}
+
+// the partially compiled sub-policy, constants are folded away
+enum Compiled<'a> {
+    Always(bool),
+    Policy(PolicyValue<'a>),
+}
+
+// joins a bit attribute and the compiled lower bits with an AND or OR, flattening nested gates of the same type
+fn join<'a>(policy_type: PolicyType, leaf: PolicyValue<'a>, lower: Compiled<'a>) -> Compiled<'a> {
+    let is_and = matches!(policy_type, PolicyType::And);
+    match lower {
+        // true absorbs an OR and is neutral for an AND, false the other way around
+        Compiled::Always(value) if value != is_and => Compiled::Always(value),
+        Compiled::Always(_) => Compiled::Policy(leaf),
+        Compiled::Policy(PolicyValue::Object((lower_type, children))) if matches!((&lower_type, is_and), (PolicyType::And, true) | (PolicyType::Or, false)) => {
+            match *children {
+                PolicyValue::Array(mut children) => {
+                    children.insert(0, leaf);
+                    Compiled::Policy(PolicyValue::Object((policy_type, Box::new(PolicyValue::Array(children)))))
+                },
+                children => Compiled::Policy(PolicyValue::Object((policy_type, Box::new(PolicyValue::Array(vec![leaf, PolicyValue::Object((lower_type, Box::new(children)))])))))
+            }
+        },
+        Compiled::Policy(lower) => Compiled::Policy(PolicyValue::Object((policy_type, Box::new(PolicyValue::Array(vec![leaf, lower]))))),
+    }
+}
+
+/// Compiles the comparison `name comparator value` to a sub-policy over the bit attributes of `name` (or `name:bits`).
+/// All leaves are placed at `position`. Comparisons that are satisfied by all values are compiled to `bit 0 = 0 or
+/// bit 0 = 1`, comparisons that cannot be satisfied and values that do not fit the width are rejected.
+pub fn comparison<'a>(name: &str, comparator: Comparator, value: u64, position: usize) -> Result<PolicyValue<'a>, RabeError> {
+    let (base, bits) = numeric_width(name)?;
+    check_value(value, bits)?;
+    let leaf = |bit: usize, set: bool| PolicyValue::String((bit_attribute(base, bits, bit, set).into(), position));
+    let mut compiled = match comparator {
+        Comparator::Less | Comparator::Greater => Compiled::Always(false),
+        Comparator::LessOrEqual | Comparator::GreaterOrEqual | Comparator::Equal => Compiled::Always(true),
+    };
+    // from the least to the most significant bit, the lower bits decide if the higher bits are equal
+    for bit in 0..bits {
+        let set = (value >> bit) & 1 == 1;
+        compiled = match (comparator, set) {
+            (Comparator::Equal, _) => join(PolicyType::And, leaf(bit, set), compiled),
+            (Comparator::Greater, false) | (Comparator::GreaterOrEqual, false) => join(PolicyType::Or, leaf(bit, true), compiled),
+            (Comparator::Greater, true) | (Comparator::GreaterOrEqual, true) => join(PolicyType::And, leaf(bit, true), compiled),
+            (Comparator::Less, true) | (Comparator::LessOrEqual, true) => join(PolicyType::Or, leaf(bit, false), compiled),
+            (Comparator::Less, false) | (Comparator::LessOrEqual, false) => join(PolicyType::And, leaf(bit, false), compiled),
+        };
+    }
+    match compiled {
+        Compiled::Policy(policy) => Ok(policy),
+        Compiled::Always(true) => Ok(PolicyValue::Object((PolicyType::Or, Box::new(PolicyValue::Array(vec![leaf(0, false), leaf(0, true)]))))),
+        Compiled::Always(false) => Err(RabeError::InvalidPolicy(format!("comparison: {} {} {} is never satisfied", name, comparator, value))),
+    }
+}
+
+/// Parses and compiles a comparison of the policy grammars
+pub(crate) fn parse_comparison<'a>(name: &str, comparator: &str, value: &str, position: usize) -> Result<PolicyValue<'a>, RabeError> {
+    comparison(name, Comparator::parse(comparator)?, parse_value(value, numeric_width(name)?.1)?, position)
+}
+
+/// Parses and compiles the inclusive range `min <= name <= max` of the policy grammars
+pub(crate) fn parse_range<'a>(name: &str, min: &str, max: &str, position: usize) -> Result<PolicyValue<'a>, RabeError> {
+    let bits = numeric_width(name)?.1;
+    range(name, parse_value(min, bits)?, parse_value(max, bits)?, position)
+}
+
+/// Compiles the inclusive range `min <= name <= max` to a sub-policy over the bit attributes of `name`, see [comparison].
+/// The lower bound only uses the attributes of set bits and the upper bound those of unset bits, so every bit attribute
+/// occurs at most once and the range fits any `max_reuse` bound of AC17 and AW11.
+pub fn range<'a>(name: &str, min: u64, max: u64, position: usize) -> Result<PolicyValue<'a>, RabeError> {
+    if min > max {
+        return Err(RabeError::InvalidPolicy(format!("comparison: empty range {} in [{}, {}]", name, min, max)));
+    }
+    // a bound that every value satisfies is left out
+    let bits = numeric_width(name)?.1;
+    match (min, max) {
+        (0, _) => comparison(name, Comparator::LessOrEqual, max, position),
+        (_, max) if max == max_value(bits) => comparison(name, Comparator::GreaterOrEqual, min, position),
+        _ => Ok(PolicyValue::Object((PolicyType::And, Box::new(PolicyValue::Array(vec![
+            comparison(name, Comparator::GreaterOrEqual, min, position)?,
+            comparison(name, Comparator::LessOrEqual, max, position)?,
+        ]))))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+    use utils::policy::msp::AbePolicy;
+    use utils::policy::pest::{parse, PolicyLanguage};
+    use utils::policy::explain::explain_value;
+    use utils::secretsharing::calc_pruned;
+    use utils::tools::traverse_policy;
+
+    #[test]
+    fn test_comparisons() {
+        let mut rng = ChaCha20Rng::seed_from_u64(12);
+        let comparators = [Comparator::Less, Comparator::LessOrEqual, Comparator::Greater, Comparator::GreaterOrEqual, Comparator::Equal];
+        let mut constants: Vec<u64> = vec![0, 1, 2, 18, 255, 256, u64::MAX - 1, u64::MAX];
+        constants.extend((0..8).map(|_| rng.gen::<u64>()));
+        for constant in constants.iter() {
+            for comparator in comparators {
+                let policy = match comparison("age", comparator, *constant, 0) {
+                    Ok(policy) => policy,
+                    Err(_) => {
+                        assert!(matches!((comparator, *constant), (Comparator::Less, 0) | (Comparator::Greater, u64::MAX)));
+                        continue;
+                    }
+                };
+                let msp = AbePolicy::from_policy(&policy).unwrap();
+                let mut values: Vec<u64> = constants.clone();
+                values.extend([constant.wrapping_sub(1), constant.wrapping_add(1)]);
+                values.extend((0..8).map(|_| rng.gen::<u64>()));
+                for value in values {
+                    let expected = match comparator {
+                        Comparator::Less => value < *constant,
+                        Comparator::LessOrEqual => value <= *constant,
+                        Comparator::Greater => value > *constant,
+                        Comparator::GreaterOrEqual => value >= *constant,
+                        Comparator::Equal => value == *constant,
+                    };
+                    let attributes = numeric_attributes("age", value).unwrap();
+                    assert_eq!(traverse_policy(&attributes, &policy, PolicyType::Leaf), expected, "{} {:?} {}", value, comparator, constant);
+                    assert_eq!(calc_pruned(&attributes, &policy, None).unwrap().0, expected);
+                    assert_eq!(msp.reconstruction(&attributes).is_some(), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_range_reuse() {
+        // a lower bound only uses the attributes of set bits and an upper bound only those of unset bits, so a range
+        // uses every bit attribute at most once and fits the tightest max_reuse bound of AC17 and AW11
+        let mut rng = ChaCha20Rng::seed_from_u64(16);
+        let mut bounds: Vec<(u64, u64)> = vec![(1, u64::MAX - 1), (18, 65), (255, 256), (42, 42), (0, 7), (7, u64::MAX)];
+        bounds.extend((0..8).map(|_| {
+            let (a, b) = (rng.gen::<u64>(), rng.gen::<u64>());
+            (a.min(b), a.max(b))
+        }));
+        for (min, max) in bounds {
+            let policy = range("age", min, max, 0).unwrap();
+            assert!(AbePolicy::from_policy_with_max_reuse(&policy, 1).is_ok(), "[{}, {}]", min, max);
+            for value in [min, max, min.wrapping_sub(1), max.wrapping_add(1), rng.gen()] {
+                let expected = min <= value && value <= max;
+                assert_eq!(traverse_policy(&numeric_attributes("age", value).unwrap(), &policy, PolicyType::Leaf), expected, "{} in [{}, {}]", value, min, max);
+            }
+        }
+        // the same holds for a range that is written as two comparisons
+        let policy = parse(r#""A" and age >= 18 and age < 65"#, PolicyLanguage::HumanPolicy).unwrap();
+        assert_eq!(AbePolicy::from_policy(&policy).unwrap().max_reuse(), 1);
+    }
+
+    #[test]
+    fn test_expand_attributes() {
+        let attributes = expand_attributes(&["A", "level = 5"]).unwrap();
+        assert_eq!(attributes.len(), 65);
+        assert_eq!(attributes[0], "A");
+        assert!(attributes.contains(&String::from("level#0=1")));
+        assert!(attributes.contains(&String::from("level#1=0")));
+        assert!(attributes.contains(&String::from("level#2=1")));
+        assert!(attributes.contains(&String::from("level#63=0")));
+        assert!(expand_attributes(&["level = -5"]).is_err());
+        assert!(expand_attributes(&["level = 18446744073709551616"]).is_err());
+        // values that are not integers are no numeric attributes
+        assert_eq!(expand_attributes(&["dept=hr", "level = 1.5", "x="]).unwrap(), ["dept=hr", "level = 1.5", "x="]);
+        // the width is part of the name, a width of 64 bits is the same as no width
+        let attributes = expand_attributes(&["level:3 = 5"]).unwrap();
+        assert_eq!(attributes, ["level:3#0=1", "level:3#1=0", "level:3#2=1"]);
+        assert_eq!(expand_attributes(&["level:64 = 5"]).unwrap(), expand_attributes(&["level = 5"]).unwrap());
+        assert!(expand_attributes(&["level:3 = 8"]).is_err());
+        assert!(expand_attributes(&["level:0 = 0"]).is_err());
+        assert!(expand_attributes(&["level:65 = 0"]).is_err());
+        // names with a colon that is not followed by a number have 64 bits
+        assert_eq!(expand_attributes(&["idsc:level = 5"]).unwrap().len(), 64);
+    }
+
+    #[test]
+    fn test_widths() {
+        let comparators = [Comparator::Less, Comparator::LessOrEqual, Comparator::Greater, Comparator::GreaterOrEqual, Comparator::Equal];
+        for bits in [1u32, 3, 8] {
+            let name = format!("age:{}", bits);
+            let max = (1u64 << bits) - 1;
+            for constant in 0..=max {
+                for comparator in comparators {
+                    let policy = match comparison(&name, comparator, constant, 0) {
+                        Ok(policy) => policy,
+                        Err(_) => {
+                            assert!(matches!((comparator, constant), (Comparator::Less, 0)) || (comparator == Comparator::Greater && constant == max));
+                            continue;
+                        }
+                    };
+                    for value in 0..=max {
+                        let expected = match comparator {
+                            Comparator::Less => value < constant,
+                            Comparator::LessOrEqual => value <= constant,
+                            Comparator::Greater => value > constant,
+                            Comparator::GreaterOrEqual => value >= constant,
+                            Comparator::Equal => value == constant,
+                        };
+                        let attributes = numeric_attributes(&name, value).unwrap();
+                        assert_eq!(traverse_policy(&attributes, &policy, PolicyType::Leaf), expected, "{} {:?} {}", value, comparator, constant);
+                    }
+                }
+            }
+            assert!(comparison(&name, Comparator::Less, max + 1, 0).is_err());
+            assert!(numeric_attributes(&name, max + 1).is_err());
+            assert!(range(&name, 0, max + 1, 0).is_err());
+        }
+        // the upper bound of a range is left out if it is the largest value of the width
+        let policy = parse("age:8 in [18, 255]", PolicyLanguage::HumanPolicy).unwrap();
+        assert_eq!(AbePolicy::from_policy(&policy).unwrap().pi.len(), 7);
+        for (value, expected) in [(17, false), (18, true), (255, true)] {
+            assert_eq!(traverse_policy(&numeric_attributes("age:8", value).unwrap(), &policy, PolicyType::Leaf), expected);
+        }
+        assert!(parse("age:8 >= 256", PolicyLanguage::HumanPolicy).is_err());
+        assert!(parse(r#"{"name": "age:8", ">=": 256}"#, PolicyLanguage::JsonPolicy).is_err());
+    }
+
+    #[test]
+    fn test_check_widths() {
+        let policy = parse(r#""A" and age:8 >= 18"#, PolicyLanguage::HumanPolicy).unwrap();
+        assert!(check_widths(&expand_attributes(&["A", "age:8 = 42"]).unwrap(), &policy).is_ok());
+        // other numeric attributes and attributes with a colon do not matter
+        assert!(check_widths(&expand_attributes(&["A", "level = 42", "idsc:age"]).unwrap(), &policy).is_ok());
+        for attributes in [["A", "age = 42"], ["A", "age:7 = 42"]] {
+            let attributes = expand_attributes(&attributes).unwrap();
+            assert!(matches!(check_widths(&attributes, &policy), Err(RabeError::InvalidPolicy(_))));
+            // a policy that would never match is rejected when it is explained
+            assert!(matches!(explain_value(&attributes, &policy), Err(RabeError::InvalidPolicy(_))));
+        }
+    }
+}
@@ -9,6 +9,11 @@ use crate::{
 };
 use utils::policy::pest::{PolicyLanguage, PolicyValue, parse, PolicyType};
 
+/// The maximum number of terms a policy is expanded to by [to_dnf], and by the encryption of MKE08 and BDABE. Every
+/// term costs a group element in the ciphertext, and the number of terms grows exponentially with the number of ANDs
+/// of ORs. Use [to_dnf_with_limit] for another limit.
+pub const MAX_DNF_TERMS: usize = 1024;
+
 /// A DNF policy for the MKE08 scheme and the BDABE scheme
 pub struct DnfPolicy {
     pub terms: Vec<(Vec<String>, Gt, Gt, G1, G2)>,
@@ -99,93 +104,123 @@ impl PublicAttributeKey for BdabePublicAttributeKey {
     }
 }
 
-// this calcluates the sum's of all AND terms in a Bdabe DNF policy
-pub fn dnf<K: PublicAttributeKey>(
-    dnf_policy: &mut DnfPolicy,
-    pks: &[&K],
-    policy_value: &PolicyValue,
-    i: usize,
-    parent: Option<&PolicyType>
-) -> bool {
-    let mut ret = true;
-    // inner node
-    return match policy_value {
-        PolicyValue::String(_s) => {
-            for pak in pks.iter() {
-                if pak.attr() == *_s.0 {
-                    if dnf_policy.terms.len() > i.clone() {
-                        dnf_policy.terms[i.clone()].0.push(pak.attr().to_string());
-                        dnf_policy.terms[i.clone()] = (
-                            dnf_policy.terms[i.clone()].0.clone(),
-                            dnf_policy.terms[i.clone()].1 * pak.gt1(),
-                            dnf_policy.terms[i.clone()].2 * pak.gt2(),
-                            dnf_policy.terms[i.clone()].3 + pak.g1(),
-                            dnf_policy.terms[i.clone()].4 + pak.g2(),
-                        );
-                    } else {
-                        dnf_policy.terms.push((
-                            vec![pak.attr().to_string()],
-                            pak.gt1(),
-                            pak.gt2(),
-                            pak.g1(),
-                            pak.g2(),
-                        ));
-                    }
-                }
+/// Converts an arbitrary policy of AND, OR and THRESHOLD gates to disjunctive normal form. Returns the conjunctions
+/// (terms) of the DNF as sorted lists of attribute names. Terms that contain another term are absorbed, i.e. the DNF is
+/// minimal. Policies that expand to more than [MAX_DNF_TERMS] terms are rejected, negations are not supported.
+pub fn to_dnf(policy_value: &PolicyValue) -> Result<Vec<Vec<String>>, RabeError> {
+    to_dnf_with_limit(policy_value, MAX_DNF_TERMS)
+}
+
+/// Like [to_dnf], but rejects policies that expand to more than `limit` terms.
+pub fn to_dnf_with_limit(policy_value: &PolicyValue, limit: usize) -> Result<Vec<Vec<String>>, RabeError> {
+    let (policy_type, child) = match policy_value {
+        PolicyValue::String(node) => return Ok(vec![vec![node.0.to_string()]]),
+        PolicyValue::Not(_) => return Err(RabeError::InvalidPolicy(String::from("dnf: negations are not supported by the DNF schemes"))),
+        PolicyValue::Array(_) => return Err(RabeError::InvalidPolicy(String::from("dnf: children without AND, OR or THRESHOLD"))),
+        PolicyValue::Object((PolicyType::Leaf, child)) => return to_dnf_with_limit(child, limit),
+        PolicyValue::Object((policy_type, child)) => (policy_type, child),
+    };
+    let children = match child.as_ref() {
+        PolicyValue::Array(children) if !children.is_empty() => children,
+        _ => return Err(RabeError::InvalidPolicy(String::from("dnf: AND, OR or THRESHOLD without children"))),
+    };
+    match policy_type {
+        PolicyType::Or => {
+            let mut terms: Vec<Vec<String>> = Vec::new();
+            for child in children {
+                terms.extend(to_dnf_with_limit(child, limit)?);
             }
-            true
+            minimize(terms, limit)
         },
-        PolicyValue::Array(children) => {
-            return match parent {
-                Some(PolicyType::And) => {
-                    for child in children {
-                        ret = ret && dnf(dnf_policy, pks, &child, i.clone(), Some(&PolicyType::And))
-                    }
-                    ret
-                },
-                Some(PolicyType::Or) => {
-                    for (i, child) in children.iter().enumerate() {
-                        ret = ret && dnf(dnf_policy, pks, &child, i.clone() + i.clone(), Some(&PolicyType::Or))
-                    }
-                    ret
-                },
-                _ => false,
+        PolicyType::Threshold(k) if *k != children.len() => {
+            let k = *k;
+            if k == 0 || k > children.len() {
+                return Err(RabeError::InvalidPolicy(format!("dnf: threshold {} of {} children", k, children.len())));
             }
-        },
-        PolicyValue::Object(obj) => {
-            return match parent {
-                None => dnf(dnf_policy, pks, &obj.1, i, Some(&obj.0)),
-                Some(PolicyType::Leaf) => dnf(dnf_policy, pks, &obj.1, i, Some(&PolicyType::Leaf)),
-                Some(PolicyType::Or) => {
-                    match &obj.0 {
-                        PolicyType::And => dnf(dnf_policy, pks, &obj.1, i, Some(&PolicyType::And)),
-                        _ => false,
-                    }
-                }
-                Some(PolicyType::And) => {
-                    match &obj.0 {
-                        PolicyType::Leaf => dnf(dnf_policy, pks, &obj.1, i, Some(&PolicyType::Leaf)),
-                        _ => false,
-                    }
+            // at_least[j] is the DNF of "at least j of the children seen so far", the empty DNF is never satisfied
+            let mut at_least: Vec<Vec<Vec<String>>> = vec![vec![vec![]]];
+            at_least.resize(k + 1, Vec::new());
+            for child in children {
+                let child = to_dnf_with_limit(child, limit)?;
+                for j in (1..=k).rev() {
+                    let mut terms = conjunction(&at_least[j - 1], &child, limit)?;
+                    terms.append(&mut at_least[j]);
+                    at_least[j] = minimize(terms, limit)?;
                 }
             }
+            Ok(at_least.swap_remove(k))
+        },
+        // an AND, or a threshold n of n
+        _ => {
+            let mut terms: Vec<Vec<String>> = vec![vec![]];
+            for child in children {
+                terms = minimize(conjunction(&terms, &to_dnf_with_limit(child, limit)?, limit)?, limit)?;
+            }
+            Ok(terms)
+        },
+    }
+}
+
+// the DNF of the AND of two DNFs, i.e. the union of all pairs of their terms
+fn conjunction(left: &[Vec<String>], right: &[Vec<String>], limit: usize) -> Result<Vec<Vec<String>>, RabeError> {
+    if left.len().saturating_mul(right.len()) > limit {
+        return Err(RabeError::InvalidPolicy(format!("dnf: policy expands to more than {} terms", limit)));
+    }
+    let mut terms: Vec<Vec<String>> = Vec::new();
+    for l in left {
+        for r in right {
+            let mut term: Vec<String> = l.iter().chain(r.iter()).cloned().collect();
+            term.sort();
+            term.dedup();
+            terms.push(term);
         }
     }
+    Ok(terms)
 }
 
-// this calcluates the sum's of all conjunction terms in a Bdabe DNF policy ( see fn dnf() )
+// removes duplicate terms and terms that contain another term
+fn minimize(mut terms: Vec<Vec<String>>, limit: usize) -> Result<Vec<Vec<String>>, RabeError> {
+    terms.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+    terms.dedup();
+    let mut minimal: Vec<Vec<String>> = Vec::new();
+    for term in terms {
+        if !minimal.iter().any(|shorter| shorter.iter().all(|attribute| term.contains(attribute))) {
+            minimal.push(term);
+        }
+    }
+    if minimal.len() > limit {
+        return Err(RabeError::InvalidPolicy(format!("dnf: policy expands to more than {} terms", limit)));
+    }
+    Ok(minimal)
+}
+
+/// Converts a policy to DNF (see [to_dnf]) and calculates the sums of the public attribute keys of all terms. Returns
+/// [RabeError::UnknownAttribute] if an attribute of the policy has no public attribute key in `pks`.
 pub fn json_to_dnf<K: PublicAttributeKey>(
     policy_value: &PolicyValue,
     pks: &[&K],
 ) -> Result<DnfPolicy, RabeError> {
     let mut dnfp = DnfPolicy::new();
-    if dnf(&mut dnfp, pks, policy_value, 0, None) {
-        dnfp.terms.sort_by(|a, b| a.0.len().cmp(&b.0.len()));
-        Ok(dnfp)
+    for term in to_dnf(policy_value)? {
+        let mut sums: Option<(Gt, Gt, G1, G2)> = None;
+        for attribute in term.iter() {
+            let pk = pks
+                .iter()
+                .find(|pk| pk.attr() == *attribute)
+                .ok_or_else(|| RabeError::UnknownAttribute(attribute.to_string()))?;
+            sums = Some(match sums {
+                None => (pk.gt1(), pk.gt2(), pk.g1(), pk.g2()),
+                Some(sums) => (sums.0 * pk.gt1(), sums.1 * pk.gt2(), sums.2 + pk.g1(), sums.3 + pk.g2()),
+            });
+        }
+        if let Some(sums) = sums {
+            dnfp.terms.push((term, sums.0, sums.1, sums.2, sums.3));
+        }
     }
-    else {
-        Err(RabeError::new("Error in json_to_dnf: could not parse policy as DNF"))
+    if dnfp.terms.is_empty() {
+        return Err(RabeError::InvalidPolicy(String::from("dnf: policy without terms")));
     }
+    Ok(dnfp)
 }
 
 pub fn policy_in_dnf(
@@ -199,9 +234,13 @@ pub fn policy_in_dnf(
                 PolicyType::And=> policy_in_dnf(&obj.1.as_ref(), true, Some(PolicyType::And)),
                 PolicyType::Or => policy_in_dnf(&obj.1.as_ref(), conjunction, Some(PolicyType::Or)),
                 PolicyType::Leaf => policy_in_dnf(&obj.1.as_ref(), conjunction, Some(PolicyType::Leaf)),
+                // a threshold gate is neither a conjunction nor a disjunction
+                PolicyType::Threshold(_) => false,
             }
         },
         PolicyValue::String(_str) => true,
+        // negations cannot be expressed by the monotone DNF schemes
+        PolicyValue::Not(_) => false,
         PolicyValue::Array(children) => {
             let mut ret = true;
             match parent {
@@ -221,10 +260,8 @@ pub fn policy_in_dnf(
                     }
                     return ret;
                 },
-                _ => {
-                    println!("policy_in_dnf: policy is not in DNF! Array without parent AND or OR.");
-                    false
-                },
+                // an array without parent AND or OR
+                _ => false,
             }
         }
     }
@@ -240,29 +277,14 @@ mod tests {
         let policy_in_dnf1 = String::from(r#"{"name": "or", "children": [{"name": "and", "children": [{"name": "A"}, {"name": "B"}]}, {"name": "and", "children":  [{"name": "A"}, {"name": "C"}]}]}"#);
         let policy_in_dnf2 = String::from(r#"{"name": "and", "children": [{"name": "C"}, {"name": "D"}]}"#);
         let policy_in_dnf3 = String::from(r#"{"name": "or", "children": [{"name": "C"}, {"name": "and",  "children": [{"name": "A"}, {"name": "C"}]}, {"name" :"and",  "children": [{"name": "A"}, {"name": "D"}]}]}"#);
-        let policy_not_dnf1 = String::from(r#"{"name": "and", "children": [{"name": "or",  "children":: [{"name": "A"}, {"name": "B"}]}, {"name": "and",  "children": [{"name": "C"}, {"name": "D"}]}]}"#);
+        let policy_not_dnf1 = String::from(r#"{"name": "and", "children": [{"name": "or",  "children": [{"name": "A"}, {"name": "B"}]}, {"name": "and",  "children": [{"name": "C"}, {"name": "D"}]}]}"#);
         let policy_not_dnf2 = String::from(r#"{"name": "or", "children":  [{"name": "and",  "children": [{"name": "or",  "children": [{"name": "C"}, {"name": "D"}]}, {"name": "B"}]}, {"name": "and",  "children": [{"name": "C"}, {"name": "D"}]}]}"#);
 
-        match parse(policy_in_dnf1.as_ref(), PolicyLanguage::JsonPolicy) {
-            Ok(pol) => assert!(policy_in_dnf(&pol, false, None)),
-            Err(e) => println!("could not parse policy_in_dnf1 {}", e)
-        }
-        match parse(policy_in_dnf2.as_ref(), PolicyLanguage::JsonPolicy) {
-            Ok(pol) => assert!(policy_in_dnf(&pol, false, None)),
-            Err(e) => println!("could not parse policy_in_dnf2 {}", e)
-        }
-        match parse(policy_in_dnf3.as_ref(), PolicyLanguage::JsonPolicy) {
-            Ok(pol) => assert!(policy_in_dnf(&pol, false, None)),
-            Err(e) => println!("could not parse policy_in_dnf3 {}", e)
-        }
-
-        match parse(policy_not_dnf1.as_ref(), PolicyLanguage::JsonPolicy) {
-            Ok(pol) => assert!(!policy_in_dnf(&pol, false, None)),
-            Err(e) => println!("could not parse policy_not_dnf1 (this is intended): {}", e)
+        for policy in [&policy_in_dnf1, &policy_in_dnf2, &policy_in_dnf3] {
+            assert!(policy_in_dnf(&parse(policy, PolicyLanguage::JsonPolicy).unwrap(), false, None), "{}", policy);
         }
-        match parse(policy_not_dnf2.as_ref(), PolicyLanguage::JsonPolicy) {
-            Ok(pol) => assert!(!policy_in_dnf(&pol, false, None)),
-            Err(e) => println!("could not parse policy_not_dnf2 (this is intended): {}", e)
+        for policy in [&policy_not_dnf1, &policy_not_dnf2] {
+            assert!(!policy_in_dnf(&parse(policy, PolicyLanguage::JsonPolicy).unwrap(), false, None), "{}", policy);
         }
 
         let pk_a = BdabePublicAttributeKey {
@@ -299,9 +321,39 @@ mod tests {
         let policy2: DnfPolicy = DnfPolicy::from_string(&policy_in_dnf2, &pks.as_slice(), PolicyLanguage::JsonPolicy).unwrap();
         let policy3: DnfPolicy = DnfPolicy::from_string(&policy_in_dnf3, &pks.as_slice(), PolicyLanguage::JsonPolicy).unwrap();
 
-        assert_eq!(policy1.terms.len(), 3);
+        assert_eq!(policy1.terms.len(), 2);
         assert_eq!(policy2.terms.len(), 1);
-        assert_eq!(policy3.terms.len(), 5);
+        // "C" absorbs "A" and "C"
+        assert_eq!(policy3.terms.len(), 2);
+        assert_eq!(policy3.terms[1].0, vec!["A", "D"]);
+        // an attribute without public attribute key is an error, even if other terms could be encrypted
+        for policy in [r#"("A" and "E") or "B""#, r#""A" and "E""#] {
+            match DnfPolicy::from_string(policy, pks.as_slice(), PolicyLanguage::HumanPolicy) {
+                Err(RabeError::UnknownAttribute(attribute)) => assert_eq!(attribute, "E"),
+                _ => panic!("dnf: unknown attribute E accepted in {}", policy),
+            }
+        }
+    }
+
+    #[test]
+    fn test_to_dnf() {
+        let dnf = |policy: &str| to_dnf(&parse(policy, PolicyLanguage::HumanPolicy).unwrap());
+        let terms = |terms: &[&[&str]]| -> Vec<Vec<String>> { terms.iter().map(|t| t.iter().map(|a| a.to_string()).collect()).collect() };
+        assert_eq!(dnf(r#""A""#).unwrap(), terms(&[&["A"]]));
+        assert_eq!(dnf(r#"("A" or "B") and ("C" or "D")"#).unwrap(), terms(&[&["A", "C"], &["A", "D"], &["B", "C"], &["B", "D"]]));
+        assert_eq!(dnf(r#""A" and ("A" or "B") and "B""#).unwrap(), terms(&[&["A", "B"]]));
+        assert_eq!(dnf(r#""A" or ("A" and "B")"#).unwrap(), terms(&[&["A"]]));
+        assert_eq!(dnf(r#"2 of ("A", "B", "C")"#).unwrap(), terms(&[&["A", "B"], &["A", "C"], &["B", "C"]]));
+        assert_eq!(dnf(r#""A" and 2 of ("B", "C" or "D", "E")"#).unwrap().len(), 5);
+        assert!(matches!(dnf(r#""A" and not "B""#), Err(RabeError::InvalidPolicy(_))));
+        // 2^11 terms exceed the limit
+        let blowup: Vec<String> = (0..11).map(|i| format!(r#"("A{}" or "B{}")"#, i, i)).collect();
+        assert!(matches!(dnf(&blowup.join(" and ")), Err(RabeError::InvalidPolicy(_))));
+        let limit: Vec<String> = (0..10).map(|i| format!(r#"("A{}" or "B{}")"#, i, i)).collect();
+        assert_eq!(dnf(&limit.join(" and ")).unwrap().len(), MAX_DNF_TERMS);
+        let policy = parse(r#"("A" or "B") and ("C" or "D")"#, PolicyLanguage::HumanPolicy).unwrap();
+        assert_eq!(to_dnf_with_limit(&policy, 4).unwrap().len(), 4);
+        assert!(matches!(to_dnf_with_limit(&policy, 3), Err(RabeError::InvalidPolicy(_))));
     }
 
 }
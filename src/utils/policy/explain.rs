@@ -0,0 +1,256 @@
+//! Explains why a set of attributes does or does not satisfy a policy.
+//!
+//! An [Explanation] tells if the attributes satisfy the policy, which of the attributes are needed to satisfy it and,
+//! if the policy is not satisfied, which attributes are missing. This can be used to check if a secret key is able to
+//! decrypt a ciphertext before any pairing is calculated.
+//!
+//! # Examples
+//!
+//! ```
+//! use rabe::utils::policy::explain::explain;
+//! use rabe::utils::policy::pest::PolicyLanguage;
+//! let policy = r#"("A" and "B") or ("C" and "D" and "E")"#;
+//! let attributes = vec![String::from("A"), String::from("C")];
+//! let explanation = explain(&attributes, (policy, PolicyLanguage::HumanPolicy)).unwrap();
+//! assert!(!explanation.satisfied);
+//! assert_eq!(explanation.missing, vec![vec![String::from("B")]]);
+//! let attributes = vec![String::from("A"), String::from("B"), String::from("C")];
+//! let explanation = explain(&attributes, (policy, PolicyLanguage::HumanPolicy)).unwrap();
+//! assert!(explanation.satisfied);
+//! assert_eq!(explanation.used, vec![String::from("A"), String::from("B")]);
+//! ```
+use std::fmt::{Display, Formatter, Result as FormatResult};
+use crate::error::RabeError;
+use utils::policy::ast::PolicySource;
+use utils::policy::pest::{negation_normal_form, PolicyType, PolicyValue};
+use utils::policy::comparison::check_widths;
+use utils::tools::{contains, traverse_policy};
+
+/// The maximum number of alternative attribute sets that are kept for each node of the policy
+const MAX_ALTERNATIVES: usize = 16;
+
+/// The result of [explain]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Explanation {
+    /// True if the attributes satisfy the policy
+    pub satisfied: bool,
+    /// A smallest subset of the attributes that satisfies the policy, empty if the policy is not satisfied. Negated
+    /// attributes are satisfied by the absence of an attribute and do not appear here.
+    pub used: Vec<String>,
+    /// The smallest sets of attributes, one of which has to be added to satisfy the policy. Empty if the policy is
+    /// satisfied or if it cannot be satisfied by adding attributes, e.g. because one of the attributes is negated.
+    pub missing: Vec<Vec<String>>,
+}
+
+impl Display for Explanation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        let quoted = |attributes: &[String]| attributes.iter().map(|attribute| format!("\"{}\"", attribute)).collect::<Vec<String>>().join(", ");
+        if self.satisfied {
+            return write!(f, "satisfied by [{}]", quoted(&self.used));
+        }
+        if self.missing.is_empty() {
+            return write!(f, "not satisfied, adding attributes does not help");
+        }
+        let missing: Vec<String> = self.missing.iter().map(|attributes| format!("[{}]", quoted(attributes))).collect();
+        write!(f, "not satisfied, missing one of {}", missing.join(", "))
+    }
+}
+
+/// Explains if `attributes` satisfy `policy`, see [Explanation].
+///
+/// # Arguments
+///
+///	* `attributes` - The attributes, e.g. of a CP-ABE secret key or a KP-ABE ciphertext
+///	* `policy` - The policy, e.g. of a CP-ABE ciphertext or a KP-ABE secret key, see [PolicySource]
+pub fn explain<P: PolicySource>(attributes: &[String], policy: P) -> Result<Explanation, RabeError> {
+    explain_value(attributes, &policy.value()?.0)
+}
+
+/// Explains if `attributes` satisfy the parsed `policy`, see [explain]. The attribute sets are exact if every attribute
+/// occurs only once in the policy, otherwise they are satisfying but not necessarily the smallest ones. Numeric
+/// attributes whose width differs from the one in the policy are rejected, see [check_widths].
+pub fn explain_value(attributes: &[String], policy: &PolicyValue) -> Result<Explanation, RabeError> {
+    check_widths(attributes, policy)?;
+    let attributes = attributes.to_vec();
+    let policy = negation_normal_form(policy.clone());
+    let satisfied = traverse_policy(&attributes, &policy, PolicyType::Leaf);
+    let held = |attribute: &str| contains(&attributes, &attribute.to_string());
+    if satisfied {
+        // the held attributes are used, negated attributes are satisfied by their absence
+        let used = smallest(&policy, &|attribute, negated| match (held(attribute), negated) {
+            (true, false) => Some(vec![(attribute.to_string(), false)]),
+            (false, true) => Some(vec![(attribute.to_string(), true)]),
+            _ => None,
+        })?;
+        Ok(Explanation { satisfied, used: used.into_iter().map(attributes_of).next().unwrap_or_default(), missing: Vec::new() })
+    } else {
+        // the missing attributes are added, negated attributes that are held cannot be fixed
+        let missing = smallest(&policy, &|attribute, negated| match (held(attribute), negated) {
+            (true, false) => Some(vec![]),
+            (false, false) => Some(vec![(attribute.to_string(), false)]),
+            (false, true) => Some(vec![(attribute.to_string(), true)]),
+            (true, true) => None,
+        })?;
+        let mut missing: Vec<Vec<String>> = missing.into_iter().map(attributes_of).collect();
+        missing.sort();
+        missing.dedup();
+        Ok(Explanation { satisfied, used: Vec::new(), missing })
+    }
+}
+
+/// The error of a failed decapsulation, explaining which attributes are missing, or rejecting numeric attributes whose
+/// width differs from the one in the policy
+pub(crate) fn not_satisfied(message: &str, attributes: &[String], policy: &PolicyValue) -> RabeError {
+    if let Err(RabeError::InvalidPolicy(reason)) = check_widths(attributes, policy) {
+        return RabeError::InvalidPolicy(format!("{}, {}", message, reason));
+    }
+    match explain_value(attributes, policy) {
+        Ok(explanation) => RabeError::PolicyNotSatisfied(format!("{}, {}", message, explanation)),
+        Err(_) => RabeError::PolicyNotSatisfied(message.to_string()),
+    }
+}
+
+// a set of literals, i.e. attributes that have to be present (false) or absent (true)
+type Literals = Vec<(String, bool)>;
+
+fn attributes_of(literals: Literals) -> Vec<String> {
+    literals.into_iter().filter(|(_, negated)| !negated).map(|(attribute, _)| attribute).collect()
+}
+
+// the smallest sorted literal sets that satisfy a node, empty if the node cannot be satisfied. `leaf` returns the
+// literals of an attribute or a negated attribute, or None if the leaf cannot be satisfied. The size of a set is the
+// number of attributes that have to be present.
+fn smallest(policy: &PolicyValue, leaf: &dyn Fn(&str, bool) -> Option<Literals>) -> Result<Vec<Literals>, RabeError> {
+    let (policy_type, child) = match policy {
+        PolicyValue::String(node) => return Ok(leaf(&node.0, false).into_iter().collect()),
+        PolicyValue::Not(child) => match child.as_ref() {
+            PolicyValue::String(node) => return Ok(leaf(&node.0, true).into_iter().collect()),
+            _ => return Err(RabeError::InvalidPolicy(String::from("explain: only attributes can be negated"))),
+        },
+        PolicyValue::Array(_) => return Err(RabeError::InvalidPolicy(String::from("explain: children without AND, OR or THRESHOLD"))),
+        PolicyValue::Object((PolicyType::Leaf, child)) => return smallest(child, leaf),
+        PolicyValue::Object((policy_type, child)) => (policy_type, child),
+    };
+    let children = match child.as_ref() {
+        PolicyValue::Array(children) if !children.is_empty() => children,
+        _ => return Err(RabeError::InvalidPolicy(String::from("explain: AND, OR or THRESHOLD without children"))),
+    };
+    let k = match policy_type {
+        PolicyType::And => children.len(),
+        PolicyType::Threshold(k) => *k,
+        _ => 1,
+    };
+    if k == 0 || k > children.len() {
+        return Err(RabeError::InvalidPolicy(format!("explain: threshold {} of {} children", k, children.len())));
+    }
+    // at_least[j] are the smallest sets that satisfy at least j of the children seen so far
+    let mut at_least: Vec<Vec<Literals>> = vec![vec![vec![]]];
+    at_least.resize(k + 1, Vec::new());
+    for child in children {
+        let child = smallest(child, leaf)?;
+        for j in (1..=k).rev() {
+            let mut sets: Vec<Literals> = Vec::new();
+            for set in at_least[j - 1].iter() {
+                for other in child.iter() {
+                    let mut union = set.clone();
+                    union.extend(other.iter().cloned());
+                    union.sort();
+                    union.dedup();
+                    // an attribute cannot be present and absent
+                    if union.windows(2).all(|pair| pair[0].0 != pair[1].0) {
+                        sets.push(union);
+                    }
+                }
+            }
+            sets.append(&mut at_least[j]);
+            at_least[j] = keep_smallest(sets);
+        }
+    }
+    Ok(at_least.swap_remove(k))
+}
+
+// removes duplicate sets and all sets that are larger than the smallest set
+fn keep_smallest(mut sets: Vec<Literals>) -> Vec<Literals> {
+    let size = |set: &Literals| set.iter().filter(|(_, negated)| !negated).count();
+    let smallest = sets.iter().map(size).min().unwrap_or(0);
+    sets.retain(|set| size(set) == smallest);
+    sets.sort();
+    sets.dedup();
+    sets.truncate(MAX_ALTERNATIVES);
+    sets
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+    use utils::policy::ast::Policy;
+    use utils::policy::pest::{parse, PolicyLanguage};
+    use utils::policy::comparison::Comparator;
+
+    fn strings(attributes: &[&str]) -> Vec<String> {
+        attributes.iter().map(|attribute| attribute.to_string()).collect()
+    }
+
+    #[test]
+    fn test_explain() {
+        let policy = r#""A" and 2 of ("B", "C" and "D", "E" or "F", not "G")"#;
+        let explanation = explain(&strings(&["A", "B", "C", "D", "E"]), (policy, PolicyLanguage::HumanPolicy)).unwrap();
+        assert_eq!(explanation, Explanation { satisfied: true, used: strings(&["A", "B"]), missing: vec![] });
+        let explanation = explain(&strings(&["A", "C", "D", "E", "G"]), (policy, PolicyLanguage::HumanPolicy)).unwrap();
+        assert_eq!(explanation.used, strings(&["A", "C", "D", "E"]));
+        let explanation = explain(&strings(&["A", "G"]), (policy, PolicyLanguage::HumanPolicy)).unwrap();
+        assert_eq!(explanation.missing, vec![strings(&["B", "E"]), strings(&["B", "F"])]);
+        assert_eq!(explanation.to_string(), r#"not satisfied, missing one of ["B", "E"], ["B", "F"]"#);
+        let explanation = explain(&strings(&["C"]), (policy, PolicyLanguage::HumanPolicy)).unwrap();
+        assert_eq!(explanation.missing, vec![strings(&["A", "B"]), strings(&["A", "D"]), strings(&["A", "E"]), strings(&["A", "F"])]);
+        let explanation = explain(&strings(&["A", "B"]), (r#""A" and not "B""#, PolicyLanguage::HumanPolicy)).unwrap();
+        assert_eq!(explanation, Explanation { satisfied: false, used: vec![], missing: vec![] });
+        assert_eq!(explanation.to_string(), "not satisfied, adding attributes does not help");
+        let policy = Policy::and([Policy::attr("A").unwrap(), Policy::comparison("age", Comparator::GreaterOrEqual, 18).unwrap()]);
+        let explanation = explain(&strings(&["A"]), &policy).unwrap();
+        assert!(!explanation.satisfied && !explanation.missing.is_empty());
+    }
+
+    fn random_policy<R: Rng>(rng: &mut R, depth: usize) -> Policy {
+        if depth == 0 || rng.gen_bool(0.3) {
+            let attribute = Policy::attr(["A", "B", "C", "D", "E"][rng.gen_range(0..5)]).unwrap();
+            return if rng.gen_bool(0.1) { !attribute } else { attribute };
+        }
+        let children: Vec<Policy> = (0..rng.gen_range(1..4)).map(|_| random_policy(rng, depth - 1)).collect();
+        match rng.gen_range(0..3) {
+            0 => Policy::And(children),
+            1 => Policy::Or(children),
+            _ => Policy::Threshold(rng.gen_range(1..=children.len()), children),
+        }
+    }
+
+    #[test]
+    fn test_explain_random() {
+        let mut rng = ChaCha20Rng::seed_from_u64(17);
+        let universe = ["A", "B", "C", "D", "E"];
+        for _ in 0..200 {
+            let text = random_policy(&mut rng, 3).to_string();
+            let policy = parse(&text, PolicyLanguage::HumanPolicy).unwrap();
+            for subset in 1..1 << universe.len() {
+                let attributes: Vec<String> = (0..universe.len()).filter(|i| subset >> i & 1 == 1).map(|i| universe[i].to_string()).collect();
+                let explanation = explain_value(&attributes, &policy).unwrap();
+                assert_eq!(explanation.satisfied, traverse_policy(&attributes, &policy, PolicyType::Leaf), "{} {:?}", text, attributes);
+                if explanation.satisfied && !explanation.used.is_empty() {
+                    // the used attributes are a satisfying subset of the attributes
+                    assert!(explanation.used.iter().all(|attribute| attributes.contains(attribute)));
+                    assert!(traverse_policy(&explanation.used, &policy, PolicyType::Leaf), "{} {:?}", text, explanation);
+                }
+                for missing in explanation.missing.iter() {
+                    // adding any of the missing sets satisfies the policy
+                    assert!(missing.iter().all(|attribute| !attributes.contains(attribute)));
+                    let mut extended = attributes.clone();
+                    extended.extend(missing.iter().cloned());
+                    assert!(traverse_policy(&extended, &policy, PolicyType::Leaf), "{} {:?} {:?}", text, attributes, missing);
+                }
+            }
+        }
+    }
+}
@@ -1,3 +1,7 @@
 pub mod pest;
 pub mod dnf;
 pub mod msp;
+pub mod comparison;
+pub mod ast;
+pub mod normalize;
+pub mod explain;
@@ -2,14 +2,15 @@ use std::string::String;
 use utils::policy::pest::{PolicyLanguage, PolicyValue, parse, PolicyType};
 use crate::error::RabeError;
 use std::fmt::{Display, Formatter, Result as FormatResult};
+use rabe_bn::Fr;
+use utils::tools::usize_to_fr;
 
-const ZERO: i8 = 0;
-const PLUS: i8 = 1;
-const MINUS: i8 = -1;
 
 
+/// A monotone span program (M, pi): row i of the matrix `m` belongs to the attribute `pi[i]`, `c` is the number of columns.
+/// An attribute may label several rows, see [AbePolicy::max_reuse].
 pub struct AbePolicy {
-    pub m: Vec<Vec<i8>>,
+    pub m: Vec<Vec<Fr>>,
     pub pi: Vec<String>,
     pub c: usize,
 }
@@ -41,6 +42,80 @@ impl AbePolicy {
     pub fn from_policy(content: &PolicyValue) -> Result<AbePolicy, RabeError> {
         calculate_msp(content)
     }
+
+    /// Like `from_policy()`, but rejects policies in which an attribute labels more than `max_reuse` rows. Schemes whose
+    /// security proof only allows a bounded reuse of attributes use this to enforce the bound, see
+    /// `EncryptOptions::max_reuse()` of `ac17::cp_encapsulate_with()` and `aw11::encapsulate_with()`, and
+    /// `ac17::kp_keygen_with_max_reuse()`. A bound of `usize::MAX` allows any reuse.
+    pub fn from_policy_with_max_reuse(content: &PolicyValue, max_reuse: usize) -> Result<AbePolicy, RabeError> {
+        let msp = calculate_msp(content)?;
+        if max_reuse >= msp.pi.len() {
+            return Ok(msp);
+        }
+        match msp.pi.iter().find(|attribute| msp.pi.iter().filter(|a| a == attribute).count() > max_reuse) {
+            Some(attribute) => Err(RabeError::InvalidPolicy(format!("msp: attribute {} is used more than {} times", attribute, max_reuse))),
+            None => Ok(msp),
+        }
+    }
+
+    /// Returns the maximum number of rows that are labeled with the same attribute
+    pub fn max_reuse(&self) -> usize {
+        self.pi.iter().map(|attribute| self.pi.iter().filter(|a| *a == attribute).count()).max().unwrap_or(0)
+    }
+
+    /// Returns coefficients w_i for all rows M_i, such that the sum of all w_i * M_i is (1, 0, ..., 0) and w_i is
+    /// zero for all rows whose attribute is not in `attributes`. Returns None if the attributes do not satisfy the policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `attributes` - The attributes that are available for reconstruction
+    pub fn reconstruction(&self, attributes: &[String]) -> Option<Vec<Fr>> {
+        let rows: Vec<usize> = (0..self.m.len()).filter(|i| attributes.contains(&self.pi[*i])).collect();
+        let unknowns = rows.len();
+        // one equation per column, the last entry of every equation is its right hand side
+        let mut eqs: Vec<Vec<Fr>> = (0..self.c)
+            .map(|j| {
+                let mut eq: Vec<Fr> = rows.iter().map(|r| self.m[*r].get(j).copied().unwrap_or_else(Fr::zero)).collect();
+                eq.push(if j == 0 { Fr::one() } else { Fr::zero() });
+                eq
+            })
+            .collect();
+        // gauss jordan elimination
+        let mut pivots: Vec<(usize, usize)> = Vec::new();
+        let mut row = 0usize;
+        for unknown in 0..unknowns {
+            if row == eqs.len() {
+                break;
+            }
+            let pivot = match (row..eqs.len()).find(|r| !eqs[*r][unknown].is_zero()) {
+                Some(pivot) => pivot,
+                None => continue,
+            };
+            eqs.swap(row, pivot);
+            let inverse = eqs[row][unknown].inverse()?;
+            let pivot_eq: Vec<Fr> = eqs[row].iter().map(|x| *x * inverse).collect();
+            for (r, eq) in eqs.iter_mut().enumerate() {
+                if r != row && !eq[unknown].is_zero() {
+                    let factor = eq[unknown];
+                    for (x, p) in eq.iter_mut().zip(pivot_eq.iter()) {
+                        *x = *x - factor * *p;
+                    }
+                }
+            }
+            eqs[row] = pivot_eq;
+            pivots.push((row, unknown));
+            row += 1;
+        }
+        // the remaining equations have no unknowns left, the system is inconsistent if their right hand side is not zero
+        if eqs[row..].iter().any(|eq| !eq[unknowns].is_zero()) {
+            return None;
+        }
+        let mut coefficients = vec![Fr::zero(); self.m.len()];
+        for (r, unknown) in pivots {
+            coefficients[rows[unknown]] = eqs[r][unknowns];
+        }
+        Some(coefficients)
+    }
 }
 
 impl Display for AbePolicy {
@@ -58,7 +133,7 @@ impl Display for AbePolicy {
         for row in &self.m {
             _m_str.push('(');
             for col in row {
-                _m_str.push_str(&col.to_string());
+                _m_str.push_str(&fr_to_string(*col));
                 _m_str.push(',');
             }
             _m_str.pop();
@@ -72,80 +147,129 @@ impl Display for AbePolicy {
 }
 
 
-//#[doc = /**
-// * BEWARE: policy must be in DNF!
-// */]
+/// Converts a monotone policy to a monotone span program. AND and OR gates are converted with the algorithm of
+/// Lewko and Waters, threshold gates k of n with a Vandermonde matrix of k - 1 new columns. Every leaf of the policy
+/// becomes one row, i.e. attributes that occur several times in the policy are reused.
 pub fn calculate_msp(p: &PolicyValue) -> Result<AbePolicy, RabeError> {
-    let mut v: Vec<i8> = Vec::new();
-    let mut _values: Vec<Vec<i8>> = Vec::new();
-    let mut _attributes: Vec<String> = Vec::new();
     let mut msp = AbePolicy {
-        m: _values,
-        pi: _attributes,
+        m: Vec::new(),
+        pi: Vec::new(),
         c: 1,
     };
-    v.push(PLUS);
-    if lw(&mut msp, p, &v, None) {
-        for val in &mut msp.m {
-            val.resize(msp.c, ZERO);
-        }
-        // permutate both _pi and _m according to _pi
-        let permutation = permutation::sort(&msp.pi[..]);
-        msp.pi = permutation.apply_slice(&msp.pi[..]);
-        msp.m = permutation.apply_slice(&msp.m[..]);
-        return Ok(msp);
+    lw(&mut msp, p, &[Fr::one()], None)?;
+    for val in &mut msp.m {
+        val.resize(msp.c, Fr::zero());
     }
-    return Err(RabeError::new("lewko waters algorithm failed =("))
+    // permutate both _pi and _m according to _pi
+    let permutation = permutation::sort(&msp.pi[..]);
+    msp.pi = permutation.apply_slice(&msp.pi[..]);
+    msp.m = permutation.apply_slice(&msp.m[..]);
+    Ok(msp)
 }
 /// Converting from Boolean Formulas to LSSS Matrices
 /// Lewko Waters: "Decentralizing Attribute-Based Encryption" Appendix G
-fn lw(msp: &mut AbePolicy, p: &PolicyValue, v: &Vec<i8>, _parent: Option<PolicyType>) -> bool {
-    let mut v_tmp_left = Vec::new();
-    let mut v_tmp_right = v.clone();
-    return match p {
+fn lw(msp: &mut AbePolicy, p: &PolicyValue, v: &[Fr], _parent: Option<PolicyType>) -> Result<(), RabeError> {
+    match p {
         PolicyValue::String(attr) => {
-            msp.m.insert(0, v_tmp_right);
+            msp.m.insert(0, v.to_vec());
             msp.pi.insert(0, attr.0.to_string());
-            true
-        },
-        PolicyValue::Object(obj) => {
-            match obj.0 {
-                PolicyType::And => lw(msp, &obj.1.as_ref(), v, Some(PolicyType::And)),
-                PolicyType::Or => lw(msp, &obj.1.as_ref(), v, Some(PolicyType::Or)),
-                PolicyType::Leaf => lw(msp, &obj.1.as_ref(), v, Some(PolicyType::Leaf)),
-            }
+            Ok(())
         },
+        PolicyValue::Not(_) => Err(RabeError::InvalidPolicy(String::from("msp: negations are not supported by monotone span programs"))),
+        PolicyValue::Object(obj) => lw(msp, obj.1.as_ref(), v, Some(obj.0)),
         PolicyValue::Array(policies) => {
-            let len = policies.len();
-            if len < 2 {
-                panic!("lw: policy with just a single attribute is not allowed");
+            if policies.is_empty() {
+                return Err(RabeError::InvalidPolicy(String::from("msp: AND, OR or THRESHOLD without children")));
             }
-            return match _parent {
-                Some(PolicyType::Or) => {
-                    let mut _ret = true;
-                    for policy in policies {
-                        _ret &= lw(msp, &policy, &v, Some(PolicyType::Or));
-                    }
-                    return _ret;
-                },
-                Some(PolicyType::And) => {
-                    if len != 2 {
-                        panic!("lw: Invalid policy. Number of arguments under AND != 2");
-                    }
-                    v_tmp_right.resize(msp.c, ZERO);
-                    v_tmp_right.push(PLUS);
-                    v_tmp_left.resize(msp.c, ZERO);
-                    v_tmp_left.push(MINUS);
-                    msp.c += 1;
-                    lw(msp, &policies[0], &v_tmp_right, Some(PolicyType::And)) && lw(msp, &policies[1], &v_tmp_left, Some(PolicyType::And))
-                },
-                Some(PolicyType::Leaf) => false,
-                None => false,
+            let children: Vec<&PolicyValue> = policies.iter().collect();
+            match _parent {
+                Some(PolicyType::Or) => lw_or(msp, &children, v),
+                Some(PolicyType::And) => lw_and(msp, &children, v),
+                Some(PolicyType::Threshold(k)) => lw_threshold(msp, &children, k, v),
+                _ => Err(RabeError::InvalidPolicy(String::from("msp: children without AND, OR or THRESHOLD"))),
             }
         }
+    }
+}
+
+/// All children of an OR are labeled with the vector of the OR.
+fn lw_or(msp: &mut AbePolicy, children: &[&PolicyValue], v: &[Fr]) -> Result<(), RabeError> {
+    for policy in children {
+        lw(msp, policy, v, Some(PolicyType::Or))?;
+    }
+    Ok(())
+}
+
+/// An AND with more than two children is treated as a chain of binary ANDs.
+fn lw_and(msp: &mut AbePolicy, children: &[&PolicyValue], v: &[Fr]) -> Result<(), RabeError> {
+    let (last, rest) = match children.split_last() {
+        Some(split) => split,
+        None => return Err(RabeError::InvalidPolicy(String::from("msp: AND without children")))
     };
+    let mut v_rest = v.to_vec();
+    for policy in rest {
+        let mut v_tmp_right = v_rest;
+        v_tmp_right.resize(msp.c, Fr::zero());
+        v_tmp_right.push(Fr::one());
+        let mut v_tmp_left = vec![Fr::zero(); msp.c];
+        v_tmp_left.push(-Fr::one());
+        msp.c += 1;
+        lw(msp, policy, &v_tmp_right, Some(PolicyType::And))?;
+        v_rest = v_tmp_left;
+    }
+    lw(msp, last, &v_rest, Some(PolicyType::And))
+}
+
+/// A threshold gate k of n adds k - 1 new columns. Child x (counted from 1) is labeled with the vector of the gate,
+/// followed by x, x^2, ..., x^(k-1) in the new columns, so that any k children reconstruct the vector of the gate by
+/// Lagrange interpolation.
+fn lw_threshold(msp: &mut AbePolicy, children: &[&PolicyValue], k: usize, v: &[Fr]) -> Result<(), RabeError> {
+    let n = children.len();
+    if k == 0 || k > n {
+        return Err(RabeError::InvalidPolicy(format!("msp: threshold {} of {} children", k, n)));
+    }
+    if k == 1 {
+        return lw_or(msp, children, v);
+    }
+    if k == n {
+        return lw_and(msp, children, v);
+    }
+    let columns = msp.c;
+    msp.c += k - 1;
+    for (i, policy) in children.iter().enumerate() {
+        let x = usize_to_fr(i + 1);
+        let mut v_child = v.to_vec();
+        v_child.resize(columns, Fr::zero());
+        let mut power = x;
+        for _ in 1..k {
+            v_child.push(power);
+            power = power * x;
+        }
+        lw(msp, policy, &v_child, Some(PolicyType::Threshold(k)))?;
+    }
+    Ok(())
 }
 
+// small integers are written as such, all other entries as field elements
+fn fr_to_string(value: Fr) -> String {
+    if value.is_zero() {
+        return String::from("0");
+    }
+    for i in 1..=16i8 {
+        if value == i8_to_fr(i) {
+            return i.to_string();
+        }
+        if value == i8_to_fr(-i) {
+            return (-i).to_string();
+        }
+    }
+    value.to_string()
+}
+
+fn i8_to_fr(value: i8) -> Fr {
+    let abs = usize_to_fr(value.unsigned_abs() as usize);
+    if value < 0 { Fr::zero() - abs } else { abs }
+}
 
 #[cfg(test)]
 mod tests {
@@ -168,7 +292,7 @@ mod tests {
         match parse(policy.as_ref(), PolicyLanguage::JsonPolicy) {
             Ok(pol) => {
                 let _msp_static = AbePolicy {
-                    m: vec![p1, p2, p3, p4],
+                    m: vec![p1, p2, p3, p4].into_iter().map(|row| row.into_iter().map(i8_to_fr).collect()).collect(),
                     pi: vec![
                         String::from("A"),
                         String::from("B"),
@@ -197,4 +321,58 @@ mod tests {
         }
 
     }
+
+    // checks that the coefficients combine the rows to the target vector (1, 0, ..., 0)
+    fn assert_reconstructs(msp: &AbePolicy, attributes: &[&str]) {
+        let attributes: Vec<String> = attributes.iter().map(|a| a.to_string()).collect();
+        let coefficients = msp.reconstruction(&attributes).unwrap();
+        for j in 0..msp.c {
+            let mut sum = Fr::zero();
+            for (row, w) in msp.m.iter().zip(coefficients.iter()) {
+                sum = sum + row[j] * *w;
+            }
+            assert!(sum == if j == 0 { Fr::one() } else { Fr::zero() });
+        }
+    }
+
+    #[test]
+    fn test_msp_threshold() {
+        let pol = parse(r#"3 of ("A", "B", "C", "D", "E")"#, PolicyLanguage::HumanPolicy).unwrap();
+        let msp = AbePolicy::from_policy(&pol).unwrap();
+        // one row per child and k - 1 new columns
+        assert_eq!(msp.m.len(), 5);
+        assert_eq!(msp.c, 3);
+        for (x, row) in msp.m.iter().enumerate() {
+            let x = (x + 1) as i8;
+            assert!(*row == vec![Fr::one(), i8_to_fr(x), i8_to_fr(x * x)]);
+        }
+        assert_reconstructs(&msp, &["A", "C", "E"]);
+        assert_reconstructs(&msp, &["B", "C", "D", "E"]);
+        assert!(msp.reconstruction(&[String::from("A"), String::from("E")]).is_none());
+        // a threshold does not expand to all subsets of its children anymore
+        let children: Vec<String> = (0..30).map(|i| format!("\"A{}\"", i)).collect();
+        let policy = format!("15 of ({})", children.join(", "));
+        let pol = parse(&policy, PolicyLanguage::HumanPolicy).unwrap();
+        let msp = AbePolicy::from_policy(&pol).unwrap();
+        assert_eq!((msp.m.len(), msp.c), (30, 15));
+        let attributes: Vec<String> = (0..30).step_by(2).map(|i| format!("A{}", i)).collect();
+        assert!(msp.reconstruction(&attributes).is_some());
+        assert!(msp.reconstruction(&attributes[1..]).is_none());
+        // nested gates below a threshold
+        let pol = parse(r#""A" and 2 of ("B" and "C", "D" or "E", "F")"#, PolicyLanguage::HumanPolicy).unwrap();
+        let msp = AbePolicy::from_policy(&pol).unwrap();
+        assert_reconstructs(&msp, &["A", "B", "C", "F"]);
+        assert_reconstructs(&msp, &["A", "E", "F"]);
+        assert!(msp.reconstruction(&[String::from("A"), String::from("B"), String::from("F")]).is_none());
+    }
+
+    #[test]
+    fn test_msp_reuse() {
+        let pol = parse(r#"("A" and "B") or ("A" and "C") or 2 of ("A", "B", "D")"#, PolicyLanguage::HumanPolicy).unwrap();
+        let msp = AbePolicy::from_policy(&pol).unwrap();
+        assert_eq!(msp.max_reuse(), 3);
+        assert_reconstructs(&msp, &["A", "C"]);
+        assert!(AbePolicy::from_policy_with_max_reuse(&pol, 3).is_ok());
+        assert!(matches!(AbePolicy::from_policy_with_max_reuse(&pol, 2), Err(RabeError::InvalidPolicy(_))));
+    }
 }
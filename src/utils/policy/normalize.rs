@@ -0,0 +1,169 @@
+//! Simplification of policies to a canonical form.
+//!
+//! A normalized policy has the same satisfying attribute sets as the original policy, but usually fewer leaves, which
+//! results in smaller ciphertexts (CP-ABE) and secret keys (KP-ABE). Two policies that differ only in the order of
+//! children, duplicate children or nesting of gates have the same normal form, which can be compared or hashed.
+//!
+//! # Examples
+//!
+//! ```
+//! use rabe::utils::policy::ast::Policy;
+//! use rabe::utils::policy::pest::PolicyLanguage;
+//! let policy = Policy::parse(r#"("A" and "A") or ("A" and "B")"#, PolicyLanguage::HumanPolicy).unwrap();
+//! assert_eq!(policy.normalize(), Policy::attr("A").unwrap());
+//! let policy = Policy::parse(r#""C" or ("B" or "A")"#, PolicyLanguage::HumanPolicy).unwrap();
+//! assert_eq!(policy.normalize().to_string(), r#"("A" or "B" or "C")"#);
+//! ```
+use utils::policy::ast::Policy;
+
+/// Returns the normal form of `policy`. The normal form is computed bottom up:
+///
+///	* negations are pushed down to the attributes, double negations are removed
+///	* threshold gates `1 of n` become OR gates and `n of n` become AND gates
+///	* children of the same gate type are merged into their parent
+///	* children are sorted, duplicate children of AND and OR gates are removed
+///	* absorbed children are removed: `A or (A and B)` is `A`, `A and (A or B)` is `A`
+///	* gates with a single child are replaced by the child
+///
+/// Invalid gates, i.e. gates without children and thresholds `k of n` with k = 0 or k > n, are kept as they are.
+pub fn normalize(policy: &Policy) -> Policy {
+    normalize_negated(policy, false)
+}
+
+fn normalize_negated(policy: &Policy, negated: bool) -> Policy {
+    match policy {
+        Policy::Attribute(_) if negated => Policy::Not(Box::new(policy.clone())),
+        Policy::Attribute(_) => policy.clone(),
+        Policy::Not(child) => normalize_negated(child, !negated),
+        // De Morgan, a negated threshold k of n is satisfied if at least n - k + 1 children are not satisfied
+        Policy::And(children) if negated => gate(Gate::Or, children, true),
+        Policy::Or(children) if negated => gate(Gate::And, children, true),
+        Policy::And(children) => gate(Gate::And, children, false),
+        Policy::Or(children) => gate(Gate::Or, children, false),
+        Policy::Threshold(k, children) if *k == 0 || *k > children.len() => {
+            let invalid = Policy::Threshold(*k, children.iter().map(normalize).collect());
+            if negated { Policy::Not(Box::new(invalid)) } else { invalid }
+        },
+        Policy::Threshold(k, children) if negated => threshold(children.len() - k + 1, children, true),
+        Policy::Threshold(k, children) => threshold(*k, children, false),
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Gate {
+    And,
+    Or,
+}
+
+fn threshold(k: usize, children: &[Policy], negated: bool) -> Policy {
+    if k == 1 {
+        return gate(Gate::Or, children, negated);
+    }
+    if k == children.len() {
+        return gate(Gate::And, children, negated);
+    }
+    // duplicate children of a threshold gate count multiple times and are kept
+    let mut children: Vec<Policy> = children.iter().map(|child| normalize_negated(child, negated)).collect();
+    children.sort();
+    Policy::Threshold(k, children)
+}
+
+fn gate(gate: Gate, children: &[Policy], negated: bool) -> Policy {
+    let mut flat: Vec<Policy> = Vec::new();
+    for child in children {
+        match (gate, normalize_negated(child, negated)) {
+            (Gate::And, Policy::And(grandchildren)) | (Gate::Or, Policy::Or(grandchildren)) => flat.extend(grandchildren),
+            (_, child) => flat.push(child),
+        }
+    }
+    flat.sort();
+    flat.dedup();
+    // a child is absorbed, if the terms of another child are a subset of its own terms
+    let absorbed: Vec<bool> = flat
+        .iter()
+        .map(|child| flat.iter().any(|other| other != child && terms(gate, other).iter().all(|term| terms(gate, child).contains(term))))
+        .collect();
+    let mut flat: Vec<Policy> = flat.into_iter().zip(absorbed).filter(|(_, absorbed)| !absorbed).map(|(child, _)| child).collect();
+    match (flat.len(), gate) {
+        (1, _) => flat.remove(0),
+        (_, Gate::And) => Policy::And(flat),
+        (_, Gate::Or) => Policy::Or(flat),
+    }
+}
+
+// the terms of a child of an AND are its disjuncts, the terms of a child of an OR are its conjuncts
+fn terms(gate: Gate, child: &Policy) -> &[Policy] {
+    match (gate, child) {
+        (Gate::And, Policy::Or(children)) | (Gate::Or, Policy::And(children)) => children,
+        _ => std::slice::from_ref(child),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+    use utils::policy::pest::{parse, PolicyLanguage, PolicyType};
+    use utils::tools::traverse_policy;
+
+    fn human(policy: &str) -> Policy {
+        Policy::parse(policy, PolicyLanguage::HumanPolicy).unwrap()
+    }
+
+    #[test]
+    fn test_normalize() {
+        let cases = [
+            (r#"("A" and "A") or ("A" and "B")"#, r#""A""#),
+            (r#""A" or ("B" or "C")"#, r#""A" or "B" or "C""#),
+            (r#"("C" and "B") or ("B" and "C") or "D""#, r#""D" or ("B" and "C")"#),
+            (r#""A" and ("A" or "B") and ("A" or "B" or "C")"#, r#""A""#),
+            (r#"("A" or "B") and ("A" or "B" or "C")"#, r#""A" or "B""#),
+            (r#"1 of ("B", "A", "B")"#, r#""A" or "B""#),
+            (r#"2 of ("B", "A")"#, r#""A" and "B""#),
+            (r#"2 of ("B", "A", "B")"#, r#"2 of ("A", "B", "B")"#),
+            (r#"not ("A" and not "B")"#, r#""B" or not "A""#),
+            (r#"not not "A""#, r#""A""#),
+            (r#"not 2 of ("A", "B", "C")"#, r#"2 of (not "A", not "B", not "C")"#),
+        ];
+        for (policy, expected) in cases.iter() {
+            assert_eq!(normalize(&human(policy)), human(expected), "{}", policy);
+        }
+        let single = Policy::parse(r#"{"name": "and", "children": [{"name": "or", "children": [{"name": "A"}]}]}"#, PolicyLanguage::JsonPolicy).unwrap();
+        assert_eq!(normalize(&single), Policy::attr("A").unwrap());
+        assert_eq!(normalize(&Policy::threshold(3, [Policy::attr("A").unwrap()])), Policy::threshold(3, [Policy::attr("A").unwrap()]));
+    }
+
+    fn random_policy<R: Rng>(rng: &mut R, depth: usize) -> Policy {
+        if depth == 0 || rng.gen_bool(0.3) {
+            return Policy::attr(["A", "B", "C", "D"][rng.gen_range(0..4)]).unwrap();
+        }
+        let children: Vec<Policy> = (0..rng.gen_range(1..4)).map(|_| random_policy(rng, depth - 1)).collect();
+        match rng.gen_range(0..4) {
+            0 => Policy::And(children),
+            1 => Policy::Or(children),
+            2 => Policy::Threshold(rng.gen_range(1..=children.len()), children),
+            _ => !Policy::Or(children),
+        }
+    }
+
+    #[test]
+    fn test_normalize_random() {
+        let mut rng = ChaCha20Rng::seed_from_u64(14);
+        let universe = ["A", "B", "C", "D"];
+        for _ in 0..200 {
+            let policy = random_policy(&mut rng, 4);
+            let normal = normalize(&policy);
+            assert_eq!(normalize(&normal), normal, "{}", policy);
+            let (original, simplified) = (policy.to_string(), normal.to_string());
+            let original = parse(&original, PolicyLanguage::HumanPolicy).unwrap();
+            let simplified = parse(&simplified, PolicyLanguage::HumanPolicy).unwrap();
+            // both policies are satisfied by the same subsets of the universe
+            for subset in 0..1 << universe.len() {
+                let attributes: Vec<String> = (0..universe.len()).filter(|i| subset >> i & 1 == 1).map(|i| universe[i].to_string()).collect();
+                assert_eq!(traverse_policy(&attributes, &original, PolicyType::Leaf), traverse_policy(&attributes, &simplified, PolicyType::Leaf), "{} {}", policy, normal);
+            }
+        }
+    }
+}
@@ -1,39 +1,89 @@
-use utils::policy::pest::{PolicyValue, PolicyType};
+use utils::policy::pest::{PolicyValue, PolicyType, threshold};
+use utils::policy::comparison::{parse_comparison, parse_range};
 use pest::iterators::Pair;
+use error::RabeError;
 
 #[derive(Parser)]
 #[grammar = "human.policy.pest"]
 pub(crate) struct HumanPolicyParser;
 
-pub(crate) fn parse(pair: Pair<Rule>) -> PolicyValue {
+pub(crate) fn parse(pair: Pair<Rule>) -> Result<PolicyValue, RabeError> {
     match pair.as_rule() {
         Rule::string => {
-            let p = pair.into_inner().next().unwrap();
-            PolicyValue::String((p.as_str(), p.line_col().1))
-        },
-        Rule::number => {
-            let p = pair.into_inner().next().unwrap();
-            PolicyValue::String((p.as_str(), p.line_col().1))
+            match pair.into_inner().next() {
+                Some(p) => Ok(PolicyValue::String((p.as_str().into(), p.line_col().1))),
+                None => Err(RabeError::InvalidPolicy(String::from("policy: string without content")))
+            }
         },
+        // numbers are atomic and have no inner pairs
+        Rule::number => Ok(PolicyValue::String((pair.as_str().into(), pair.line_col().1))),
         Rule::and => {
             let mut vec = Vec::new();
             for child in pair.into_inner() {
-                vec.push(parse(child, ));
+                vec.push(parse(child)?);
             }
-            PolicyValue::Object((PolicyType::And, Box::new(PolicyValue::Array(vec))))
+            if vec.is_empty() {
+                return Err(RabeError::InvalidPolicy(String::from("policy: and without children")));
+            }
+            Ok(PolicyValue::Object((PolicyType::And, Box::new(PolicyValue::Array(vec)))))
         },
         Rule::or => {
             let mut vec = Vec::new();
             for child in pair.into_inner() {
-                vec.push(parse(child, ));
+                vec.push(parse(child)?);
+            }
+            if vec.is_empty() {
+                return Err(RabeError::InvalidPolicy(String::from("policy: or without children")));
+            }
+            Ok(PolicyValue::Object((PolicyType::Or, Box::new(PolicyValue::Array(vec)))))
+        },
+        Rule::threshold => {
+            let mut k = "";
+            let mut vec = Vec::new();
+            for child in pair.into_inner() {
+                match child.as_rule() {
+                    Rule::k => k = child.as_str(),
+                    _ => vec.push(parse(child)?),
+                }
+            }
+            threshold(k, vec)
+        },
+        Rule::comparison | Rule::range => {
+            let rule = pair.as_rule();
+            let mut inner = pair.into_inner();
+            let (name, position) = match inner.next() {
+                Some(attribute) => {
+                    let position = attribute.line_col().1;
+                    (attribute_name(attribute), position)
+                },
+                None => return Err(RabeError::InvalidPolicy(String::from("policy: comparison without attribute"))),
+            };
+            match (rule, inner.next(), inner.next()) {
+                (Rule::comparison, Some(comparator), Some(value)) => parse_comparison(name, comparator.as_str(), value.as_str(), position),
+                (Rule::range, Some(min), Some(max)) => parse_range(name, min.as_str(), max.as_str(), position),
+                _ => Err(RabeError::InvalidPolicy(String::from("policy: incomplete comparison")))
+            }
+        },
+        Rule::not => {
+            match pair.into_inner().next() {
+                Some(child) => Ok(PolicyValue::Not(Box::new(parse(child)?))),
+                None => Err(RabeError::InvalidPolicy(String::from("policy: not without child")))
             }
-            PolicyValue::Object((PolicyType::Or, Box::new(PolicyValue::Array(vec))))
         },
         Rule::content
         | Rule::EOI
         | Rule::inner
         | Rule::orinner
         | Rule::andinner
+        | Rule::ofvalue
+        | Rule::k
+        | Rule::attribute
+        | Rule::identifier
+        | Rule::identchar
+        | Rule::keyword
+        | Rule::comparator
+        | Rule::invalue
+        | Rule::notvalue
         | Rule::term
         | Rule::node
         | Rule::value
@@ -44,6 +94,14 @@ pub(crate) fn parse(pair: Pair<Rule>) -> PolicyValue {
         | Rule::BRACEOPEN
         | Rule::BRACECLOSE
         | Rule::QUOTE
-        | Rule::WHITESPACE => unreachable!(),
+        | Rule::WHITESPACE => Err(RabeError::InvalidPolicy(format!("policy: unexpected rule {:?}", pair.as_rule()))),
+    }
+}
+
+// the name of a quoted or unquoted attribute
+fn attribute_name<'i>(pair: Pair<'i, Rule>) -> &'i str {
+    match pair.as_rule() {
+        Rule::string => pair.into_inner().next().map(|inner| inner.as_str()).unwrap_or_default(),
+        _ => pair.as_str(),
     }
-}
\ No newline at end of file
+}
@@ -1,39 +1,89 @@
-use utils::policy::pest::{PolicyValue, PolicyType};
+use utils::policy::pest::{PolicyValue, PolicyType, threshold};
+use utils::policy::comparison::parse_comparison;
 use pest::iterators::Pair;
+use error::RabeError;
 
 #[derive(Parser)]
 #[grammar = "json.policy.pest"]
 pub(crate) struct JSONPolicyParser;
 
-pub(crate) fn parse(pair: Pair<Rule>) -> PolicyValue {
+pub(crate) fn parse(pair: Pair<Rule>) -> Result<PolicyValue, RabeError> {
     match pair.as_rule() {
         Rule::string => {
-            let p = pair.into_inner().next().unwrap();
-            PolicyValue::String((p.as_str(), p.line_col().1))
-        },
-        Rule::number => {
-            let p = pair.into_inner().next().unwrap();
-            PolicyValue::String((p.as_str(), p.line_col().1))
+            match pair.into_inner().next() {
+                Some(p) => Ok(PolicyValue::String((p.as_str().into(), p.line_col().1))),
+                None => Err(RabeError::InvalidPolicy(String::from("policy: string without content")))
+            }
         },
+        // numbers are atomic and have no inner pairs
+        Rule::number => Ok(PolicyValue::String((pair.as_str().into(), pair.line_col().1))),
         Rule::and => {
             let mut vec = Vec::new();
             for child in pair.into_inner() {
-                vec.push(parse(child, ));
+                vec.push(parse(child)?);
             }
-            PolicyValue::Object((PolicyType::And, Box::new(PolicyValue::Array(vec))))
+            if vec.is_empty() {
+                return Err(RabeError::InvalidPolicy(String::from("policy: and without children")));
+            }
+            Ok(PolicyValue::Object((PolicyType::And, Box::new(PolicyValue::Array(vec)))))
         },
         Rule::or => {
             let mut vec = Vec::new();
             for child in pair.into_inner() {
-                vec.push(parse(child, ));
+                vec.push(parse(child)?);
+            }
+            if vec.is_empty() {
+                return Err(RabeError::InvalidPolicy(String::from("policy: or without children")));
+            }
+            Ok(PolicyValue::Object((PolicyType::Or, Box::new(PolicyValue::Array(vec)))))
+        },
+        Rule::threshold => {
+            let mut k = "";
+            let mut vec = Vec::new();
+            for child in pair.into_inner() {
+                match child.as_rule() {
+                    Rule::k => k = child.as_str(),
+                    _ => vec.push(parse(child)?),
+                }
+            }
+            threshold(k, vec)
+        },
+        Rule::comparison => {
+            let mut inner = pair.into_inner();
+            // every comparison is placed at its comparator, so that the bit attributes of different comparisons differ
+            let name = match inner.next().and_then(|string| string.into_inner().next()) {
+                Some(name) => name.as_str(),
+                None => return Err(RabeError::InvalidPolicy(String::from("policy: comparison without attribute"))),
+            };
+            let mut vec = Vec::new();
+            while let (Some(comparator), Some(value)) = (inner.next(), inner.next()) {
+                vec.push(parse_comparison(name, comparator.as_str(), value.as_str(), comparator.line_col().1)?);
+            }
+            match vec.len() {
+                1 => Ok(vec.remove(0)),
+                _ => Ok(PolicyValue::Object((PolicyType::And, Box::new(PolicyValue::Array(vec))))),
+            }
+        },
+        Rule::not => {
+            match pair.into_inner().next() {
+                Some(child) => Ok(PolicyValue::Not(Box::new(parse(child)?))),
+                None => Err(RabeError::InvalidPolicy(String::from("policy: not without child")))
             }
-            PolicyValue::Object((PolicyType::Or, Box::new(PolicyValue::Array(vec))))
         },
         Rule::content
         | Rule::EOI
         | Rule::inner
         | Rule::orinner
         | Rule::andinner
+        | Rule::thresholdinner
+        | Rule::thresholdvalue
+        | Rule::children
+        | Rule::k
+        | Rule::comparator
+        | Rule::comparatorinner
+        | Rule::notinner
+        | Rule::notvalue
+        | Rule::K
         | Rule::node
         | Rule::value
         | Rule::andvalue
@@ -43,6 +93,6 @@ pub(crate) fn parse(pair: Pair<Rule>) -> PolicyValue {
         | Rule::CHILDREN
         | Rule::COMMENT
         | Rule::QUOTE
-        | Rule::WHITESPACE => unreachable!(),
+        | Rule::WHITESPACE => Err(RabeError::InvalidPolicy(format!("policy: unexpected rule {:?}", pair.as_rule()))),
     }
 }
\ No newline at end of file
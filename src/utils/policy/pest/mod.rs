@@ -1,4 +1,5 @@
 use pest::Parser;
+use std::borrow::Cow;
 use std::string::String;
 use crate::error::RabeError;
 use self::human::HumanPolicyParser;
@@ -22,18 +23,108 @@ pub enum PolicyLanguage {
     HumanPolicy,
 }
 
-/// Internally there are only three types of nodes: AND, OR and LEAF nodes
+/// Internally there are four types of nodes: AND, OR, THRESHOLD and LEAF nodes.
+/// A THRESHOLD node is satisfied if at least `k` of its children are satisfied.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum PolicyType {
     And,
     Or,
+    Threshold(usize),
     Leaf
 }
 
-/// The value of a node may either be a String (with a position stored in a u8), and Array of values oder a child with value
+/// The value of a node may either be a String (with a position stored in a u8), and Array of values oder a child with value.
+/// Strings borrow from the parsed policy, unless they were generated by the parser (e.g. the bits of a comparison).
+/// A negated child is satisfied if the child is not satisfied.
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub enum PolicyValue<'a> {
     Object((PolicyType, Box<PolicyValue<'a>>)),
     Array(Vec<PolicyValue<'a>>),
-    String((&'a str, usize)),
+    String((Cow<'a, str>, usize)),
+    Not(Box<PolicyValue<'a>>),
+}
+
+/// The prefix of the dummy attributes that encode negated attributes for monotone schemes, see [encode_negations]
+pub const NEGATION_PREFIX: &str = "not:";
+
+/// Creates a THRESHOLD node from its (unparsed) `k` and children, `k` has to be between 1 and the number of children
+pub(crate) fn threshold<'a>(k: &str, children: Vec<PolicyValue<'a>>) -> Result<PolicyValue<'a>, RabeError> {
+    let k: usize = k
+        .parse()
+        .map_err(|_| RabeError::InvalidPolicy(format!("policy: invalid threshold {}", k)))?;
+    if k == 0 || k > children.len() {
+        return Err(RabeError::InvalidPolicy(format!("policy: threshold {} of {} children", k, children.len())));
+    }
+    Ok(PolicyValue::Object((PolicyType::Threshold(k), Box::new(PolicyValue::Array(children)))))
+}
+
+/// Pushes all negations of a policy down to its attributes, using De Morgan's laws. A negated threshold gate
+/// k of n becomes the threshold gate n-k+1 of the negated children.
+pub fn negation_normal_form(policy: PolicyValue) -> PolicyValue {
+    push_negations(policy, false)
+}
+
+fn push_negations(policy: PolicyValue, negated: bool) -> PolicyValue {
+    match policy {
+        PolicyValue::String(node) => {
+            if negated {
+                PolicyValue::Not(Box::new(PolicyValue::String(node)))
+            } else {
+                PolicyValue::String(node)
+            }
+        },
+        PolicyValue::Not(child) => push_negations(*child, !negated),
+        PolicyValue::Array(children) => PolicyValue::Array(children.into_iter().map(|child| push_negations(child, negated)).collect()),
+        PolicyValue::Object((policy_type, child)) => {
+            let policy_type = match policy_type {
+                PolicyType::And if negated => PolicyType::Or,
+                PolicyType::Or if negated => PolicyType::And,
+                PolicyType::Threshold(k) if negated => match child.as_ref() {
+                    PolicyValue::Array(children) if k <= children.len() => PolicyType::Threshold(children.len() - k + 1),
+                    _ => PolicyType::Threshold(k),
+                },
+                policy_type => policy_type,
+            };
+            PolicyValue::Object((policy_type, Box::new(push_negations(*child, negated))))
+        },
+    }
+}
+
+/// Returns true if the policy does not contain a negation
+pub fn is_monotone(policy: &PolicyValue) -> bool {
+    match policy {
+        PolicyValue::String(_) => true,
+        PolicyValue::Not(_) => false,
+        PolicyValue::Array(children) => children.iter().all(is_monotone),
+        PolicyValue::Object(obj) => is_monotone(obj.1.as_ref()),
+    }
+}
+
+/// Returns an error if the policy contains a negation, which the monotone scheme `scheme` cannot enforce.
+/// Use [encode_negations] to express negations with dummy attributes instead.
+pub fn check_monotone(policy: &PolicyValue, scheme: &str) -> Result<(), RabeError> {
+    if is_monotone(policy) {
+        Ok(())
+    } else {
+        Err(RabeError::InvalidPolicy(format!("{}: negations are not supported by this monotone scheme, see encode_negations()", scheme)))
+    }
+}
+
+/// Encodes the negations of a policy with dummy attributes, so that it can be used with a monotone scheme: `not "A"`
+/// becomes the attribute `"not:A"`. The other party (the key in CP-ABE, the ciphertext in KP-ABE) has to hold the dummy
+/// attributes of [negated_attributes]. The encoded policy is returned as JSON policy.
+pub fn encode_negations(policy: &str, language: PolicyLanguage) -> Result<String, RabeError> {
+    let policy = negation_normal_form(parse(policy, language)?);
+    serialize(&policy, PolicyLanguage::JsonPolicy, None, true)
+}
+
+/// Returns the dummy attributes of all attributes of `universe` that are not in `attributes`, see [encode_negations]
+pub fn negated_attributes(universe: &[&str], attributes: &[&str]) -> Vec<String> {
+    universe
+        .iter()
+        .filter(|attribute| !attributes.contains(attribute))
+        .map(|attribute| format!("{}{}", NEGATION_PREFIX, attribute))
+        .collect()
 }
 
 /// Parses a &str in a give [PolicyLanguage] to a PolicyValue tree
@@ -45,21 +136,21 @@ pub fn parse(
         PolicyLanguage::JsonPolicy => {
             use utils::policy::pest::json::Rule;
             match JSONPolicyParser::parse(Rule::content, policy) {
-                Ok(mut result) => Ok(json::parse(result.next().unwrap())),
-                Err(e) => {
-                    println!("error Json Parse: {}", e);
-                    Err(e.into())
-                }
+                Ok(mut result) => match result.next() {
+                    Some(pair) => json::parse(pair),
+                    None => Err(RabeError::InvalidPolicy(String::from("policy: empty policy")))
+                },
+                Err(e) => Err(e.into())
             }
         },
         PolicyLanguage::HumanPolicy => {
             use utils::policy::pest::human::Rule;
             match HumanPolicyParser::parse(Rule::content, policy) {
-                Ok(mut result) => Ok(human::parse(result.next().unwrap())),
-                Err(e) => {
-                    println!("error Human Parse: {}", e);
-                    Err(e.into())
-                }
+                Ok(mut result) => match result.next() {
+                    Some(pair) => human::parse(pair),
+                    None => Err(RabeError::InvalidPolicy(String::from("policy: empty policy")))
+                },
+                Err(e) => Err(e.into())
             }
         }
     }
@@ -69,43 +160,64 @@ pub fn serialize_policy(
     val: &PolicyValue,
     language: PolicyLanguage,
     parent: Option<PolicyType>
-) -> String {
+) -> Result<String, RabeError> {
+    serialize(val, language, parent, false)
+}
+
+// negations of attributes are written as dummy attributes if `encode` is set
+fn serialize(
+    val: &PolicyValue,
+    language: PolicyLanguage,
+    parent: Option<PolicyType>,
+    encode: bool
+) -> Result<String, RabeError> {
     use self::PolicyValue::*;
+    if let (true, Not(child)) = (encode, val) {
+        return match child.as_ref() {
+            String(s) => serialize(&String((format!("{}{}", NEGATION_PREFIX, s.0).into(), s.1)), language, parent, false),
+            _ => Err(RabeError::InvalidPolicy("serialize_policy: negation of a gate cannot be encoded".to_string()))
+        };
+    }
     match language {
         PolicyLanguage::JsonPolicy => {
             match val {
                 Object(obj) => {
                     match obj.0 {
-                        PolicyType::And => format!("{{\"name\": \"and\", {}}}", serialize_policy(obj.1.as_ref(), language, None)),
-                        PolicyType::Or => format!("{{\"name\": \"or\", {}}}", serialize_policy(obj.1.as_ref(), language, None)),
-                        PolicyType::Leaf => serialize_policy(&obj.1.as_ref(), language, None)
+                        PolicyType::And => Ok(format!("{{\"name\": \"and\", {}}}", serialize(obj.1.as_ref(), language, None, encode)?)),
+                        PolicyType::Or => Ok(format!("{{\"name\": \"or\", {}}}", serialize(obj.1.as_ref(), language, None, encode)?)),
+                        PolicyType::Threshold(k) => Ok(format!("{{\"name\": \"threshold\", \"k\": {}, {}}}", k, serialize(obj.1.as_ref(), language, None, encode)?)),
+                        PolicyType::Leaf => serialize(obj.1.as_ref(), language, None, encode)
                     }
                 },
                 Array(a) => {
-                    let contents: Vec<_> = a.iter().map(|val| serialize_policy(val, language, None)).collect();
-                    format!("\"children\": [{}]", contents.join(", "))
+                    let contents = a.iter().map(|val| serialize(val, language, None, encode)).collect::<Result<Vec<_>, _>>()?;
+                    Ok(format!("\"children\": [{}]", contents.join(", ")))
                 }
-                String(s) => format!("{{\"name\": \"{}\"}}", s.0),
+                String(s) => Ok(format!("{{\"name\": \"{}\"}}", s.0)),
+                Not(child) => Ok(format!("{{\"name\": \"not\", \"children\": [{}]}}", serialize(child.as_ref(), language, None, encode)?)),
             }
         },
         PolicyLanguage::HumanPolicy => {
             match val {
                 Object(obj) => {
                     match obj.0 {
-                        PolicyType::And => format!("{}", serialize_policy(obj.1.as_ref(), language, Some(PolicyType::And))),
-                        PolicyType::Or => format!("{}", serialize_policy(obj.1.as_ref(), language, Some(PolicyType::Or))),
-                        PolicyType::Leaf => serialize_policy(&obj.1.as_ref(), language, Some(PolicyType::Leaf))
+                        PolicyType::And => serialize(obj.1.as_ref(), language, Some(PolicyType::And), encode),
+                        PolicyType::Or => serialize(obj.1.as_ref(), language, Some(PolicyType::Or), encode),
+                        PolicyType::Threshold(k) => serialize(obj.1.as_ref(), language, Some(PolicyType::Threshold(k)), encode),
+                        PolicyType::Leaf => serialize(obj.1.as_ref(), language, Some(PolicyType::Leaf), encode)
                     }
                 },
                 Array(a) => {
-                    let contents: Vec<_> = a.iter().map(|val| serialize_policy(val, language, None)).collect();
+                    let contents = a.iter().map(|val| serialize(val, language, None, encode)).collect::<Result<Vec<_>, _>>()?;
                     match parent {
-                        Some(PolicyType::And) => format!("({})", contents.join(" and ")),
-                        Some(PolicyType::Or) => format!("({})", contents.join(" or ")),
-                        _ => panic!("children without parent")
+                        Some(PolicyType::And) => Ok(format!("({})", contents.join(" and "))),
+                        Some(PolicyType::Or) => Ok(format!("({})", contents.join(" or "))),
+                        Some(PolicyType::Threshold(k)) => Ok(format!("{} of ({})", k, contents.join(", "))),
+                        _ => Err(RabeError::InvalidPolicy("serialize_policy: children without parent".to_string()))
                     }
                 }
-                String(s) => format!("{}", s.0),
+                String(s) => Ok(s.0.to_string()),
+                Not(child) => Ok(format!("not {}", serialize(child.as_ref(), language, None, encode)?)),
             }
         }
     }
@@ -114,14 +226,22 @@ pub fn serialize_policy(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rabe_bn::Fr;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+    use utils::policy::msp::AbePolicy;
+    use utils::policy::dnf::policy_in_dnf;
+    use utils::secretsharing::{calc_coefficients, calc_pruned, gen_shares_policy};
+    use utils::tools::traverse_policy;
+    use utils::policy::comparison::expand_attributes;
 
     #[test]
     fn test_single_parsing() {
         let pol = String::from(r#"{"name": "A"}"#);
         let human = String::from("A");
         let json: PolicyValue = parse(&pol, PolicyLanguage::JsonPolicy).expect("unsuccessful parse");
-        let serialized_json = serialize_policy(&json, PolicyLanguage::JsonPolicy, None);
-        let serialized_human = serialize_policy(&json, PolicyLanguage::HumanPolicy, None);
+        let serialized_json = serialize_policy(&json, PolicyLanguage::JsonPolicy, None).unwrap();
+        let serialized_human = serialize_policy(&json, PolicyLanguage::HumanPolicy, None).unwrap();
         assert_eq!(serialized_json, pol);
         assert_eq!(serialized_human, human);
     }
@@ -131,8 +251,8 @@ mod tests {
         let pol = String::from(r#"{"name": "and", "children": [{"name": "B"}, {"name": "C"}]}"#);
         let human = String::from("(B and C)");
         let json: PolicyValue = parse(&pol, PolicyLanguage::JsonPolicy).expect("unsuccessful parse");
-        let serialized_json =serialize_policy(&json, PolicyLanguage::JsonPolicy, None);
-        let serialized_human =serialize_policy(&json, PolicyLanguage::HumanPolicy, None);
+        let serialized_json =serialize_policy(&json, PolicyLanguage::JsonPolicy, None).unwrap();
+        let serialized_human =serialize_policy(&json, PolicyLanguage::HumanPolicy, None).unwrap();
         assert_eq!(serialized_json, pol);
         assert_eq!(serialized_human, human);
     }
@@ -142,9 +262,231 @@ mod tests {
         let pol = String::from(r#"{"name": "or", "children": [{"name": "A"}, {"name": "and", "children": [{"name": "B"}, {"name": "C"}]}]}"#);
         let human = String::from(r#"(A or (B and C))"#);
         let json: PolicyValue = parse(&pol, PolicyLanguage::JsonPolicy).expect("unsuccessful parse");
-        let serialized_json =serialize_policy(&json, PolicyLanguage::JsonPolicy, None);
-        let serialized_human =serialize_policy(&json, PolicyLanguage::HumanPolicy, None);
+        let serialized_json =serialize_policy(&json, PolicyLanguage::JsonPolicy, None).unwrap();
+        let serialized_human =serialize_policy(&json, PolicyLanguage::HumanPolicy, None).unwrap();
+        assert_eq!(serialized_json, pol);
+        assert_eq!(serialized_human, human);
+    }
+
+    #[test]
+    fn test_threshold_parsing() {
+        let pol = String::from(r#"{"name": "and", "children": [{"name": "A"}, {"name": "threshold", "k": 2, "children": [{"name": "B"}, {"name": "C"}, {"name": "D"}]}]}"#);
+        let human = String::from(r#"(A and 2 of (B, C, D))"#);
+        let json: PolicyValue = parse(&pol, PolicyLanguage::JsonPolicy).expect("unsuccessful parse");
+        let serialized_json = serialize_policy(&json, PolicyLanguage::JsonPolicy, None).unwrap();
+        let serialized_human = serialize_policy(&json, PolicyLanguage::HumanPolicy, None).unwrap();
         assert_eq!(serialized_json, pol);
         assert_eq!(serialized_human, human);
+        // children may precede k, the human grammar needs quoted attributes
+        let reordered = String::from(r#"{"name": "threshold", "children": [{"name": "B"}, {"name": "C"}], "k": 1}"#);
+        assert!(parse(&reordered, PolicyLanguage::JsonPolicy).is_ok());
+        let human = parse(r#""A" and 2 of ("B", "C", "D")"#, PolicyLanguage::HumanPolicy).expect("unsuccessful parse");
+        assert_eq!(serialize_policy(&human, PolicyLanguage::JsonPolicy, None).unwrap(), pol);
+    }
+
+    #[test]
+    fn test_negation_parsing() {
+        let pol = String::from(r#"{"name": "and", "children": [{"name": "A"}, {"name": "not", "children": [{"name": "or", "children": [{"name": "B"}, {"name": "C"}]}]}]}"#);
+        let human = String::from("(A and not (B or C))");
+        let json: PolicyValue = parse(&pol, PolicyLanguage::JsonPolicy).expect("unsuccessful parse");
+        assert_eq!(serialize_policy(&json, PolicyLanguage::JsonPolicy, None).unwrap(), pol);
+        assert_eq!(serialize_policy(&json, PolicyLanguage::HumanPolicy, None).unwrap(), human);
+        for negated in [r#""A" and not ("B" or "C")"#, r#""A" AND NOT ("B" || "C")"#, r#""A" && !("B" or "C")"#] {
+            let human = parse(negated, PolicyLanguage::HumanPolicy).expect("unsuccessful parse");
+            assert_eq!(serialize_policy(&human, PolicyLanguage::JsonPolicy, None).unwrap(), pol);
+        }
+        // negations have exactly one child
+        assert!(parse(r#"{"name": "not", "children": []}"#, PolicyLanguage::JsonPolicy).is_err());
+        assert!(parse(r#"{"name": "not", "children": [{"name": "A"}, {"name": "B"}]}"#, PolicyLanguage::JsonPolicy).is_err());
+        // an attribute may still be called "not"
+        assert!(parse(r#"{"name": "not"}"#, PolicyLanguage::JsonPolicy).is_ok());
+        assert!(parse(r#""not" and "A""#, PolicyLanguage::HumanPolicy).is_ok());
+    }
+
+    #[test]
+    fn test_negation_normal_form() {
+        let policy = parse(r#"not ("A" and not "B" and not 2 of ("C", "D", not "E"))"#, PolicyLanguage::HumanPolicy).unwrap();
+        assert!(!is_monotone(&policy));
+        assert!(matches!(check_monotone(&policy, "test"), Err(RabeError::InvalidPolicy(_))));
+        let nnf = negation_normal_form(policy);
+        assert_eq!(serialize_policy(&nnf, PolicyLanguage::HumanPolicy, None).unwrap(), "(not A or B or 2 of (C, D, not E))");
+        let monotone = parse(r#"not not "A""#, PolicyLanguage::HumanPolicy).unwrap();
+        assert!(is_monotone(&negation_normal_form(monotone)));
+        // negations are encoded as dummy attributes
+        let encoded = encode_negations(r#""A" and not ("B" or "C")"#, PolicyLanguage::HumanPolicy).unwrap();
+        assert_eq!(encoded, r#"{"name": "and", "children": [{"name": "A"}, {"name": "and", "children": [{"name": "not:B"}, {"name": "not:C"}]}]}"#);
+        assert!(is_monotone(&parse(&encoded, PolicyLanguage::JsonPolicy).unwrap()));
+        assert_eq!(negated_attributes(&["A", "B", "C"], &["B"]), vec!["not:A", "not:C"]);
+    }
+
+    #[test]
+    fn test_comparison_parsing() {
+        let policies = [
+            (r#""A" and age >= 18"#, PolicyLanguage::HumanPolicy),
+            (r#""A" and ("age" > 17 or not "A")"#, PolicyLanguage::HumanPolicy),
+            (r#""A" AND age IN [18, 99]"#, PolicyLanguage::HumanPolicy),
+            (r#"{"name": "and", "children": [{"name": "A"}, {"name": "age", ">=": 18}]}"#, PolicyLanguage::JsonPolicy),
+            (r#"{"name": "and", "children": [{"name": "A"}, {"name": "age", ">": 17, "<=": 99}]}"#, PolicyLanguage::JsonPolicy),
+        ];
+        for (policy, language) in policies {
+            let pol = parse(policy, language).expect("unsuccessful parse");
+            for (age, satisfied) in [("age = 18", true), ("age = 42", true), ("age = 17", false), ("age = 0", false)] {
+                let attributes = expand_attributes(&["A", age]).unwrap();
+                assert_eq!(traverse_policy(&attributes, &pol, PolicyType::Leaf), satisfied, "{} {}", policy, age);
+                assert_eq!(calc_pruned(&attributes, &pol, None).unwrap().0, satisfied, "{} {}", policy, age);
+            }
+        }
+        for (policy, language) in [(r#"level == 5"#, PolicyLanguage::HumanPolicy), (r#"{"name": "level", "=": 5}"#, PolicyLanguage::JsonPolicy)] {
+            let pol = parse(policy, language).expect("unsuccessful parse");
+            assert!(traverse_policy(&expand_attributes(&["level = 5"]).unwrap(), &pol, PolicyType::Leaf));
+            assert!(!traverse_policy(&expand_attributes(&["level = 4"]).unwrap(), &pol, PolicyType::Leaf));
+        }
+        // ranges that include 0 or the maximum
+        let pol = parse(r#"level in [0, 18446744073709551615]"#, PolicyLanguage::HumanPolicy).unwrap();
+        assert!(traverse_policy(&expand_attributes(&["level = 7"]).unwrap(), &pol, PolicyType::Leaf));
+        // comparisons need unsigned 64 bit integers and have to be satisfiable
+        for policy in [r#"age >= -1"#, r#"age >= 1.5"#, r#"age < 0"#, r#"age > 18446744073709551615"#, r#"age in [5, 2]"#, r#"age >= 18446744073709551616"#] {
+            assert!(matches!(parse(policy, PolicyLanguage::HumanPolicy), Err(RabeError::InvalidPolicy(_))), "{}", policy);
+        }
+        assert!(parse(r#"age => 18"#, PolicyLanguage::HumanPolicy).is_err());
+        assert!(matches!(parse(r#"{"name": "age", ">=": -1}"#, PolicyLanguage::JsonPolicy), Err(RabeError::InvalidPolicy(_))));
+    }
+
+    const HUMAN_TOKENS: &[&str] = &[
+        "\"A\"", "\"B\"", "\"C\"", "5", "-1.5e3", "and", "or", "AND", "&&", "||", "(", ")", "[", "]", "{", "}", "of", "0", "2", ",", "not", "!", "age", ">=", "<", "==", "in",
+        "\"", "\"\"", "\"\\u00e4\"", "\"ä\"", "\\", "/*", "*/", " ", "\n",
+    ];
+    const JSON_TOKENS: &[&str] = &[
+        "{", "}", "[", "]", ":", ",", "\"name\"", "name", "\"children\"", "\"and\"", "\"or\"", "\"A\"", "\"B\"",
+        "\"\"", "7", "\"", " ", "\"threshold\"", "\"k\"", "0", "2", "\"not\"", "\">=\"", "\"<\"",
+    ];
+
+    /// Generates a random, well-formed policy tree with AND, OR and THRESHOLD nodes of 1 to 4 children
+    fn random_policy<R: Rng>(rng: &mut R, depth: usize, language: PolicyLanguage) -> String {
+        let attribute = ["A", "B", "C", "D", "E"][rng.gen_range(0..5)];
+        if depth == 0 || rng.gen_bool(0.3) {
+            return match language {
+                PolicyLanguage::HumanPolicy => format!("\"{}\"", attribute),
+                PolicyLanguage::JsonPolicy => format!("{{\"name\": \"{}\"}}", attribute),
+            };
+        }
+        let children: Vec<String> = (0..rng.gen_range(1..5)).map(|_| random_policy(rng, depth - 1, language)).collect();
+        if rng.gen_bool(0.3) {
+            let k = rng.gen_range(1..=children.len());
+            return match language {
+                PolicyLanguage::HumanPolicy => format!("{} of ({})", k, children.join(", ")),
+                PolicyLanguage::JsonPolicy => format!("{{\"name\": \"threshold\", \"k\": {}, \"children\": [{}]}}", k, children.join(", ")),
+            };
+        }
+        let operator = if rng.gen_bool(0.5) { "and" } else { "or" };
+        match language {
+            PolicyLanguage::HumanPolicy => format!("({})", children.join(&format!(" {} ", operator))),
+            PolicyLanguage::JsonPolicy => format!("{{\"name\": \"{}\", \"children\": [{}]}}", operator, children.join(", ")),
+        }
+    }
+
+    /// Runs every consumer of a parsed policy, none of them may panic
+    fn consume(policy: &str, pol: &PolicyValue) {
+        let attributes = vec![String::from("A"), String::from("B"), String::from("C")];
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _ = AbePolicy::from_policy(pol);
+            let pruned = calc_pruned(&attributes, pol, None).map(|p| p.1).unwrap_or_default();
+            let _ = gen_shares_policy(Fr::one(), pol, None);
+            let _ = calc_coefficients(pol, Some(Fr::one()), Vec::new(), None, &pruned);
+            let _ = serialize_policy(pol, PolicyLanguage::HumanPolicy, None);
+            let _ = serialize_policy(pol, PolicyLanguage::JsonPolicy, None);
+            let _ = traverse_policy(&attributes, pol, PolicyType::Leaf);
+            let _ = policy_in_dnf(pol, false, None);
+        }));
+        assert!(result.is_ok(), "panic on policy {:?}", policy);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn fuzz_policies() {
+        let mut rng = ChaCha20Rng::seed_from_u64(9);
+        for (language, tokens) in [(PolicyLanguage::HumanPolicy, HUMAN_TOKENS), (PolicyLanguage::JsonPolicy, JSON_TOKENS)] {
+            // random token soup, most of it does not parse
+            for _ in 0..2000 {
+                let policy: String = (0..rng.gen_range(0..12)).map(|_| tokens[rng.gen_range(0..tokens.len())]).collect();
+                let parsed = std::panic::catch_unwind(|| parse(&policy, language).is_ok());
+                assert!(parsed.is_ok(), "panic while parsing {:?}", policy);
+                if let Ok(pol) = parse(&policy, language) {
+                    consume(&policy, &pol);
+                }
+            }
+            // well-formed policies with single children, more than two children and thresholds
+            for _ in 0..200 {
+                let policy = random_policy(&mut rng, 3, language);
+                let pol = parse(&policy, language).expect("unsuccessful parse");
+                consume(&policy, &pol);
+                let msp = AbePolicy::from_policy(&pol).expect("no msp");
+                // all backends agree on whether a random set of attributes satisfies the policy
+                let attributes: Vec<String> = ["A", "B", "C", "D", "E"]
+                    .iter()
+                    .filter(|_| rng.gen_bool(0.7))
+                    .map(|a| a.to_string())
+                    .collect();
+                let (matched, pruned) = calc_pruned(&attributes, &pol, None).unwrap();
+                assert_eq!(matched, traverse_policy(&attributes, &pol, PolicyType::Leaf), "{} {:?}", policy, attributes);
+                assert_eq!(matched, msp.reconstruction(&attributes).is_some(), "{} {:?}", policy, attributes);
+                if !matched {
+                    continue;
+                }
+                // the shares of the pruned attributes reconstruct the secret
+                let secret: Fr = rng.gen();
+                let shares = gen_shares_policy(secret, &pol, None).unwrap();
+                let coefficients = calc_coefficients(&pol, Some(Fr::one()), Vec::new(), None, &pruned).unwrap();
+                let mut reconstructed = Fr::zero();
+                for (_, node) in pruned.iter() {
+                    let share = shares.iter().find(|s| &s.0 == node).unwrap().1;
+                    let coefficient = coefficients.iter().find(|c| &c.0 == node).unwrap().1;
+                    reconstructed = reconstructed + coefficient * share;
+                }
+                assert!(reconstructed == secret, "could not reconstruct secret of {}", policy);
+            }
+            // the negation normal form of a negated policy is satisfied by the complementary attribute sets
+            for _ in 0..200 {
+                let policy = random_policy(&mut rng, 3, language);
+                let negated = match language {
+                    PolicyLanguage::HumanPolicy => format!("not ({})", policy),
+                    PolicyLanguage::JsonPolicy => format!("{{\"name\": \"not\", \"children\": [{}]}}", policy),
+                };
+                let pol = parse(&policy, language).expect("unsuccessful parse");
+                let neg = negation_normal_form(parse(&negated, language).expect("unsuccessful parse"));
+                consume(&negated, &neg);
+                let attributes: Vec<String> = ["A", "B", "C", "D", "E"]
+                    .iter()
+                    .filter(|_| rng.gen_bool(0.5))
+                    .map(|a| a.to_string())
+                    .collect();
+                if attributes.is_empty() {
+                    continue;
+                }
+                let (matched, pruned) = calc_pruned(&attributes, &neg, None).unwrap();
+                assert_eq!(matched, !traverse_policy(&attributes, &pol, PolicyType::Leaf), "{} {:?}", negated, attributes);
+                assert_eq!(matched, traverse_policy(&attributes, &neg, PolicyType::Leaf), "{} {:?}", negated, attributes);
+                if !matched {
+                    continue;
+                }
+                let secret: Fr = rng.gen();
+                let shares = gen_shares_policy(secret, &neg, None).unwrap();
+                let coefficients = calc_coefficients(&neg, Some(Fr::one()), Vec::new(), None, &pruned).unwrap();
+                let mut reconstructed = Fr::zero();
+                for (_, node) in pruned.iter() {
+                    let share = shares.iter().find(|s| &s.0 == node).unwrap().1;
+                    let coefficient = coefficients.iter().find(|c| &c.0 == node).unwrap().1;
+                    reconstructed = reconstructed + coefficient * share;
+                }
+                assert!(reconstructed == secret, "could not reconstruct secret of {}", negated);
+            }
+        }
+        // AND and OR without children
+        assert!(matches!(parse(r#"{"name": "and", "children": []}"#, PolicyLanguage::JsonPolicy), Err(RabeError::InvalidPolicy(_))));
+        // numbers are leaves, too
+        assert!(parse(r#""A" and 5"#, PolicyLanguage::HumanPolicy).is_ok());
+        // thresholds need 1 <= k <= number of children
+        assert!(matches!(parse(r#"0 of ("A", "B")"#, PolicyLanguage::HumanPolicy), Err(RabeError::InvalidPolicy(_))));
+        assert!(matches!(parse(r#"3 of ("A", "B")"#, PolicyLanguage::HumanPolicy), Err(RabeError::InvalidPolicy(_))));
+        assert!(matches!(parse(r#"{"name": "threshold", "k": 1, "children": []}"#, PolicyLanguage::JsonPolicy), Err(RabeError::InvalidPolicy(_))));
+    }
+}
@@ -1,102 +1,163 @@
 use rabe_bn::*;
-use rand::Rng;
+use std::borrow::Cow;
+use rand::{CryptoRng, Rng, RngCore};
 use utils::{
     tools::{contains, usize_to_fr, get_value},
     policy::pest::{PolicyValue, PolicyLanguage, parse, PolicyType}
 };
 use crate::error::RabeError;
 
-pub fn calc_coefficients(policy_value: &PolicyValue, coeff: Option<Fr>, mut coeff_list: Vec<(String, Fr)>, policy_type: Option<PolicyType>) -> Option<Vec<(String, Fr)>> {
+/// Calculates the coefficients that recombine the shares of `gen_shares_policy()`. Threshold gates are recombined from the
+/// children that contain a node of `pruned`, the list of nodes returned by `calc_pruned()`.
+pub fn calc_coefficients(policy_value: &PolicyValue, coeff: Option<Fr>, mut coeff_list: Vec<(String, Fr)>, policy_type: Option<PolicyType>, pruned: &[(String, String)]) -> Result<Vec<(String, Fr)>, RabeError> {
+    let coeff = coeff.ok_or_else(|| RabeError::InvalidInput(String::from("calc_coefficients: no coefficient given")))?;
     return match policy_value {
         PolicyValue::Object(obj) => {
             match obj.0 {
-                PolicyType::And => calc_coefficients(&obj.1.as_ref(), coeff, coeff_list, Some(PolicyType::And) ),
-                PolicyType::Or => calc_coefficients(&obj.1.as_ref(), coeff, coeff_list, Some(PolicyType::Or) ),
+                PolicyType::And => calc_coefficients(&obj.1.as_ref(), Some(coeff), coeff_list, Some(PolicyType::And), pruned),
+                PolicyType::Or => calc_coefficients(&obj.1.as_ref(), Some(coeff), coeff_list, Some(PolicyType::Or), pruned),
+                PolicyType::Threshold(k) => calc_coefficients(obj.1.as_ref(), Some(coeff), coeff_list, Some(PolicyType::Threshold(k)), pruned),
                 _ => {
                     // Single attribute policy use case
-                    coeff_list.push((get_value(&obj.1), coeff.unwrap()));
-                    return Some(coeff_list);
+                    coeff_list.push((get_value(&obj.1), coeff));
+                    return Ok(coeff_list);
                 }
             }
         }
         PolicyValue::Array(children) => {
-            match policy_type.unwrap() {
-                PolicyType::And => {
+            match policy_type {
+                Some(PolicyType::And) => {
                     let mut this_coeff_vec = vec![Fr::one()];
                     for _i in 1..children.len() {
                         let prev = this_coeff_vec[_i - 1].clone();
                         this_coeff_vec.push(prev + Fr::one());
                     }
-                    let this_coeff = recover_coefficients(this_coeff_vec);
+                    let this_coeff = recover_coefficients(this_coeff_vec)?;
                     for (i, child) in children.iter().enumerate() {
-                        match calc_coefficients(&child, Some(coeff.unwrap() * this_coeff[i]), coeff_list.clone(), None ) {
-                            None => return None,
-                            Some(res) => coeff_list = res
-                        }
+                        coeff_list = calc_coefficients(&child, Some(coeff * this_coeff[i]), coeff_list, None, pruned)?;
                     }
-                    Some(coeff_list)
+                    Ok(coeff_list)
                 },
-                PolicyType::Or => {
-                    let this_coeff = recover_coefficients(vec![Fr::one()]);
+                Some(PolicyType::Or) => {
+                    let this_coeff = recover_coefficients(vec![Fr::one()])?;
                     for child in children.iter() {
-                        match calc_coefficients(&child, Some(coeff.unwrap() * this_coeff[0]), coeff_list.clone(), None ) {
-                            None => return None,
-                            Some(res) => coeff_list = res
-                        }
+                        coeff_list = calc_coefficients(&child, Some(coeff * this_coeff[0]), coeff_list, None, pruned)?;
+                    }
+                    Ok(coeff_list)
+                }
+                Some(PolicyType::Threshold(k)) => {
+                    let selected: Vec<usize> = (0..children.len())
+                        .filter(|i| is_pruned(&children[*i], pruned))
+                        .take(k)
+                        .collect();
+                    // a gate that is not part of the pruned policy needs no coefficients
+                    if selected.is_empty() {
+                        return Ok(coeff_list);
+                    }
+                    if selected.len() < k {
+                        return Err(RabeError::PolicyNotSatisfied(format!("calc_coefficients: less than {} children of a threshold gate are pruned", k)));
+                    }
+                    // the shares of child i are evaluated at i + 1
+                    let this_coeff = recover_coefficients(selected.iter().map(|i| usize_to_fr(i + 1)).collect())?;
+                    for (i, this_coeff) in selected.into_iter().zip(this_coeff) {
+                        coeff_list = calc_coefficients(&children[i], Some(coeff * this_coeff), coeff_list, None, pruned)?;
                     }
-                    Some(coeff_list)
+                    Ok(coeff_list)
                 }
-                _ => None
+                _ => Err(RabeError::InvalidPolicy(String::from("calc_coefficients: children without AND, OR or THRESHOLD")))
             }
         }
         PolicyValue::String(node) => {
-            coeff_list.push((node_index(node), coeff.unwrap()));
-            Some(coeff_list)
+            coeff_list.push((node_index(node), coeff));
+            Ok(coeff_list)
+        }
+        PolicyValue::Not(child) => {
+            coeff_list.push((negated_node_index(child)?, coeff));
+            Ok(coeff_list)
         }
     };
 }
 
+// checks if a subtree contains a node of the pruned list
+fn is_pruned(policy_value: &PolicyValue, pruned: &[(String, String)]) -> bool {
+    match policy_value {
+        PolicyValue::String(node) => {
+            let index = node_index(node);
+            pruned.iter().any(|p| p.1 == index)
+        },
+        PolicyValue::Object(obj) => is_pruned(obj.1.as_ref(), pruned),
+        PolicyValue::Array(children) => children.iter().any(|child| is_pruned(child, pruned)),
+        PolicyValue::Not(child) => match negated_node_index(child) {
+            Ok(index) => pruned.iter().any(|p| p.1 == index),
+            Err(_) => false,
+        },
+    }
+}
+
 // lagrange interpolation
-pub fn recover_coefficients(list: Vec<Fr>) -> Vec<Fr> {
+pub fn recover_coefficients(list: Vec<Fr>) -> Result<Vec<Fr>, RabeError> {
     let mut coeff: Vec<Fr> = Vec::new();
     for _i in list.clone() {
         let mut result = Fr::one();
         for _j in list.clone() {
             if _i != _j {
-                result = result * ((Fr::zero() - _j) * (_i - _j).inverse().unwrap());
+                match (_i - _j).inverse() {
+                    Some(inverse) => result = result * ((Fr::zero() - _j) * inverse),
+                    None => return Err(RabeError::InvalidInput(String::from("recover_coefficients: interpolation points are not distinct")))
+                }
             }
         }
         coeff.push(result);
     }
-    return coeff;
+    return Ok(coeff);
 }
 
-pub fn node_index(node: &(&str, usize)) -> String {
+pub fn node_index(node: &(Cow<str>, usize)) -> String {
     [node.0.to_string(), String::from("_"), node.1.to_string()].concat()
 }
+/// The index of a negated attribute, i.e. the index of the attribute prefixed with `!`. Only attributes can be negated,
+/// see `negation_normal_form()`.
+pub fn negated_node_index(child: &PolicyValue) -> Result<String, RabeError> {
+    match child {
+        PolicyValue::String(node) => Ok(format!("!{}", node_index(node))),
+        _ => Err(RabeError::InvalidPolicy(String::from("secretsharing: only attributes can be negated, the policy is not in negation normal form")))
+    }
+}
 pub fn remove_index(node: &String) -> String {
     let parts: Vec<_> = node.split('_').collect();
     parts[0].to_string()
 }
 
-pub fn gen_shares_policy(secret: Fr, policy_value: &PolicyValue, policy_type: Option<PolicyType>) -> Option<Vec<(String, Fr)>> {
+pub fn gen_shares_policy(secret: Fr, policy_value: &PolicyValue, policy_type: Option<PolicyType>) -> Result<Vec<(String, Fr)>, RabeError> {
+    gen_shares_policy_with_rng(secret, policy_value, policy_type, &mut rand::thread_rng())
+}
+
+pub fn gen_shares_policy_with_rng<R: RngCore + CryptoRng>(secret: Fr, policy_value: &PolicyValue, policy_type: Option<PolicyType>, rng: &mut R) -> Result<Vec<(String, Fr)>, RabeError> {
     let mut result: Vec<(String, Fr)> = Vec::new();
     let k;
     let n;
     match policy_value {
         PolicyValue::String(node) => {
             result.push((node_index(node), secret));
-            Some(result)
+            Ok(result)
+        },
+        PolicyValue::Not(child) => {
+            result.push((negated_node_index(child)?, secret));
+            Ok(result)
         },
         PolicyValue::Object(obj) => {
             match obj.0 {
-                PolicyType::And => gen_shares_policy(secret, &obj.1.as_ref(), Some(PolicyType::And)),
-                PolicyType::Or => gen_shares_policy(secret, &obj.1.as_ref(), Some(PolicyType::Or)),
-                _ => gen_shares_policy(secret, &obj.1.as_ref(), Some(PolicyType::Leaf)),
+                PolicyType::And => gen_shares_policy_with_rng(secret, &obj.1.as_ref(), Some(PolicyType::And), rng),
+                PolicyType::Or => gen_shares_policy_with_rng(secret, &obj.1.as_ref(), Some(PolicyType::Or), rng),
+                PolicyType::Threshold(k) => gen_shares_policy_with_rng(secret, obj.1.as_ref(), Some(PolicyType::Threshold(k)), rng),
+                _ => gen_shares_policy_with_rng(secret, &obj.1.as_ref(), Some(PolicyType::Leaf), rng),
             }
         },
         PolicyValue::Array(children) => {
             n = children.len();
+            if n == 0 {
+                return Err(RabeError::InvalidPolicy(String::from("gen_shares_policy: AND, OR or THRESHOLD without children")));
+            }
             match policy_type {
                 Some(PolicyType::And) => {
                     k = n;
@@ -104,28 +165,30 @@ pub fn gen_shares_policy(secret: Fr, policy_value: &PolicyValue, policy_type: Op
                 Some(PolicyType::Or) => {
                     k = 1;
                 }
-                None => panic!("this should not happen =( Array is always AND or OR."),
-                _ => panic!("this should not happen =( Array is always AND or OR.")
-            }
-            let shares = gen_shares(secret, k, n);
-            for _i in 0..n {
-                match gen_shares_policy(shares[_i + 1], &children[_i], None) {
-                    None => panic!("Error in gen_shares_policy: Returned None."),
-                    Some(_items) => {
-                        result.extend(_items.iter().cloned());
+                Some(PolicyType::Threshold(t)) => {
+                    if t == 0 || t > n {
+                        return Err(RabeError::InvalidPolicy(format!("gen_shares_policy: threshold {} of {} children", t, n)));
                     }
+                    k = t;
                 }
+                _ => return Err(RabeError::InvalidPolicy(String::from("gen_shares_policy: children without AND, OR or THRESHOLD")))
+            }
+            let shares = gen_shares_with_rng(secret, k, n, rng);
+            for _i in 0..n {
+                result.extend(gen_shares_policy_with_rng(shares[_i + 1], &children[_i], None, rng)?);
             }
-            Some(result)
+            Ok(result)
         }
     }
 }
 
 pub fn gen_shares(secret: Fr, k: usize, n: usize) -> Vec<Fr> {
+    gen_shares_with_rng(secret, k, n, &mut rand::thread_rng())
+}
+
+pub fn gen_shares_with_rng<R: RngCore + CryptoRng>(secret: Fr, k: usize, n: usize, rng: &mut R) -> Vec<Fr> {
     let mut shares: Vec<Fr> = Vec::new();
     if k <= n {
-        // random number generator
-        let mut rng = rand::thread_rng();
         // polynomial coefficients
         let mut a: Vec<Fr> = Vec::new();
         a.push(secret);
@@ -147,6 +210,7 @@ pub fn calc_pruned(attr: &Vec<String>, policy_value: &PolicyValue, policy_type:
             match obj.0 {
                 PolicyType::And => calc_pruned(attr, &obj.1.as_ref(), Some(PolicyType::And)),
                 PolicyType::Or => calc_pruned(attr, &obj.1.as_ref(), Some(PolicyType::Or)),
+                PolicyType::Threshold(k) => calc_pruned(attr, obj.1.as_ref(), Some(PolicyType::Threshold(k))),
                 _ => calc_pruned(attr, &obj.1.as_ref(), Some(PolicyType::Leaf)),
             }
         },
@@ -155,16 +219,15 @@ pub fn calc_pruned(attr: &Vec<String>, policy_value: &PolicyValue, policy_type:
             match policy_type {
                 Some(PolicyType::And) => {
                     let mut policy_match: bool = true;
-                    if len >= 2 {
-                        for _i in 0usize..len {
-                            let (_found, mut _list) = calc_pruned(attr, &children[_i], None).unwrap();
-                            policy_match = policy_match && _found;
-                            if policy_match {
-                                empty.append(&mut _list);
-                            }
+                    if len == 0 {
+                        return Err(RabeError::InvalidPolicy(String::from("calc_pruned: AND without children")));
+                    }
+                    for _i in 0usize..len {
+                        let (_found, mut _list) = calc_pruned(attr, &children[_i], None)?;
+                        policy_match = policy_match && _found;
+                        if policy_match {
+                            empty.append(&mut _list);
                         }
-                    } else {
-                        panic!("Error: Invalid policy (AND with just a single child).");
                     }
                     if !policy_match.clone() {
                         empty = Vec::new();
@@ -173,21 +236,41 @@ pub fn calc_pruned(attr: &Vec<String>, policy_value: &PolicyValue, policy_type:
                 },
                 Some(PolicyType::Or) => {
                     let mut _match: bool = false;
-                    if len >= 2 {
-                        for _i in 0usize..len {
-                            let (_found, mut _list) = calc_pruned(attr, &children[_i], None).unwrap();
-                            _match = _match || _found;
-                            if _match {
-                                empty.append(&mut _list);
+                    if len == 0 {
+                        return Err(RabeError::InvalidPolicy(String::from("calc_pruned: OR without children")));
+                    }
+                    for _i in 0usize..len {
+                        let (_found, mut _list) = calc_pruned(attr, &children[_i], None)?;
+                        _match = _match || _found;
+                        if _match {
+                            empty.append(&mut _list);
+                            break;
+                        }
+                    }
+                    return Ok((_match, empty));
+                },
+                Some(PolicyType::Threshold(k)) => {
+                    if len == 0 {
+                        return Err(RabeError::InvalidPolicy(String::from("calc_pruned: THRESHOLD without children")));
+                    }
+                    // the first k satisfied children are used
+                    let mut satisfied = 0usize;
+                    for child in children.iter() {
+                        let (_found, mut _list) = calc_pruned(attr, child, None)?;
+                        if _found {
+                            empty.append(&mut _list);
+                            satisfied += 1;
+                            if satisfied == k {
                                 break;
                             }
                         }
-                        return Ok((_match, empty));
-                    } else {
-                        panic!("Error: Invalid policy (OR with just a single child).")
                     }
+                    if satisfied < k {
+                        empty = Vec::new();
+                    }
+                    return Ok((satisfied == k, empty));
                 },
-                _ => Err(RabeError::new("Error in calc_pruned: unknown array type!")),
+                _ => Err(RabeError::InvalidPolicy(String::from("calc_pruned: unknown array type"))),
             }
         },
         PolicyValue::String(node) => {
@@ -197,19 +280,103 @@ pub fn calc_pruned(attr: &Vec<String>, policy_value: &PolicyValue, policy_type:
                 Ok((false, empty))
             }
         }
+        // a negated attribute is satisfied if the attribute is missing
+        PolicyValue::Not(child) => {
+            let index = negated_node_index(child)?;
+            match child.as_ref() {
+                PolicyValue::String(node) if !contains(attr, &node.0.to_string()) => Ok((true, vec![(format!("!{}", node.0), index)])),
+                _ => Ok((false, empty)),
+            }
+        }
+    }
+}
+
+/// The decryption cost of a leaf of a policy, e.g. the number of pairings a scheme computes per leaf
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct LeafWeights {
+    /// The cost of an attribute
+    pub attribute: usize,
+    /// The cost of a negated attribute
+    pub negated: usize,
+}
+
+/// Like `calc_pruned()`, but selects the satisfying nodes with the lowest total weight: the cheapest satisfied child of
+/// an OR gate and the k cheapest satisfied children of a THRESHOLD gate, instead of the first ones.
+///
+/// # Arguments
+///
+///	* `attr` - The attributes, e.g. of a secret key
+///	* `policy_value` - The parsed policy
+///	* `weights` - The cost of the leaves of the policy
+///
+pub fn calc_pruned_minimal(attr: &Vec<String>, policy_value: &PolicyValue, weights: LeafWeights) -> Result<(bool, Vec<(String, String)>), RabeError> {
+    Ok(match cheapest(attr, policy_value, None, weights)? {
+        Some((_, pruned)) => (true, pruned),
+        None => (false, Vec::new()),
+    })
+}
+
+// the total weight and the nodes of a satisfying selection
+type Selection = (usize, Vec<(String, String)>);
+
+// the cheapest satisfying selection, None if the node is not satisfied
+fn cheapest(attr: &Vec<String>, policy_value: &PolicyValue, policy_type: Option<PolicyType>, weights: LeafWeights) -> Result<Option<Selection>, RabeError> {
+    match policy_value {
+        PolicyValue::Object(obj) => cheapest(attr, obj.1.as_ref(), Some(obj.0), weights),
+        PolicyValue::Array(children) => {
+            let k = match policy_type {
+                Some(PolicyType::And) => children.len(),
+                Some(PolicyType::Or) => 1,
+                Some(PolicyType::Threshold(k)) => k,
+                _ => return Err(RabeError::InvalidPolicy(String::from("calc_pruned_minimal: unknown array type"))),
+            };
+            if children.is_empty() {
+                return Err(RabeError::InvalidPolicy(String::from("calc_pruned_minimal: gate without children")));
+            }
+            let mut satisfied: Vec<(usize, Selection)> = Vec::new();
+            for (position, child) in children.iter().enumerate() {
+                if let Some(selection) = cheapest(attr, child, None, weights)? {
+                    satisfied.push((position, selection));
+                }
+            }
+            if satisfied.len() < k {
+                return Ok(None);
+            }
+            // the k cheapest children, the first ones if several children have the same weight
+            satisfied.sort_by_key(|(position, (weight, _))| (*weight, *position));
+            satisfied.truncate(k);
+            satisfied.sort_by_key(|(position, _)| *position);
+            let weight = satisfied.iter().map(|(_, (weight, _))| weight).sum();
+            Ok(Some((weight, satisfied.into_iter().flat_map(|(_, (_, pruned))| pruned).collect())))
+        },
+        PolicyValue::String(node) => {
+            if contains(attr, &node.0.to_string()) {
+                Ok(Some((weights.attribute, vec![(node.0.to_string(), node_index(node))])))
+            } else {
+                Ok(None)
+            }
+        }
+        // a negated attribute is satisfied if the attribute is missing
+        PolicyValue::Not(child) => {
+            let index = negated_node_index(child)?;
+            match child.as_ref() {
+                PolicyValue::String(node) if !contains(attr, &node.0.to_string()) => Ok(Some((weights.negated, vec![(format!("!{}", node.0), index)]))),
+                _ => Ok(None),
+            }
+        }
     }
 }
 
 #[allow(dead_code)]
-pub fn recover_secret(_shares: Vec<Fr>, _policy: &String) -> Fr {
-    let policy = parse(_policy, PolicyLanguage::JsonPolicy).unwrap();
+pub fn recover_secret(_shares: Vec<Fr>, _policy: &String) -> Result<Fr, RabeError> {
+    let policy = parse(_policy, PolicyLanguage::JsonPolicy)?;
     let mut coeff_list: Vec<(String, Fr)> = Vec::new();
-    coeff_list = calc_coefficients(&policy, Some(Fr::one()), coeff_list, None).unwrap();
+    coeff_list = calc_coefficients(&policy, Some(Fr::one()), coeff_list, None, &Vec::new())?;
     let mut _secret = Fr::zero();
-    for _i in 0usize.._shares.len() {
-        _secret = _secret + (coeff_list[_i].1 * _shares[_i]);
+    for (_coeff, _share) in coeff_list.iter().zip(_shares.iter()) {
+        _secret = _secret + (_coeff.1 * *_share);
     }
-    return _secret;
+    return Ok(_secret);
 }
 
 pub fn polynomial(_coeff: Vec<Fr>, _x: Fr) -> Fr {
@@ -239,7 +406,7 @@ mod tests {
         let _reconstruct = recover_secret(
             _input,
             &String::from(r#"{"name":"or", "children": [{"name": "A"}, {"name": "B"}]}"#),
-        );
+        ).unwrap();
         assert!(_k == _reconstruct);
     }
 
@@ -253,7 +420,7 @@ mod tests {
             Ok(pol) => {
                 let _shares = gen_shares_policy(_secret, &pol, None).unwrap();
                 let coeff_list: Vec<(String, Fr)> = Vec::new();
-                let _coeff = calc_coefficients(&pol, Some(Fr::one()), coeff_list, None).unwrap();
+                let _coeff = calc_coefficients(&pol, Some(Fr::one()), coeff_list, None, &Vec::new()).unwrap();
                 assert_eq!(_coeff.len(), _shares.len());
             },
             Err(e) => println!("test_gen_shares_json: could not parse policy {}", e)
@@ -277,7 +444,7 @@ mod tests {
         let _reconstruct = recover_secret(
             _input,
             &String::from(r#"{"name": "and", "children": [{"name": "A"}, {"name": "B"}]}"#),
-        );
+        ).unwrap();
         //println!("_reconstructed: {:?}", into_dec(_reconstruct).unwrap());
         assert!(_k == _reconstruct);
     }
@@ -322,4 +489,38 @@ mod tests {
         assert_eq!(_match3, true);
         assert!(_list3 == vec![("A".to_string(), "A_68".to_string()), ("C".to_string(), "C_83".to_string())]);
     }
+
+    #[test]
+    fn test_pruning_minimal() {
+        let attributes: Vec<String> = ["A", "B", "C", "D", "E"].iter().map(|a| a.to_string()).collect();
+        let weights = LeafWeights { attribute: 2, negated: 3 };
+        let names = |policy: &str| -> (bool, Vec<String>) {
+            let pol = parse(policy, PolicyLanguage::HumanPolicy).unwrap();
+            let (matched, pruned) = calc_pruned_minimal(&attributes, &pol, weights).unwrap();
+            assert_eq!(matched, calc_pruned(&attributes, &pol, None).unwrap().0);
+            if matched {
+                // the coefficients of the selected nodes recombine the secret
+                let secret: Fr = rand::thread_rng().gen();
+                let shares = gen_shares_policy(secret, &pol, None).unwrap();
+                let coefficients = calc_coefficients(&pol, Some(Fr::one()), Vec::new(), None, &pruned).unwrap();
+                let mut recovered = Fr::zero();
+                for (_, index) in pruned.iter() {
+                    let share = shares.iter().find(|s| s.0 == *index).unwrap().1;
+                    let coefficient = coefficients.iter().find(|c| c.0 == *index).unwrap().1;
+                    recovered = recovered + share * coefficient;
+                }
+                assert!(recovered == secret, "{}", policy);
+            }
+            (matched, pruned.into_iter().map(|p| p.0).collect())
+        };
+        // the first branch of the OR is satisfied, but more expensive
+        assert_eq!(names(r#"("A" and "B" and "C") or "D""#), (true, vec![String::from("D")]));
+        assert_eq!(names(r#"("A" and "B") or "D" or "F""#), (true, vec![String::from("D")]));
+        // the two cheapest children of the threshold
+        assert_eq!(names(r#"2 of ("A" and "B", "C", "F", "D" or "E")"#), (true, vec![String::from("C"), String::from("D")]));
+        // a negated attribute is more expensive than an attribute
+        assert_eq!(names(r#"not "F" or "A""#), (true, vec![String::from("A")]));
+        assert_eq!(names(r#"not "F" or ("A" and "B")"#), (true, vec![String::from("!F")]));
+        assert_eq!(names(r#""A" and ("F" or "G")"#), (false, vec![]));
+    }
 }
@@ -4,12 +4,19 @@ use utils::policy::pest::{PolicyValue, PolicyType};
 use utils::secretsharing::node_index;
 
 pub fn is_negative(attr: &String) -> bool {
-    let first_char = &attr[..1];
-    return first_char == '!'.to_string();
+    return attr.starts_with('!');
 }
 
 pub fn usize_to_fr(i: usize) -> Fr {
-    return Fr::from_str(&i.to_string()).unwrap();
+    // double and add, so that there is no (unreachable) parse error to handle
+    let mut result = Fr::zero();
+    for bit in (0..usize::BITS).rev() {
+        result = result + result;
+        if (i >> bit) & 1 == 1 {
+            result = result + Fr::one();
+        }
+    }
+    return result;
 }
 
 pub fn contains(data: &Vec<String>, value: &String) -> bool {
@@ -30,11 +37,13 @@ pub fn is_subset(subset: &[&str], attr: &[&str]) -> bool {
 // used to traverse / check policy tree
 pub fn traverse_policy(attr: &Vec<String>, policy_value: &PolicyValue, policy_type: PolicyType) -> bool {
     return (attr.len() > 0) && match policy_value {
-        PolicyValue::String(node) => (&attr).into_iter().any(|x| x == node.0),
+        PolicyValue::String(node) => (&attr).into_iter().any(|x| *x == node.0),
+        PolicyValue::Not(child) => !traverse_policy(attr, child, PolicyType::Leaf),
         PolicyValue::Object(obj) => {
             return match obj.0 {
                 PolicyType::And => traverse_policy(attr, &obj.1.as_ref(), PolicyType::And),
                 PolicyType::Or => traverse_policy(attr, &obj.1.as_ref(), PolicyType::Or),
+                PolicyType::Threshold(k) => traverse_policy(attr, obj.1.as_ref(), PolicyType::Threshold(k)),
                 _ => true,
             }
         },
@@ -54,6 +63,12 @@ pub fn traverse_policy(attr: &Vec<String>, policy_value: &PolicyValue, policy_ty
                     }
                     ret
                 }
+                PolicyType::Threshold(k) => {
+                    arrayref
+                        .iter()
+                        .filter(|obj| traverse_policy(attr, obj, PolicyType::Leaf))
+                        .count() >= k
+                }
                 PolicyType::Leaf => false
             };
         }
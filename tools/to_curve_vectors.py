@@ -0,0 +1,217 @@
+#!/usr/bin/env python3
+# Computes the known answer values of the test in src/utils/hash/to_curve.rs with a plain implementation of RFC 9380,
+# "Hashing to Elliptic Curves", for the suites BN254G1_XMD:SHA3-256_SVDW_RO_ and BN254G2_XMD:SHA3-256_SVDW_RO_.
+#
+# The script follows the generic description of the RFC (Sections 5.2, 5.3.1, 6.6.1 and Appendix H.1) and works with
+# affine points and python integers, it shares no code with the rust implementation. The suites are specific to rabe,
+# RFC 9380 publishes no vectors for them. Run it with python 3.8 or later.
+from hashlib import sha3_256
+
+# the base field of BN254 and the order of G1 and G2
+Q = 0x30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd47
+R = 0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001
+# 2q - r, the cofactor of the twist
+COFACTOR_G2 = 2 * Q - R
+# bytes per field element, ceil((ceil(log2(q)) + 128) / 8)
+L = 48
+
+
+def expand_message_xmd(msg, dst, length):
+    # RFC 9380, Section 5.3.1 with H = SHA3-256, b_in_bytes = 32 and s_in_bytes = 136
+    ell = (length + 31) // 32
+    assert ell <= 255 and length <= 65535 and len(dst) <= 255
+    dst_prime = dst + bytes([len(dst)])
+    b_0 = sha3_256(bytes(136) + msg + length.to_bytes(2, "big") + bytes([0]) + dst_prime).digest()
+    b = [sha3_256(b_0 + bytes([1]) + dst_prime).digest()]
+    for i in range(2, ell + 1):
+        xored = bytes(x ^ y for x, y in zip(b_0, b[-1]))
+        b.append(sha3_256(xored + bytes([i]) + dst_prime).digest())
+    return b"".join(b)[:length]
+
+
+class Fq2:
+    # c0 + c1 * u with u^2 = -1
+    def __init__(self, c0, c1=0):
+        self.c0, self.c1 = c0 % Q, c1 % Q
+
+    def __add__(self, other):
+        return Fq2(self.c0 + other.c0, self.c1 + other.c1)
+
+    def __sub__(self, other):
+        return Fq2(self.c0 - other.c0, self.c1 - other.c1)
+
+    def __neg__(self):
+        return Fq2(-self.c0, -self.c1)
+
+    def __mul__(self, other):
+        return Fq2(self.c0 * other.c0 - self.c1 * other.c1, self.c0 * other.c1 + self.c1 * other.c0)
+
+    def __eq__(self, other):
+        return self.c0 == other.c0 and self.c1 == other.c1
+
+    def __pow__(self, exponent):
+        result, base = Fq2(1), self
+        while exponent:
+            if exponent & 1:
+                result = result * base
+            base = base * base
+            exponent >>= 1
+        return result
+
+    def is_zero(self):
+        return self.c0 == 0 and self.c1 == 0
+
+
+class Field:
+    # the operations of RFC 9380, Section 4, for Fq (degree 1) and Fq2 (degree 2)
+    def __init__(self, degree):
+        self.degree = degree
+
+    def element(self, values):
+        return values[0] % Q if self.degree == 1 else Fq2(values[0], values[1])
+
+    def one(self):
+        return self.element([1, 0])
+
+    def mul(self, a, b):
+        return a * b % Q if self.degree == 1 else a * b
+
+    def add(self, a, b):
+        return (a + b) % Q if self.degree == 1 else a + b
+
+    def sub(self, a, b):
+        return (a - b) % Q if self.degree == 1 else a - b
+
+    def neg(self, a):
+        return -a % Q if self.degree == 1 else -a
+
+    def pow(self, a, e):
+        return pow(a, e, Q) if self.degree == 1 else a ** e
+
+    def is_zero(self, a):
+        return a == 0 if self.degree == 1 else a.is_zero()
+
+    def inv0(self, a):
+        if self.degree == 1:
+            return pow(a, Q - 2, Q)
+        # 1 / (c0 + c1 u) = (c0 - c1 u) / (c0^2 + c1^2)
+        norm = pow(a.c0 * a.c0 + a.c1 * a.c1, Q - 2, Q)
+        return Fq2(a.c0 * norm, -a.c1 * norm)
+
+    def is_square(self, a):
+        norm = a if self.degree == 1 else (a.c0 * a.c0 + a.c1 * a.c1) % Q
+        return norm == 0 or pow(norm, (Q - 1) // 2, Q) == 1
+
+    def sqrt(self, a):
+        if self.degree == 1:
+            root = pow(a, (Q + 1) // 4, Q)
+        else:
+            # Algorithm 9 of Adj and Rodriguez-Henriquez, "Square root computation over even extension fields"
+            a1 = a ** ((Q - 3) // 4)
+            alpha = a1 * a1 * a
+            x0 = a1 * a
+            if alpha == Fq2(-1):
+                root = Fq2(0, 1) * x0
+            else:
+                root = (Fq2(1) + alpha) ** ((Q - 1) // 2) * x0
+        assert self.mul(root, root) == a, "not a square"
+        return root
+
+    def sgn0(self, a):
+        if self.degree == 1:
+            return a % 2
+        return (a.c0 % 2) | ((a.c0 == 0) & (a.c1 % 2))
+
+
+class Curve:
+    # y^2 = x^3 + b in affine coordinates, None is the point at infinity
+    def __init__(self, field, b):
+        self.field, self.b = field, b
+
+    def g(self, x):
+        f = self.field
+        return f.add(f.mul(f.mul(x, x), x), self.b)
+
+    def add(self, p, r):
+        f = self.field
+        if p is None:
+            return r
+        if r is None:
+            return p
+        if p[0] == r[0]:
+            if f.is_zero(f.add(p[1], r[1])):
+                return None
+            # doubling, 3x^2 / 2y
+            slope = f.mul(f.mul(f.element([3, 0]), f.mul(p[0], p[0])), f.inv0(f.add(p[1], p[1])))
+        else:
+            slope = f.mul(f.sub(r[1], p[1]), f.inv0(f.sub(r[0], p[0])))
+        x = f.sub(f.sub(f.mul(slope, slope), p[0]), r[0])
+        return (x, f.sub(f.mul(slope, f.sub(p[0], x)), p[1]))
+
+    def mul(self, p, k):
+        result = None
+        while k:
+            if k & 1:
+                result = self.add(result, p)
+            p = self.add(p, p)
+            k >>= 1
+        return result
+
+    def map_to_curve(self, u):
+        # Shallue-van de Woestijne with Z = 1, RFC 9380, Section 6.6.1
+        f = self.field
+        z = f.one()
+        g_z = self.g(z)
+        three_z_squared = f.mul(f.element([3, 0]), f.mul(z, z))
+        c1 = g_z
+        c2 = f.neg(f.mul(z, f.inv0(f.element([2, 0]))))
+        c3 = f.sqrt(f.neg(f.mul(g_z, three_z_squared)))
+        if f.sgn0(c3):
+            c3 = f.neg(c3)
+        c4 = f.mul(f.neg(f.mul(f.element([4, 0]), g_z)), f.inv0(three_z_squared))
+        tv1 = f.mul(f.mul(u, u), c1)
+        tv2 = f.add(f.one(), tv1)
+        tv1 = f.sub(f.one(), tv1)
+        tv3 = f.inv0(f.mul(tv1, tv2))
+        tv4 = f.mul(f.mul(f.mul(u, tv1), tv3), c3)
+        x1 = f.sub(c2, tv4)
+        x2 = f.add(c2, tv4)
+        x3 = f.add(f.mul(f.pow(f.mul(f.mul(tv2, tv2), tv3), 2), c4), z)
+        x = x1 if f.is_square(self.g(x1)) else x2 if f.is_square(self.g(x2)) else x3
+        y = f.sqrt(self.g(x))
+        if f.sgn0(u) != f.sgn0(y):
+            y = f.neg(y)
+        return (x, y)
+
+
+def hash_to_field(msg, dst, degree):
+    uniform = expand_message_xmd(msg, dst, 2 * degree * L)
+    elements = [int.from_bytes(uniform[i * L:(i + 1) * L], "big") for i in range(2 * degree)]
+    return [elements[i * degree:(i + 1) * degree] + [0] for i in range(2)]
+
+
+def hash_to_curve(curve, msg, dst, cofactor):
+    u0, u1 = (curve.field.element(u) for u in hash_to_field(msg, dst, curve.field.degree))
+    return curve.mul(curve.add(curve.map_to_curve(u0), curve.map_to_curve(u1)), cofactor)
+
+
+def hex32(value):
+    return "%064x" % value
+
+
+if __name__ == "__main__":
+    g1 = Curve(Field(1), 3)
+    fq2 = Field(2)
+    # the twist y^2 = x^3 + 3 / (9 + u)
+    g2 = Curve(fq2, Fq2(3) * fq2.inv0(Fq2(9, 1)))
+    dst_g1 = b"RABE-TEST-with-BN254G1_XMD:SHA3-256_SVDW_RO_"
+    dst_g2 = b"RABE-TEST-with-BN254G2_XMD:SHA3-256_SVDW_RO_"
+    print("expand_message_xmd", expand_message_xmd(b"abc", dst_g1, 32).hex())
+    x, y = hash_to_curve(g1, b"abc", dst_g1, 1)
+    print("G1 x", hex32(x))
+    print("G1 y", hex32(y))
+    x, y = hash_to_curve(g2, b"abc", dst_g2, COFACTOR_G2)
+    print("G2 x.c0", hex32(x.c0))
+    print("G2 x.c1", hex32(x.c1))
+    print("G2 y.c0", hex32(y.c0))
+    print("G2 y.c1", hex32(y.c1))